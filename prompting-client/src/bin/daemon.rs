@@ -1,9 +1,13 @@
 //! The daemon prompting client for apparmor prompting
+use chrono::Utc;
 use prompting_client::{
-    daemon::run_daemon, exit_with, log_filter, snapd_client::SnapdSocketClient, ExitStatus, Result,
-    DEFAULT_LOG_LEVEL,
+    daemon::{default_notices_cursor_path, run_daemon, NoticesCursor},
+    exit_with, log_filter,
+    snapd_client::SnapdSocketClient,
+    ExitStatus, Result, DEFAULT_LOG_LEVEL,
 };
 use std::{env, io::stdout};
+use tracing::info;
 use tracing::subscriber::set_global_default;
 use tracing_subscriber::FmtSubscriber;
 
@@ -21,7 +25,18 @@ async fn main() -> Result<()> {
 
     set_global_default(subscriber).expect("unable to set a global tracing subscriber");
 
-    let c = SnapdSocketClient::new().await;
+    // Pick up from where the previous run of the daemon left off so that we don't miss notices
+    // that arrived while we were not running.
+    let cursor = default_notices_cursor_path().map(NoticesCursor::new);
+    let after = match cursor.as_ref().and_then(|c| c.load()) {
+        Some(after) => {
+            info!(%after, "restoring notices cursor");
+            after
+        }
+        None => Utc::now(),
+    };
+
+    let c = SnapdSocketClient::new_with_notices_after(after).await;
     c.exit_if_prompting_not_enabled().await?;
 
     // If we can't see a valid X11 or Wayland display then we need to exit with an error code
@@ -33,5 +48,5 @@ async fn main() -> Result<()> {
         exit_with(ExitStatus::Failure);
     }
 
-    run_daemon(c, reload_handle).await
+    run_daemon(c, cursor, reload_handle).await
 }
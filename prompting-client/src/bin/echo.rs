@@ -9,13 +9,20 @@ struct Args {
     /// Optionally record events to a specified file on Ctrl-C
     #[clap(short, long, value_name = "FILE")]
     record: Option<String>,
+
+    /// Optionally record all raw traffic with snapd to a specified cassette file
+    #[clap(short, long, value_name = "FILE")]
+    cassette: Option<String>,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let Args { record } = Args::parse();
+    let Args { record, cassette } = Args::parse();
     let mut c = SnapdSocketClient::new().await;
     c.exit_if_prompting_not_enabled().await?;
 
-    run_echo_loop(&mut c, record).await
+    match cassette {
+        Some(path) => run_echo_loop(&mut c.record_to(path)?, record).await,
+        None => run_echo_loop(&mut c, record).await,
+    }
 }
@@ -0,0 +1,31 @@
+//! This is a helper command for inspecting the prompts that have recently been handled by the
+//! daemon while it is running. Only a bounded number of prompts are held in memory (configured
+//! via `max-recent-prompts` in the daemon config file) so for a full history the audit log should
+//! be used instead.
+use clap::Parser;
+use prompting_client::cli_actions::list_recent_prompts;
+use std::process::exit;
+
+/// List the prompts most recently handled by a running instance of the prompting client daemon.
+///
+/// Prompts are printed one per line, most recent first, along with how they were resolved.
+#[derive(Debug, Parser)]
+#[clap(about, long_about = None)]
+struct Args {}
+
+#[tokio::main]
+async fn main() {
+    let Args {} = Args::parse();
+
+    match list_recent_prompts().await {
+        Ok(lines) => {
+            for line in lines {
+                println!("{line}");
+            }
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            exit(1);
+        }
+    }
+}
@@ -1,17 +1,17 @@
 use crate::{
     daemon::{PollLoop, PromptUpdate},
     recording::PromptRecording,
-    snapd_client::{PromptId, SnapdSocketClient, TypedPrompt},
+    snapd_client::{Client, PromptId, SnapdClient, TypedPrompt},
     Result,
 };
 use tokio::sync::mpsc::unbounded_channel;
 use tracing::info;
 
 /// A simple echo loop that prints out the prompts seen when polling for notices
-pub async fn run_echo_loop(
-    snapd_client: &mut SnapdSocketClient,
-    path: Option<String>,
-) -> Result<()> {
+pub async fn run_echo_loop<C>(snapd_client: &mut SnapdClient<C>, path: Option<String>) -> Result<()>
+where
+    C: Client + Clone + 'static,
+{
     let (tx_prompts, mut rx_prompts) = unbounded_channel();
     let mut rec = PromptRecording::new(path);
 
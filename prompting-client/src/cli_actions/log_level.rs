@@ -1,9 +1,4 @@
-use crate::{
-    protos::apparmor_prompting::app_armor_prompting_client::AppArmorPromptingClient, Error, Result,
-    SOCKET_ENV_VAR,
-};
-use std::env;
-use tonic::transport::Channel;
+use crate::{cli_actions::client_from_env, Error, Result};
 
 pub async fn set_logging_filter(filter: String) -> Result<String> {
     let mut client = client_from_env().await;
@@ -15,10 +10,3 @@ pub async fn set_logging_filter(filter: String) -> Result<String> {
         }),
     }
 }
-
-async fn client_from_env() -> AppArmorPromptingClient<Channel> {
-    let path = env::var(SOCKET_ENV_VAR).expect("socket env var not set");
-    AppArmorPromptingClient::connect(format!("unix://{path}"))
-        .await
-        .unwrap()
-}
@@ -1,7 +1,22 @@
+use crate::{
+    protos::apparmor_prompting::app_armor_prompting_client::AppArmorPromptingClient, SOCKET_ENV_VAR,
+};
+use std::env;
+use tonic::transport::Channel;
+
 mod echo_loop;
 mod log_level;
+mod recent;
 mod scripted;
 
 pub use echo_loop::run_echo_loop;
 pub use log_level::set_logging_filter;
+pub use recent::list_recent_prompts;
 pub use scripted::ScriptedClient;
+
+async fn client_from_env() -> AppArmorPromptingClient<Channel> {
+    let path = env::var(SOCKET_ENV_VAR).expect("socket env var not set");
+    AppArmorPromptingClient::connect(format!("unix://{path}"))
+        .await
+        .unwrap()
+}
@@ -0,0 +1,95 @@
+use crate::{
+    cli_actions::client_from_env,
+    protos::apparmor_prompting::{
+        recent_prompt::Outcome, Action, RecentPrompt, RecentPromptReply, RuleLifespan,
+    },
+    Error, Result,
+};
+
+/// Fetch the prompts most recently handled by the daemon, formatted one per line with the most
+/// recent first.
+pub async fn list_recent_prompts() -> Result<Vec<String>> {
+    let mut client = client_from_env().await;
+
+    match client.list_recent_prompts(()).await {
+        Ok(resp) => Ok(resp
+            .into_inner()
+            .prompts
+            .iter()
+            .map(format_prompt)
+            .collect()),
+        Err(e) => Err(Error::UnableToListRecentPrompts {
+            reason: e.to_string(),
+        }),
+    }
+}
+
+fn format_prompt(p: &RecentPrompt) -> String {
+    let outcome = match &p.outcome {
+        Some(Outcome::Replied(reply)) => format!("replied {}", format_reply(reply)),
+        Some(Outcome::Policy(reply)) => format!("policy {}", format_reply(reply)),
+        Some(Outcome::Error(error)) => format!("error ({error})"),
+        Some(Outcome::Timeout(_)) => "timeout".to_string(),
+        Some(Outcome::NotFound(_)) => "not-found".to_string(),
+        Some(Outcome::ResolvedBy(id)) => format!("resolved-by {id}"),
+        Some(Outcome::Cancelled(_)) => "cancelled".to_string(),
+        Some(Outcome::ProcessExited(_)) => "process-exited".to_string(),
+        None => "unknown".to_string(),
+    };
+
+    format!(
+        "{} id={} snap={} interface={} outcome={outcome}",
+        p.timestamp, p.prompt_id, p.snap, p.interface
+    )
+}
+
+fn format_reply(reply: &RecentPromptReply) -> String {
+    let action = match reply.action() {
+        Action::Allow => "allow",
+        Action::Deny => "deny",
+    };
+    let lifespan = match reply.lifespan() {
+        RuleLifespan::Single => "single",
+        RuleLifespan::Session => "session",
+        RuleLifespan::Forever => "forever",
+        RuleLifespan::Timespan => "timespan",
+    };
+
+    format!("{action}/{lifespan}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    fn recent_prompt(outcome: Outcome) -> RecentPrompt {
+        RecentPrompt {
+            prompt_id: "1".to_string(),
+            timestamp: "2024-01-01T00:00:00+00:00".to_string(),
+            snap: "firefox".to_string(),
+            interface: "home".to_string(),
+            outcome: Some(outcome),
+        }
+    }
+
+    #[test_case(
+        Outcome::Replied(RecentPromptReply { action: Action::Allow as i32, lifespan: RuleLifespan::Forever as i32 }),
+        "replied allow/forever";
+        "replied"
+    )]
+    #[test_case(Outcome::Timeout(()), "timeout"; "timeout")]
+    #[test_case(Outcome::ResolvedBy("2".to_string()), "resolved-by 2"; "resolved by")]
+    #[test_case(Outcome::ProcessExited(()), "process-exited"; "process exited")]
+    #[test]
+    fn prompts_are_formatted(outcome: Outcome, expected: &str) {
+        let s = format_prompt(&recent_prompt(outcome));
+
+        assert_eq!(
+            s,
+            format!(
+                "2024-01-01T00:00:00+00:00 id=1 snap=firefox interface=home outcome={expected}"
+            )
+        );
+    }
+}
@@ -6,7 +6,7 @@ use crate::{
             home::{HomeConstraintsFilter, HomeInterface},
             SnapInterface,
         },
-        Action, PromptId, PromptNotice, SnapdSocketClient, TypedPrompt, TypedPromptReply,
+        Action, Client, PromptId, PromptNotice, SnapdClient, TypedPrompt, TypedPromptReply,
     },
     Error, Result, SNAP_NAME,
 };
@@ -17,7 +17,7 @@ use tracing::{debug, error, info, warn};
 
 /// Poll for outstanding prompts and auto-deny them before returning an error. This function will
 /// loop until at least one un-actioned prompt is encountered.
-async fn grace_period_deny_and_error(snapd_client: &mut SnapdSocketClient) -> Result<()> {
+async fn grace_period_deny_and_error<C: Client>(snapd_client: &mut SnapdClient<C>) -> Result<()> {
     loop {
         let notices = snapd_client.pending_prompt_notices().await?;
         let mut prompts = Vec::with_capacity(notices.len());
@@ -59,11 +59,14 @@ pub struct ScriptedClient {
 }
 
 impl ScriptedClient {
-    pub fn try_new(
+    pub fn try_new<C>(
         path: String,
         vars: &[(&str, &str)],
-        mut snapd_client: SnapdSocketClient,
-    ) -> Result<Self> {
+        mut snapd_client: SnapdClient<C>,
+    ) -> Result<Self>
+    where
+        C: Client + 'static,
+    {
         // We need to spawn a task to wait for the read prompt we generate when reading in our
         // script file. We can't handle this in the main poll loop as we need to construct the
         // client up front.
@@ -109,17 +112,27 @@ impl ScriptedClient {
         Ok(Self { seq, raw_seq, path })
     }
 
+    /// Send replies to snapd without first checking them locally, so that invalid replies are
+    /// rejected by snapd itself.
+    pub fn without_reply_validation(mut self) -> Self {
+        self.seq.skip_reply_validation();
+        self
+    }
+
     pub fn raw_seq(&self) -> &str {
         &self.raw_seq
     }
 
     /// Run a scripted client that actions prompts based on a predefined sequence of prompts that we
     /// expect to see.
-    pub async fn run(
+    pub async fn run<C>(
         &mut self,
-        snapd_client: &mut SnapdSocketClient,
+        snapd_client: &mut SnapdClient<C>,
         grace_period: Option<u64>,
-    ) -> Result<()> {
+    ) -> Result<()>
+    where
+        C: Client + Clone + 'static,
+    {
         let (tx_prompts, mut rx_prompts) = unbounded_channel();
 
         info!("starting poll loop");
@@ -186,10 +199,10 @@ impl ScriptedClient {
         !self.seq.is_empty()
     }
 
-    async fn reply(
+    async fn reply<C: Client>(
         &mut self,
         EnrichedPrompt { prompt, .. }: EnrichedPrompt,
-        snapd_client: &mut SnapdSocketClient,
+        snapd_client: &mut SnapdClient<C>,
     ) -> Result<()> {
         let mut reply = match self.reply_for_prompt(prompt.clone(), None).await? {
             Some(reply) => reply,
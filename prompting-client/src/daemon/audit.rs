@@ -0,0 +1,294 @@
+//! An append only log of the prompts handled by the daemon and how they were resolved.
+//!
+//! Entries are written as JSON lines to `$XDG_STATE_HOME/prompting-client/audit.jsonl` (falling
+//! back to `$HOME/.local/state` if `XDG_STATE_HOME` is not set). Once the log reaches its
+//! maximum size it is rotated to `audit.jsonl.1`, with older logs being shifted up until the
+//! maximum number of rotated logs is reached.
+//!
+//! The daemon writes to the log via an [AuditWriter] so that the file system access happens on
+//! the blocking thread pool rather than stalling the worker.
+use crate::{
+    daemon::{EnrichedPrompt, UiFallback},
+    snapd_client::{PromptId, TypedPrompt, TypedPromptReply},
+};
+use chrono::Utc;
+use serde::Serialize;
+use std::{
+    env,
+    ffi::OsString,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedSender},
+    task::{spawn_blocking, JoinHandle},
+};
+use tracing::{debug, error};
+
+const STATE_DIR: &str = "prompting-client";
+const AUDIT_FILE: &str = "audit.jsonl";
+
+const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+const DEFAULT_MAX_ROTATED_FILES: usize = 5;
+
+/// The default location of the audit log for the current user.
+pub fn default_audit_log_path() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local/state"),
+    };
+
+    Some(base.join(STATE_DIR).join(AUDIT_FILE))
+}
+
+/// How a prompt was ultimately resolved.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "outcome", rename_all = "kebab-case")]
+pub enum AuditOutcome {
+    /// The user replied to the prompt via the UI.
+    Replied { reply: TypedPromptReply },
+    /// The prompt matched a rule in the daemon policy.
+    Policy { reply: TypedPromptReply },
+    /// We were unable to show the prompt to the user so we replied with deny once.
+    Error { error: String },
+    /// We timed out waiting for the UI to reply so we applied the fallback configured for the
+    /// interface. Prompts that are requeued are only recorded once they are resolved, so the
+    /// fallback here is always one that replied to snapd.
+    Timeout { fallback: UiFallback },
+    /// Snapd reported that the prompt no longer exists when the user replied.
+    NotFound,
+    /// The prompt was resolved by the reply to another prompt.
+    ResolvedBy { by: PromptId },
+    /// Snapd reported that the prompt was resolved before we were able to reply to it.
+    Cancelled,
+    /// The process that triggered the prompt exited before we were able to show it to the user so
+    /// we replied with deny once.
+    ProcessExited,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct AuditEntry<'a> {
+    timestamp: String,
+    prompt: &'a TypedPrompt,
+    #[serde(flatten)]
+    outcome: &'a AuditOutcome,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+    max_bytes: u64,
+    max_rotated_files: usize,
+}
+
+impl AuditLog {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            max_bytes: DEFAULT_MAX_BYTES,
+            max_rotated_files: DEFAULT_MAX_ROTATED_FILES,
+        }
+    }
+
+    pub fn with_limits(mut self, max_bytes: u64, max_rotated_files: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self.max_rotated_files = max_rotated_files;
+        self
+    }
+
+    /// Record the outcome for a given prompt.
+    ///
+    /// Failing to write to the audit log should not prevent us from handling prompts so any
+    /// errors are logged rather than returned.
+    pub fn record(&self, enriched_prompt: &EnrichedPrompt, outcome: AuditOutcome) {
+        if let Some(line) = entry_line(enriched_prompt, &outcome) {
+            self.write_line(&line);
+        }
+    }
+
+    /// Move this log onto the blocking thread pool, returning a handle for recording outcomes
+    /// along with the task writing them. Entries are written in the order they are recorded and
+    /// the task exits once every [AuditWriter] has been dropped.
+    pub fn spawn_writer(self) -> (AuditWriter, JoinHandle<()>) {
+        let (tx, mut rx) = unbounded_channel::<Vec<u8>>();
+        let task = spawn_blocking(move || {
+            while let Some(line) = rx.blocking_recv() {
+                self.write_line(&line);
+            }
+        });
+
+        (AuditWriter { tx }, task)
+    }
+
+    fn write_line(&self, line: &[u8]) {
+        match self.append(line) {
+            Ok(()) => debug!("audit log entry written"),
+            Err(e) => error!(path=%self.path.display(), "unable to write to audit log: {e}"),
+        }
+    }
+
+    fn append(&self, line: &[u8]) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let current_len = match fs::metadata(&self.path) {
+            Ok(meta) => meta.len(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e),
+        };
+
+        if current_len > 0 && current_len + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        let mut f = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
+        f.write_all(line)
+    }
+
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut s = OsString::from(self.path.as_os_str());
+        s.push(format!(".{n}"));
+
+        PathBuf::from(s)
+    }
+
+    fn rotate(&self) -> io::Result<()> {
+        if self.max_rotated_files == 0 {
+            return fs::remove_file(&self.path);
+        }
+
+        for n in (1..self.max_rotated_files).rev() {
+            let from = self.rotated_path(n);
+            if from.exists() {
+                fs::rename(from, self.rotated_path(n + 1))?;
+            }
+        }
+
+        fs::rename(&self.path, self.rotated_path(1))
+    }
+}
+
+/// A handle for recording outcomes to an [AuditLog] that is being written to by a background
+/// task.
+#[derive(Debug, Clone)]
+pub struct AuditWriter {
+    tx: UnboundedSender<Vec<u8>>,
+}
+
+impl AuditWriter {
+    /// Record the outcome for a given prompt. As with [AuditLog::record], errors are logged
+    /// rather than returned.
+    pub fn record(&self, enriched_prompt: &EnrichedPrompt, outcome: AuditOutcome) {
+        let Some(line) = entry_line(enriched_prompt, &outcome) else {
+            return;
+        };
+
+        if self.tx.send(line).is_err() {
+            error!(id=%enriched_prompt.prompt.id().0, "audit log writer has exited");
+        }
+    }
+}
+
+/// Serialize an entry for the log, timestamped with the current time.
+fn entry_line(enriched_prompt: &EnrichedPrompt, outcome: &AuditOutcome) -> Option<Vec<u8>> {
+    let entry = AuditEntry {
+        timestamp: Utc::now().to_rfc3339(),
+        prompt: &enriched_prompt.prompt,
+        outcome,
+    };
+
+    match serde_json::to_vec(&entry) {
+        Ok(mut line) => {
+            line.push(b'\n');
+            Some(line)
+        }
+        Err(e) => {
+            error!(id=%enriched_prompt.prompt.id().0, "unable to serialize audit log entry: {e}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon::enriched_home_prompt;
+    use serde_json::Value;
+    use uuid::Uuid;
+
+    fn read_lines(path: &PathBuf) -> Vec<Value> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn entries_are_appended_as_json_lines() {
+        let dir = env::temp_dir().join(Uuid::new_v4().to_string());
+        let path = dir.join(AUDIT_FILE);
+        let log = AuditLog::new(path.clone());
+
+        log.record(
+            &enriched_home_prompt("1", "cgroup_0"),
+            AuditOutcome::Timeout {
+                fallback: UiFallback::DenyOnce,
+            },
+        );
+        log.record(
+            &enriched_home_prompt("2", "cgroup_0"),
+            AuditOutcome::ResolvedBy {
+                by: PromptId("1".to_string()),
+            },
+        );
+
+        let lines = read_lines(&path);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["outcome"], "timeout");
+        assert_eq!(lines[0]["fallback"], "deny-once");
+        assert_eq!(lines[0]["prompt"]["id"], "1");
+        assert_eq!(lines[0]["prompt"]["snap"], "test");
+        assert_eq!(lines[1]["outcome"], "resolved-by");
+        assert_eq!(lines[1]["by"], "1");
+        assert_eq!(lines[1]["prompt"]["id"], "2");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn logs_are_rotated_when_full() {
+        let dir = env::temp_dir().join(Uuid::new_v4().to_string());
+        let path = dir.join(AUDIT_FILE);
+        // Small enough that every entry triggers a rotation
+        let log = AuditLog::new(path.clone()).with_limits(10, 2);
+
+        for id in ["1", "2", "3", "4"] {
+            log.record(
+                &enriched_home_prompt(id, "cgroup_0"),
+                AuditOutcome::NotFound,
+            );
+        }
+
+        let ids = |p: &PathBuf| -> Vec<String> {
+            read_lines(p)
+                .iter()
+                .map(|v| v["prompt"]["id"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        assert_eq!(ids(&path), vec!["4"]);
+        assert_eq!(ids(&log.rotated_path(1)), vec!["3"]);
+        assert_eq!(ids(&log.rotated_path(2)), vec!["2"]);
+        assert!(!log.rotated_path(3).exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
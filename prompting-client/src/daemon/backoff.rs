@@ -0,0 +1,148 @@
+//! Jittered exponential backoff for reconnecting to snapd.
+//!
+//! When snapd is restarted (for example during a refresh of the snapd snap) the socket goes away
+//! for a short period of time. Rather than hammering the socket or giving up after a fixed number
+//! of attempts, we back off exponentially (with jitter so that all of the per-user daemons on a
+//! system do not reconnect in lock step) until we either succeed or the total time spent failing
+//! exceeds the configured maximum outage.
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::{Duration, Instant},
+};
+
+const BASE_DELAY: Duration = Duration::from_millis(200);
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Default)]
+pub struct Backoff {
+    attempts: u32,
+    started: Option<Instant>,
+}
+
+impl Backoff {
+    /// Whether or not we are currently backing off following a failure.
+    pub fn is_failing(&self) -> bool {
+        self.started.is_some()
+    }
+
+    /// How long we have been failing for.
+    pub fn outage(&self) -> Duration {
+        self.started.map(|t| t.elapsed()).unwrap_or_default()
+    }
+
+    /// Clear any recorded failures following a successful request.
+    pub fn reset(&mut self) {
+        self.attempts = 0;
+        self.started = None;
+    }
+
+    /// Record a failure and return how long to wait before trying again, or `None` if we have
+    /// been failing for longer than `max_outage`.
+    pub fn next_delay(&mut self, max_outage: Duration) -> Option<Duration> {
+        self.next_delay_at(Instant::now(), max_outage, jitter())
+    }
+
+    fn next_delay_at(
+        &mut self,
+        now: Instant,
+        max_outage: Duration,
+        jitter: f64,
+    ) -> Option<Duration> {
+        let started = *self.started.get_or_insert(now);
+        let elapsed = now.saturating_duration_since(started);
+        if elapsed >= max_outage {
+            return None;
+        }
+
+        let exp = BASE_DELAY.saturating_mul(2u32.saturating_pow(self.attempts));
+        let delay = exp.min(MAX_DELAY);
+        self.attempts = self.attempts.saturating_add(1);
+
+        // Half of the delay is fixed and the other half is random
+        let delay = delay / 2 + delay.mul_f64(jitter / 2.0);
+
+        // Never sleep past the end of the outage window
+        Some(delay.min(max_outage - elapsed))
+    }
+}
+
+/// A value in the range [0, 1) derived from the randomly seeded std hasher.
+fn jitter() -> f64 {
+    let n = RandomState::new().build_hasher().finish();
+
+    (n >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    const MAX_OUTAGE: Duration = Duration::from_secs(300);
+
+    #[test_case(0.0, &[100, 200, 400, 800, 1600]; "no jitter")]
+    #[test_case(1.0, &[200, 400, 800, 1600, 3200]; "full jitter")]
+    #[test]
+    fn delays_grow_exponentially(jitter: f64, expected_ms: &[u64]) {
+        let mut b = Backoff::default();
+        let now = Instant::now();
+
+        let delays: Vec<_> = expected_ms
+            .iter()
+            .map(|_| b.next_delay_at(now, MAX_OUTAGE, jitter).unwrap())
+            .collect();
+        let expected: Vec<_> = expected_ms
+            .iter()
+            .map(|&ms| Duration::from_millis(ms))
+            .collect();
+
+        assert_eq!(delays, expected);
+    }
+
+    #[test]
+    fn delays_are_capped() {
+        let mut b = Backoff::default();
+        let now = Instant::now();
+
+        for _ in 0..100 {
+            let delay = b.next_delay_at(now, MAX_OUTAGE, 1.0).unwrap();
+            assert!(delay <= MAX_DELAY);
+        }
+    }
+
+    #[test]
+    fn outage_window_is_bounded() {
+        let mut b = Backoff::default();
+        let now = Instant::now();
+
+        b.next_delay_at(now, MAX_OUTAGE, 0.5).unwrap();
+        let delay = b
+            .next_delay_at(
+                now + MAX_OUTAGE - Duration::from_millis(10),
+                MAX_OUTAGE,
+                1.0,
+            )
+            .unwrap();
+        assert_eq!(delay, Duration::from_millis(10));
+
+        assert_eq!(b.next_delay_at(now + MAX_OUTAGE, MAX_OUTAGE, 0.5), None);
+    }
+
+    #[test]
+    fn reset_clears_failures() {
+        let mut b = Backoff::default();
+        let now = Instant::now();
+
+        b.next_delay_at(now, MAX_OUTAGE, 0.0).unwrap();
+        b.next_delay_at(now, MAX_OUTAGE, 0.0).unwrap();
+        assert!(b.is_failing());
+
+        b.reset();
+        assert!(!b.is_failing());
+        let delay = b
+            .next_delay_at(now + MAX_OUTAGE * 2, MAX_OUTAGE, 0.0)
+            .unwrap();
+        assert_eq!(delay, BASE_DELAY / 2);
+    }
+}
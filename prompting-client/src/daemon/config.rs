@@ -0,0 +1,564 @@
+//! Runtime configuration for the daemon.
+//!
+//! Configuration is read from `$XDG_CONFIG_HOME/prompting-client/config.toml` (falling back to
+//! `$HOME/.config` if `XDG_CONFIG_HOME` is not set) when the daemon starts and can be reloaded
+//! either by sending the daemon a SIGHUP or via the `ReloadConfig` GRPC endpoint. A missing
+//! config file is not an error: all fields are optional and have defaults matching the behaviour
+//! of the daemon without a config file.
+//!
+//! ```toml
+//! log-level = "debug"
+//! policy-file = "/etc/prompting-client/policy.json"
+//! recv-timeout-ms = 500
+//! max-outage-secs = 600
+//! max-recent-prompts = 100
+//!
+//! # Prompts that the UI is not able to show by default
+//! experimental-prompts = ["generic", "removable-media", "files", "device"]
+//!
+//! # How long to wait for a reply from the UI after it exits and what to do if we don't get one
+//! [interfaces.home]
+//! recv-timeout-ms = 1000
+//! fallback = "requeue"
+//! ```
+//!
+//! New config is fully validated before it replaces the current config, so an invalid file on
+//! reload leaves the daemon running with its previous config.
+use crate::{
+    daemon::{server::SetLogFilter, Policy},
+    log_filter,
+    snapd_client::{
+        interfaces::{
+            camera::CameraInterface,
+            files::{PersonalFilesInterface, SystemFilesInterface},
+            home::HomeInterface,
+            microphone::MicrophoneInterface,
+            removable_media::RemovableMediaInterface,
+            SnapInterface,
+        },
+        TypedPrompt,
+    },
+    Error, Result, POLICY_ENV_VAR,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::watch;
+use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
+
+const CONFIG_DIR: &str = "prompting-client";
+const CONFIG_FILE: &str = "config.toml";
+
+const DEFAULT_RECV_TIMEOUT_MS: u64 = 200;
+const DEFAULT_MAX_OUTAGE_SECS: u64 = 300;
+const DEFAULT_MAX_RECENT_PROMPTS: usize = 50;
+
+/// The default location of the config file for the current user.
+pub fn default_config_path() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(base.join(CONFIG_DIR).join(CONFIG_FILE))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+struct RawConfig {
+    log_level: Option<String>,
+    policy_file: Option<PathBuf>,
+    recv_timeout_ms: u64,
+    max_outage_secs: u64,
+    max_recent_prompts: usize,
+    experimental_prompts: Vec<ExperimentalPrompt>,
+    interfaces: HashMap<String, RawInterfaceConfig>,
+}
+
+impl Default for RawConfig {
+    fn default() -> Self {
+        Self {
+            log_level: None,
+            policy_file: None,
+            recv_timeout_ms: DEFAULT_RECV_TIMEOUT_MS,
+            max_outage_secs: DEFAULT_MAX_OUTAGE_SECS,
+            max_recent_prompts: DEFAULT_MAX_RECENT_PROMPTS,
+            experimental_prompts: Vec::new(),
+            interfaces: HashMap::new(),
+        }
+    }
+}
+
+/// Prompts that the daemon is able to handle but that the Flutter UI is not yet able to show.
+///
+/// Unless enabled in the config (or noted otherwise below), these are replied to with deny once if
+/// they do not match a policy rule rather than being shown to the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExperimentalPrompt {
+    /// Prompts for interfaces without dedicated support, offering only allow or deny once.
+    Generic,
+    /// Prompts for files on removable media.
+    RemovableMedia,
+    /// Prompts for the personal-files and system-files interfaces.
+    Files,
+    /// Camera and microphone prompts from the same app presented as a single device prompt.
+    /// When disabled they are shown one after the other.
+    Device,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+struct RawInterfaceConfig {
+    recv_timeout_ms: Option<u64>,
+    fallback: UiFallback,
+}
+
+/// What to do when the UI exits without the user having replied to the prompt it was showing.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UiFallback {
+    /// Deny this request only.
+    #[default]
+    DenyOnce,
+    /// Deny this and all matching requests for the remainder of the user's session.
+    DenySession,
+    /// Show the prompt to the user again.
+    Requeue,
+}
+
+/// Per-interface settings for how we handle the UI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterfaceConfig {
+    recv_timeout: Duration,
+    fallback: UiFallback,
+}
+
+impl InterfaceConfig {
+    pub fn recv_timeout(&self) -> Duration {
+        self.recv_timeout
+    }
+
+    pub fn fallback(&self) -> UiFallback {
+        self.fallback
+    }
+}
+
+/// Validated daemon configuration.
+#[derive(Debug, Clone)]
+pub struct Config {
+    log_level: Option<String>,
+    policy: Arc<Policy>,
+    recv_timeout: Duration,
+    max_outage: Duration,
+    max_recent_prompts: usize,
+    experimental_prompts: Vec<ExperimentalPrompt>,
+    interfaces: HashMap<String, InterfaceConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            log_level: None,
+            policy: Arc::new(Policy::default()),
+            recv_timeout: Duration::from_millis(DEFAULT_RECV_TIMEOUT_MS),
+            max_outage: Duration::from_secs(DEFAULT_MAX_OUTAGE_SECS),
+            max_recent_prompts: DEFAULT_MAX_RECENT_PROMPTS,
+            experimental_prompts: Vec::new(),
+            interfaces: HashMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Load and validate the config file at the given path, returning the default config if the
+    /// file does not exist.
+    pub fn try_load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(content) => Self::try_new_from_string(&content),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                Self::try_from_raw(RawConfig::default())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn try_new_from_string(content: &str) -> Result<Self> {
+        let raw: RawConfig = toml::from_str(content).map_err(|e| Error::InvalidConfig {
+            reason: e.to_string(),
+        })?;
+
+        Self::try_from_raw(raw)
+    }
+
+    fn try_from_raw(raw: RawConfig) -> Result<Self> {
+        if let Some(level) = &raw.log_level {
+            EnvFilter::try_new(log_filter(level)).map_err(|_| Error::InvalidConfig {
+                reason: format!("{level:?} is not a valid logging filter"),
+            })?;
+        }
+
+        if raw.recv_timeout_ms == 0 {
+            return Err(Error::InvalidConfig {
+                reason: "recv-timeout-ms must be greater than zero".to_string(),
+            });
+        }
+
+        if raw.max_outage_secs == 0 {
+            return Err(Error::InvalidConfig {
+                reason: "max-outage-secs must be greater than zero".to_string(),
+            });
+        }
+
+        let supported = [
+            CameraInterface::NAME,
+            HomeInterface::NAME,
+            MicrophoneInterface::NAME,
+            PersonalFilesInterface::NAME,
+            RemovableMediaInterface::NAME,
+            SystemFilesInterface::NAME,
+        ];
+        let mut interfaces = HashMap::with_capacity(raw.interfaces.len());
+        for (name, iface) in raw.interfaces {
+            if !supported.contains(&name.as_str()) {
+                return Err(Error::InvalidConfig {
+                    reason: format!("{name} is not currently supported for apparmor prompting"),
+                });
+            }
+
+            let recv_timeout_ms = iface.recv_timeout_ms.unwrap_or(raw.recv_timeout_ms);
+            if recv_timeout_ms == 0 {
+                return Err(Error::InvalidConfig {
+                    reason: format!("interfaces.{name}.recv-timeout-ms must be greater than zero"),
+                });
+            }
+
+            let config = InterfaceConfig {
+                recv_timeout: Duration::from_millis(recv_timeout_ms),
+                fallback: iface.fallback,
+            };
+            interfaces.insert(name, config);
+        }
+
+        // The policy file location can also be provided via an environment variable for
+        // deployments that are not using a config file.
+        let policy_file = raw
+            .policy_file
+            .or_else(|| env::var_os(POLICY_ENV_VAR).map(PathBuf::from));
+        let policy = match policy_file {
+            Some(path) => {
+                let path = path.to_string_lossy();
+                Policy::try_new_from_file(&path).map_err(|e| Error::InvalidConfig {
+                    reason: format!("unable to load policy from {path}: {e}"),
+                })?
+            }
+            None => Policy::default(),
+        };
+
+        Ok(Self {
+            log_level: raw.log_level,
+            policy: Arc::new(policy),
+            recv_timeout: Duration::from_millis(raw.recv_timeout_ms),
+            max_outage: Duration::from_secs(raw.max_outage_secs),
+            max_recent_prompts: raw.max_recent_prompts,
+            experimental_prompts: raw.experimental_prompts,
+            interfaces,
+        })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = Arc::new(policy);
+        self
+    }
+
+    #[cfg(test)]
+    pub(crate) fn with_recv_timeout(mut self, recv_timeout: Duration) -> Self {
+        self.recv_timeout = recv_timeout;
+        self
+    }
+
+    #[cfg(test)]
+    pub(crate) fn with_experimental_prompt(mut self, prompt: ExperimentalPrompt) -> Self {
+        self.experimental_prompts.push(prompt);
+        self
+    }
+
+    pub fn log_level(&self) -> Option<&str> {
+        self.log_level.as_deref()
+    }
+
+    pub fn policy(&self) -> &Policy {
+        &self.policy
+    }
+
+    pub fn recv_timeout(&self) -> Duration {
+        self.recv_timeout
+    }
+
+    /// The UI settings for the given interface, falling back to the top level settings if the
+    /// interface has no config of its own.
+    pub fn interface(&self, interface: &str) -> InterfaceConfig {
+        self.interfaces
+            .get(interface)
+            .copied()
+            .unwrap_or(InterfaceConfig {
+                recv_timeout: self.recv_timeout,
+                fallback: UiFallback::default(),
+            })
+    }
+
+    /// How long we keep trying to reach snapd before exiting.
+    pub fn max_outage(&self) -> Duration {
+        self.max_outage
+    }
+
+    /// The number of recently handled prompts to keep in memory for `ListRecentPrompts`.
+    pub fn max_recent_prompts(&self) -> usize {
+        self.max_recent_prompts
+    }
+
+    /// Whether the given prompt can be shown to the user, which is only the case for prompts the
+    /// UI does not yet support if they have been enabled under `experimental-prompts`.
+    pub fn ui_enabled_for(&self, prompt: &TypedPrompt) -> bool {
+        let required = match prompt {
+            TypedPrompt::Generic(_) => ExperimentalPrompt::Generic,
+            TypedPrompt::RemovableMedia(_) => ExperimentalPrompt::RemovableMedia,
+            TypedPrompt::PersonalFiles(_) | TypedPrompt::SystemFiles(_) => {
+                ExperimentalPrompt::Files
+            }
+            _ => return true,
+        };
+
+        self.experimental_prompt_enabled(required)
+    }
+
+    pub fn experimental_prompt_enabled(&self, prompt: ExperimentalPrompt) -> bool {
+        self.experimental_prompts.contains(&prompt)
+    }
+}
+
+/// A read only view of the current daemon config that is updated when the config is reloaded.
+pub type RefConfig = watch::Receiver<Arc<Config>>;
+
+/// Loads the daemon config and notifies all [RefConfig] holders when it changes.
+#[derive(Debug, Clone)]
+pub struct ConfigReloader {
+    path: Option<PathBuf>,
+    tx: Arc<watch::Sender<Arc<Config>>>,
+}
+
+impl ConfigReloader {
+    /// Load the initial config from the given path.
+    ///
+    /// An invalid config is an error rather than falling back to the defaults, as doing so would
+    /// silently drop any policy rules the user was relying on.
+    pub fn try_new(path: Option<PathBuf>) -> Result<Self> {
+        let config = match &path {
+            Some(path) => Config::try_load(path)?,
+            None => {
+                warn!("unable to determine config path, using defaults");
+                Config::default()
+            }
+        };
+        let (tx, _) = watch::channel(Arc::new(config));
+
+        Ok(Self {
+            path,
+            tx: Arc::new(tx),
+        })
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn subscribe(&self) -> RefConfig {
+        self.tx.subscribe()
+    }
+
+    pub fn current(&self) -> Arc<Config> {
+        self.tx.borrow().clone()
+    }
+
+    /// Apply any settings from the current config that are not read directly from [RefConfig].
+    pub fn apply(&self, log_handle: &impl SetLogFilter) -> Result<()> {
+        match self.current().log_level() {
+            Some(level) => log_handle.set_filter(&log_filter(level)),
+            None => Ok(()),
+        }
+    }
+
+    /// Reload the config from disk, replacing the current config only if the new one is valid.
+    pub fn reload(&self, log_handle: &impl SetLogFilter) -> Result<Arc<Config>> {
+        let path = self.path.as_deref().ok_or_else(|| Error::InvalidConfig {
+            reason: "unable to determine config path".to_string(),
+        })?;
+
+        let config = Config::try_load(path)
+            .inspect_err(|e| warn!(path=%path.display(), "not reloading invalid config: {e}"))?;
+        self.tx.send_replace(Arc::new(config));
+        self.apply(log_handle)?;
+        info!(path=%path.display(), "reloaded config");
+
+        Ok(self.current())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+    use std::sync::Mutex;
+    use uuid::Uuid;
+
+    #[derive(Debug, Default)]
+    struct RecordingLogHandle(Mutex<Vec<String>>);
+
+    impl SetLogFilter for RecordingLogHandle {
+        fn set_filter(&self, filter: &str) -> Result<()> {
+            self.0.lock().unwrap().push(filter.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn empty_config_uses_defaults() {
+        let config = Config::try_new_from_string("").unwrap();
+
+        assert_eq!(config.log_level(), None);
+        assert!(config.policy().is_empty());
+        assert_eq!(config.recv_timeout(), Duration::from_millis(200));
+        assert_eq!(config.max_outage(), Duration::from_secs(300));
+        assert_eq!(config.max_recent_prompts(), 50);
+    }
+
+    #[test]
+    fn all_fields_are_parsed() {
+        let config = Config::try_new_from_string(
+            r#"
+            log-level = "debug"
+            recv-timeout-ms = 500
+            max-outage-secs = 60
+            max-recent-prompts = 10
+            experimental-prompts = ["generic", "removable-media", "files", "device"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.log_level(), Some("debug"));
+        assert_eq!(config.recv_timeout(), Duration::from_millis(500));
+        assert_eq!(config.max_outage(), Duration::from_secs(60));
+        assert_eq!(config.max_recent_prompts(), 10);
+        assert_eq!(
+            config.experimental_prompts,
+            vec![
+                ExperimentalPrompt::Generic,
+                ExperimentalPrompt::RemovableMedia,
+                ExperimentalPrompt::Files,
+                ExperimentalPrompt::Device,
+            ]
+        );
+    }
+
+    #[test]
+    fn interface_config_falls_back_to_top_level() {
+        let config = Config::try_new_from_string(
+            r#"
+            recv-timeout-ms = 300
+
+            [interfaces.home]
+            fallback = "requeue"
+
+            [interfaces.camera]
+            recv-timeout-ms = 1000
+            fallback = "deny-session"
+            "#,
+        )
+        .unwrap();
+
+        let expected = [
+            ("home", 300, UiFallback::Requeue),
+            ("camera", 1000, UiFallback::DenySession),
+            ("audio-record", 300, UiFallback::DenyOnce),
+        ];
+
+        for (interface, recv_timeout_ms, fallback) in expected {
+            let iface = config.interface(interface);
+            assert_eq!(
+                iface.recv_timeout(),
+                Duration::from_millis(recv_timeout_ms),
+                "{interface}"
+            );
+            assert_eq!(iface.fallback(), fallback, "{interface}");
+        }
+    }
+
+    #[test_case("recv-timeout-ms = 0"; "zero recv timeout")]
+    #[test_case("recv-timeout-ms = -1"; "negative recv timeout")]
+    #[test_case("max-outage-secs = 0"; "zero max outage")]
+    #[test_case("log-level = \"foo=notalevel\""; "invalid log level")]
+    #[test_case("policy-file = \"/does/not/exist.json\""; "missing policy file")]
+    #[test_case("unknown-field = true"; "unknown field")]
+    #[test_case("experimental-prompts = [\"unknown\"]"; "unknown experimental prompt")]
+    #[test_case("[interfaces.unknown]\nfallback = \"requeue\""; "unknown interface")]
+    #[test_case("[interfaces.home]\nrecv-timeout-ms = 0"; "zero interface recv timeout")]
+    #[test_case("[interfaces.home]\nfallback = \"allow\""; "invalid fallback")]
+    #[test_case("not toml"; "invalid toml")]
+    #[test]
+    fn invalid_config_errors(content: &str) {
+        let res = Config::try_new_from_string(content);
+
+        assert!(
+            matches!(res, Err(Error::InvalidConfig { .. })),
+            "expected InvalidConfig, got {res:?}"
+        );
+    }
+
+    #[test]
+    fn invalid_initial_config_errors() {
+        let path = env::temp_dir().join(format!("{}.toml", Uuid::new_v4()));
+        fs::write(&path, "policy-file = \"/does/not/exist.json\"").unwrap();
+
+        let res = ConfigReloader::try_new(Some(path.clone()));
+        fs::remove_file(&path).unwrap();
+
+        assert!(
+            matches!(res, Err(Error::InvalidConfig { .. })),
+            "expected InvalidConfig, got {res:?}"
+        );
+    }
+
+    #[test]
+    fn reload_keeps_previous_config_if_invalid() {
+        let path = env::temp_dir().join(format!("{}.toml", Uuid::new_v4()));
+        fs::write(&path, "recv-timeout-ms = 500").unwrap();
+
+        let reloader = ConfigReloader::try_new(Some(path.clone())).unwrap();
+        let mut rx = reloader.subscribe();
+        let handle = RecordingLogHandle::default();
+        assert_eq!(rx.borrow().recv_timeout(), Duration::from_millis(500));
+
+        fs::write(&path, "recv-timeout-ms = 0").unwrap();
+        assert!(reloader.reload(&handle).is_err());
+        assert!(!rx.has_changed().unwrap());
+        assert_eq!(rx.borrow().recv_timeout(), Duration::from_millis(500));
+
+        fs::write(&path, "recv-timeout-ms = 100\nlog-level = \"debug\"").unwrap();
+        reloader.reload(&handle).unwrap();
+        assert!(rx.has_changed().unwrap());
+        assert_eq!(
+            rx.borrow_and_update().recv_timeout(),
+            Duration::from_millis(100)
+        );
+        assert_eq!(*handle.0.lock().unwrap(), vec![log_filter("debug")]);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
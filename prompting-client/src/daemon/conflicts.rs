@@ -0,0 +1,394 @@
+//! Resolution of conflicts between a prompt reply and the existing rules stored by snapd.
+//!
+//! When a reply would create a rule whose outcome for a given permission conflicts with an
+//! existing rule, snapd rejects the reply and reports the conflicting rule IDs. If the user has
+//! opted in to replacing conflicting rules, we patch each of those rules in place so that they no
+//! longer cover the conflicting permissions (removing them entirely if nothing else remains) and
+//! then retry the reply.
+//!
+//! Snapd does not provide a way for us to make these changes transactionally so instead we track
+//! the changes we make and undo them if any step fails. Rules that were narrowed keep their ID
+//! but rules that had to be removed will have a new ID once restored.
+use crate::{
+    daemon::{ManageRules, ReplyToPrompt},
+    snapd_client::{PromptId, RuleConflict, RuleId, TypedPromptReply, TypedRule},
+    Result,
+};
+use chrono::Utc;
+use std::collections::BTreeMap;
+use tracing::{debug, error, info};
+
+/// A change we have made to an existing rule in order to resolve a conflict.
+#[derive(Debug)]
+enum RuleChange {
+    /// The rule was patched to remove the conflicting permissions.
+    Narrowed {
+        original: TypedRule,
+        removed: Vec<String>,
+    },
+    /// The rule only covered conflicting permissions and was removed.
+    Removed { original: TypedRule },
+}
+
+/// Narrow or remove the rules identified in `conflicts` and then retry the reply, rolling back
+/// any changes made to existing rules if we are unable to successfully reply.
+pub(crate) async fn reply_replacing_conflicting_rules<C>(
+    client: &C,
+    id: &PromptId,
+    reply: TypedPromptReply,
+    conflicts: Vec<RuleConflict>,
+) -> Result<Vec<PromptId>>
+where
+    C: ReplyToPrompt + ManageRules,
+{
+    let mut conflicting_permissions: BTreeMap<RuleId, Vec<String>> = BTreeMap::new();
+    for RuleConflict {
+        permission,
+        conflicting_id,
+        ..
+    } in conflicts
+    {
+        conflicting_permissions
+            .entry(RuleId(conflicting_id))
+            .or_default()
+            .push(permission);
+    }
+
+    let mut changes = Vec::with_capacity(conflicting_permissions.len());
+    for (rule_id, permissions) in conflicting_permissions {
+        match narrow_rule(client, &rule_id, &permissions, &mut changes).await {
+            Ok(()) => debug!(id=%rule_id.0, ?permissions, "narrowed conflicting rule"),
+            Err(e) => {
+                error!(id=%rule_id.0, "unable to narrow conflicting rule: {e}");
+                rollback(client, changes).await;
+                return Err(e);
+            }
+        }
+    }
+
+    match client.reply(id, reply).await {
+        Ok(others) => {
+            info!(id=%id.0, "replaced {} conflicting rules", changes.len());
+            Ok(others)
+        }
+        Err(e) => {
+            error!(id=%id.0, "retrying reply after replacing conflicting rules failed: {e}");
+            rollback(client, changes).await;
+            Err(e)
+        }
+    }
+}
+
+async fn narrow_rule<C>(
+    client: &C,
+    id: &RuleId,
+    permissions: &[String],
+    changes: &mut Vec<RuleChange>,
+) -> Result<()>
+where
+    C: ManageRules,
+{
+    let original = client.get_rule(id).await?;
+    let narrowed = original
+        .clone()
+        .without_permissions(permissions)
+        .and_then(|r| r.remaining_from(Utc::now()));
+
+    if narrowed.is_none() {
+        client.remove_rule(id).await?;
+        changes.push(RuleChange::Removed { original });
+        return Ok(());
+    }
+
+    // Snapd merges the permissions in a patch with the existing ones so the conflicting
+    // permissions need to be explicitly removed.
+    let removed: Vec<String> = permissions
+        .iter()
+        .filter(|p| original.permissions().contains_key(*p))
+        .cloned()
+        .collect();
+    let patch = removed.iter().map(|p| (p.clone(), None)).collect();
+    client.patch_rule(id, patch).await?;
+    changes.push(RuleChange::Narrowed { original, removed });
+
+    Ok(())
+}
+
+/// Undo the provided changes in reverse order. Failures are logged rather than returned so that
+/// we make a best effort attempt at restoring as much of the original state as possible.
+async fn rollback<C>(client: &C, changes: Vec<RuleChange>)
+where
+    C: ManageRules,
+{
+    for change in changes.into_iter().rev() {
+        let now = Utc::now();
+        let (id, res) = match change {
+            RuleChange::Narrowed { original, removed } => {
+                let patch: BTreeMap<_, _> = original
+                    .permissions()
+                    .iter()
+                    .filter(|(p, _)| removed.contains(p))
+                    .filter_map(|(p, e)| Some((p.clone(), Some(e.clone().remaining_from(now)?))))
+                    .collect();
+                if patch.is_empty() {
+                    debug!(id=%original.id().0, "not restoring permissions that have since expired");
+                    continue;
+                }
+                (
+                    original.id().clone(),
+                    client.patch_rule(original.id(), patch).await,
+                )
+            }
+            RuleChange::Removed { original } => match original.clone().remaining_from(now) {
+                Some(rule) => (original.id().clone(), client.add_rule(rule).await),
+                None => {
+                    debug!(id=%original.id().0, "not restoring rule that has since expired");
+                    continue;
+                }
+            },
+        };
+
+        match res {
+            Ok(restored) => info!(id=%id.0, new_id=%restored.id().0, "restored rule"),
+            Err(e) => error!(id=%id.0, "unable to restore rule: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        snapd_client::{
+            interfaces::home::{HomeInterface, HomeRuleConstraints},
+            Action, PermissionEntry, PromptReply, Rule, SnapdError,
+        },
+        Error,
+    };
+    use hyper::StatusCode;
+    use std::{io, sync::Mutex};
+    use tonic::async_trait;
+
+    #[derive(Debug, Default)]
+    struct MockClient {
+        reply_err: bool,
+        rules: Mutex<Vec<TypedRule>>,
+        next_id: Mutex<usize>,
+    }
+
+    impl MockClient {
+        fn new(rules: Vec<TypedRule>) -> Self {
+            Self {
+                next_id: Mutex::new(rules.len() + 1),
+                rules: Mutex::new(rules),
+                ..Default::default()
+            }
+        }
+
+        fn ids(&self) -> Vec<String> {
+            self.rules
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|r| r.id().0.clone())
+                .collect()
+        }
+
+        fn rules(&self) -> Vec<(String, Vec<String>)> {
+            self.rules
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|r| match r {
+                    TypedRule::Home(r) => (
+                        r.constraints.path_pattern.clone(),
+                        r.constraints.permissions.keys().cloned().collect(),
+                    ),
+                    _ => panic!("unexpected rule"),
+                })
+                .collect()
+        }
+    }
+
+    fn not_found() -> Error {
+        Error::SnapdError {
+            status: StatusCode::NOT_FOUND,
+            message: "rule not found".to_string(),
+            err: Box::new(SnapdError::RuleNotFound),
+        }
+    }
+
+    #[async_trait]
+    impl ReplyToPrompt for MockClient {
+        async fn reply(&self, _id: &PromptId, _reply: TypedPromptReply) -> Result<Vec<PromptId>> {
+            if self.reply_err {
+                return Err(Error::Io(io::Error::other(
+                    "error requested of mock client",
+                )));
+            }
+
+            Ok(Vec::new())
+        }
+    }
+
+    #[async_trait]
+    impl ManageRules for MockClient {
+        async fn list_rules(&self, _snap: Option<&str>) -> Result<Vec<TypedRule>> {
+            Ok(self.rules.lock().unwrap().clone())
+        }
+
+        async fn get_rule(&self, id: &RuleId) -> Result<TypedRule> {
+            let rules = self.rules.lock().unwrap();
+            rules
+                .iter()
+                .find(|r| r.id() == id)
+                .cloned()
+                .ok_or_else(not_found)
+        }
+
+        async fn add_rule(&self, mut rule: TypedRule) -> Result<TypedRule> {
+            let mut next_id = self.next_id.lock().unwrap();
+            if let TypedRule::Home(r) = &mut rule {
+                r.id = RuleId(next_id.to_string());
+            }
+            *next_id += 1;
+            self.rules.lock().unwrap().push(rule.clone());
+
+            Ok(rule)
+        }
+
+        // Merges permissions in the same way as snapd: only explicit `None` entries are removed.
+        async fn patch_rule(
+            &self,
+            id: &RuleId,
+            permissions: BTreeMap<String, Option<PermissionEntry>>,
+        ) -> Result<TypedRule> {
+            let mut rules = self.rules.lock().unwrap();
+            let existing = rules
+                .iter_mut()
+                .find(|r| r.id() == id)
+                .ok_or_else(not_found)?;
+            let TypedRule::Home(r) = existing else {
+                panic!("unexpected rule");
+            };
+            for (permission, entry) in permissions {
+                match entry {
+                    Some(entry) => r.constraints.permissions.insert(permission, entry),
+                    None => r.constraints.permissions.remove(&permission),
+                };
+            }
+
+            Ok(existing.clone())
+        }
+
+        async fn remove_rule(&self, id: &RuleId) -> Result<TypedRule> {
+            let mut rules = self.rules.lock().unwrap();
+            let ix = rules
+                .iter()
+                .position(|r| r.id() == id)
+                .ok_or_else(not_found)?;
+
+            Ok(rules.remove(ix))
+        }
+    }
+
+    fn rule(id: &str, path_pattern: &str, permissions: &[&str]) -> TypedRule {
+        TypedRule::Home(Rule {
+            id: RuleId(id.to_string()),
+            timestamp: "0".to_string(),
+            user: 1000,
+            snap: "firefox".to_string(),
+            interface: "home".to_string(),
+            constraints: HomeRuleConstraints::new(
+                path_pattern,
+                permissions
+                    .iter()
+                    .map(|&p| (p, PermissionEntry::forever(Action::Deny))),
+            ),
+        })
+    }
+
+    fn conflict(permission: &str, conflicting_id: &str) -> RuleConflict {
+        RuleConflict {
+            permission: permission.to_string(),
+            variant: "/home/ubuntu/**".to_string(),
+            conflicting_id: conflicting_id.to_string(),
+        }
+    }
+
+    fn initial_rules() -> Vec<TypedRule> {
+        vec![
+            rule("1", "/home/ubuntu/**", &["read", "write"]),
+            rule("2", "/home/ubuntu/Documents/**", &["read"]),
+        ]
+    }
+
+    fn reply() -> TypedPromptReply {
+        PromptReply::<HomeInterface>::default().into()
+    }
+
+    #[tokio::test]
+    async fn conflicting_rules_are_narrowed_or_removed() {
+        let client = MockClient::new(initial_rules());
+        let conflicts = vec![conflict("read", "1"), conflict("read", "2")];
+
+        let res =
+            reply_replacing_conflicting_rules(&client, &PromptId("1".into()), reply(), conflicts)
+                .await;
+
+        assert!(res.is_ok(), "{res:?}");
+        assert_eq!(
+            client.rules(),
+            vec![("/home/ubuntu/**".to_string(), vec!["write".to_string()])]
+        );
+        assert_eq!(client.ids(), vec!["1".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn failed_reply_rolls_back_changes() {
+        let client = MockClient {
+            reply_err: true,
+            ..MockClient::new(initial_rules())
+        };
+        let conflicts = vec![conflict("read", "1"), conflict("read", "2")];
+
+        let res =
+            reply_replacing_conflicting_rules(&client, &PromptId("1".into()), reply(), conflicts)
+                .await;
+
+        assert!(res.is_err());
+        // The narrowed rule keeps its ID while the removed rule is re-created with a new one
+        assert_eq!(client.ids(), vec!["1".to_string(), "3".to_string()]);
+        let mut rules = client.rules();
+        rules.sort();
+        assert_eq!(
+            rules,
+            vec![
+                (
+                    "/home/ubuntu/**".to_string(),
+                    vec!["read".to_string(), "write".to_string()]
+                ),
+                (
+                    "/home/ubuntu/Documents/**".to_string(),
+                    vec!["read".to_string()]
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_conflicting_rule_rolls_back_changes() {
+        let client = MockClient::new(initial_rules());
+        let conflicts = vec![conflict("read", "2"), conflict("read", "3")];
+
+        let res =
+            reply_replacing_conflicting_rules(&client, &PromptId("1".into()), reply(), conflicts)
+                .await;
+
+        match res {
+            Err(Error::SnapdError { err, .. }) => assert_eq!(*err, SnapdError::RuleNotFound),
+            res => panic!("expected rule not found, got {res:?}"),
+        }
+        assert_eq!(client.rules().len(), 2);
+    }
+}
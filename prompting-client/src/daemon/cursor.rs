@@ -0,0 +1,166 @@
+//! Persistence of our position in the snapd notices API across daemon restarts.
+//!
+//! The `after` timestamp used when long-polling for notices is written to
+//! `$XDG_STATE_HOME/prompting-client/notices-cursor` (falling back to `$HOME/.local/state` if
+//! `XDG_STATE_HOME` is not set) each time it advances so that a restarted daemon can pick up any
+//! notices that arrived while it was not running. Cursors older than [MAX_CURSOR_AGE] are clamped
+//! to avoid replaying an unbounded number of notices after a long outage.
+use chrono::{DateTime, TimeDelta, Utc};
+use std::{
+    env,
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use tracing::{debug, error, warn};
+
+const STATE_DIR: &str = "prompting-client";
+const CURSOR_FILE: &str = "notices-cursor";
+
+/// The oldest cursor that we will restore on startup.
+pub const MAX_CURSOR_AGE: TimeDelta = TimeDelta::days(1);
+
+/// The default location of the notices cursor for the current user.
+pub fn default_notices_cursor_path() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local/state"),
+    };
+
+    Some(base.join(STATE_DIR).join(CURSOR_FILE))
+}
+
+#[derive(Debug, Clone)]
+pub struct NoticesCursor {
+    path: PathBuf,
+}
+
+impl NoticesCursor {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load the persisted cursor, returning `None` if there is no valid cursor on disk.
+    ///
+    /// Cursors older than [MAX_CURSOR_AGE] are clamped to `now - MAX_CURSOR_AGE`.
+    pub fn load(&self) -> Option<DateTime<Utc>> {
+        self.load_relative_to(Utc::now())
+    }
+
+    fn load_relative_to(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let raw = match fs::read_to_string(&self.path) {
+            Ok(raw) => raw,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                warn!(path=%self.path.display(), "unable to read notices cursor: {e}");
+                return None;
+            }
+        };
+
+        let after = match DateTime::parse_from_rfc3339(raw.trim()) {
+            Ok(dt) => dt.with_timezone(&Utc),
+            Err(e) => {
+                warn!(path=%self.path.display(), ?raw, "invalid notices cursor: {e}");
+                return None;
+            }
+        };
+
+        let oldest = now - MAX_CURSOR_AGE;
+        if after < oldest {
+            warn!(%after, %oldest, "notices cursor is too old: only replaying recent notices");
+            return Some(oldest);
+        }
+
+        Some(after.min(now))
+    }
+
+    /// Persist the provided cursor.
+    ///
+    /// The cursor is written to a temporary file before being moved into place so that a crash
+    /// part way through writing can not leave a truncated cursor behind. Failing to persist the
+    /// cursor should not prevent us from handling prompts so any errors are logged rather than
+    /// returned.
+    pub fn store(&self, after: &str) {
+        match self.write_atomic(after) {
+            Ok(()) => debug!(%after, "notices cursor updated"),
+            Err(e) => error!(path=%self.path.display(), "unable to write notices cursor: {e}"),
+        }
+    }
+
+    fn write_atomic(&self, after: &str) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut tmp = OsString::from(self.path.as_os_str());
+        tmp.push(".tmp");
+        fs::write(&tmp, after)?;
+
+        fs::rename(&tmp, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+    use uuid::Uuid;
+
+    const NOW: &str = "2024-06-01T12:00:00Z";
+
+    fn ts(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test_case(
+        Some("2024-06-01T11:00:00.123456789Z"),
+        Some("2024-06-01T11:00:00.123456789Z");
+        "recent cursor is restored"
+    )]
+    #[test_case(
+        Some("2024-05-01T11:00:00Z"),
+        Some("2024-05-31T12:00:00Z");
+        "old cursor is clamped"
+    )]
+    #[test_case(Some("2024-06-02T12:00:00Z"), Some(NOW); "future cursor is clamped")]
+    #[test_case(Some("not a timestamp"), None; "invalid cursor")]
+    #[test_case(None, None; "missing cursor")]
+    #[test]
+    fn load_cursor(content: Option<&str>, expected: Option<&str>) {
+        let dir = env::temp_dir().join(Uuid::new_v4().to_string());
+        let cursor = NoticesCursor::new(dir.join(CURSOR_FILE));
+        if let Some(content) = content {
+            cursor.store(content);
+        }
+
+        let loaded = cursor.load_relative_to(ts(NOW));
+        let expected = expected.map(ts);
+
+        assert_eq!(loaded, expected);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn store_replaces_existing_cursor() {
+        let dir = env::temp_dir().join(Uuid::new_v4().to_string());
+        let cursor = NoticesCursor::new(dir.join(CURSOR_FILE));
+
+        cursor.store("2024-06-01T10:00:00Z");
+        cursor.store("2024-06-01T11:00:00Z");
+
+        assert_eq!(
+            fs::read_to_string(cursor.path()).unwrap(),
+            "2024-06-01T11:00:00Z"
+        );
+        assert_eq!(
+            fs::read_dir(&dir).unwrap().count(),
+            1,
+            "tmp file left behind"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
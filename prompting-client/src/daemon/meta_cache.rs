@@ -0,0 +1,428 @@
+//! A disk-backed cache of the snap meta-data and icons used when rendering prompts.
+//!
+//! Entries are written to `$XDG_CACHE_HOME/prompting-client/snaps/<snap>/<revision>` (falling
+//! back to `$HOME/.cache` if `XDG_CACHE_HOME` is not set). The current revision of a snap is read
+//! from its `current` symlink under `/snap` so that cache hits do not require a round trip to
+//! snapd, and so that entries are invalidated when the snap is refreshed.
+//!
+//! Icons are validated against their declared mime type and normalised to a fixed size before
+//! being cached so that the UI never needs to handle oversized or mislabelled images.
+use crate::snapd_client::{Client, SnapIcon, SnapMeta, SnapdClient};
+use hyper::body::Bytes;
+use image::{imageops::FilterType, ImageFormat};
+use regex::Regex;
+use std::{
+    env, fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+use tracing::{debug, warn};
+
+const CACHE_DIR: &str = "prompting-client/snaps";
+const META_FILE: &str = "meta.json";
+const ICON_FILE: &str = "icon";
+const DEFAULT_SNAP_MOUNT_DIR: &str = "/snap";
+
+/// The width and height that icons are downscaled to.
+pub const ICON_SIZE: u32 = 256;
+
+/// The largest icon that we will attempt to process.
+pub const MAX_ICON_BYTES: usize = 2 * 1024 * 1024;
+
+const PNG: &str = "image/png";
+const JPEG: &str = "image/jpeg";
+const SVG: &str = "image/svg+xml";
+
+static SVG_ROOT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<svg\b[^>]*>").unwrap());
+static SVG_DIMENSION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\s(width|height)\s*=\s*("[^"]*"|'[^']*')"#).unwrap());
+static SVG_VIEWBOX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\sviewBox\s*=").unwrap());
+
+/// The default location of the snap meta-data cache for the current user.
+pub fn default_meta_cache_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".cache"),
+    };
+
+    Some(base.join(CACHE_DIR))
+}
+
+#[derive(Debug, Clone)]
+pub struct SnapMetaCache {
+    dir: Option<PathBuf>,
+    snap_mount_dir: PathBuf,
+}
+
+impl Default for SnapMetaCache {
+    fn default() -> Self {
+        Self::new(default_meta_cache_dir())
+    }
+}
+
+impl SnapMetaCache {
+    /// Create a new cache in the given directory. If no directory is provided then meta-data is
+    /// always pulled from snapd.
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self {
+            dir,
+            snap_mount_dir: DEFAULT_SNAP_MOUNT_DIR.into(),
+        }
+    }
+
+    /// Read the current revision of snaps from the provided directory rather than `/snap`.
+    pub fn with_snap_mount_dir(mut self, snap_mount_dir: impl Into<PathBuf>) -> Self {
+        self.snap_mount_dir = snap_mount_dir.into();
+        self
+    }
+
+    /// Load the meta-data for the given snap from the cache if we have an entry for its current
+    /// revision, otherwise pull it from snapd and cache it.
+    pub async fn get<C: Client>(&self, client: &SnapdClient<C>, snap: &str) -> Option<SnapMeta> {
+        let (cache, owned_snap) = (self.clone(), snap.to_string());
+        let cached = run_blocking(move || {
+            let revision = cache.current_revision(&owned_snap)?;
+            cache.load(&owned_snap, &revision)
+        })
+        .await;
+
+        if let Some(meta) = cached {
+            debug!(
+                snap,
+                revision = meta.revision,
+                "using cached snap meta-data"
+            );
+            return Some(meta);
+        }
+
+        let (snap_icon, meta) = tokio::join!(client.snap_icon(snap), client.snap_details(snap));
+        let meta = meta?;
+        let cache = self.clone();
+
+        run_blocking(move || {
+            let meta = SnapMeta {
+                snap_icon: snap_icon.and_then(normalise_icon),
+                ..meta
+            };
+            cache.store(&meta);
+
+            Some(meta)
+        })
+        .await
+    }
+
+    fn current_revision(&self, snap: &str) -> Option<String> {
+        let target = fs::read_link(self.snap_mount_dir.join(snap).join("current")).ok()?;
+
+        Some(target.file_name()?.to_str()?.to_string())
+    }
+
+    fn entry_dir(&self, snap: &str, revision: &str) -> Option<PathBuf> {
+        // Guard against anything that would let us escape the cache directory
+        let valid = |s: &str| !s.is_empty() && !s.starts_with('.') && !s.contains('/');
+        if !valid(snap) || !valid(revision) {
+            return None;
+        }
+
+        Some(self.dir.as_ref()?.join(snap).join(revision))
+    }
+
+    fn load(&self, snap: &str, revision: &str) -> Option<SnapMeta> {
+        let dir = self.entry_dir(snap, revision)?;
+        let raw = fs::read(dir.join(META_FILE)).ok()?;
+        let meta: SnapMeta = match serde_json::from_slice(&raw) {
+            Ok(meta) => meta,
+            Err(e) => {
+                warn!(path=%dir.display(), "invalid cached snap meta-data: {e}");
+                return None;
+            }
+        };
+        // Cached icons have already been normalised so we only need to check that the data on
+        // disk is still a valid image.
+        let snap_icon = fs::read(dir.join(ICON_FILE)).ok().and_then(|bytes| {
+            let mime_type = sniff_mime_type(&bytes)?.to_string();
+            Some(SnapIcon {
+                bytes: bytes.into(),
+                mime_type,
+            })
+        });
+
+        Some(SnapMeta { snap_icon, ..meta })
+    }
+
+    fn store(&self, meta: &SnapMeta) {
+        let Some(dir) = self.entry_dir(&meta.name, &meta.revision) else {
+            return;
+        };
+
+        if let Err(e) = write_entry(&dir, meta) {
+            warn!(path=%dir.display(), "unable to cache snap meta-data: {e}");
+            return;
+        }
+
+        // Entries for previous revisions are no longer needed
+        if let Some(Ok(entries)) = dir.parent().map(fs::read_dir) {
+            for entry in entries.flatten() {
+                if entry.path() != dir {
+                    let _ = fs::remove_dir_all(entry.path());
+                }
+            }
+        }
+    }
+}
+
+/// Run file system access and image processing on the blocking thread pool rather than stalling
+/// the async runtime.
+pub(crate) async fn run_blocking<T, F>(f: F) -> Option<T>
+where
+    F: FnOnce() -> Option<T> + Send + 'static,
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(res) => res,
+        Err(e) => {
+            warn!("blocking task failed: {e}");
+            None
+        }
+    }
+}
+
+fn write_entry(dir: &Path, meta: &SnapMeta) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join(META_FILE), serde_json::to_vec(meta)?)?;
+    match &meta.snap_icon {
+        Some(icon) => fs::write(dir.join(ICON_FILE), &icon.bytes),
+        None => match fs::remove_file(dir.join(ICON_FILE)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        },
+    }
+}
+
+/// Determine the mime type of an image from its contents.
+fn sniff_mime_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(PNG)
+    } else if bytes.starts_with(b"\xff\xd8\xff") {
+        Some(JPEG)
+    } else {
+        let head = String::from_utf8_lossy(&bytes[..bytes.len().min(4096)]);
+        head.contains("<svg").then_some(SVG)
+    }
+}
+
+/// Validate that an icon matches its declared mime type and is not oversized, downscaling it to
+/// [ICON_SIZE] if needed. Icons that fail validation are dropped.
+pub fn normalise_icon(icon: SnapIcon) -> Option<SnapIcon> {
+    if icon.bytes.len() > MAX_ICON_BYTES {
+        warn!(n_bytes = icon.bytes.len(), "rejecting oversized icon");
+        return None;
+    }
+
+    let declared = match icon.mime_type.split(';').next().unwrap_or_default().trim() {
+        "image/jpg" => JPEG,
+        mime_type => mime_type,
+    };
+    let sniffed = sniff_mime_type(&icon.bytes);
+    if sniffed != Some(declared) {
+        warn!(
+            declared,
+            ?sniffed,
+            "rejecting icon that does not match its mime type"
+        );
+        return None;
+    }
+
+    let res = match declared {
+        PNG => downscale_raster(&icon.bytes, ImageFormat::Png),
+        JPEG => downscale_raster(&icon.bytes, ImageFormat::Jpeg),
+        _ => normalise_svg(&icon.bytes),
+    };
+
+    res.map(|(bytes, mime_type)| SnapIcon {
+        bytes,
+        mime_type: mime_type.to_string(),
+    })
+}
+
+fn downscale_raster(bytes: &Bytes, format: ImageFormat) -> Option<(Bytes, &'static str)> {
+    let mime_type = format.to_mime_type();
+    let img = match image::load_from_memory_with_format(bytes, format) {
+        Ok(img) => img,
+        Err(e) => {
+            warn!(mime_type, "rejecting icon that could not be decoded: {e}");
+            return None;
+        }
+    };
+
+    if img.width() <= ICON_SIZE && img.height() <= ICON_SIZE {
+        return Some((bytes.clone(), mime_type));
+    }
+
+    debug!(
+        width = img.width(),
+        height = img.height(),
+        "downscaling icon"
+    );
+    let mut buf = Cursor::new(Vec::new());
+    img.resize(ICON_SIZE, ICON_SIZE, FilterType::Triangle)
+        .write_to(&mut buf, ImageFormat::Png)
+        .inspect_err(|e| warn!("unable to encode downscaled icon: {e}"))
+        .ok()?;
+
+    Some((buf.into_inner().into(), PNG))
+}
+
+/// SVGs are scaled by the UI when they are rendered so rather than rasterising them we set the
+/// size of the root element, adding a viewBox based on the original size if there isn't one
+/// already so that the content is scaled rather than clipped.
+fn normalise_svg(bytes: &Bytes) -> Option<(Bytes, &'static str)> {
+    let Ok(raw) = std::str::from_utf8(bytes) else {
+        warn!("rejecting svg icon that is not valid utf-8");
+        return None;
+    };
+    let Some(root) = SVG_ROOT.find(raw) else {
+        warn!("rejecting svg icon without a root svg element");
+        return None;
+    };
+
+    let tag = root.as_str();
+    let dimension = |name: &str| {
+        SVG_DIMENSION
+            .captures_iter(tag)
+            .find(|c| &c[1] == name)
+            .and_then(|c| c[2].trim_matches(['"', '\'']).parse::<f64>().ok())
+    };
+    let view_box = match (
+        SVG_VIEWBOX.is_match(tag),
+        dimension("width"),
+        dimension("height"),
+    ) {
+        (false, Some(w), Some(h)) => format!(r#" viewBox="0 0 {w} {h}""#),
+        _ => String::new(),
+    };
+
+    let stripped = SVG_DIMENSION.replace_all(tag, "");
+    let (open, close) = match stripped.strip_suffix("/>") {
+        Some(open) => (open, "/>"),
+        None => (stripped.strip_suffix('>')?, ">"),
+    };
+    let new_tag = format!(r#"{open}{view_box} width="{ICON_SIZE}" height="{ICON_SIZE}"{close}"#);
+
+    let mut normalised = String::with_capacity(raw.len() + 64);
+    normalised.push_str(&raw[..root.start()]);
+    normalised.push_str(&new_tag);
+    normalised.push_str(&raw[root.end()..]);
+
+    Some((normalised.into(), SVG))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapd_client::FakeSnapd;
+    use chrono::Utc;
+    use image::{DynamicImage, RgbaImage};
+    use simple_test_case::test_case;
+    use std::os::unix::fs::symlink;
+    use uuid::Uuid;
+
+    fn png(width: u32, height: u32) -> Bytes {
+        let mut buf = Cursor::new(Vec::new());
+        DynamicImage::ImageRgba8(RgbaImage::new(width, height))
+            .write_to(&mut buf, ImageFormat::Png)
+            .unwrap();
+
+        buf.into_inner().into()
+    }
+
+    fn icon(bytes: impl Into<Bytes>, mime_type: &str) -> SnapIcon {
+        SnapIcon {
+            bytes: bytes.into(),
+            mime_type: mime_type.to_string(),
+        }
+    }
+
+    #[test]
+    fn small_pngs_are_unchanged() {
+        let original = icon(png(64, 64), PNG);
+
+        assert_eq!(normalise_icon(original.clone()), Some(original));
+    }
+
+    #[test]
+    fn large_pngs_are_downscaled() {
+        let normalised = normalise_icon(icon(png(1024, 512), PNG)).unwrap();
+        let img = image::load_from_memory(&normalised.bytes).unwrap();
+
+        assert_eq!((img.width(), img.height()), (ICON_SIZE, ICON_SIZE / 2));
+        assert_eq!(normalised.mime_type, PNG);
+    }
+
+    #[test_case(icon(png(64, 64), JPEG); "png declared as jpeg")]
+    #[test_case(icon(&b"<svg></svg>"[..], PNG); "svg declared as png")]
+    #[test_case(icon(&b"GIF89a"[..], "image/gif"); "unsupported type")]
+    #[test_case(icon(&b"\x89PNG\r\n\x1a\nnot really a png"[..], PNG); "corrupt png")]
+    #[test_case(icon(vec![0; MAX_ICON_BYTES + 1], PNG); "oversized")]
+    #[test]
+    fn invalid_icons_are_rejected(icon: SnapIcon) {
+        assert_eq!(normalise_icon(icon), None);
+    }
+
+    #[test_case(
+        r#"<?xml version="1.0"?><svg xmlns="http://www.w3.org/2000/svg" width="1024" height="512"><rect/></svg>"#,
+        r#"<?xml version="1.0"?><svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 1024 512" width="256" height="256"><rect/></svg>"#;
+        "dimensions without viewbox"
+    )]
+    #[test_case(
+        r#"<svg viewBox="0 0 48 48" width='48px'><rect/></svg>"#,
+        r#"<svg viewBox="0 0 48 48" width="256" height="256"><rect/></svg>"#;
+        "existing viewbox"
+    )]
+    #[test]
+    fn svgs_are_resized(raw: &'static str, expected: &str) {
+        let normalised = normalise_icon(icon(raw, "image/svg+xml; charset=utf-8")).unwrap();
+
+        assert_eq!(normalised.bytes, expected);
+        assert_eq!(normalised.mime_type, SVG);
+    }
+
+    #[tokio::test]
+    async fn entries_are_keyed_on_revision() {
+        let root = env::temp_dir().join(Uuid::new_v4().to_string());
+        let mount_dir = root.join("snap");
+        fs::create_dir_all(mount_dir.join("firefox")).unwrap();
+        symlink("4793", mount_dir.join("firefox/current")).unwrap();
+
+        let meta = SnapMeta {
+            name: "firefox".to_string(),
+            revision: "4793".to_string(),
+            snap_icon: Some(icon(png(512, 512), PNG)),
+            ..Default::default()
+        };
+        let fake = FakeSnapd::builder().with_snap_meta(meta).build();
+        let client = SnapdClient::new_with_client(fake.clone(), Utc::now());
+        let cache = SnapMetaCache::new(Some(root.join("cache"))).with_snap_mount_dir(&mount_dir);
+
+        let fetched = cache.get(&client, "firefox").await.unwrap();
+        let fetched_icon = fetched.snap_icon.clone().unwrap();
+        assert_eq!(
+            image::load_from_memory(&fetched_icon.bytes)
+                .unwrap()
+                .width(),
+            ICON_SIZE
+        );
+
+        // The cached entry is served without needing snapd
+        fake.set_available(false);
+        assert_eq!(cache.get(&client, "firefox").await, Some(fetched));
+
+        // Refreshing the snap invalidates the entry
+        fs::remove_file(mount_dir.join("firefox/current")).unwrap();
+        symlink("4794", mount_dir.join("firefox/current")).unwrap();
+        assert_eq!(cache.get(&client, "firefox").await, None);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
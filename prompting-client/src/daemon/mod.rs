@@ -1,21 +1,53 @@
 use crate::{
     exit_with,
-    snapd_client::{PromptId, SnapMeta, SnapdSocketClient, TypedPrompt, TypedPromptReply},
+    procfs::PromptingProcess,
+    snapd_client::{
+        interfaces::{
+            camera::CameraInterface,
+            files::{PersonalFilesInterface, SystemFilesInterface},
+            home::HomeInterface,
+            microphone::MicrophoneInterface,
+            removable_media::RemovableMediaInterface,
+        },
+        PermissionEntry, PromptId, RuleId, SnapMeta, SnapdSocketClient, TypedPrompt,
+        TypedPromptReply, TypedRule,
+    },
     Result, SOCKET_ENV_VAR,
 };
 use serde::Serialize;
-use std::{env, fmt::Debug, fs, sync::Arc};
-use tokio::{sync::mpsc::unbounded_channel, task::JoinSet};
+use std::{collections::BTreeMap, env, fmt::Debug, fs, sync::Arc};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::mpsc::unbounded_channel,
+    task::JoinSet,
+};
 use tokio_stream::wrappers::UnixListenerStream;
 use tonic::{async_trait, transport::Server};
-use tracing::{debug, error};
+use tracing::{debug, error, info};
 use tracing_subscriber::{reload::Handle, EnvFilter};
 
+mod audit;
+mod backoff;
+mod config;
+mod conflicts;
+mod cursor;
+mod meta_cache;
+mod policy;
 mod poll;
+mod recent;
 mod server;
 mod worker;
 
+pub use audit::{default_audit_log_path, AuditLog, AuditOutcome, AuditWriter};
+pub use config::{
+    default_config_path, Config, ConfigReloader, ExperimentalPrompt, InterfaceConfig, RefConfig,
+    UiFallback,
+};
+pub use cursor::{default_notices_cursor_path, NoticesCursor, MAX_CURSOR_AGE};
+pub use meta_cache::{default_meta_cache_dir, SnapMetaCache};
+pub use policy::{Policy, PolicyAction};
 pub use poll::PollLoop;
+pub use recent::{RecentPrompt, RefRecentPrompts};
 use server::new_server_and_listener;
 use worker::Worker;
 
@@ -31,11 +63,104 @@ impl ReplyToPrompt for SnapdSocketClient {
     }
 }
 
+#[async_trait]
+pub trait ManageRules: Debug + Send + Sync + 'static {
+    async fn list_rules(&self, snap: Option<&str>) -> crate::Result<Vec<TypedRule>>;
+    async fn get_rule(&self, id: &RuleId) -> crate::Result<TypedRule>;
+    /// Create a new rule with the same snap and constraints as the one provided.
+    async fn add_rule(&self, rule: TypedRule) -> crate::Result<TypedRule>;
+    /// Merge the given permissions into those of an existing rule, removing any that are `None`.
+    async fn patch_rule(
+        &self,
+        id: &RuleId,
+        permissions: BTreeMap<String, Option<PermissionEntry>>,
+    ) -> crate::Result<TypedRule>;
+    async fn remove_rule(&self, id: &RuleId) -> crate::Result<TypedRule>;
+}
+
+#[async_trait]
+impl ManageRules for SnapdSocketClient {
+    async fn list_rules(&self, snap: Option<&str>) -> crate::Result<Vec<TypedRule>> {
+        SnapdSocketClient::list_rules(self, snap, None).await
+    }
+
+    async fn get_rule(&self, id: &RuleId) -> crate::Result<TypedRule> {
+        SnapdSocketClient::get_rule(self, id).await
+    }
+
+    async fn add_rule(&self, rule: TypedRule) -> crate::Result<TypedRule> {
+        let rule = match rule {
+            TypedRule::Camera(r) => TypedRule::Camera(
+                SnapdSocketClient::add_rule::<CameraInterface>(self, &r.snap, r.constraints)
+                    .await?,
+            ),
+            TypedRule::Home(r) => TypedRule::Home(
+                SnapdSocketClient::add_rule::<HomeInterface>(self, &r.snap, r.constraints).await?,
+            ),
+            TypedRule::Microphone(r) => TypedRule::Microphone(
+                SnapdSocketClient::add_rule::<MicrophoneInterface>(self, &r.snap, r.constraints)
+                    .await?,
+            ),
+            TypedRule::PersonalFiles(r) => TypedRule::PersonalFiles(
+                SnapdSocketClient::add_rule::<PersonalFilesInterface>(self, &r.snap, r.constraints)
+                    .await?,
+            ),
+            TypedRule::RemovableMedia(r) => TypedRule::RemovableMedia(
+                SnapdSocketClient::add_rule::<RemovableMediaInterface>(
+                    self,
+                    &r.snap,
+                    r.constraints,
+                )
+                .await?,
+            ),
+            TypedRule::SystemFiles(r) => TypedRule::SystemFiles(
+                SnapdSocketClient::add_rule::<SystemFilesInterface>(self, &r.snap, r.constraints)
+                    .await?,
+            ),
+        };
+
+        Ok(rule)
+    }
+
+    async fn patch_rule(
+        &self,
+        id: &RuleId,
+        permissions: BTreeMap<String, Option<PermissionEntry>>,
+    ) -> crate::Result<TypedRule> {
+        SnapdSocketClient::patch_rule_permissions(self, id, permissions).await
+    }
+
+    async fn remove_rule(&self, id: &RuleId) -> crate::Result<TypedRule> {
+        SnapdSocketClient::remove_rule(self, id).await
+    }
+}
+
 // Poll loop -> worker
 #[derive(Debug, Clone, Serialize)]
 pub struct EnrichedPrompt {
     pub(crate) prompt: TypedPrompt,
     pub(crate) meta: Option<SnapMeta>,
+    pub(crate) process: PromptingProcess,
+}
+
+/// A home prompt from the "test" snap for use in tests.
+#[cfg(test)]
+pub(crate) fn enriched_home_prompt(id: &str, cgroup: &str) -> EnrichedPrompt {
+    use crate::snapd_client::{interfaces::home::HomeConstraints, Prompt};
+
+    EnrichedPrompt {
+        prompt: TypedPrompt::Home(Prompt {
+            id: PromptId(id.to_string()),
+            timestamp: String::new(),
+            snap: "test".to_string(),
+            pid: 1234,
+            cgroup: cgroup.into(),
+            interface: "home".to_string(),
+            constraints: HomeConstraints::default(),
+        }),
+        meta: None,
+        process: PromptingProcess::Unknown,
+    }
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -48,13 +173,26 @@ pub enum PromptUpdate {
 // Server -> worker
 #[derive(Debug, Clone)]
 pub enum ActionedPrompt {
-    Actioned { id: PromptId, others: Vec<PromptId> },
-    NotFound { id: PromptId },
+    Actioned {
+        id: PromptId,
+        reply: TypedPromptReply,
+        others: Vec<PromptId>,
+    },
+    NotFound {
+        id: PromptId,
+    },
 }
 
 /// Start our backgroud polling and processing loops before dropping into running the tonic GRPC
 /// server for handling incoming requestes from the Flutter UI client.
-pub async fn run_daemon<L, S>(c: SnapdSocketClient, reload_handle: Handle<L, S>) -> Result<()>
+///
+/// If a [NoticesCursor] is provided then it is updated as we receive notices from snapd so that
+/// the client for the next run of the daemon can be created from where we left off.
+pub async fn run_daemon<L, S>(
+    c: SnapdSocketClient,
+    cursor: Option<NoticesCursor>,
+    reload_handle: Handle<L, S>,
+) -> Result<()>
 where
     L: From<EnvFilter> + Send + Sync + 'static,
     S: 'static,
@@ -62,8 +200,17 @@ where
     let (tx_prompts, rx_prompts) = unbounded_channel();
     let (tx_actioned, rx_actioned) = unbounded_channel();
 
-    let mut worker = Worker::new(rx_prompts, rx_actioned, c.clone());
+    let reload_handle = Arc::new(reload_handle);
+    let config = ConfigReloader::try_new(default_config_path())
+        .inspect_err(|e| error!("unable to load config: {e}"))?;
+    if let Err(e) = config.apply(&reload_handle) {
+        error!("unable to apply config: {e}");
+    }
+    info!("loaded {} policy rules", config.current().policy().len());
+
+    let mut worker = Worker::new(rx_prompts, rx_actioned, c.clone(), config.subscribe());
     let active_prompt = worker.read_only_active_prompt();
+    let recent_prompts = worker.read_only_recent_prompts();
 
     let path = env::var(SOCKET_ENV_VAR).expect("socket env var not set");
     if let Err(e) = fs::remove_file(&path) {
@@ -71,8 +218,10 @@ where
     }
     let (server, listener) = new_server_and_listener(
         c.clone(),
-        Arc::new(reload_handle),
+        reload_handle.clone(),
+        config.clone(),
         active_prompt,
+        recent_prompts,
         tx_actioned,
         path,
     );
@@ -80,7 +229,11 @@ where
     let mut set = JoinSet::new();
 
     debug!("spawning poll loop");
-    let poll_loop = PollLoop::new(c, tx_prompts);
+    let mut poll_loop = PollLoop::new(c, tx_prompts);
+    poll_loop.with_config(config.subscribe());
+    if let Some(cursor) = cursor {
+        poll_loop.with_notices_cursor(cursor);
+    }
     set.spawn(async move {
         poll_loop
             .run()
@@ -96,6 +249,19 @@ where
             .inspect_err(|e| error!("worker exited with error: {:?}", e))
     });
 
+    debug!("spawning config reload handler");
+    let mut sighup = signal(SignalKind::hangup())?;
+    set.spawn(async move {
+        while sighup.recv().await.is_some() {
+            info!("received SIGHUP: reloading config");
+            if let Err(e) = config.reload(&reload_handle) {
+                error!("unable to reload config: {e}");
+            }
+        }
+
+        Ok(())
+    });
+
     debug!("serving incoming grpc connections");
     set.spawn(async move {
         Server::builder()
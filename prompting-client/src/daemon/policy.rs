@@ -0,0 +1,387 @@
+//! A declarative policy for automatically handling prompts without spawning the UI.
+//!
+//! Policy files are JSON documents containing an ordered list of rules. Each rule contains a
+//! prompt filter (using the same matching semantics as prompt sequences for the scripted client)
+//! along with the action to take for prompts that match it. The first matching rule wins and
+//! prompts that match no rules are presented to the user as normal.
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "rules": [
+//!     {
+//!       "prompt-filter": {
+//!         "snap": "firefox",
+//!         "interface": "home",
+//!         "constraints": { "path": "^/home/[^/]+/Downloads/.*" }
+//!       },
+//!       "action": "allow",
+//!       "lifespan": "session"
+//!     }
+//!   ]
+//! }
+//! ```
+use crate::{
+    prompt_sequence::{MatchError, PromptFilter},
+    snapd_client::{
+        interfaces::{
+            camera::CameraInterface,
+            files::{PersonalFilesInterface, SystemFilesInterface},
+            home::HomeInterface,
+            microphone::MicrophoneInterface,
+            removable_media::RemovableMediaInterface,
+            ReplyConstraintsOverrides, SnapInterface,
+        },
+        Action, Lifespan, Prompt, PromptReply, TypedPrompt, TypedPromptReply,
+    },
+    Error, Result,
+};
+use serde::{de, Deserialize, Deserializer};
+use std::fs;
+use tracing::{debug, warn};
+
+const POLICY_VERSION: u8 = 1;
+
+/// An ordered set of rules for automatically replying to prompts.
+#[derive(Debug, Default)]
+pub struct Policy {
+    rules: Vec<TypedPolicyRule>,
+}
+
+impl Policy {
+    pub fn try_new_from_file(path: &str) -> Result<Self> {
+        Self::try_new_from_string(fs::read_to_string(path)?)
+    }
+
+    pub fn try_new_from_string(content: impl AsRef<str>) -> Result<Self> {
+        let raw: RawPolicy = serde_json::from_str(content.as_ref())?;
+        if raw.version != POLICY_VERSION {
+            return Err(Error::InvalidPolicyVersion {
+                version: raw.version,
+            });
+        }
+
+        Ok(Self { rules: raw.rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Determine the reply that should be sent for this prompt without consulting the user.
+    ///
+    /// Returns `None` if the prompt should be presented to the user, either because it does not
+    /// match any rules or because the first rule it matches has an action of `ask`.
+    pub fn reply_for(&self, prompt: &TypedPrompt) -> Option<TypedPromptReply> {
+        let (index, rule) = self
+            .rules
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| rule.matches(prompt))?;
+        debug!(id=%prompt.id().0, index, "prompt matched policy rule");
+
+        let res = match (rule, prompt.clone()) {
+            (TypedPolicyRule::Camera(r), TypedPrompt::Camera(p)) => {
+                r.reply(p).map(|res| res.map(TypedPromptReply::Camera))
+            }
+            (TypedPolicyRule::Home(r), TypedPrompt::Home(p)) => {
+                r.reply(p).map(|res| res.map(TypedPromptReply::Home))
+            }
+            (TypedPolicyRule::Microphone(r), TypedPrompt::Microphone(p)) => {
+                r.reply(p).map(|res| res.map(TypedPromptReply::Microphone))
+            }
+            (TypedPolicyRule::PersonalFiles(r), TypedPrompt::PersonalFiles(p)) => r
+                .reply(p)
+                .map(|res| res.map(TypedPromptReply::PersonalFiles)),
+            (TypedPolicyRule::RemovableMedia(r), TypedPrompt::RemovableMedia(p)) => r
+                .reply(p)
+                .map(|res| res.map(TypedPromptReply::RemovableMedia)),
+            (TypedPolicyRule::SystemFiles(r), TypedPrompt::SystemFiles(p)) => {
+                r.reply(p).map(|res| res.map(TypedPromptReply::SystemFiles))
+            }
+            _ => unreachable!("policy rules only match prompts for the same interface"),
+        };
+
+        res.unwrap_or_else(|error| {
+            warn!(id=%prompt.id().0, index, %error, "unable to apply policy rule: asking the user");
+            None
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawPolicy {
+    version: u8,
+    #[serde(default)]
+    rules: Vec<TypedPolicyRule>,
+}
+
+/// The action to take for prompts matching a given policy rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyAction {
+    Allow,
+    Deny,
+    /// Present the prompt to the user rather than considering any further rules.
+    Ask,
+}
+
+#[derive(Debug)]
+enum TypedPolicyRule {
+    Camera(PolicyRule<CameraInterface>),
+    Home(PolicyRule<HomeInterface>),
+    Microphone(PolicyRule<MicrophoneInterface>),
+    PersonalFiles(PolicyRule<PersonalFilesInterface>),
+    RemovableMedia(PolicyRule<RemovableMediaInterface>),
+    SystemFiles(PolicyRule<SystemFilesInterface>),
+}
+
+impl TypedPolicyRule {
+    fn matches(&self, prompt: &TypedPrompt) -> bool {
+        match (self, prompt) {
+            (Self::Camera(r), TypedPrompt::Camera(p)) => r.prompt_filter.matches(p).is_success(),
+            (Self::Home(r), TypedPrompt::Home(p)) => r.prompt_filter.matches(p).is_success(),
+            (Self::Microphone(r), TypedPrompt::Microphone(p)) => {
+                r.prompt_filter.matches(p).is_success()
+            }
+            (Self::PersonalFiles(r), TypedPrompt::PersonalFiles(p)) => {
+                r.prompt_filter.matches(p).is_success()
+            }
+            (Self::RemovableMedia(r), TypedPrompt::RemovableMedia(p)) => {
+                r.prompt_filter.matches(p).is_success()
+            }
+            (Self::SystemFiles(r), TypedPrompt::SystemFiles(p)) => {
+                r.prompt_filter.matches(p).is_success()
+            }
+            _ => false,
+        }
+    }
+}
+
+// The constraint filters for each interface share field names so we can't rely on an untagged
+// enum to select the correct variant: we require that each rule explicitly names its interface.
+impl<'de> Deserialize<'de> for TypedPolicyRule {
+    fn deserialize<D>(de: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(de)?;
+        let interface = value
+            .get("prompt-filter")
+            .and_then(|f| f.get("interface"))
+            .and_then(|i| i.as_str())
+            .ok_or_else(|| de::Error::missing_field("prompt-filter.interface"))?;
+
+        let res = match interface {
+            CameraInterface::NAME => serde_json::from_value(value).map(Self::Camera),
+            HomeInterface::NAME => serde_json::from_value(value).map(Self::Home),
+            MicrophoneInterface::NAME => serde_json::from_value(value).map(Self::Microphone),
+            PersonalFilesInterface::NAME => serde_json::from_value(value).map(Self::PersonalFiles),
+            RemovableMediaInterface::NAME => {
+                serde_json::from_value(value).map(Self::RemovableMedia)
+            }
+            SystemFilesInterface::NAME => serde_json::from_value(value).map(Self::SystemFiles),
+            interface => {
+                return Err(de::Error::custom(format!(
+                    "{interface} is not currently supported for apparmor prompting"
+                )))
+            }
+        };
+
+        res.map_err(de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PolicyRule<I>
+where
+    I: SnapInterface,
+{
+    prompt_filter: PromptFilter<I>,
+    action: PolicyAction,
+    #[serde(default)]
+    lifespan: Lifespan,
+    duration: Option<String>,
+    constraints: Option<I::ReplyConstraintsOverrides>,
+}
+
+impl<I> PolicyRule<I>
+where
+    I: SnapInterface,
+{
+    fn reply(&self, p: Prompt<I>) -> std::result::Result<Option<PromptReply<I>>, MatchError> {
+        let action = match self.action {
+            PolicyAction::Allow => Action::Allow,
+            PolicyAction::Deny => Action::Deny,
+            PolicyAction::Ask => return Ok(None),
+        };
+
+        let mut reply = I::prompt_to_reply(p, action);
+        reply.lifespan = self.lifespan;
+        reply.duration = self.duration.clone();
+        if let Some(constraints) = self.constraints.clone() {
+            reply.constraints = constraints.apply(reply.constraints);
+            I::ReplyConstraintsOverrides::validate(&reply.constraints)?;
+        }
+
+        Ok(Some(reply))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapd_client::{
+        interfaces::{camera::CameraConstraints, home::HomeConstraints},
+        PromptId,
+    };
+    use simple_test_case::{dir_cases, test_case};
+
+    const POLICY: &str = r#"{
+      "version": 1,
+      "rules": [
+        {
+          "prompt-filter": {
+            "snap": "firefox",
+            "interface": "home",
+            "constraints": { "path": "^/home/ubuntu/Downloads/.*" }
+          },
+          "action": "allow",
+          "lifespan": "forever",
+          "constraints": { "path-pattern": "/home/ubuntu/Downloads/**" }
+        },
+        {
+          "prompt-filter": {
+            "snap": "firefox",
+            "interface": "home",
+            "constraints": { "path": "^/home/ubuntu/\\.ssh/.*" }
+          },
+          "action": "ask"
+        },
+        {
+          "prompt-filter": { "interface": "home" },
+          "action": "deny",
+          "lifespan": "session"
+        }
+      ]
+    }"#;
+
+    fn home_prompt(snap: &str, path: &str) -> TypedPrompt {
+        TypedPrompt::Home(Prompt {
+            id: PromptId("1".to_string()),
+            timestamp: String::new(),
+            snap: snap.to_string(),
+            pid: 1234,
+            cgroup: "cgroup".into(),
+            interface: "home".to_string(),
+            constraints: HomeConstraints {
+                path: path.to_string(),
+                requested_permissions: vec!["read".to_string()],
+                available_permissions: vec!["read".to_string(), "write".to_string()],
+            },
+        })
+    }
+
+    fn camera_prompt() -> TypedPrompt {
+        TypedPrompt::Camera(Prompt {
+            id: PromptId("1".to_string()),
+            timestamp: String::new(),
+            snap: "firefox".to_string(),
+            pid: 1234,
+            cgroup: "cgroup".into(),
+            interface: "camera".to_string(),
+            constraints: CameraConstraints::default(),
+        })
+    }
+
+    #[test_case(
+        home_prompt("firefox", "/home/ubuntu/Downloads/foo.txt"),
+        Some((Action::Allow, Lifespan::Forever, "/home/ubuntu/Downloads/**"));
+        "first matching rule wins"
+    )]
+    #[test_case(
+        home_prompt("firefox", "/home/ubuntu/.ssh/id_rsa"),
+        None;
+        "ask defers to the user"
+    )]
+    #[test_case(
+        home_prompt("other", "/home/ubuntu/Downloads/foo.txt"),
+        Some((Action::Deny, Lifespan::Session, "/home/ubuntu/Downloads/foo.txt"));
+        "falls through to later rule"
+    )]
+    #[test]
+    fn reply_for_home_prompts(prompt: TypedPrompt, expected: Option<(Action, Lifespan, &str)>) {
+        let policy = Policy::try_new_from_string(POLICY).unwrap();
+
+        let reply = policy.reply_for(&prompt).map(|reply| match reply {
+            TypedPromptReply::Home(r) => (r.action, r.lifespan, r.constraints.path_pattern),
+            r => panic!("expected home reply, got {r:?}"),
+        });
+
+        assert_eq!(
+            reply,
+            expected.map(|(a, l, p)| (a, l, p.to_string())),
+            "{prompt:?}"
+        );
+    }
+
+    #[test]
+    fn unmatched_prompts_are_not_handled() {
+        let policy = Policy::try_new_from_string(POLICY).unwrap();
+
+        assert!(policy.reply_for(&camera_prompt()).is_none());
+    }
+
+    #[test]
+    fn invalid_reply_overrides_defer_to_the_user() {
+        let policy = Policy::try_new_from_string(
+            r#"{
+              "version": 1,
+              "rules": [{
+                "prompt-filter": { "interface": "home" },
+                "action": "allow",
+                "constraints": { "path-pattern": "/not/matching" }
+              }]
+            }"#,
+        )
+        .unwrap();
+
+        let prompt = home_prompt("firefox", "/home/ubuntu/foo.txt");
+
+        assert!(policy.reply_for(&prompt).is_none());
+    }
+
+    #[test_case(r#"{ "version": 2, "rules": [] }"#; "unsupported version")]
+    #[test_case(
+        r#"{ "version": 1, "rules": [{ "prompt-filter": {}, "action": "allow" }] }"#;
+        "missing interface"
+    )]
+    #[test_case(
+        r#"{ "version": 1, "rules": [{ "prompt-filter": { "interface": "unknown" }, "action": "allow" }] }"#;
+        "unsupported interface"
+    )]
+    #[test_case(
+        r#"{ "version": 1, "rules": [{ "prompt-filter": { "interface": "home" }, "action": "maybe" }] }"#;
+        "invalid action"
+    )]
+    #[test]
+    fn invalid_policies_error(content: &str) {
+        let res = Policy::try_new_from_string(content);
+
+        assert!(res.is_err(), "{res:?}");
+    }
+
+    #[dir_cases("resources/policy-tests")]
+    #[test]
+    fn example_policies_parse(path: &str, data: &str) {
+        let res = Policy::try_new_from_string(data);
+
+        assert!(res.is_ok(), "error parsing {path}: {res:?}");
+    }
+}
@@ -5,44 +5,57 @@
 //! enriched prompts themselves are simply passed off on a channel for downstream consumption and
 //! mapping into the data required for the prompt UI.
 use crate::{
-    daemon::{EnrichedPrompt, PromptUpdate},
+    daemon::{
+        backoff::Backoff,
+        meta_cache::{normalise_icon, run_blocking, SnapMetaCache},
+        Config, EnrichedPrompt, NoticesCursor, PromptUpdate, RefConfig,
+    },
+    desktop::DesktopEntries,
     exit_with,
-    snapd_client::{PromptId, PromptNotice, SnapMeta, SnapdSocketClient, TypedPrompt},
+    procfs::ProcFs,
+    snapd_client::{Client, PromptNotice, SnapMeta, SnapdClient, TypedPrompt},
+    socket_client::UnixSocketClient,
     Error, ExitStatus, Result,
 };
-use cached::proc_macro::cached;
+use futures::{stream, Stream, StreamExt};
 use hyper::StatusCode;
-use std::time::Duration;
-use tokio::{sync::mpsc::UnboundedSender, time::sleep};
+use std::{future::Future, sync::Arc};
+use tokio::{
+    sync::{mpsc::UnboundedSender, watch},
+    time::sleep,
+};
 use tracing::{debug, error, info, warn};
 
-const MAX_POLL_RETRIES: usize = 3;
-const RETRY_SLEEP_DURATION: Duration = Duration::from_millis(200);
-
-#[cached(
-    time = 3600,  // seconds
-    option = true,
-    sync_writes = true,
-    key = "String",
-    convert = r#"{ String::from(snap) }"#
-)]
-async fn get_snap_meta(client: &SnapdSocketClient, snap: &str) -> Option<SnapMeta> {
-    client.snap_metadata(snap).await
-}
+/// The maximum number of prompts that we pull details and snap meta-data for concurrently.
+const MAX_IN_FLIGHT: usize = 8;
 
 #[derive(Debug, Clone)]
-pub struct PollLoop {
-    client: SnapdSocketClient,
+pub struct PollLoop<C = UnixSocketClient>
+where
+    C: Client,
+{
+    client: SnapdClient<C>,
+    enricher: Enricher,
     tx: UnboundedSender<PromptUpdate>,
+    config: RefConfig,
+    cursor: Option<NoticesCursor>,
     running: bool,
     skip_outstanding_prompts: bool,
 }
 
-impl PollLoop {
-    pub fn new(client: SnapdSocketClient, tx: UnboundedSender<PromptUpdate>) -> Self {
+impl<C> PollLoop<C>
+where
+    C: Client + Clone,
+{
+    pub fn new(client: SnapdClient<C>, tx: UnboundedSender<PromptUpdate>) -> Self {
+        let (_, config) = watch::channel(Arc::new(Config::default()));
+
         Self {
             client,
+            enricher: Enricher::default(),
             tx,
+            config,
+            cursor: None,
             running: true,
             skip_outstanding_prompts: false,
         }
@@ -52,6 +65,39 @@ impl PollLoop {
         self.skip_outstanding_prompts = true;
     }
 
+    /// Use the provided daemon config rather than the default config.
+    pub fn with_config(&mut self, config: RefConfig) {
+        self.config = config;
+    }
+
+    /// Read the details of prompting processes from the provided procfs rather than `/proc`.
+    pub fn with_procfs(&mut self, procfs: ProcFs) {
+        self.enricher.procfs = procfs;
+    }
+
+    /// Look up the desktop entries for prompting apps using the provided resolver rather than
+    /// the one configured from the environment.
+    pub fn with_desktop_entries(&mut self, desktop_entries: DesktopEntries) {
+        self.enricher.desktop_entries = desktop_entries;
+    }
+
+    /// Cache snap meta-data using the provided cache rather than the default one for the current
+    /// user.
+    pub fn with_meta_cache(&mut self, meta_cache: SnapMetaCache) {
+        self.enricher.meta_cache = meta_cache;
+    }
+
+    /// Persist our position in the notices API each time it advances.
+    pub fn with_notices_cursor(&mut self, cursor: NoticesCursor) {
+        self.cursor = Some(cursor);
+    }
+
+    fn store_notices_cursor(&self) {
+        if let Some(cursor) = &self.cursor {
+            cursor.store(self.client.notices_after());
+        }
+    }
+
     /// Run our poll loop for prompting notices from snapd (runs as a top level task).
     ///
     /// This first checks for any outstanding (unactioned) prompts on the system for the user
@@ -63,7 +109,8 @@ impl PollLoop {
             self.handle_outstanding_prompts().await;
         }
 
-        let mut retries = 0;
+        let mut backoff = Backoff::default();
+        let mut snapd_gone = false;
 
         while self.running {
             debug!("polling for notices");
@@ -81,28 +128,65 @@ impl PollLoop {
                     exit_with(ExitStatus::Failure);
                 }
 
-                Err(error) if retries < MAX_POLL_RETRIES => {
-                    error!(%error, "unable to pull prompt ids: retrying");
-                    self.client.exit_if_prompting_not_enabled().await?;
-                    sleep(RETRY_SLEEP_DURATION).await;
-                    retries += 1;
-                    continue;
-                }
-
                 Err(error) => {
-                    error!(%error, "retries exceeded trying to establish notices long poll: exiting");
-                    exit_with(ExitStatus::Failure);
+                    if !self.client.is_available().await {
+                        if !snapd_gone {
+                            warn!(%error, "snapd socket has gone away: waiting for it to return");
+                            snapd_gone = true;
+                        }
+                    } else {
+                        error!(%error, "unable to pull prompt ids: retrying");
+                        if let Err(error) = self.client.exit_if_prompting_not_enabled().await {
+                            error!(%error, "unable to check if prompting is enabled");
+                        }
+                    }
+
+                    let max_outage = self.config.borrow().max_outage();
+                    match backoff.next_delay(max_outage) {
+                        Some(delay) => {
+                            debug!(?delay, "backing off before retrying notices long poll");
+                            sleep(delay).await;
+                            continue;
+                        }
+                        None => {
+                            error!(?max_outage, "unable to reach snapd: exiting");
+                            exit_with(ExitStatus::Failure);
+                        }
+                    }
                 }
             };
 
-            retries = 0;
-            debug!(?notices, "processing notices");
-            for notice in notices {
-                match notice {
-                    PromptNotice::Update(id) => self.pull_and_process_prompt(id).await,
-                    PromptNotice::Resolved(id) => self.send_update(PromptUpdate::Drop(id)),
+            if backoff.is_failing() {
+                let outage = backoff.outage();
+                if snapd_gone {
+                    info!(?outage, "snapd has restarted: resuming notices long poll");
+                } else {
+                    info!(?outage, "reconnected to snapd: resuming notices long poll");
+                }
+                backoff.reset();
+            }
+
+            if snapd_gone {
+                // The restart may have been an upgrade or a change in config so we need to re-run
+                // the check we made on startup. Snapd may not be able to answer straight away so
+                // we keep checking after each poll until it does.
+                match self.client.exit_if_prompting_not_enabled().await {
+                    Ok(()) => snapd_gone = false,
+                    Err(error) => {
+                        error!(%error, "unable to check if prompting is enabled: retrying")
+                    }
                 }
             }
+
+            if !notices.is_empty() {
+                self.store_notices_cursor();
+            }
+            debug!(?notices, "processing notices");
+            let (client, enricher) = (self.client.clone(), self.enricher.clone());
+            let updates = notices
+                .into_iter()
+                .map(|notice| pull_prompt_update(&client, &enricher, notice));
+            self.send_updates_in_order(updates).await;
         }
 
         Ok(())
@@ -115,35 +199,23 @@ impl PollLoop {
         }
     }
 
-    async fn pull_and_process_prompt(&mut self, id: PromptId) {
-        debug!(?id, "pulling prompt details from snapd");
-        let prompt = match self.client.prompt_details(&id).await {
-            Ok(p) => p,
+    /// Send the updates produced by the provided futures in the order that they are provided,
+    /// while running up to [MAX_IN_FLIGHT] of them concurrently.
+    async fn send_updates_in_order<F>(&mut self, updates: impl IntoIterator<Item = F>)
+    where
+        F: Future<Output = Option<PromptUpdate>>,
+    {
+        let mut updates = in_order_bounded(updates, MAX_IN_FLIGHT);
 
-            Err(Error::SnapdError { status, .. }) if status == StatusCode::NOT_FOUND => {
-                self.send_update(PromptUpdate::Drop(id));
-                return;
+        while let Some(update) = updates.next().await {
+            if let Some(update) = update {
+                self.send_update(update);
             }
 
-            Err(Error::UnsupportedInterface { interface }) => {
-                warn!(?id, %interface, "unsupported interface from snapd; ignoring prompt");
+            if !self.running {
                 return;
             }
-
-            Err(e) => {
-                warn!(%e, "unable to pull prompt");
-                return;
-            }
-        };
-
-        debug!("prompt details: {prompt:?}");
-
-        self.process_prompt(prompt).await;
-    }
-
-    async fn process_prompt(&mut self, prompt: TypedPrompt) {
-        let meta = get_snap_meta(&self.client, prompt.snap()).await;
-        self.send_update(PromptUpdate::Add(EnrichedPrompt { prompt, meta }));
+        }
     }
 
     /// Catch up on all pending prompts before dropping into polling the notices API
@@ -163,11 +235,12 @@ impl PollLoop {
 
         let n_prompts = pending.len();
         info!(%n_prompts, "processing {n_prompts} pending prompts");
-        let mut seen = Vec::with_capacity(pending.len());
-        for prompt in pending {
-            seen.push(prompt.id().clone());
-            self.process_prompt(prompt).await;
-        }
+        let seen: Vec<_> = pending.iter().map(|p| p.id().clone()).collect();
+        let (client, enricher) = (self.client.clone(), self.enricher.clone());
+        let updates = pending
+            .into_iter()
+            .map(|prompt| async { Some(enricher.enrich(&client, prompt).await) });
+        self.send_updates_in_order(updates).await;
 
         // The timestamps we get back from the prompts API are not semantically compatible with
         // the ones that we need to provide for the notices API, so we deliberately set up an
@@ -180,15 +253,304 @@ impl PollLoop {
                 return;
             }
         };
+        if !notices.is_empty() {
+            self.store_notices_cursor();
+        }
+
+        let updates = notices
+            .into_iter()
+            .filter(|notice| matches!(notice, PromptNotice::Update(id) if !seen.contains(id)))
+            .map(|notice| pull_prompt_update(&client, &enricher, notice));
+        self.send_updates_in_order(updates).await;
+    }
+}
+
+/// Run the provided futures with at most `limit` in flight at any one time, yielding their
+/// outputs in the order that the futures were provided rather than the order they complete in.
+fn in_order_bounded<F>(
+    futures: impl IntoIterator<Item = F>,
+    limit: usize,
+) -> impl Stream<Item = F::Output>
+where
+    F: Future,
+{
+    stream::iter(futures).buffered(limit)
+}
+
+async fn pull_prompt_update<C: Client>(
+    client: &SnapdClient<C>,
+    enricher: &Enricher,
+    notice: PromptNotice,
+) -> Option<PromptUpdate> {
+    let id = match notice {
+        PromptNotice::Update(id) => id,
+        PromptNotice::Resolved(id) => return Some(PromptUpdate::Drop(id)),
+    };
+
+    debug!(?id, "pulling prompt details from snapd");
+    let prompt = match client.prompt_details(&id).await {
+        Ok(p) => p,
+
+        Err(Error::SnapdError { status, .. }) if status == StatusCode::NOT_FOUND => {
+            return Some(PromptUpdate::Drop(id));
+        }
+
+        Err(e) => {
+            warn!(%e, "unable to pull prompt");
+            return None;
+        }
+    };
+
+    debug!("prompt details: {prompt:?}");
+
+    Some(enricher.enrich(client, prompt).await)
+}
+
+/// The local and remote sources of additional information about a prompt that we pull before
+/// passing it on to the worker.
+#[derive(Debug, Clone, Default)]
+struct Enricher {
+    procfs: ProcFs,
+    desktop_entries: DesktopEntries,
+    meta_cache: SnapMetaCache,
+}
+
+impl Enricher {
+    async fn enrich<C: Client>(
+        &self,
+        client: &SnapdClient<C>,
+        prompt: TypedPrompt,
+    ) -> PromptUpdate {
+        // The process needs to be looked up as soon as possible after we receive the prompt in
+        // order to reduce the chance of it exiting (and its pid being reused) in the meantime.
+        let process = self.procfs.prompting_process(prompt.pid());
+        let app_name = match prompt.snap_cgroup() {
+            Ok(snap_cgroup) => snap_cgroup.app().map(String::from),
+            Err(error) => {
+                warn!(id=%prompt.id().0, %error, "unable to identify prompting app from cgroup");
+                None
+            }
+        };
+        let (desktop_entries, snap, app) = (
+            self.desktop_entries.clone(),
+            prompt.snap().to_string(),
+            app_name.clone(),
+        );
+        let app_details = run_blocking(move || {
+            let entry = desktop_entries.lookup(&snap, app.as_deref())?;
+            let title = entry.name(desktop_entries.locale()).to_string();
 
-        for notice in notices {
-            match notice {
-                PromptNotice::Update(id) if !seen.contains(&id) => {
-                    self.pull_and_process_prompt(id).await
+            Some((title, entry.load_icon().and_then(normalise_icon)))
+        });
+        let (meta, app_details) =
+            tokio::join!(self.meta_cache.get(client, prompt.snap()), app_details);
+        let meta = meta.map(|mut meta| {
+            // Prefer the icon of the specific app over the one for the snap as a whole
+            if let Some((title, icon)) = app_details {
+                meta.app_title = title;
+                if icon.is_some() {
+                    meta.snap_icon = icon;
                 }
+            }
 
-                _ => (),
-            };
+            SnapMeta {
+                app_name: app_name.unwrap_or_default(),
+                ..meta
+            }
+        });
+
+        PromptUpdate::Add(EnrichedPrompt {
+            prompt,
+            meta,
+            process,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapd_client::{
+        interfaces::{
+            home::{HomeConstraints, HomeInterface},
+            SnapInterface,
+        },
+        FakeSnapd, Prompt, PromptId,
+    };
+    use chrono::Utc;
+    use std::{
+        env, fs,
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+    use tokio::{
+        sync::mpsc::{unbounded_channel, UnboundedReceiver},
+        time::timeout,
+    };
+    use uuid::Uuid;
+
+    fn home_prompt(id: &str) -> TypedPrompt {
+        TypedPrompt::Home(Prompt {
+            id: PromptId(id.to_string()),
+            timestamp: "2024-08-14T09:47:03.350324989Z".to_string(),
+            snap: "firefox".to_string(),
+            pid: 1234,
+            cgroup: "cgroup".into(),
+            interface: HomeInterface::NAME.to_string(),
+            constraints: HomeConstraints::default(),
+        })
+    }
+
+    async fn next_update(rx: &mut UnboundedReceiver<PromptUpdate>) -> PromptUpdate {
+        timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("timed out waiting for update")
+            .expect("channel closed")
+    }
+
+    #[tokio::test]
+    async fn poll_loop_forwards_outstanding_and_new_prompts() {
+        let fake = FakeSnapd::builder().with_prompt(home_prompt("1")).build();
+        let client = SnapdClient::new_with_client(fake.clone(), Utc::now());
+        let (tx, mut rx) = unbounded_channel();
+        let handle = tokio::spawn(PollLoop::new(client, tx).run());
+
+        match next_update(&mut rx).await {
+            PromptUpdate::Add(ep) => assert_eq!(ep.prompt.id(), &PromptId("1".into())),
+            update => panic!("unexpected update: {update:?}"),
+        }
+
+        fake.add_prompt(home_prompt("2"));
+        match next_update(&mut rx).await {
+            PromptUpdate::Add(ep) => assert_eq!(ep.prompt.id(), &PromptId("2".into())),
+            update => panic!("unexpected update: {update:?}"),
+        }
+
+        fake.resolve_prompt(&PromptId("2".into()));
+        match next_update(&mut rx).await {
+            PromptUpdate::Drop(id) => assert_eq!(id, PromptId("2".into())),
+            update => panic!("unexpected update: {update:?}"),
+        }
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn app_details_are_added_to_snap_meta() {
+        let mut prompt = home_prompt("1");
+        if let TypedPrompt::Home(p) = &mut prompt {
+            p.snap = "code".to_string();
+            p.cgroup = "/user.slice/user-1000.slice/user@1000.service/app.slice/snap.code.url-handler-4b9a3f4e-1f67-4a6e-9d0b-2b1c0e4f7a11.scope".into();
+        }
+        let fake = FakeSnapd::builder()
+            .with_prompt(prompt)
+            .with_snap_meta(SnapMeta {
+                name: "code".to_string(),
+                ..Default::default()
+            })
+            .build();
+        let data_dir = env::temp_dir().join(Uuid::new_v4().to_string());
+        fs::create_dir_all(data_dir.join("applications")).unwrap();
+        fs::write(
+            data_dir.join("applications/code_url-handler.desktop"),
+            "[Desktop Entry]\nName=Code - URL Handler\nName[de]=Code - URL-Handler\n",
+        )
+        .unwrap();
+        let client = SnapdClient::new_with_client(fake, Utc::now());
+        let (tx, mut rx) = unbounded_channel();
+        let mut poll_loop = PollLoop::new(client, tx);
+        poll_loop.with_desktop_entries(DesktopEntries::new(
+            vec![data_dir.clone()],
+            Some("de_DE.UTF-8".to_string()),
+        ));
+        let handle = tokio::spawn(poll_loop.run());
+
+        match next_update(&mut rx).await {
+            PromptUpdate::Add(ep) => {
+                let meta = ep.meta.expect("snap meta");
+                assert_eq!(meta.app_name, "url-handler");
+                assert_eq!(meta.app_title, "Code - URL-Handler");
+            }
+            update => panic!("unexpected update: {update:?}"),
+        }
+
+        handle.abort();
+        fs::remove_dir_all(data_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn poll_loop_resumes_after_snapd_restarts() {
+        let fake = FakeSnapd::default();
+        let client = SnapdClient::new_with_client(fake.clone(), Utc::now());
+        let (tx, mut rx) = unbounded_channel();
+        let mut poll_loop = PollLoop::new(client, tx);
+        poll_loop.skip_outstanding_prompts();
+
+        fake.set_available(false);
+        let handle = tokio::spawn(poll_loop.run());
+        sleep(Duration::from_millis(300)).await;
+        assert!(!handle.is_finished());
+
+        fake.set_available(true);
+        fake.add_prompt(home_prompt("1"));
+        match next_update(&mut rx).await {
+            PromptUpdate::Add(ep) => assert_eq!(ep.prompt.id(), &PromptId("1".into())),
+            update => panic!("unexpected update: {update:?}"),
+        }
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn errors_checking_prompting_after_a_restart_are_retried() {
+        let fake = FakeSnapd::default();
+        let client = SnapdClient::new_with_client(fake.clone(), Utc::now());
+        let (tx, mut rx) = unbounded_channel();
+        let mut poll_loop = PollLoop::new(client, tx);
+        poll_loop.skip_outstanding_prompts();
+
+        fake.set_available(false);
+        let handle = tokio::spawn(poll_loop.run());
+        sleep(Duration::from_millis(300)).await;
+
+        fake.fail_system_info(1);
+        fake.set_available(true);
+        fake.add_prompt(home_prompt("1"));
+        match next_update(&mut rx).await {
+            PromptUpdate::Add(ep) => assert_eq!(ep.prompt.id(), &PromptId("1".into())),
+            update => panic!("unexpected update: {update:?}"),
         }
+
+        fake.add_prompt(home_prompt("2"));
+        match next_update(&mut rx).await {
+            PromptUpdate::Add(ep) => assert_eq!(ep.prompt.id(), &PromptId("2".into())),
+            update => panic!("unexpected update: {update:?}"),
+        }
+        assert!(!handle.is_finished());
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn in_order_bounded_preserves_order_and_limits_concurrency() {
+        let in_flight = AtomicUsize::new(0);
+        let max_seen = AtomicUsize::new(0);
+
+        // Earlier futures take longer to complete than later ones
+        let futures = (0..10u64).map(|i| {
+            let (in_flight, max_seen) = (&in_flight, &max_seen);
+            async move {
+                let n = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_seen.fetch_max(n, Ordering::SeqCst);
+                sleep(Duration::from_millis(50 - 5 * i)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                i
+            }
+        });
+
+        let outputs: Vec<u64> = in_order_bounded(futures, 3).collect().await;
+
+        assert_eq!(outputs, (0..10).collect::<Vec<_>>());
+        assert_eq!(max_seen.load(Ordering::SeqCst), 3);
     }
 }
@@ -0,0 +1,166 @@
+//! An in-memory record of the most recently handled prompts and how they were resolved.
+//!
+//! This is the same information that is written to the [AuditLog][crate::daemon::AuditLog] but
+//! it is held by the worker so that it can be queried over GRPC without needing to read and parse
+//! the log file.
+use crate::{
+    daemon::{AuditOutcome, EnrichedPrompt},
+    map_enum,
+    protos::apparmor_prompting::{
+        self, recent_prompt::Outcome as ProtoOutcome, RecentPrompt as ProtoRecentPrompt,
+        RecentPromptReply,
+    },
+    snapd_client::{self, TypedPrompt, TypedPromptReply},
+};
+use chrono::{DateTime, Utc};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+/// A prompt handled by the worker along with its outcome.
+#[derive(Debug, Clone)]
+pub struct RecentPrompt {
+    pub(crate) timestamp: DateTime<Utc>,
+    pub(crate) prompt: TypedPrompt,
+    pub(crate) outcome: AuditOutcome,
+}
+
+impl RecentPrompt {
+    pub fn new(enriched_prompt: &EnrichedPrompt, outcome: AuditOutcome) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            prompt: enriched_prompt.prompt.clone(),
+            outcome,
+        }
+    }
+
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    pub fn prompt(&self) -> &TypedPrompt {
+        &self.prompt
+    }
+
+    pub fn outcome(&self) -> &AuditOutcome {
+        &self.outcome
+    }
+}
+
+/// A bounded buffer of [RecentPrompt]s shared between the worker and the GRPC server.
+#[derive(Debug, Clone, Default)]
+pub struct RefRecentPrompts(Arc<Mutex<VecDeque<RecentPrompt>>>);
+
+impl RefRecentPrompts {
+    /// Record a newly handled prompt, evicting the oldest entries if we are over capacity.
+    pub fn push(&self, recent_prompt: RecentPrompt, capacity: usize) {
+        let mut guard = match self.0.lock() {
+            Ok(guard) => guard,
+            Err(err) => err.into_inner(),
+        };
+
+        guard.push_front(recent_prompt);
+        guard.truncate(capacity);
+    }
+
+    /// The currently held prompts, most recent first.
+    pub fn snapshot(&self) -> Vec<RecentPrompt> {
+        let guard = match self.0.lock() {
+            Ok(guard) => guard,
+            Err(err) => err.into_inner(),
+        };
+
+        guard.iter().cloned().collect()
+    }
+}
+
+fn map_reply(reply: &TypedPromptReply) -> RecentPromptReply {
+    let action = map_enum!(
+        snapd_client::Action => apparmor_prompting::Action;
+        [Allow, Deny];
+        reply.action();
+    );
+    let lifespan = map_enum!(
+        snapd_client::Lifespan => apparmor_prompting::RuleLifespan;
+        [Single, Session, Forever, Timespan];
+        reply.lifespan();
+    );
+
+    RecentPromptReply {
+        action: action as i32,
+        lifespan: lifespan as i32,
+    }
+}
+
+impl From<RecentPrompt> for ProtoRecentPrompt {
+    fn from(recent_prompt: RecentPrompt) -> Self {
+        let outcome = match &recent_prompt.outcome {
+            AuditOutcome::Replied { reply } => ProtoOutcome::Replied(map_reply(reply)),
+            AuditOutcome::Policy { reply } => ProtoOutcome::Policy(map_reply(reply)),
+            AuditOutcome::Error { error } => ProtoOutcome::Error(error.clone()),
+            AuditOutcome::Timeout { .. } => ProtoOutcome::Timeout(()),
+            AuditOutcome::NotFound => ProtoOutcome::NotFound(()),
+            AuditOutcome::ResolvedBy { by } => ProtoOutcome::ResolvedBy(by.0.clone()),
+            AuditOutcome::Cancelled => ProtoOutcome::Cancelled(()),
+            AuditOutcome::ProcessExited => ProtoOutcome::ProcessExited(()),
+        };
+
+        Self {
+            prompt_id: recent_prompt.prompt.id().0.clone(),
+            timestamp: recent_prompt.timestamp.to_rfc3339(),
+            snap: recent_prompt.prompt.snap().to_string(),
+            interface: recent_prompt.prompt.interface().to_string(),
+            outcome: Some(outcome),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon::{enriched_home_prompt, UiFallback};
+
+    #[test]
+    fn oldest_prompts_are_evicted_when_full() {
+        let recent_prompts = RefRecentPrompts::default();
+
+        for id in ["1", "2", "3", "4"] {
+            recent_prompts.push(
+                RecentPrompt::new(
+                    &enriched_home_prompt(id, "cgroup_0"),
+                    AuditOutcome::Timeout {
+                        fallback: UiFallback::DenyOnce,
+                    },
+                ),
+                3,
+            );
+        }
+
+        let ids: Vec<_> = recent_prompts
+            .snapshot()
+            .into_iter()
+            .map(|r| r.prompt.id().0.clone())
+            .collect();
+
+        assert_eq!(ids, vec!["4", "3", "2"]);
+    }
+
+    #[test]
+    fn replies_are_mapped_to_proto() {
+        let ep = enriched_home_prompt("1", "cgroup_0");
+        let reply = ep.prompt.clone().into_allow_forever();
+        let proto = ProtoRecentPrompt::from(RecentPrompt::new(&ep, AuditOutcome::Policy { reply }));
+
+        assert_eq!(proto.prompt_id, "1");
+        assert_eq!(proto.snap, "test");
+        assert_eq!(proto.interface, "home");
+        assert_eq!(
+            proto.outcome,
+            Some(ProtoOutcome::Policy(RecentPromptReply {
+                action: apparmor_prompting::Action::Allow as i32,
+                lifespan: apparmor_prompting::RuleLifespan::Forever as i32,
+            }))
+        );
+    }
+}
@@ -1,13 +1,22 @@
 //! The GRPC server that handles incoming connections from client UIs.
 use crate::{
-    daemon::{worker::RefActivePrompts, ActionedPrompt, ReplyToPrompt},
+    daemon::{
+        conflicts::reply_replacing_conflicting_rules, worker::RefActivePrompts, ActionedPrompt,
+        ConfigReloader, ManageRules, RefRecentPrompts, ReplyToPrompt,
+    },
     log_filter,
     protos::{
-        apparmor_prompting::{HomePermission, PromptReply, SetLoggingFilterResponse},
-        AppArmorPrompting, AppArmorPromptingServer, GetCurrentPromptResponse, PromptReplyResponse,
-        ResolveHomePatternTypeResponse,
+        apparmor_prompting::{
+            HomePatternType, HomePermission, PromptReply, SetLoggingFilterResponse,
+        },
+        AppArmorPrompting, AppArmorPromptingServer, GetCurrentPromptResponse,
+        ListRecentPromptsResponse, ListRulesResponse, PromptReplyResponse, ReloadConfigResponse,
+        RemoveRuleResponse, ResolveHomePatternTypeResponse,
+    },
+    snapd_client::{
+        interfaces::{device::split_device_reply, home::HomeInterface},
+        Cgroup, PromptId, RuleId, SnapdError, TypedPromptReply,
     },
-    snapd_client::{Cgroup, PromptId, SnapdError, TypedPromptReply},
     Error,
 };
 use std::sync::Arc;
@@ -23,18 +32,22 @@ use tracing_subscriber::{reload::Handle, EnvFilter};
 pub fn new_server_and_listener<R, S>(
     client: R,
     reload_handle: S,
+    config: ConfigReloader,
     active_prompts: RefActivePrompts,
+    recent_prompts: RefRecentPrompts,
     tx_actioned_prompts: UnboundedSender<ActionedPrompt>,
     socket_path: String,
 ) -> (AppArmorPromptingServer<Service<R, S>>, UnixListener)
 where
-    R: ReplyToPrompt + Clone,
+    R: ReplyToPrompt + ManageRules + Clone,
     S: SetLogFilter,
 {
     let service = Service::new(
         client.clone(),
         reload_handle,
+        config,
         active_prompts,
+        recent_prompts,
         tx_actioned_prompts,
     );
     let listener = UnixListener::bind(&socket_path).expect("to be able to bind to our socket");
@@ -69,30 +82,36 @@ where
 
 pub struct Service<R, S>
 where
-    R: ReplyToPrompt,
+    R: ReplyToPrompt + ManageRules,
     S: SetLogFilter,
 {
     client: R,
     reload_handle: S,
+    config: ConfigReloader,
     active_prompts: RefActivePrompts,
+    recent_prompts: RefRecentPrompts,
     tx_actioned_prompts: UnboundedSender<ActionedPrompt>,
 }
 
 impl<R, S> Service<R, S>
 where
-    R: ReplyToPrompt,
+    R: ReplyToPrompt + ManageRules,
     S: SetLogFilter,
 {
     pub fn new(
         client: R,
         reload_handle: S,
+        config: ConfigReloader,
         active_prompts: RefActivePrompts,
+        recent_prompts: RefRecentPrompts,
         tx_actioned_prompts: UnboundedSender<ActionedPrompt>,
     ) -> Self {
         Self {
             client,
             reload_handle,
+            config,
             active_prompts,
+            recent_prompts,
             tx_actioned_prompts,
         }
     }
@@ -102,12 +121,32 @@ where
             panic!("send on closed tx_actioned_prompts channel: {e}");
         }
     }
+
+    async fn list_rules_response(
+        &self,
+        snap: Option<&str>,
+    ) -> Result<Response<ListRulesResponse>, Status> {
+        let rules = self
+            .client
+            .list_rules(snap)
+            .await
+            .map_err(|e| Status::internal(format!("unable to list rules: {e}")))?;
+
+        debug!(?snap, "listing {} rules", rules.len());
+
+        Ok(Response::new(ListRulesResponse {
+            rules: rules
+                .into_iter()
+                .map(|r| r.try_into())
+                .collect::<Result<Vec<_>, Status>>()?,
+        }))
+    }
 }
 
 #[async_trait]
 impl<R, S> AppArmorPrompting for Service<R, S>
 where
-    R: ReplyToPrompt,
+    R: ReplyToPrompt + ManageRules,
     S: SetLogFilter,
 {
     type GetCurrentPromptStream = ReceiverStream<Result<GetCurrentPromptResponse, Status>>;
@@ -163,12 +202,83 @@ where
 
         let req = request.into_inner();
         let id = PromptId(req.prompt_id.clone());
-        let reply: TypedPromptReply = req.try_into()?;
+        let replace_conflicting_rules = req.replace_conflicting_rules;
+        // Replies to device prompts are applied to the camera prompt first and then to the
+        // microphone prompt that was presented alongside it.
+        let (reply, microphone_reply): (TypedPromptReply, _) = match split_device_reply(&req) {
+            Some(res) => {
+                let [(_, camera_reply), (microphone_id, microphone_reply)] = res?;
+                // The microphone prompt is taken from the pairing made by the worker rather than
+                // trusting the ID sent by the client.
+                match self.active_prompts.paired_prompt_id(&id) {
+                    Some(Some(paired_id)) if paired_id == microphone_id => {
+                        (camera_reply, Some((paired_id, microphone_reply)))
+                    }
+                    // The microphone prompt has gone away while the dialog was open so only the
+                    // camera prompt still needs a reply.
+                    Some(None) => (camera_reply, None),
+                    Some(Some(paired_id)) => {
+                        return Err(Status::invalid_argument(format!(
+                            "prompt {} is paired with microphone prompt {}, not {}",
+                            id.0, paired_id.0, microphone_id.0
+                        )));
+                    }
+                    None => {
+                        return Err(Status::invalid_argument(format!(
+                            "prompt {} is not paired with a microphone prompt",
+                            id.0
+                        )));
+                    }
+                }
+            }
+            None => {
+                let prompt = self.active_prompts.prompt_with_id(&id);
+                let reply = TypedPromptReply::try_from_proto_for_prompt(req, prompt.as_ref())?;
+                (reply, None)
+            }
+        };
 
         debug!(id=%id.0, "replying to prompt id={}", id.0);
-        let resp = match self.client.reply(&id, reply).await {
+        let mut res = self.client.reply(&id, reply.clone()).await;
+
+        let conflicts = match &res {
+            Err(Error::SnapdError { err, .. }) => match err.as_ref() {
+                SnapdError::RuleConflicts { conflicts } => Some(conflicts.clone()),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        if let (true, Some(conflicts)) = (replace_conflicting_rules, conflicts) {
+            info!(id=%id.0, "replacing conflicting rules before retrying reply");
+            res = reply_replacing_conflicting_rules(&self.client, &id, reply.clone(), conflicts)
+                .await;
+        }
+
+        if let (Ok(others), Some((microphone_id, microphone_reply))) = (&mut res, microphone_reply)
+        {
+            debug!(id=%microphone_id.0, "replying to paired microphone prompt");
+            match self.client.reply(&microphone_id, microphone_reply).await {
+                Ok(microphone_others) => {
+                    others.extend(microphone_others);
+                    others.push(microphone_id);
+                }
+                Err(Error::SnapdError { err, .. })
+                    if matches!(err.as_ref(), SnapdError::PromptNotFound) =>
+                {
+                    others.push(microphone_id);
+                }
+                // The worker will present the microphone prompt on its own once the camera prompt
+                // has been cleared.
+                Err(e) => {
+                    warn!(id=%microphone_id.0, "unable to reply to paired microphone prompt: {e}")
+                }
+            }
+        }
+
+        let resp = match res {
             Ok(others) => {
-                self.update_worker(ActionedPrompt::Actioned { id, others })
+                self.update_worker(ActionedPrompt::Actioned { id, reply, others })
                     .await;
 
                 PromptReplyResponse {
@@ -256,13 +366,22 @@ where
 
     async fn resolve_home_pattern_type(
         &self,
-        _: Request<String>,
+        request: Request<String>,
     ) -> Result<Response<ResolveHomePatternTypeResponse>, Status> {
-        // FIXME: finish this endpoint
-        Err(Status::new(
-            Code::Unimplemented,
-            "this endpoint is not yet implemented",
-        ))
+        let path_pattern = request.into_inner();
+        if !path_pattern.starts_with('/') {
+            return Err(Status::new(
+                Code::InvalidArgument,
+                format!("{path_pattern:?} is not an absolute path pattern"),
+            ));
+        }
+
+        let pattern_type = HomeInterface::resolve_pattern_type(&path_pattern);
+        debug!(%path_pattern, ?pattern_type, "resolved home pattern type");
+
+        Ok(Response::new(ResolveHomePatternTypeResponse {
+            home_pattern_type: HomePatternType::from(pattern_type) as i32,
+        }))
     }
 
     async fn set_logging_filter(
@@ -279,6 +398,79 @@ where
             )),
         }
     }
+
+    async fn reload_config(
+        &self,
+        _request: Request<()>,
+    ) -> Result<Response<ReloadConfigResponse>, Status> {
+        match self.config.reload(&self.reload_handle) {
+            Ok(_) => Ok(Response::new(ReloadConfigResponse {
+                path: self
+                    .config
+                    .path()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_default(),
+            })),
+            Err(e) => Err(Status::new(
+                Code::InvalidArgument,
+                format!("unable to reload config: {e}"),
+            )),
+        }
+    }
+
+    async fn list_recent_prompts(
+        &self,
+        _request: Request<()>,
+    ) -> Result<Response<ListRecentPromptsResponse>, Status> {
+        let prompts = self.recent_prompts.snapshot();
+        debug!("listing {} recent prompts", prompts.len());
+
+        Ok(Response::new(ListRecentPromptsResponse {
+            prompts: prompts.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn list_rules(
+        &self,
+        _request: Request<()>,
+    ) -> Result<Response<ListRulesResponse>, Status> {
+        self.list_rules_response(None).await
+    }
+
+    async fn list_rules_for_snap(
+        &self,
+        request: Request<String>,
+    ) -> Result<Response<ListRulesResponse>, Status> {
+        let snap = request.into_inner();
+        if snap.is_empty() {
+            return Err(Status::new(Code::InvalidArgument, "no snap name provided"));
+        }
+
+        self.list_rules_response(Some(&snap)).await
+    }
+
+    async fn remove_rule(
+        &self,
+        request: Request<String>,
+    ) -> Result<Response<RemoveRuleResponse>, Status> {
+        let id = RuleId(request.into_inner());
+
+        match self.client.remove_rule(&id).await {
+            Ok(rule) => {
+                info!(id=%id.0, snap=rule.snap(), "removed rule (id={})", id.0);
+                Ok(Response::new(RemoveRuleResponse {
+                    rule: Some(rule.try_into()?),
+                }))
+            }
+
+            Err(Error::SnapdError { message, err, .. }) if *err == SnapdError::RuleNotFound => {
+                warn!(id=%id.0, "rule not found (id={})", id.0);
+                Err(Status::not_found(message))
+            }
+
+            Err(e) => Err(Status::internal(format!("unable to remove rule: {e}"))),
+        }
+    }
 }
 
 fn map_permission(perm: &str) -> Result<i32, Status> {
@@ -302,8 +494,9 @@ mod tests {
     use crate::{
         daemon::{
             worker::{ActivePrompt, RefActivePrompts},
-            EnrichedPrompt,
+            AuditOutcome, EnrichedPrompt, RecentPrompt, UiFallback,
         },
+        procfs::PromptingProcess,
         protos::apparmor_prompting::{
             self,
             app_armor_prompting_client::AppArmorPromptingClient,
@@ -311,24 +504,34 @@ mod tests {
             get_current_prompt_response::Prompt,
             prompt_reply::{self, PromptReply::HomePromptReply},
             prompt_reply_response::PromptReplyType,
-            Action, EnrichedPathKind as ProtoEnrichedPathKind, HomeDir, HomePrompt, Lifespan,
-            MetaData,
+            recent_prompt::Outcome,
+            Action, DevicePermission, DevicePromptReply, EnrichedPathKind as ProtoEnrichedPathKind,
+            HomeDir, HomePrompt, Lifespan, MetaData,
         },
         snapd_client::{
             self,
-            interfaces::home::{
-                EnrichedPathKind, HomeConstraints, HomeInterface, HomeReplyConstraints,
-                HomeUiInputData,
+            interfaces::{
+                camera::{
+                    CameraConstraints, CameraInterface, CameraRuleConstraints, CameraUiInputData,
+                },
+                device::DeviceUiInput,
+                home::{
+                    EnrichedPathKind, HomeConstraints, HomeInterface, HomeReplyConstraints,
+                    HomeRuleConstraints, HomeUiInputData,
+                },
+                microphone::{MicrophoneConstraints, MicrophoneInterface, MicrophoneUiInputData},
             },
-            Cgroup, Prompt as SnapPrompt, PromptId, PromptReply as SnapPromptReply, SnapIcon,
-            SnapMeta, TypedPrompt, TypedPromptReply, TypedUiInput, UiInput,
+            Cgroup, PermissionEntry, Prompt as SnapPrompt, PromptId,
+            PromptReply as SnapPromptReply, Rule, SnapIcon, SnapMeta, TypedPrompt,
+            TypedPromptReply, TypedRule, TypedUiInput, UiInput,
         },
         Error,
     };
+    use hyper::StatusCode;
     use simple_test_case::test_case;
     use std::{
-        collections::HashMap,
-        fs, io,
+        collections::{BTreeMap, HashMap},
+        env, fs, io,
         ops::{Deref, DerefMut},
     };
     use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
@@ -347,6 +550,61 @@ mod tests {
         expected_reply: Option<TypedPromptReply>,
     }
 
+    #[async_trait]
+    impl ManageRules for MockClient {
+        async fn list_rules(&self, snap: Option<&str>) -> crate::Result<Vec<TypedRule>> {
+            if self.want_err {
+                return Err(Error::Io(io::Error::other(
+                    "error requested of mock snapd client",
+                )));
+            }
+
+            Ok(rules()
+                .into_iter()
+                .filter(|r| snap.is_none_or(|s| r.snap() == s))
+                .collect())
+        }
+
+        async fn get_rule(&self, id: &RuleId) -> crate::Result<TypedRule> {
+            self.remove_rule(id).await
+        }
+
+        async fn add_rule(&self, rule: TypedRule) -> crate::Result<TypedRule> {
+            if self.want_err {
+                return Err(Error::Io(io::Error::other(
+                    "error requested of mock snapd client",
+                )));
+            }
+
+            Ok(rule)
+        }
+
+        async fn patch_rule(
+            &self,
+            id: &RuleId,
+            _permissions: BTreeMap<String, Option<PermissionEntry>>,
+        ) -> crate::Result<TypedRule> {
+            self.get_rule(id).await
+        }
+
+        async fn remove_rule(&self, id: &RuleId) -> crate::Result<TypedRule> {
+            if self.want_err {
+                return Err(Error::Io(io::Error::other(
+                    "error requested of mock snapd client",
+                )));
+            }
+
+            rules()
+                .into_iter()
+                .find(|r| r.id() == id)
+                .ok_or_else(|| Error::SnapdError {
+                    status: StatusCode::NOT_FOUND,
+                    message: "rule not found".to_string(),
+                    err: Box::new(SnapdError::RuleNotFound),
+                })
+        }
+    }
+
     #[async_trait]
     impl ReplyToPrompt for MockClient {
         async fn reply(
@@ -413,6 +671,21 @@ mod tests {
         mock_client: MockClient,
         active_prompts: RefActivePrompts,
         tx_actioned_prompts: UnboundedSender<ActionedPrompt>,
+    ) -> SelfCleaningClient {
+        setup_server_and_client_with_recent_prompts(
+            mock_client,
+            active_prompts,
+            RefRecentPrompts::default(),
+            tx_actioned_prompts,
+        )
+        .await
+    }
+
+    async fn setup_server_and_client_with_recent_prompts(
+        mock_client: MockClient,
+        active_prompts: RefActivePrompts,
+        recent_prompts: RefRecentPrompts,
+        tx_actioned_prompts: UnboundedSender<ActionedPrompt>,
     ) -> SelfCleaningClient {
         let test_name = Uuid::new_v4().to_string();
         let socket_path = format!("/tmp/{test_name}_socket");
@@ -421,7 +694,9 @@ mod tests {
         let (server, listener) = new_server_and_listener(
             mock_client,
             MockReloadHandle,
+            ConfigReloader::try_new(None).unwrap(),
             active_prompts,
+            recent_prompts,
             tx_actioned_prompts,
             socket_path.clone(),
         );
@@ -446,6 +721,56 @@ mod tests {
         ActivePrompt {
             typed_ui_input: ui_input(),
             enriched_prompt: enriched_prompt(),
+            paired_prompt: None,
+            ui_handle: Some(ui_handle),
+        }
+    }
+
+    /// An active device prompt for the "camera" prompt, optionally still paired with the
+    /// "microphone" prompt.
+    fn device_active_prompt(paired: bool) -> ActivePrompt {
+        let (_, ui_handle) = Context::new();
+        let enriched = |prompt| EnrichedPrompt {
+            prompt,
+            meta: None,
+            process: PromptingProcess::Unknown,
+        };
+        let camera = TypedPrompt::Camera(SnapPrompt {
+            id: PromptId("camera".to_string()),
+            timestamp: "0".to_string(),
+            snap: "2".to_string(),
+            pid: 1234,
+            cgroup: Cgroup("cgroup".to_string()),
+            interface: "camera".to_string(),
+            constraints: CameraConstraints::default(),
+        });
+        let microphone = TypedPrompt::Microphone(SnapPrompt {
+            id: PromptId("microphone".to_string()),
+            timestamp: "0".to_string(),
+            snap: "2".to_string(),
+            pid: 1234,
+            cgroup: Cgroup("cgroup".to_string()),
+            interface: "audio-record".to_string(),
+            constraints: MicrophoneConstraints::default(),
+        });
+
+        ActivePrompt {
+            typed_ui_input: TypedUiInput::Device(DeviceUiInput {
+                camera: UiInput::<CameraInterface> {
+                    id: PromptId("camera".to_string()),
+                    meta: SnapMeta::default(),
+                    process: None,
+                    data: CameraUiInputData {},
+                },
+                microphone: UiInput::<MicrophoneInterface> {
+                    id: PromptId("microphone".to_string()),
+                    meta: SnapMeta::default(),
+                    process: None,
+                    data: MicrophoneUiInputData {},
+                },
+            }),
+            enriched_prompt: enriched(camera),
+            paired_prompt: paired.then(|| enriched(microphone)),
             ui_handle: Some(ui_handle),
         }
     }
@@ -477,13 +802,16 @@ mod tests {
                     bytes: vec![1, 2, 3].into(),
                     mime_type: "8".into(),
                 }),
+                ..Default::default()
             }),
+            process: PromptingProcess::Unknown,
         }
     }
 
     fn ui_input() -> TypedUiInput {
         TypedUiInput::Home(UiInput::<HomeInterface> {
             id: PromptId("1".to_string()),
+            process: None,
             meta: SnapMeta {
                 name: "2".to_string(),
                 updated_at: "3".to_string(),
@@ -493,6 +821,7 @@ mod tests {
                     bytes: vec![1, 2, 3].into(),
                     mime_type: "8".into(),
                 }),
+                ..Default::default()
             },
             data: HomeUiInputData {
                 requested_path: "6".to_string(),
@@ -517,6 +846,7 @@ mod tests {
                 updated_at: "3".to_string(),
                 snap_icon: vec![1, 2, 3],
                 snap_icon_mime_type: "8".to_string(),
+                ..Default::default()
             }),
             requested_path: "6".to_string(),
             home_dir: "7".to_string(),
@@ -537,12 +867,13 @@ mod tests {
             action: Action::Allow as i32,
             lifespan: Lifespan::Single as i32,
             prompt_reply: prompt_reply_inner,
+            replace_conflicting_rules: false,
         }
     }
 
-    fn prompt_reply_inner() -> Option<prompt_reply::PromptReply> {
+    fn prompt_reply_inner(path_pattern: &str) -> Option<prompt_reply::PromptReply> {
         Some(HomePromptReply(apparmor_prompting::HomePromptReply {
-            path_pattern: "6".to_string(),
+            path_pattern: path_pattern.to_string(),
             permissions: Vec::new(),
         }))
     }
@@ -553,13 +884,44 @@ mod tests {
             lifespan: snapd_client::Lifespan::Single,
             duration: None,
             constraints: HomeReplyConstraints {
-                path_pattern: "6".to_string(),
+                path_pattern: "/6".to_string(),
                 permissions: Vec::new(),
                 available_permissions: Vec::new(),
+                requested_path: String::new(),
             },
         })
     }
 
+    fn rules() -> Vec<TypedRule> {
+        vec![
+            TypedRule::Home(Rule {
+                id: RuleId("1".to_string()),
+                timestamp: "0".to_string(),
+                user: 1000,
+                snap: "firefox".to_string(),
+                interface: "home".to_string(),
+                constraints: HomeRuleConstraints::new(
+                    "/home/ubuntu/**",
+                    [(
+                        "read",
+                        PermissionEntry::forever(snapd_client::Action::Allow),
+                    )],
+                ),
+            }),
+            TypedRule::Camera(Rule {
+                id: RuleId("2".to_string()),
+                timestamp: "0".to_string(),
+                user: 1000,
+                snap: "cheese".to_string(),
+                interface: "camera".to_string(),
+                constraints: CameraRuleConstraints::new([(
+                    "access",
+                    PermissionEntry::for_session(snapd_client::Action::Deny),
+                )]),
+            }),
+        ]
+    }
+
     struct ExpectedErrors {
         snapd_err: bool,
         tx_err: bool,
@@ -616,9 +978,10 @@ mod tests {
     }
 
     #[test_case(prompt_reply(None), ExpectedErrors{snapd_err: false, tx_err: false, want_err: true}; "Error when map_prompt_reply fails")]
-    #[test_case(prompt_reply(prompt_reply_inner()), ExpectedErrors{snapd_err: true, tx_err: false, want_err: false}; "Returns unknown error code when snapd returns an error")]
-    #[test_case(prompt_reply(prompt_reply_inner()), ExpectedErrors{snapd_err: false, tx_err: true, want_err: true}; "Error when returning actioned prompts returns an error")]
-    #[test_case(prompt_reply(prompt_reply_inner()), ExpectedErrors{snapd_err: false, tx_err: false, want_err: false}; "Succesfully reply to a prompt")]
+    #[test_case(prompt_reply(prompt_reply_inner("6")), ExpectedErrors{snapd_err: false, tx_err: false, want_err: true}; "Error when the path pattern is invalid")]
+    #[test_case(prompt_reply(prompt_reply_inner("/6")), ExpectedErrors{snapd_err: true, tx_err: false, want_err: false}; "Returns unknown error code when snapd returns an error")]
+    #[test_case(prompt_reply(prompt_reply_inner("/6")), ExpectedErrors{snapd_err: false, tx_err: true, want_err: true}; "Error when returning actioned prompts returns an error")]
+    #[test_case(prompt_reply(prompt_reply_inner("/6")), ExpectedErrors{snapd_err: false, tx_err: false, want_err: false}; "Succesfully reply to a prompt")]
     #[tokio::test]
     async fn test_reply_to_prompt(prompt_reply: PromptReply, expected_errors: ExpectedErrors) {
         let mock_client = MockClient {
@@ -658,4 +1021,247 @@ mod tests {
             }
         }
     }
+
+    #[test_case("/home/ubuntu/Documents/**", true; "matching pattern")]
+    #[test_case("/home/ubuntu/Pictures/**", false; "non-matching pattern")]
+    #[tokio::test]
+    async fn test_reply_to_prompt_checks_path_pattern_against_active_prompt(
+        path_pattern: &str,
+        ok: bool,
+    ) {
+        let mock_client = MockClient {
+            want_err: false,
+            expected_reply: None,
+        };
+        let mut active_prompt = active_prompt();
+        if let TypedPrompt::Home(p) = &mut active_prompt.enriched_prompt.prompt {
+            p.constraints.path = "/home/ubuntu/Documents/foo.txt".to_string();
+        }
+        let cgroup = active_prompt.enriched_prompt.prompt.cgroup().clone();
+        let active_prompts = RefActivePrompts::new(HashMap::from([(cgroup, active_prompt)]));
+        let (tx_actioned_prompts, _rx_actioned_prompts) = unbounded_channel();
+        let mut client =
+            setup_server_and_client(mock_client, active_prompts, tx_actioned_prompts).await;
+
+        let reply = prompt_reply(prompt_reply_inner(path_pattern));
+        let resp = client.reply_to_prompt(Request::new(reply)).await;
+
+        assert_eq!(resp.is_ok(), ok, "{resp:?}");
+    }
+
+    #[test_case(Some(true), "microphone", Some(vec!["microphone"]); "paired prompts")]
+    #[test_case(Some(false), "microphone", Some(vec![]); "microphone prompt gone")]
+    #[test_case(Some(true), "other", None; "mismatched microphone prompt")]
+    #[test_case(None, "microphone", None; "no active device prompt")]
+    #[tokio::test]
+    async fn test_reply_to_device_prompt_uses_paired_prompt(
+        paired: Option<bool>,
+        microphone_prompt_id: &str,
+        expected_others: Option<Vec<&str>>,
+    ) {
+        let mock_client = MockClient {
+            want_err: false,
+            expected_reply: None,
+        };
+        let (tx_actioned_prompts, mut rx_actioned_prompts) = unbounded_channel();
+        let active_prompts = match paired {
+            Some(paired) => RefActivePrompts::new(HashMap::from([(
+                Cgroup("cgroup".to_string()),
+                device_active_prompt(paired),
+            )])),
+            None => RefActivePrompts::new(HashMap::new()),
+        };
+        let mut client =
+            setup_server_and_client(mock_client, active_prompts, tx_actioned_prompts).await;
+
+        let reply = PromptReply {
+            prompt_id: "camera".to_string(),
+            action: Action::Allow as i32,
+            lifespan: Lifespan::Forever as i32,
+            prompt_reply: Some(prompt_reply::PromptReply::DevicePromptReply(
+                DevicePromptReply {
+                    microphone_prompt_id: microphone_prompt_id.to_string(),
+                    permissions: vec![DevicePermission::Access as i32],
+                },
+            )),
+            replace_conflicting_rules: false,
+        };
+        let resp = client.reply_to_prompt(Request::new(reply)).await;
+
+        let expected_others = match expected_others {
+            Some(others) => others,
+            None => {
+                let status = resp.expect_err("reply should be rejected");
+                assert_eq!(status.code(), tonic::Code::InvalidArgument);
+                assert!(rx_actioned_prompts.try_recv().is_err());
+                return;
+            }
+        };
+
+        assert!(matches!(
+            resp.unwrap().into_inner().prompt_reply_type.unwrap(),
+            PromptReplyType::Success(())
+        ));
+        match rx_actioned_prompts.recv().await {
+            Some(ActionedPrompt::Actioned { id, reply, others }) => {
+                assert_eq!(id.0, "camera");
+                assert!(matches!(reply, TypedPromptReply::Camera(_)));
+                let expected: Vec<_> = expected_others
+                    .into_iter()
+                    .map(|id| PromptId(id.to_string()))
+                    .collect();
+                assert_eq!(others, expected);
+            }
+            res => panic!("expected actioned prompt, got {res:?}"),
+        }
+    }
+
+    #[test_case("/home/ubuntu/**", Some(HomePatternType::HomeDirectory); "home directory")]
+    #[test_case("/home/ubuntu/Documents/**", Some(HomePatternType::TopLevelDirectory); "top level directory")]
+    #[test_case("/home/ubuntu/Documents/foo.txt", Some(HomePatternType::RequestedFile); "requested file")]
+    #[test_case("~/Documents/foo.txt", None; "relative path errors")]
+    #[tokio::test]
+    async fn test_resolve_home_pattern_type(path_pattern: &str, expected: Option<HomePatternType>) {
+        let mock_client = MockClient {
+            want_err: false,
+            expected_reply: None,
+        };
+        let (tx_actioned_prompts, _rx_actioned_prompts) = unbounded_channel();
+        let active_prompts = RefActivePrompts::new(HashMap::new());
+        let mut client =
+            setup_server_and_client(mock_client, active_prompts, tx_actioned_prompts).await;
+
+        // We need this env var set to be able to resolve patterns relative to the home directory
+        env::set_var("SNAP_REAL_HOME", "/home/ubuntu");
+
+        let resp = client
+            .resolve_home_pattern_type(Request::new(path_pattern.to_string()))
+            .await;
+
+        match (resp, expected) {
+            (Ok(resp), Some(expected)) => {
+                assert_eq!(resp.into_inner().home_pattern_type(), expected)
+            }
+            (Err(status), None) => assert_eq!(status.code(), Code::InvalidArgument),
+            (resp, expected) => panic!("expected {expected:?}, got {resp:?}"),
+        }
+    }
+
+    #[test_case(None, false, &["1", "2"]; "all rules")]
+    #[test_case(Some("firefox"), false, &["1"]; "rules for snap")]
+    #[test_case(Some("not-a-snap"), false, &[]; "no rules for snap")]
+    #[test_case(Some(""), false, &[]; "empty snap name errors")]
+    #[test_case(None, true, &[]; "snapd error")]
+    #[tokio::test]
+    async fn test_list_rules(snap: Option<&str>, snapd_err: bool, expected_ids: &[&str]) {
+        let mock_client = MockClient {
+            want_err: snapd_err,
+            expected_reply: None,
+        };
+        let (tx_actioned_prompts, _rx_actioned_prompts) = unbounded_channel();
+        let active_prompts = RefActivePrompts::new(HashMap::new());
+        let mut client =
+            setup_server_and_client(mock_client, active_prompts, tx_actioned_prompts).await;
+
+        let resp = match snap {
+            Some(snap) => {
+                client
+                    .list_rules_for_snap(Request::new(snap.to_string()))
+                    .await
+            }
+            None => client.list_rules(Request::new(())).await,
+        };
+
+        match resp {
+            Ok(resp) => {
+                let ids: Vec<String> = resp.into_inner().rules.into_iter().map(|r| r.id).collect();
+                assert_eq!(ids, expected_ids);
+            }
+            Err(status) if snapd_err => assert_eq!(status.code(), Code::Internal),
+            Err(status) if snap == Some("") => assert_eq!(status.code(), Code::InvalidArgument),
+            Err(status) => panic!("unexpected error: {status}"),
+        }
+    }
+
+    #[test_case("1", Ok("firefox"); "home rule")]
+    #[test_case("2", Ok("cheese"); "camera rule")]
+    #[test_case("3", Err(Code::NotFound); "unknown rule")]
+    #[tokio::test]
+    async fn test_remove_rule(id: &str, expected: Result<&str, Code>) {
+        let mock_client = MockClient {
+            want_err: false,
+            expected_reply: None,
+        };
+        let (tx_actioned_prompts, _rx_actioned_prompts) = unbounded_channel();
+        let active_prompts = RefActivePrompts::new(HashMap::new());
+        let mut client =
+            setup_server_and_client(mock_client, active_prompts, tx_actioned_prompts).await;
+
+        let resp = client.remove_rule(Request::new(id.to_string())).await;
+
+        match (resp, expected) {
+            (Ok(resp), Ok(snap)) => {
+                let rule = resp.into_inner().rule.unwrap();
+                assert_eq!(rule.id, id);
+                assert_eq!(rule.snap, snap);
+            }
+            (Err(status), Err(code)) => assert_eq!(status.code(), code),
+            (resp, expected) => panic!("expected {expected:?}, got {resp:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_recent_prompts() {
+        let mock_client = MockClient {
+            want_err: false,
+            expected_reply: None,
+        };
+        let (tx_actioned_prompts, _rx_actioned_prompts) = unbounded_channel();
+        let active_prompts = RefActivePrompts::new(HashMap::new());
+        let recent_prompts = RefRecentPrompts::default();
+        let ep = EnrichedPrompt {
+            prompt: TypedPrompt::Home(SnapPrompt {
+                id: PromptId("1".to_string()),
+                timestamp: String::new(),
+                snap: "firefox".to_string(),
+                pid: 1234,
+                cgroup: "cgroup".into(),
+                interface: "home".to_string(),
+                constraints: HomeConstraints::default(),
+            }),
+            meta: None,
+            process: PromptingProcess::Unknown,
+        };
+        recent_prompts.push(
+            RecentPrompt::new(
+                &ep,
+                AuditOutcome::Timeout {
+                    fallback: UiFallback::DenyOnce,
+                },
+            ),
+            10,
+        );
+        recent_prompts.push(RecentPrompt::new(&ep, AuditOutcome::Cancelled), 10);
+
+        let mut client = setup_server_and_client_with_recent_prompts(
+            mock_client,
+            active_prompts,
+            recent_prompts,
+            tx_actioned_prompts,
+        )
+        .await;
+
+        let prompts = client
+            .list_recent_prompts(Request::new(()))
+            .await
+            .unwrap()
+            .into_inner()
+            .prompts;
+
+        let outcomes: Vec<_> = prompts.into_iter().map(|p| p.outcome).collect();
+        assert_eq!(
+            outcomes,
+            vec![Some(Outcome::Cancelled(())), Some(Outcome::Timeout(()))]
+        );
+    }
 }
@@ -7,10 +7,19 @@
 )]
 
 use crate::{
-    daemon::{ActionedPrompt, EnrichedPrompt, PromptUpdate, ReplyToPrompt},
-    snapd_client::{Cgroup, PromptId, SnapdSocketClient, TypedUiInput},
-    Result,
+    daemon::{
+        default_audit_log_path, ActionedPrompt, AuditLog, AuditOutcome, AuditWriter,
+        EnrichedPrompt, ExperimentalPrompt, PromptUpdate, RecentPrompt, RefConfig,
+        RefRecentPrompts, ReplyToPrompt, UiFallback,
+    },
+    procfs::PromptingProcess,
+    snapd_client::{
+        interfaces::device::DeviceUiInput, Cgroup, PromptId, SnapdError, SnapdSocketClient,
+        TypedPrompt, TypedUiInput,
+    },
+    Error, Result,
 };
+use chrono::{DateTime, TimeDelta};
 use futures::{stream::FuturesUnordered, FutureExt};
 use std::{
     collections::{HashMap, VecDeque},
@@ -30,7 +39,13 @@ use tokio_context::context::{Context, Handle};
 use tokio_stream::StreamExt;
 use tracing::{debug, error, info, warn};
 
-const RECV_TIMEOUT: Duration = Duration::from_millis(200);
+/// The number of times that we will re-show a prompt to the user when the UI exits without a
+/// reply before falling back to deny once.
+const MAX_REQUEUES: usize = 1;
+
+/// The maximum time between a camera prompt and a microphone prompt from the same process for
+/// them to be presented to the user as a single device prompt.
+const DEVICE_PROMPT_WINDOW: TimeDelta = TimeDelta::seconds(2);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Recv {
@@ -48,6 +63,9 @@ pub struct RefActivePrompts(Arc<Mutex<HashMap<Cgroup, ActivePrompt>>>);
 pub struct ActivePrompt {
     pub(crate) typed_ui_input: TypedUiInput,
     pub(crate) enriched_prompt: EnrichedPrompt,
+    /// The microphone prompt shown alongside the camera prompt in `enriched_prompt` when the two
+    /// are presented as a single device prompt.
+    pub(crate) paired_prompt: Option<EnrichedPrompt>,
     pub(crate) ui_handle: Option<Handle>,
 }
 
@@ -80,6 +98,42 @@ impl RefActivePrompts {
         Some(guard.get(cgroup)?.typed_ui_input.clone())
     }
 
+    /// The prompt with the given ID if it is currently being shown to the user.
+    pub fn prompt_with_id(&self, id: &PromptId) -> Option<TypedPrompt> {
+        let guard = match self.0.lock() {
+            Ok(guard) => guard,
+            Err(err) => err.into_inner(),
+        };
+
+        guard
+            .values()
+            .map(|active_prompt| &active_prompt.enriched_prompt.prompt)
+            .find(|prompt| prompt.id() == id)
+            .cloned()
+    }
+
+    /// The microphone prompt paired with the active device prompt with the given camera prompt ID.
+    ///
+    /// Returns `None` if there is no such device prompt and `Some(None)` if the microphone prompt
+    /// has gone away since the device prompt was shown.
+    pub fn paired_prompt_id(&self, id: &PromptId) -> Option<Option<PromptId>> {
+        let guard = match self.0.lock() {
+            Ok(guard) => guard,
+            Err(err) => err.into_inner(),
+        };
+
+        let active_prompt = guard.values().find(|active_prompt| {
+            matches!(&active_prompt.typed_ui_input, TypedUiInput::Device(input) if input.id() == id)
+        })?;
+
+        Some(
+            active_prompt
+                .paired_prompt
+                .as_ref()
+                .map(|p| p.prompt.id().clone()),
+        )
+    }
+
     pub fn get_context(&self, cgroup: &Cgroup) -> Option<Context> {
         let mut guard = match self.0.lock() {
             Ok(guard) => guard,
@@ -138,7 +192,10 @@ where
     dialog_processes: HashMap<Cgroup, D>,
     pending_prompts: HashMap<Cgroup, VecDeque<EnrichedPrompt>>,
     dead_prompts: Vec<PromptId>,
-    recv_timeout: Duration,
+    requeued_prompts: HashMap<PromptId, usize>,
+    config: RefConfig,
+    audit_log: Option<AuditWriter>,
+    recent_prompts: RefRecentPrompts,
     ui: S,
     client: R,
     running: bool,
@@ -149,6 +206,7 @@ impl Worker<FlutterUi, SnapdSocketClient, DialogProcess> {
         rx_prompts: UnboundedReceiver<PromptUpdate>,
         rx_actioned_prompts: UnboundedReceiver<ActionedPrompt>,
         client: SnapdSocketClient,
+        config: RefConfig,
     ) -> Self {
         let cmd = {
             let cmd = if let Ok(snap) = env::var("SNAP") {
@@ -181,7 +239,10 @@ impl Worker<FlutterUi, SnapdSocketClient, DialogProcess> {
             dialog_processes: HashMap::new(),
             pending_prompts: HashMap::new(),
             dead_prompts: Vec::new(),
-            recv_timeout: RECV_TIMEOUT,
+            requeued_prompts: HashMap::new(),
+            config,
+            audit_log: default_audit_log_path().map(|path| AuditLog::new(path).spawn_writer().0),
+            recent_prompts: RefRecentPrompts::default(),
             ui: FlutterUi { cmd },
             client,
             running: false,
@@ -199,6 +260,10 @@ where
         self.active_prompts.clone()
     }
 
+    pub fn read_only_recent_prompts(&self) -> RefRecentPrompts {
+        self.recent_prompts.clone()
+    }
+
     pub async fn run(&mut self) -> Result<()> {
         self.running = true;
 
@@ -255,12 +320,41 @@ where
         }
     }
 
-    fn drop_prompt(&mut self, id: PromptId) {
+    fn record_outcome(&self, enriched_prompt: &EnrichedPrompt, outcome: AuditOutcome) {
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.record(enriched_prompt, outcome.clone());
+        }
+
+        self.recent_prompts.push(
+            RecentPrompt::new(enriched_prompt, outcome),
+            self.config.borrow().max_recent_prompts(),
+        );
+    }
+
+    fn active_prompt_with_id(&self, id: &PromptId) -> Option<EnrichedPrompt> {
+        let guard = match self.active_prompts.0.lock() {
+            Ok(guard) => guard,
+            Err(err) => err.into_inner(),
+        };
+
+        guard
+            .values()
+            .find(|active_prompt| active_prompt.enriched_prompt.prompt.id() == id)
+            .map(|active_prompt| active_prompt.enriched_prompt.clone())
+    }
+
+    fn drop_prompt(&mut self, id: PromptId, outcome: AuditOutcome) {
+        let mut dropped = Vec::new();
+        self.requeued_prompts.remove(&id);
+
         for (cgroup, pending_prompts) in self.pending_prompts.iter_mut() {
-            let len = pending_prompts.len();
-            pending_prompts.retain(|enriched_prompt| enriched_prompt.prompt.id() != &id);
-            if pending_prompts.len() < len {
+            let (to_drop, to_keep) = pending_prompts
+                .drain(..)
+                .partition(|enriched_prompt| enriched_prompt.prompt.id() == &id);
+            *pending_prompts = to_keep;
+            if !to_drop.is_empty() {
                 info!(id=%id.0, cgroup=%cgroup.0, "dropping prompt as it has already been actioned");
+                dropped.extend(to_drop);
             }
         }
         self.pending_prompts
@@ -272,10 +366,22 @@ where
         };
 
         for (_, active_prompt) in guard.iter_mut() {
-            if active_prompt.typed_ui_input.id() == &id {
-                active_prompt.ui_handle.take();
+            if active_prompt.typed_ui_input.id() == &id && active_prompt.ui_handle.take().is_some()
+            {
+                dropped.push(active_prompt.enriched_prompt.clone());
+            }
+            // The dialog for a device prompt stays open if only its microphone prompt goes away
+            // as the user's reply is still needed for the camera prompt.
+            let paired_id = active_prompt.paired_prompt.as_ref().map(|p| p.prompt.id());
+            if paired_id == Some(&id) {
+                dropped.extend(active_prompt.paired_prompt.take());
             }
         }
+        drop(guard);
+
+        for enriched_prompt in dropped {
+            self.record_outcome(&enriched_prompt, outcome.clone());
+        }
     }
 
     fn process_update(&mut self, update: PromptUpdate) {
@@ -283,7 +389,7 @@ where
 
         match update {
             PromptUpdate::Add(enriched_prompt) => self.add_prompt(enriched_prompt),
-            PromptUpdate::Drop(id) => self.drop_prompt(id),
+            PromptUpdate::Drop(id) => self.drop_prompt(id, AuditOutcome::Cancelled),
         }
     }
 
@@ -306,13 +412,97 @@ where
 
         for (cgroup, enriched_prompt) in prompts_to_process {
             debug!("got prompt: {enriched_prompt:?}");
-            match TypedUiInput::try_from(enriched_prompt.clone()) {
+            if enriched_prompt.process == PromptingProcess::Exited {
+                let id = enriched_prompt.prompt.id().clone();
+                info!(id=%id.0, "prompting process has exited: replying with deny once");
+                let reply = enriched_prompt.prompt.clone().into_deny_once();
+                let outcome = match self.client.reply(&id, reply).await {
+                    Ok(_) => AuditOutcome::ProcessExited,
+                    // Snapd may have already cleaned up the prompt along with the process
+                    Err(Error::SnapdError { err, .. })
+                        if matches!(err.as_ref(), SnapdError::PromptNotFound) =>
+                    {
+                        AuditOutcome::ProcessExited
+                    }
+                    Err(e) => {
+                        error!(id=%id.0, "unable to deny prompt for exited process: {e}");
+                        AuditOutcome::Error {
+                            error: e.to_string(),
+                        }
+                    }
+                };
+                self.record_outcome(&enriched_prompt, outcome);
+                continue;
+            }
+
+            let policy_reply = self
+                .config
+                .borrow()
+                .policy()
+                .reply_for(&enriched_prompt.prompt);
+            if let Some(reply) = policy_reply {
+                let id = enriched_prompt.prompt.id().clone();
+                info!(id=%id.0, "replying to prompt using policy: {reply:?}");
+                match self.client.reply(&id, reply.clone()).await {
+                    Ok(others) => {
+                        self.record_outcome(&enriched_prompt, AuditOutcome::Policy { reply });
+                        if !others.is_empty() {
+                            debug!(to_drop=?others, "dropping prompts actioned by policy reply");
+                            for other in others {
+                                self.drop_prompt(
+                                    other,
+                                    AuditOutcome::ResolvedBy { by: id.clone() },
+                                );
+                            }
+                        }
+                        continue;
+                    }
+                    Err(Error::SnapdError { err, .. })
+                        if matches!(err.as_ref(), SnapdError::PromptNotFound) =>
+                    {
+                        warn!(id=%id.0, "prompt was resolved before replying using policy");
+                        self.record_outcome(&enriched_prompt, AuditOutcome::Cancelled);
+                        continue;
+                    }
+                    // Falling back to the UI means the user still gets to decide on the prompt, and
+                    // the outcome is recorded once they have done so.
+                    Err(e) => {
+                        error!(id=%id.0, "unable to reply using policy, showing UI instead: {e}");
+                    }
+                }
+            }
+
+            let ui_enabled = self.config.borrow().ui_enabled_for(&enriched_prompt.prompt);
+            let typed_ui_input = if ui_enabled {
+                TypedUiInput::try_from(enriched_prompt.clone())
+            } else {
+                Err(Error::PromptNotEnabled {
+                    interface: enriched_prompt.prompt.interface().to_string(),
+                })
+            };
+
+            match typed_ui_input {
                 Err(error) => {
                     error!(%error, "failed to map prompt to UI input: replying with deny once");
+                    let id = enriched_prompt.prompt.id().clone();
                     let reply = enriched_prompt.prompt.clone().into_deny_once();
-                    self.client
-                        .reply(&enriched_prompt.prompt.id().clone(), reply)
-                        .await?;
+                    let outcome = match self.client.reply(&id, reply).await {
+                        Ok(_) => AuditOutcome::Error {
+                            error: error.to_string(),
+                        },
+                        Err(Error::SnapdError { err, .. })
+                            if matches!(err.as_ref(), SnapdError::PromptNotFound) =>
+                        {
+                            AuditOutcome::Cancelled
+                        }
+                        Err(e) => {
+                            error!(id=%id.0, "unable to deny prompt: {e}");
+                            AuditOutcome::Error {
+                                error: e.to_string(),
+                            }
+                        }
+                    };
+                    self.record_outcome(&enriched_prompt, outcome);
                 }
                 Ok(typed_ui_input) => {
                     #[cfg(feature = "auto-reply")]
@@ -336,13 +526,108 @@ where
                         self.client.reply(&id, reply).await?;
                         continue;
                     }
-                    self.update_active_prompt(&cgroup, enriched_prompt, typed_ui_input)?;
+                    let (enriched_prompt, paired_prompt, typed_ui_input) =
+                        self.pair_device_prompts(&cgroup, enriched_prompt, typed_ui_input);
+                    self.update_active_prompt(
+                        &cgroup,
+                        enriched_prompt,
+                        paired_prompt,
+                        typed_ui_input,
+                    )?;
                 }
             }
         }
         Ok(())
     }
 
+    /// Look for a pending prompt that can be presented alongside a camera or microphone prompt
+    /// as a single device prompt, removing it from the queue for the cgroup if one is found.
+    ///
+    /// Prompts that will be handled without the user (because the prompting process has exited
+    /// or there is a matching policy rule) are left in the queue.
+    fn take_device_partner(
+        &mut self,
+        cgroup: &Cgroup,
+        enriched_prompt: &EnrichedPrompt,
+    ) -> Option<EnrichedPrompt> {
+        let is_partner = match &enriched_prompt.prompt {
+            TypedPrompt::Camera(_) => |p: &TypedPrompt| matches!(p, TypedPrompt::Microphone(_)),
+            TypedPrompt::Microphone(_) => |p: &TypedPrompt| matches!(p, TypedPrompt::Camera(_)),
+            _ => return None,
+        };
+        let config = self.config.borrow();
+        if !config.experimental_prompt_enabled(ExperimentalPrompt::Device) {
+            return None;
+        }
+        let pending_prompts = self.pending_prompts.get_mut(cgroup)?;
+
+        let ix = pending_prompts.iter().position(|ep| {
+            is_partner(&ep.prompt)
+                && ep.prompt.snap() == enriched_prompt.prompt.snap()
+                && ep.process != PromptingProcess::Exited
+                && within_device_prompt_window(&ep.prompt, &enriched_prompt.prompt)
+                && config.policy().reply_for(&ep.prompt).is_none()
+        })?;
+        let partner = pending_prompts.remove(ix);
+        drop(config);
+        self.pending_prompts
+            .retain(|_, pending_prompts| !pending_prompts.is_empty());
+
+        partner
+    }
+
+    /// Combine a camera or microphone prompt with a matching pending prompt for the other
+    /// interface if there is one.
+    ///
+    /// When prompts are paired, the camera prompt is always returned as the primary prompt along
+    /// with the microphone prompt that it has been paired with.
+    fn pair_device_prompts(
+        &mut self,
+        cgroup: &Cgroup,
+        enriched_prompt: EnrichedPrompt,
+        typed_ui_input: TypedUiInput,
+    ) -> (EnrichedPrompt, Option<EnrichedPrompt>, TypedUiInput) {
+        let Some(partner) = self.take_device_partner(cgroup, &enriched_prompt) else {
+            return (enriched_prompt, None, typed_ui_input);
+        };
+
+        let partner_input = match TypedUiInput::try_from(partner.clone()) {
+            Ok(input) => input,
+            Err(error) => {
+                warn!(%error, "unable to pair device prompts: presenting them separately");
+                self.pending_prompts
+                    .entry(cgroup.clone())
+                    .or_default()
+                    .push_front(partner);
+                return (enriched_prompt, None, typed_ui_input);
+            }
+        };
+
+        match (typed_ui_input, partner_input) {
+            (TypedUiInput::Camera(camera), TypedUiInput::Microphone(microphone)) => {
+                info!(camera=%camera.id.0, microphone=%microphone.id.0, "pairing device prompts");
+                let input = TypedUiInput::Device(DeviceUiInput { camera, microphone });
+                (enriched_prompt, Some(partner), input)
+            }
+            (TypedUiInput::Microphone(microphone), TypedUiInput::Camera(camera)) => {
+                info!(camera=%camera.id.0, microphone=%microphone.id.0, "pairing device prompts");
+                let input = TypedUiInput::Device(DeviceUiInput { camera, microphone });
+                (partner, Some(enriched_prompt), input)
+            }
+            _ => unreachable!("device prompts are only paired for camera and microphone"),
+        }
+    }
+
+    /// Take the microphone prompt paired with the active prompt for this cgroup, if there is one.
+    fn take_paired_prompt(&self, cgroup: &Cgroup) -> Option<EnrichedPrompt> {
+        let mut guard = match self.active_prompts.0.lock() {
+            Ok(guard) => guard,
+            Err(err) => err.into_inner(),
+        };
+
+        guard.get_mut(cgroup)?.paired_prompt.take()
+    }
+
     async fn wait_for_ui_reply(&mut self, cgroup: &Cgroup) -> Result<()> {
         debug!("waiting for ui reply");
         let exit_code = self
@@ -368,9 +653,18 @@ where
                 .clone()
         };
         let expected_id = enriched_prompt.prompt.id().clone();
+        let interface_config = self
+            .config
+            .borrow()
+            .interface(enriched_prompt.prompt.interface());
+        let mut requeued = false;
 
         loop {
-            match self.wait_for_expected_prompt(&expected_id).await {
+            let recv_timeout = interface_config.recv_timeout();
+            match self
+                .wait_for_expected_prompt(&expected_id, recv_timeout)
+                .await
+            {
                 Recv::DeadPrompt | Recv::Unexpected => continue,
                 Recv::Success | Recv::Gone => break,
                 Recv::Timeout => {
@@ -395,12 +689,49 @@ where
                             break;
                         }
                     };
-                    debug!("timeout waiting for reply from UI - sending deny once");
-                    let reply = enriched_prompt.prompt.into_deny_once();
+
+                    let n_requeues = self.requeued_prompts.get(&expected_id).copied();
+                    let fallback = match interface_config.fallback() {
+                        UiFallback::Requeue if n_requeues.unwrap_or(0) >= MAX_REQUEUES => {
+                            UiFallback::DenyOnce
+                        }
+                        fallback => fallback,
+                    };
+                    let reply = match fallback {
+                        UiFallback::Requeue => {
+                            info!(id=%expected_id.0, "timeout waiting for reply from UI - requeuing prompt");
+                            self.requeue_prompt(cgroup, enriched_prompt.clone());
+                            requeued = true;
+                            break;
+                        }
+                        UiFallback::DenySession => {
+                            debug!("timeout waiting for reply from UI - sending deny for session");
+                            enriched_prompt.prompt.clone().into_deny_for_session()
+                        }
+                        UiFallback::DenyOnce => {
+                            debug!("timeout waiting for reply from UI - sending deny once");
+                            enriched_prompt.prompt.clone().into_deny_once()
+                        }
+                    };
                     self.client
                         .reply(&expected_id, reply)
                         .await
                         .inspect_err(|e| error!("could not send reply: {e:?}"))?;
+                    self.record_outcome(&enriched_prompt, AuditOutcome::Timeout { fallback });
+
+                    if let Some(paired) = self.take_paired_prompt(cgroup) {
+                        let reply = match fallback {
+                            UiFallback::DenySession => {
+                                paired.prompt.clone().into_deny_for_session()
+                            }
+                            _ => paired.prompt.clone().into_deny_once(),
+                        };
+                        self.client
+                            .reply(paired.prompt.id(), reply)
+                            .await
+                            .inspect_err(|e| error!("could not send reply: {e:?}"))?;
+                        self.record_outcome(&paired, AuditOutcome::Timeout { fallback });
+                    }
                     break;
                 }
                 Recv::ChannelClosed => {
@@ -410,6 +741,20 @@ where
             }
         }
 
+        if !requeued {
+            self.requeued_prompts.remove(&expected_id);
+        }
+
+        // A paired microphone prompt that has not been resolved along with the camera prompt is
+        // returned to the queue so that the user is still asked about it.
+        if let Some(paired) = self.take_paired_prompt(cgroup) {
+            debug!(id=%paired.prompt.id().0, "requeuing unresolved paired prompt");
+            self.pending_prompts
+                .entry(cgroup.clone())
+                .or_default()
+                .push_front(paired);
+        }
+
         debug!("clearing active prompt");
         let mut guard = match self.active_prompts.0.lock() {
             Ok(guard) => guard,
@@ -420,6 +765,21 @@ where
         Ok(())
     }
 
+    /// Place a prompt back at the front of the queue for its cgroup so that it is the next prompt
+    /// shown to the user once the current active prompt has been cleared.
+    fn requeue_prompt(&mut self, cgroup: &Cgroup, enriched_prompt: EnrichedPrompt) {
+        let id = enriched_prompt.prompt.id().clone();
+        // We are no longer expecting a reply for this prompt from the previous UI process so we
+        // need to make sure that a reply from the new one is not treated as being for a dead
+        // prompt.
+        self.dead_prompts.retain(|i| i != &id);
+        *self.requeued_prompts.entry(id).or_default() += 1;
+        self.pending_prompts
+            .entry(cgroup.clone())
+            .or_default()
+            .push_front(enriched_prompt);
+    }
+
     async fn wait_for_dialog_processes(dialog_processes: &mut HashMap<Cgroup, D>) -> Cgroup {
         dialog_processes
             .iter_mut()
@@ -448,9 +808,9 @@ where
     ///     terminates.
     ///   - The process for the current dialog terminates (this branch is only active if there is
     ///     an active process): In this case we wait for an answer sent by the dialog. If nothing
-    ///     is received, that either means the UI crashed and a "deny once" is sent, or the UI
-    ///     handle as been dropped explicitly (see previous item) and all that's left to do is
-    ///     clean up the active prompt.
+    ///     is received, that either means the UI crashed and the configured fallback for the
+    ///     interface is applied (see [UiFallback]), or the UI handle as been dropped explicitly
+    ///     (see previous item) and all that's left to do is clean up the active prompt.
     async fn step(&mut self) -> Result<()> {
         debug!("step");
 
@@ -477,6 +837,7 @@ where
         &mut self,
         cgroup: &Cgroup,
         enriched_prompt: EnrichedPrompt,
+        paired_prompt: Option<EnrichedPrompt>,
         typed_ui_input: TypedUiInput,
     ) -> Result<()> {
         let mut guard = match self.active_prompts.0.lock() {
@@ -493,6 +854,7 @@ where
             ActivePrompt {
                 typed_ui_input,
                 enriched_prompt: enriched_prompt.clone(),
+                paired_prompt,
                 ui_handle,
             },
         );
@@ -512,16 +874,20 @@ where
         Ok(())
     }
 
-    async fn wait_for_expected_prompt(&mut self, expected_id: &PromptId) -> Recv {
+    async fn wait_for_expected_prompt(
+        &mut self,
+        expected_id: &PromptId,
+        recv_timeout: Duration,
+    ) -> Recv {
         let pending_actioned_prompts = self.rx_actioned_prompts.len();
         info!("{pending_actioned_prompts} pending actioned prompts");
-        match timeout(self.recv_timeout, self.rx_actioned_prompts.recv()).await {
-            Ok(Some(ActionedPrompt::Actioned { id, others })) => {
+        match timeout(recv_timeout, self.rx_actioned_prompts.recv()).await {
+            Ok(Some(ActionedPrompt::Actioned { id, reply, others })) => {
                 debug!(recv_id=%id.0, "reply sent for prompt");
                 if !others.is_empty() {
                     debug!(to_drop=?others, "dropping prompts actioned by last reply");
-                    for id in others {
-                        self.drop_prompt(id);
+                    for other in others {
+                        self.drop_prompt(other, AuditOutcome::ResolvedBy { by: id.clone() });
                     }
                 }
 
@@ -532,6 +898,9 @@ where
                 }
 
                 if &id == expected_id {
+                    if let Some(enriched_prompt) = self.active_prompt_with_id(&id) {
+                        self.record_outcome(&enriched_prompt, AuditOutcome::Replied { reply });
+                    }
                     Recv::Success
                 } else {
                     warn!(expected=%expected_id.0, seen=%id.0, "unexpected prompt reply");
@@ -546,6 +915,9 @@ where
                 }
 
                 if &id == expected_id {
+                    if let Some(enriched_prompt) = self.active_prompt_with_id(&id) {
+                        self.record_outcome(&enriched_prompt, AuditOutcome::NotFound);
+                    }
                     Recv::Gone
                 } else {
                     warn!(expected=%expected_id.0, seen=%id.0, "unexpected prompt is now gone");
@@ -567,23 +939,42 @@ where
     }
 }
 
+/// Whether or not two prompts were created close enough together to be presented as a single
+/// device prompt.
+fn within_device_prompt_window(a: &TypedPrompt, b: &TypedPrompt) -> bool {
+    match (
+        DateTime::parse_from_rfc3339(a.timestamp()),
+        DateTime::parse_from_rfc3339(b.timestamp()),
+    ) {
+        (Ok(a), Ok(b)) => (a - b).abs() <= DEVICE_PROMPT_WINDOW,
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::daemon::{enriched_home_prompt, Config, Policy};
     use crate::snapd_client::{
-        interfaces::home::{HomeConstraints, HomeReplyConstraints},
+        interfaces::{
+            camera::CameraConstraints, files::FilesConstraints, generic::GenericConstraints,
+            home::HomeReplyConstraints, microphone::MicrophoneConstraints,
+            removable_media::RemovableMediaConstraints,
+        },
         Action, Lifespan, Prompt, PromptReply, TypedPrompt, TypedPromptReply,
     };
+    use hyper::StatusCode;
     use simple_test_case::test_case;
-    use std::env;
+    use std::{env, fs};
     use tokio::{
         sync::{
             mpsc::{unbounded_channel, UnboundedSender},
-            oneshot,
+            oneshot, watch,
         },
         time::sleep,
     };
     use tonic::async_trait;
+    use uuid::Uuid;
 
     #[derive(Debug)]
     struct StubClient;
@@ -599,23 +990,58 @@ mod tests {
         }
     }
 
-    fn enriched_prompt(id: &str, cgroup: &str) -> EnrichedPrompt {
-        EnrichedPrompt {
-            prompt: TypedPrompt::Home(Prompt {
+    const RECV_TIMEOUT: Duration = Duration::from_millis(100);
+
+    fn test_config(policy: Policy) -> RefConfig {
+        let config = Config::default()
+            .with_recv_timeout(RECV_TIMEOUT)
+            .with_policy(policy);
+        let (_, rx) = watch::channel(Arc::new(config));
+
+        rx
+    }
+
+    fn device_prompt_config() -> RefConfig {
+        let config = Config::default()
+            .with_recv_timeout(RECV_TIMEOUT)
+            .with_experimental_prompt(ExperimentalPrompt::Device);
+        let (_, rx) = watch::channel(Arc::new(config));
+
+        rx
+    }
+
+    fn device_prompt(id: &str, interface: &str, snap: &str, timestamp: &str) -> EnrichedPrompt {
+        let prompt = match interface {
+            "camera" => TypedPrompt::Camera(Prompt {
                 id: PromptId(id.to_string()),
-                timestamp: String::new(),
-                snap: "test".to_string(),
+                timestamp: timestamp.to_string(),
+                snap: snap.to_string(),
                 pid: 1234,
-                cgroup: cgroup.into(),
-                interface: "home".to_string(),
-                constraints: HomeConstraints::default(),
+                cgroup: "cgroup_0".into(),
+                interface: interface.to_string(),
+                constraints: CameraConstraints::default(),
             }),
+            "microphone" => TypedPrompt::Microphone(Prompt {
+                id: PromptId(id.to_string()),
+                timestamp: timestamp.to_string(),
+                snap: snap.to_string(),
+                pid: 1234,
+                cgroup: "cgroup_0".into(),
+                interface: "audio-record".to_string(),
+                constraints: MicrophoneConstraints::default(),
+            }),
+            _ => panic!("unexpected interface {interface}"),
+        };
+
+        EnrichedPrompt {
+            prompt,
             meta: None,
+            process: PromptingProcess::Unknown,
         }
     }
 
     fn add(id: &str, cgroup: &str) -> PromptUpdate {
-        PromptUpdate::Add(enriched_prompt(id, cgroup))
+        PromptUpdate::Add(enriched_home_prompt(id, cgroup))
     }
 
     fn drop_id(id: &str) -> PromptUpdate {
@@ -661,7 +1087,7 @@ mod tests {
                 (
                     cgroup.clone(),
                     ids.into_iter()
-                        .map(|id| enriched_prompt(id, &cgroup.0))
+                        .map(|id| enriched_home_prompt(id, &cgroup.0))
                         .collect(),
                 )
             })
@@ -674,7 +1100,10 @@ mod tests {
             dialog_processes: HashMap::new(),
             pending_prompts,
             dead_prompts: Vec::new(),
-            recv_timeout: Duration::from_millis(100),
+            requeued_prompts: HashMap::new(),
+            config: test_config(Policy::default()),
+            audit_log: None,
+            recent_prompts: RefRecentPrompts::default(),
             ui: FlutterUi {
                 cmd: "".to_string(),
             },
@@ -723,7 +1152,10 @@ mod tests {
             dialog_processes: HashMap::new(),
             pending_prompts: HashMap::new(),
             dead_prompts: vec![PromptId("dead".to_string())],
-            recv_timeout: Duration::from_millis(100),
+            requeued_prompts: HashMap::new(),
+            config: test_config(Policy::default()),
+            audit_log: None,
+            recent_prompts: RefRecentPrompts::default(),
             ui: FlutterUi {
                 cmd: "".to_string(),
             },
@@ -735,12 +1167,15 @@ mod tests {
             sleep(Duration::from_millis(sleep_ms)).await;
             let _ = tx_actioned_prompts.send(ActionedPrompt::Actioned {
                 id: PromptId(sent_id.to_string()),
+                reply: enriched_home_prompt(sent_id, "cgroup")
+                    .prompt
+                    .into_allow_once(),
                 others: vec![PromptId("drop-me".to_string())],
             });
         });
 
         let recv = w
-            .wait_for_expected_prompt(&PromptId(expected_id.to_string()))
+            .wait_for_expected_prompt(&PromptId(expected_id.to_string()), RECV_TIMEOUT)
             .await;
 
         assert_eq!(recv, expected_recv);
@@ -775,7 +1210,10 @@ mod tests {
             dialog_processes: HashMap::new(),
             pending_prompts: HashMap::new(),
             dead_prompts: vec![PromptId("dead".to_string())],
-            recv_timeout: Duration::from_millis(100),
+            requeued_prompts: HashMap::new(),
+            config: test_config(Policy::default()),
+            audit_log: None,
+            recent_prompts: RefRecentPrompts::default(),
             ui: FlutterUi {
                 cmd: "".to_string(),
             },
@@ -788,7 +1226,7 @@ mod tests {
         });
 
         let recv = w
-            .wait_for_expected_prompt(&PromptId(expected_id.to_string()))
+            .wait_for_expected_prompt(&PromptId(expected_id.to_string()), RECV_TIMEOUT)
             .await;
 
         assert_eq!(recv, expected_recv);
@@ -814,7 +1252,10 @@ mod tests {
             dialog_processes: HashMap::new(),
             pending_prompts: HashMap::new(),
             dead_prompts: vec![PromptId("dead".to_string())],
-            recv_timeout: Duration::from_millis(100),
+            requeued_prompts: HashMap::new(),
+            config: test_config(Policy::default()),
+            audit_log: None,
+            recent_prompts: RefRecentPrompts::default(),
             ui: FlutterUi {
                 cmd: "".to_string(),
             },
@@ -823,7 +1264,9 @@ mod tests {
         };
 
         drop(tx_actioned_prompts);
-        let recv = w.wait_for_expected_prompt(&PromptId("1".to_string())).await;
+        let recv = w
+            .wait_for_expected_prompt(&PromptId("1".to_string()), RECV_TIMEOUT)
+            .await;
 
         assert_eq!(recv, Recv::ChannelClosed);
     }
@@ -939,6 +1382,7 @@ mod tests {
                     let reply = self.reply.expect("a reply");
                     let _ = self.tx.send(ActionedPrompt::Actioned {
                         id: PromptId(reply.id.to_string()),
+                        reply: enriched_home_prompt(reply.id, "cgroup").prompt.into_allow_once(),
                         others: reply
                             .drop
                             .iter()
@@ -1028,7 +1472,10 @@ mod tests {
             dialog_processes: HashMap::new(),
             pending_prompts: HashMap::new(),
             dead_prompts: vec![],
-            recv_timeout: Duration::from_millis(100),
+            requeued_prompts: HashMap::new(),
+            config: test_config(Policy::default()),
+            audit_log: None,
+            recent_prompts: RefRecentPrompts::default(),
             ui,
             client: StubClient,
             running: true,
@@ -1079,6 +1526,8 @@ mod tests {
     #[derive(Debug, Default)]
     struct AckClient {
         seen: Arc<Mutex<Vec<(PromptId, TypedPromptReply)>>>,
+        error: Option<(StatusCode, SnapdError)>,
+        others: Vec<PromptId>,
     }
 
     #[async_trait]
@@ -1090,7 +1539,14 @@ mod tests {
         ) -> crate::Result<Vec<PromptId>> {
             self.seen.lock().unwrap().push((id.clone(), reply));
 
-            Ok(Vec::new())
+            match &self.error {
+                Some((status, err)) => Err(Error::SnapdError {
+                    status: *status,
+                    message: "error requested of ack client".to_string(),
+                    err: Box::new(err.clone()),
+                }),
+                None => Ok(self.others.clone()),
+            }
         }
     }
 
@@ -1103,8 +1559,10 @@ mod tests {
         let active_prompts = RefActivePrompts::new(HashMap::new());
         let pending_prompts = HashMap::from([(
             "cgroup_0".into(),
-            vec![enriched_prompt("1", "cgroup_0")].into(),
+            vec![enriched_home_prompt("1", "cgroup_0")].into(),
         )]);
+        let audit_path = env::temp_dir().join(format!("{}.jsonl", Uuid::new_v4()));
+        let (audit_log, audit_task) = AuditLog::new(audit_path.clone()).spawn_writer();
 
         let mut w = Worker {
             rx_prompts,
@@ -1113,7 +1571,10 @@ mod tests {
             dialog_processes: HashMap::new(),
             pending_prompts,
             dead_prompts: vec![],
-            recv_timeout: Duration::from_millis(100),
+            requeued_prompts: HashMap::new(),
+            config: test_config(Policy::default()),
+            audit_log: Some(audit_log),
+            recent_prompts: RefRecentPrompts::default(),
             ui: StubUi,
             client: AckClient::default(),
             running: true,
@@ -1142,6 +1603,495 @@ mod tests {
                 })
             )]
         );
+
+        // Wait for the writer to finish with the log
+        drop(w.audit_log.take());
+        audit_task.await.unwrap();
+        let audit_entries = fs::read_to_string(&audit_path).unwrap();
+        let audit_entries: Vec<serde_json::Value> = audit_entries
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(audit_entries.len(), 1);
+        assert_eq!(audit_entries[0]["outcome"], "timeout");
+        assert_eq!(audit_entries[0]["prompt"]["id"], "1");
+        fs::remove_file(audit_path).unwrap();
+
+        let recent_prompts = w.recent_prompts.snapshot();
+        assert_eq!(recent_prompts.len(), 1);
+        assert_eq!(recent_prompts[0].prompt().id(), &PromptId("1".to_string()));
+        assert_eq!(
+            recent_prompts[0].outcome(),
+            &AuditOutcome::Timeout {
+                fallback: UiFallback::DenyOnce,
+            }
+        );
+    }
+
+    #[test_case("deny-once", 1, Lifespan::Single, UiFallback::DenyOnce; "deny once")]
+    #[test_case("deny-session", 1, Lifespan::Session, UiFallback::DenySession; "deny session")]
+    #[test_case("requeue", 2, Lifespan::Single, UiFallback::DenyOnce; "requeue then deny once")]
+    #[tokio::test]
+    async fn timeout_waiting_for_reply_uses_interface_fallback(
+        fallback: &str,
+        expected_steps: usize,
+        expected_lifespan: Lifespan,
+        expected_fallback: UiFallback,
+    ) {
+        let (_tx_prompts, rx_prompts) = unbounded_channel();
+        let (_tx_actioned_prompts, rx_actioned_prompts) = unbounded_channel();
+        let pending_prompts = HashMap::from([(
+            "cgroup_0".into(),
+            vec![enriched_home_prompt("1", "cgroup_0")].into(),
+        )]);
+        let config = Config::try_new_from_string(&format!(
+            "recv-timeout-ms = 100\n[interfaces.home]\nfallback = {fallback:?}"
+        ))
+        .unwrap();
+        let (_, config) = watch::channel(Arc::new(config));
+
+        let mut w = Worker {
+            rx_prompts,
+            rx_actioned_prompts,
+            active_prompts: RefActivePrompts::new(HashMap::new()),
+            dialog_processes: HashMap::new(),
+            pending_prompts,
+            dead_prompts: vec![],
+            requeued_prompts: HashMap::new(),
+            config,
+            audit_log: None,
+            recent_prompts: RefRecentPrompts::default(),
+            ui: StubUi,
+            client: AckClient::default(),
+            running: true,
+        };
+
+        env::set_var("SNAP_REAL_HOME", "/home/ubuntu");
+        for _ in 0..expected_steps {
+            assert!(w.client.seen.lock().unwrap().is_empty());
+            w.step().await.unwrap();
+        }
+
+        let replies_seen = w.client.seen.lock().unwrap().clone();
+        assert_eq!(replies_seen.len(), 1, "{replies_seen:?}");
+        let (id, reply) = &replies_seen[0];
+        assert_eq!(id, &PromptId("1".to_string()));
+        assert_eq!(reply.action(), Action::Deny);
+        assert_eq!(reply.lifespan(), expected_lifespan);
+        assert!(w.pending_prompts.values().all(VecDeque::is_empty));
+        assert!(w.requeued_prompts.is_empty());
+
+        // The prompt is only recorded once, with the fallback that actually replied to snapd
+        let recent_prompts = w.recent_prompts.snapshot();
+        assert_eq!(recent_prompts.len(), 1);
+        assert_eq!(
+            recent_prompts[0].outcome(),
+            &AuditOutcome::Timeout {
+                fallback: expected_fallback
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn policy_replies_without_spawning_ui() {
+        let (_tx_prompts, rx_prompts) = unbounded_channel();
+        let (_tx_actioned_prompts, rx_actioned_prompts) = unbounded_channel();
+        let pending_prompts = HashMap::from([(
+            "cgroup_0".into(),
+            vec![enriched_home_prompt("1", "cgroup_0")].into(),
+        )]);
+        let policy = Policy::try_new_from_string(
+            r#"{
+              "version": 1,
+              "rules": [{
+                "prompt-filter": { "snap": "test", "interface": "home" },
+                "action": "allow",
+                "lifespan": "forever"
+              }]
+            }"#,
+        )
+        .unwrap();
+
+        let mut w = Worker {
+            rx_prompts,
+            rx_actioned_prompts,
+            active_prompts: RefActivePrompts::new(HashMap::new()),
+            dialog_processes: HashMap::new(),
+            pending_prompts,
+            dead_prompts: vec![],
+            requeued_prompts: HashMap::new(),
+            config: test_config(policy),
+            audit_log: None,
+            recent_prompts: RefRecentPrompts::default(),
+            ui: StubUi,
+            client: AckClient::default(),
+            running: true,
+        };
+
+        w.process_next_pending_prompts().await.unwrap();
+
+        assert!(w.dialog_processes.is_empty());
+        assert!(w.active_prompts.get(&"cgroup_0".into()).is_none());
+
+        let replies_seen = Arc::into_inner(w.client.seen)
+            .unwrap()
+            .into_inner()
+            .unwrap();
+
+        assert_eq!(
+            replies_seen,
+            vec![(
+                PromptId("1".to_string()),
+                TypedPromptReply::Home(PromptReply {
+                    action: Action::Allow,
+                    lifespan: Lifespan::Forever,
+                    duration: None,
+                    constraints: HomeReplyConstraints::default(),
+                })
+            )]
+        );
+    }
+
+    #[test_case(StatusCode::NOT_FOUND, SnapdError::PromptNotFound, false; "prompt not found")]
+    #[test_case(StatusCode::INTERNAL_SERVER_ERROR, SnapdError::Raw, true; "other error")]
+    #[tokio::test]
+    async fn policy_reply_errors_do_not_stop_the_worker(
+        status: StatusCode,
+        err: SnapdError,
+        expect_ui: bool,
+    ) {
+        let (_tx_prompts, rx_prompts) = unbounded_channel();
+        let (_tx_actioned_prompts, rx_actioned_prompts) = unbounded_channel();
+        let pending_prompts = HashMap::from([(
+            "cgroup_0".into(),
+            vec![enriched_home_prompt("1", "cgroup_0")].into(),
+        )]);
+        let policy = Policy::try_new_from_string(
+            r#"{
+              "version": 1,
+              "rules": [{
+                "prompt-filter": { "snap": "test", "interface": "home" },
+                "action": "allow",
+                "lifespan": "forever"
+              }]
+            }"#,
+        )
+        .unwrap();
+
+        let mut w = Worker {
+            rx_prompts,
+            rx_actioned_prompts,
+            active_prompts: RefActivePrompts::new(HashMap::new()),
+            dialog_processes: HashMap::new(),
+            pending_prompts,
+            dead_prompts: vec![],
+            requeued_prompts: HashMap::new(),
+            config: test_config(policy),
+            audit_log: None,
+            recent_prompts: RefRecentPrompts::default(),
+            ui: StubUi,
+            client: AckClient {
+                error: Some((status, err)),
+                ..Default::default()
+            },
+            running: true,
+        };
+
+        env::set_var("SNAP_REAL_HOME", "/home/ubuntu");
+        w.process_next_pending_prompts().await.unwrap();
+
+        assert_eq!(w.client.seen.lock().unwrap().len(), 1);
+        assert_eq!(!w.dialog_processes.is_empty(), expect_ui);
+        assert_eq!(
+            w.active_prompts.get(&"cgroup_0".into()).is_some(),
+            expect_ui
+        );
+
+        // The outcome of a prompt shown in the UI is recorded once the user has replied
+        let recent_prompts = w.recent_prompts.snapshot();
+        if expect_ui {
+            assert!(recent_prompts.is_empty());
+        } else {
+            assert_eq!(recent_prompts.len(), 1);
+            assert_eq!(recent_prompts[0].outcome(), &AuditOutcome::Cancelled);
+        }
+    }
+
+    #[tokio::test]
+    async fn prompts_resolved_by_a_policy_reply_are_dropped() {
+        let (_tx_prompts, rx_prompts) = unbounded_channel();
+        let (_tx_actioned_prompts, rx_actioned_prompts) = unbounded_channel();
+        let pending_prompts = HashMap::from([(
+            "cgroup_0".into(),
+            vec![
+                enriched_home_prompt("1", "cgroup_0"),
+                enriched_home_prompt("2", "cgroup_0"),
+            ]
+            .into(),
+        )]);
+        let policy = Policy::try_new_from_string(
+            r#"{
+              "version": 1,
+              "rules": [{
+                "prompt-filter": { "snap": "test", "interface": "home" },
+                "action": "allow",
+                "lifespan": "forever"
+              }]
+            }"#,
+        )
+        .unwrap();
+
+        let mut w = Worker {
+            rx_prompts,
+            rx_actioned_prompts,
+            active_prompts: RefActivePrompts::new(HashMap::new()),
+            dialog_processes: HashMap::new(),
+            pending_prompts,
+            dead_prompts: vec![],
+            requeued_prompts: HashMap::new(),
+            config: test_config(policy),
+            audit_log: None,
+            recent_prompts: RefRecentPrompts::default(),
+            ui: StubUi,
+            client: AckClient {
+                others: vec![PromptId("2".to_string())],
+                ..Default::default()
+            },
+            running: true,
+        };
+
+        w.process_next_pending_prompts().await.unwrap();
+
+        assert_eq!(w.client.seen.lock().unwrap().len(), 1);
+        assert!(w.pending_prompts.is_empty());
+        let outcomes: Vec<_> = w
+            .recent_prompts
+            .snapshot()
+            .iter()
+            .map(|p| p.outcome().clone())
+            .collect();
+        assert!(
+            matches!(
+                &outcomes[..],
+                [AuditOutcome::ResolvedBy { by }, AuditOutcome::Policy { .. }] if by.0 == "1"
+            ),
+            "{outcomes:?}"
+        );
+    }
+
+    #[test_case(None, AuditOutcome::ProcessExited; "success")]
+    #[test_case(
+        Some((StatusCode::NOT_FOUND, SnapdError::PromptNotFound)),
+        AuditOutcome::ProcessExited;
+        "prompt not found"
+    )]
+    #[test_case(
+        Some((StatusCode::INTERNAL_SERVER_ERROR, SnapdError::Raw)),
+        AuditOutcome::Error { error: "error message returned from snapd: error requested of ack client".to_string() };
+        "other error"
+    )]
+    #[tokio::test]
+    async fn prompts_from_exited_processes_are_denied_without_spawning_ui(
+        error: Option<(StatusCode, SnapdError)>,
+        expected_outcome: AuditOutcome,
+    ) {
+        let (_tx_prompts, rx_prompts) = unbounded_channel();
+        let (_tx_actioned_prompts, rx_actioned_prompts) = unbounded_channel();
+        let mut ep = enriched_home_prompt("1", "cgroup_0");
+        ep.process = PromptingProcess::Exited;
+        let pending_prompts = HashMap::from([("cgroup_0".into(), vec![ep].into())]);
+
+        let mut w = Worker {
+            rx_prompts,
+            rx_actioned_prompts,
+            active_prompts: RefActivePrompts::new(HashMap::new()),
+            dialog_processes: HashMap::new(),
+            pending_prompts,
+            dead_prompts: vec![],
+            requeued_prompts: HashMap::new(),
+            config: test_config(Policy::default()),
+            audit_log: None,
+            recent_prompts: RefRecentPrompts::default(),
+            ui: StubUi,
+            client: AckClient {
+                error,
+                ..Default::default()
+            },
+            running: true,
+        };
+
+        w.process_next_pending_prompts().await.unwrap();
+
+        assert!(w.dialog_processes.is_empty());
+        assert!(w.active_prompts.get(&"cgroup_0".into()).is_none());
+
+        let replies_seen = w.client.seen.lock().unwrap().clone();
+        assert_eq!(replies_seen.len(), 1, "{replies_seen:?}");
+        let (id, reply) = &replies_seen[0];
+        assert_eq!(id, &PromptId("1".to_string()));
+        assert_eq!(reply.action(), Action::Deny);
+        assert_eq!(reply.lifespan(), Lifespan::Single);
+
+        let recent_prompts = w.recent_prompts.snapshot();
+        assert_eq!(recent_prompts[0].outcome(), &expected_outcome);
+    }
+
+    fn generic_prompt() -> TypedPrompt {
+        TypedPrompt::Generic(Prompt {
+            id: PromptId("1".to_string()),
+            timestamp: String::new(),
+            snap: "test".to_string(),
+            pid: 1234,
+            cgroup: "cgroup_0".into(),
+            interface: "bluetooth-control".to_string(),
+            constraints: GenericConstraints::default(),
+        })
+    }
+
+    fn removable_media_prompt() -> TypedPrompt {
+        TypedPrompt::RemovableMedia(Prompt {
+            id: PromptId("1".to_string()),
+            timestamp: String::new(),
+            snap: "test".to_string(),
+            pid: 1234,
+            cgroup: "cgroup_0".into(),
+            interface: "removable-media".to_string(),
+            constraints: RemovableMediaConstraints {
+                path: "/media/ubuntu/USB/file.txt".to_string(),
+                requested_permissions: vec!["read".to_string()],
+                available_permissions: vec!["read".to_string(), "write".to_string()],
+            },
+        })
+    }
+
+    fn system_files_prompt() -> TypedPrompt {
+        TypedPrompt::SystemFiles(Prompt {
+            id: PromptId("1".to_string()),
+            timestamp: String::new(),
+            snap: "test".to_string(),
+            pid: 1234,
+            cgroup: "cgroup_0".into(),
+            interface: "system-files".to_string(),
+            constraints: FilesConstraints {
+                path: "/etc/foo/foo.conf".to_string(),
+                requested_permissions: vec!["read".to_string()],
+                available_permissions: vec!["read".to_string()],
+                declared_paths: vec!["/etc/foo".to_string()],
+            },
+        })
+    }
+
+    #[test_case(generic_prompt(), None, false; "generic disabled by default")]
+    #[test_case(generic_prompt(), Some(ExperimentalPrompt::Generic), true; "generic enabled")]
+    #[test_case(removable_media_prompt(), None, false; "removable media disabled by default")]
+    #[test_case(
+        removable_media_prompt(),
+        Some(ExperimentalPrompt::RemovableMedia),
+        true;
+        "removable media enabled"
+    )]
+    #[test_case(system_files_prompt(), None, false; "files disabled by default")]
+    #[test_case(system_files_prompt(), Some(ExperimentalPrompt::Files), true; "files enabled")]
+    #[tokio::test]
+    async fn experimental_prompts_are_only_shown_if_enabled(
+        prompt: TypedPrompt,
+        enabled: Option<ExperimentalPrompt>,
+        expect_ui: bool,
+    ) {
+        let (_tx_prompts, rx_prompts) = unbounded_channel();
+        let (_tx_actioned_prompts, rx_actioned_prompts) = unbounded_channel();
+        let ep = EnrichedPrompt {
+            prompt,
+            meta: None,
+            process: PromptingProcess::Unknown,
+        };
+        let pending_prompts = HashMap::from([("cgroup_0".into(), vec![ep].into())]);
+        let mut config = Config::default();
+        if let Some(prompt) = enabled {
+            config = config.with_experimental_prompt(prompt);
+        }
+        let (_, config) = watch::channel(Arc::new(config));
+
+        let mut w = Worker {
+            rx_prompts,
+            rx_actioned_prompts,
+            active_prompts: RefActivePrompts::new(HashMap::new()),
+            dialog_processes: HashMap::new(),
+            pending_prompts,
+            dead_prompts: vec![],
+            requeued_prompts: HashMap::new(),
+            config,
+            audit_log: None,
+            recent_prompts: RefRecentPrompts::default(),
+            ui: StubUi,
+            client: AckClient::default(),
+            running: true,
+        };
+
+        w.process_next_pending_prompts().await.unwrap();
+
+        assert_eq!(!w.dialog_processes.is_empty(), expect_ui);
+        let replies_seen = w.client.seen.lock().unwrap().clone();
+        if expect_ui {
+            assert!(replies_seen.is_empty(), "{replies_seen:?}");
+        } else {
+            assert_eq!(replies_seen.len(), 1, "{replies_seen:?}");
+            assert_eq!(replies_seen[0].1.action(), Action::Deny);
+            assert_eq!(replies_seen[0].1.lifespan(), Lifespan::Single);
+        }
+    }
+
+    #[test_case(
+        Some((StatusCode::NOT_FOUND, SnapdError::PromptNotFound)),
+        AuditOutcome::Cancelled;
+        "prompt not found"
+    )]
+    #[test_case(
+        Some((StatusCode::INTERNAL_SERVER_ERROR, SnapdError::Raw)),
+        AuditOutcome::Error {
+            error: "error message returned from snapd: error requested of ack client".to_string(),
+        };
+        "other error"
+    )]
+    #[tokio::test]
+    async fn errors_denying_disabled_prompts_do_not_stop_the_worker(
+        error: Option<(StatusCode, SnapdError)>,
+        expected: AuditOutcome,
+    ) {
+        let (_tx_prompts, rx_prompts) = unbounded_channel();
+        let (_tx_actioned_prompts, rx_actioned_prompts) = unbounded_channel();
+        let ep = EnrichedPrompt {
+            prompt: generic_prompt(),
+            meta: None,
+            process: PromptingProcess::Unknown,
+        };
+        let pending_prompts = HashMap::from([("cgroup_0".into(), vec![ep].into())]);
+
+        let mut w = Worker {
+            rx_prompts,
+            rx_actioned_prompts,
+            active_prompts: RefActivePrompts::new(HashMap::new()),
+            dialog_processes: HashMap::new(),
+            pending_prompts,
+            dead_prompts: vec![],
+            requeued_prompts: HashMap::new(),
+            config: test_config(Policy::default()),
+            audit_log: None,
+            recent_prompts: RefRecentPrompts::default(),
+            ui: StubUi,
+            client: AckClient {
+                error,
+                ..Default::default()
+            },
+            running: true,
+        };
+
+        w.process_next_pending_prompts().await.unwrap();
+
+        assert_eq!(w.client.seen.lock().unwrap().len(), 1);
+        let recent_prompts = w.recent_prompts.snapshot();
+        assert_eq!(recent_prompts.len(), 1);
+        assert_eq!(recent_prompts[0].outcome(), &expected);
     }
 
     #[tokio::test]
@@ -1165,7 +2115,10 @@ mod tests {
             dialog_processes: HashMap::new(),
             pending_prompts: HashMap::new(),
             dead_prompts: vec![],
-            recv_timeout: Duration::from_millis(100),
+            requeued_prompts: HashMap::new(),
+            config: test_config(Policy::default()),
+            audit_log: None,
+            recent_prompts: RefRecentPrompts::default(),
             ui,
             client: StubClient,
             running: true,
@@ -1192,4 +2145,160 @@ mod tests {
 
         handle.await.expect("worker finishes");
     }
+
+    const T0: &str = "2024-08-14T09:47:03.350324989Z";
+    const T1: &str = "2024-08-14T09:47:04.350324989Z";
+    const T10: &str = "2024-08-14T09:47:13.350324989Z";
+
+    #[test_case("camera", "microphone", "test", T1, Some(("camera", "microphone")); "camera then microphone")]
+    #[test_case("microphone", "camera", "test", T1, Some(("microphone", "camera")); "microphone then camera")]
+    #[test_case("camera", "microphone", "other", T1, None; "different snaps")]
+    #[test_case("camera", "microphone", "test", T10, None; "outside of window")]
+    #[test_case("camera", "camera", "test", T1, None; "same interface")]
+    #[tokio::test]
+    async fn camera_and_microphone_prompts_are_paired(
+        first: &str,
+        second: &str,
+        second_snap: &str,
+        second_timestamp: &str,
+        expected: Option<(&str, &str)>,
+    ) {
+        let (_tx_prompts, rx_prompts) = unbounded_channel();
+        let (_tx_actioned_prompts, rx_actioned_prompts) = unbounded_channel();
+        let pending_prompts = HashMap::from([(
+            "cgroup_0".into(),
+            vec![
+                device_prompt("1", first, "test", T0),
+                device_prompt("2", second, second_snap, second_timestamp),
+            ]
+            .into(),
+        )]);
+
+        let mut w = Worker {
+            rx_prompts,
+            rx_actioned_prompts,
+            active_prompts: RefActivePrompts::new(HashMap::new()),
+            dialog_processes: HashMap::new(),
+            pending_prompts,
+            dead_prompts: vec![],
+            requeued_prompts: HashMap::new(),
+            config: device_prompt_config(),
+            audit_log: None,
+            recent_prompts: RefRecentPrompts::default(),
+            ui: StubUi,
+            client: StubClient,
+            running: true,
+        };
+
+        w.process_next_pending_prompts().await.unwrap();
+
+        let active = w.active_prompts.get(&"cgroup_0".into()).expect("active");
+        let n_pending = w.pending_prompts.values().map(VecDeque::len).sum::<usize>();
+
+        match expected {
+            Some((first_interface, _)) => {
+                let TypedUiInput::Device(input) = active else {
+                    panic!("expected a device prompt, got {active:?}");
+                };
+                // The camera prompt is always the primary prompt for the pair
+                let (camera_id, microphone_id) = if first_interface == "camera" {
+                    ("1", "2")
+                } else {
+                    ("2", "1")
+                };
+                assert_eq!(input.id(), &PromptId(camera_id.to_string()));
+                assert_eq!(input.microphone_id(), &PromptId(microphone_id.to_string()));
+                assert_eq!(n_pending, 0);
+            }
+            None => {
+                assert_eq!(active.id(), &PromptId("1".to_string()));
+                assert_eq!(n_pending, 1);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn device_prompts_are_not_paired_unless_enabled() {
+        let (_tx_prompts, rx_prompts) = unbounded_channel();
+        let (_tx_actioned_prompts, rx_actioned_prompts) = unbounded_channel();
+        let pending_prompts = HashMap::from([(
+            "cgroup_0".into(),
+            vec![
+                device_prompt("1", "camera", "test", T0),
+                device_prompt("2", "microphone", "test", T1),
+            ]
+            .into(),
+        )]);
+
+        let mut w = Worker {
+            rx_prompts,
+            rx_actioned_prompts,
+            active_prompts: RefActivePrompts::new(HashMap::new()),
+            dialog_processes: HashMap::new(),
+            pending_prompts,
+            dead_prompts: vec![],
+            requeued_prompts: HashMap::new(),
+            config: test_config(Policy::default()),
+            audit_log: None,
+            recent_prompts: RefRecentPrompts::default(),
+            ui: StubUi,
+            client: StubClient,
+            running: true,
+        };
+
+        w.process_next_pending_prompts().await.unwrap();
+
+        let active = w.active_prompts.get(&"cgroup_0".into()).expect("active");
+        assert!(matches!(active, TypedUiInput::Camera(_)), "{active:?}");
+        assert_eq!(w.pending_prompts[&"cgroup_0".into()].len(), 1);
+    }
+
+    #[tokio::test]
+    async fn timeout_waiting_for_reply_denies_paired_prompts() {
+        let (_tx_prompts, rx_prompts) = unbounded_channel();
+        let (_tx_actioned_prompts, rx_actioned_prompts) = unbounded_channel();
+        let pending_prompts = HashMap::from([(
+            "cgroup_0".into(),
+            vec![
+                device_prompt("1", "camera", "test", T0),
+                device_prompt("2", "microphone", "test", T1),
+            ]
+            .into(),
+        )]);
+
+        let mut w = Worker {
+            rx_prompts,
+            rx_actioned_prompts,
+            active_prompts: RefActivePrompts::new(HashMap::new()),
+            dialog_processes: HashMap::new(),
+            pending_prompts,
+            dead_prompts: vec![],
+            requeued_prompts: HashMap::new(),
+            config: device_prompt_config(),
+            audit_log: None,
+            recent_prompts: RefRecentPrompts::default(),
+            ui: StubUi,
+            client: AckClient::default(),
+            running: true,
+        };
+
+        w.step().await.unwrap();
+
+        let replies_seen = w.client.seen.lock().unwrap().clone();
+        let ids: Vec<&str> = replies_seen.iter().map(|(id, _)| id.0.as_str()).collect();
+        assert_eq!(ids, vec!["1", "2"]);
+        for (_, reply) in replies_seen.iter() {
+            assert_eq!(reply.action(), Action::Deny);
+            assert_eq!(reply.lifespan(), Lifespan::Single);
+        }
+        assert!(w.pending_prompts.values().all(VecDeque::is_empty));
+        assert!(w.active_prompts.get(&"cgroup_0".into()).is_none());
+
+        let recent_prompts = w.recent_prompts.snapshot();
+        assert_eq!(recent_prompts.len(), 2);
+        assert!(recent_prompts.iter().all(|p| p.outcome()
+            == &AuditOutcome::Timeout {
+                fallback: UiFallback::DenyOnce,
+            }));
+    }
 }
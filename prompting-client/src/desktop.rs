@@ -0,0 +1,276 @@
+//! Lookup of the desktop entries that snapd installs for snap apps.
+//!
+//! Snapd writes a `.desktop` file for each app in a snap to `/var/lib/snapd/desktop/applications`
+//! named `<snap>_<app>.desktop`. These give a much better name and icon for the app that
+//! triggered a prompt than the snap name and store icon, particularly for snaps that ship several
+//! apps.
+use crate::snapd_client::SnapIcon;
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+use tracing::debug;
+
+const SNAPD_DESKTOP_DIR: &str = "/var/lib/snapd/desktop";
+const DEFAULT_DATA_DIRS: &str = "/usr/local/share:/usr/share";
+const DESKTOP_ENTRY_GROUP: &str = "[Desktop Entry]";
+
+/// The fields we make use of from a desktop entry.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DesktopEntry {
+    name: String,
+    localized_names: HashMap<String, String>,
+    icon: Option<String>,
+    snap_app_name: Option<String>,
+}
+
+impl DesktopEntry {
+    /// Parse the `[Desktop Entry]` group of a desktop file, returning `None` if there is no name.
+    pub fn parse(raw: &str) -> Option<Self> {
+        let mut entry = Self::default();
+        let mut in_group = false;
+
+        for line in raw.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_group = line == DESKTOP_ENTRY_GROUP;
+                continue;
+            }
+            if !in_group || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim().to_string());
+
+            match key {
+                "Name" => entry.name = value,
+                "Icon" => entry.icon = Some(value),
+                "X-SnapAppName" => entry.snap_app_name = Some(value),
+                _ => {
+                    if let Some(locale) =
+                        key.strip_prefix("Name[").and_then(|k| k.strip_suffix(']'))
+                    {
+                        entry.localized_names.insert(locale.to_string(), value);
+                    }
+                }
+            }
+        }
+
+        (!entry.name.is_empty()).then_some(entry)
+    }
+
+    /// The name of the entry for the given locale (of the form `lang_COUNTRY.ENCODING@MODIFIER`),
+    /// following the matching rules from the desktop entry specification.
+    pub fn name(&self, locale: Option<&str>) -> &str {
+        locale
+            .into_iter()
+            .flat_map(locale_keys)
+            .find_map(|key| self.localized_names.get(&key))
+            .unwrap_or(&self.name)
+    }
+
+    pub fn icon(&self) -> Option<&str> {
+        self.icon.as_deref()
+    }
+
+    /// Load the icon for this entry if it is given as an absolute path.
+    ///
+    /// Icons given as theme icon names are not resolved.
+    pub fn load_icon(&self) -> Option<SnapIcon> {
+        let path = Path::new(self.icon.as_deref()?);
+        if !path.is_absolute() {
+            return None;
+        }
+        let mime_type = match path.extension()?.to_str()? {
+            "png" => "image/png",
+            "svg" => "image/svg+xml",
+            "jpg" | "jpeg" => "image/jpeg",
+            _ => return None,
+        };
+        let bytes = fs::read(path)
+            .inspect_err(|e| debug!(path=%path.display(), "unable to read desktop icon: {e}"))
+            .ok()?;
+
+        Some(SnapIcon {
+            bytes: bytes.into(),
+            mime_type: mime_type.to_string(),
+        })
+    }
+}
+
+/// The keys to check for a localized value in order of preference.
+fn locale_keys(locale: &str) -> Vec<String> {
+    let (locale, modifier) = match locale.split_once('@') {
+        Some((locale, modifier)) => (locale, Some(modifier)),
+        None => (locale, None),
+    };
+    // The encoding is not used when matching keys
+    let locale = locale.split_once('.').map_or(locale, |(locale, _)| locale);
+    let (lang, country) = match locale.split_once('_') {
+        Some((lang, country)) => (lang, Some(country)),
+        None => (locale, None),
+    };
+
+    let mut keys = Vec::with_capacity(4);
+    if let (Some(country), Some(modifier)) = (country, modifier) {
+        keys.push(format!("{lang}_{country}@{modifier}"));
+    }
+    if let Some(country) = country {
+        keys.push(format!("{lang}_{country}"));
+    }
+    if let Some(modifier) = modifier {
+        keys.push(format!("{lang}@{modifier}"));
+    }
+    keys.push(lang.to_string());
+
+    keys
+}
+
+/// A resolver for the desktop entries of snap apps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DesktopEntries {
+    data_dirs: Vec<PathBuf>,
+    locale: Option<String>,
+}
+
+impl Default for DesktopEntries {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+impl DesktopEntries {
+    pub fn new(data_dirs: Vec<PathBuf>, locale: Option<String>) -> Self {
+        Self { data_dirs, locale }
+    }
+
+    /// Search `$XDG_DATA_DIRS` (along with the snapd desktop directory if it is not already
+    /// included) using the locale from the environment.
+    pub fn from_env() -> Self {
+        let raw = match env::var("XDG_DATA_DIRS") {
+            Ok(dirs) if !dirs.is_empty() => dirs,
+            _ => DEFAULT_DATA_DIRS.to_string(),
+        };
+        let mut data_dirs: Vec<PathBuf> = env::split_paths(&raw).collect();
+        if !data_dirs.iter().any(|d| d == Path::new(SNAPD_DESKTOP_DIR)) {
+            data_dirs.push(SNAPD_DESKTOP_DIR.into());
+        }
+
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .find_map(|var| env::var(var).ok().filter(|v| !v.is_empty()));
+
+        Self::new(data_dirs, locale)
+    }
+
+    pub fn locale(&self) -> Option<&str> {
+        self.locale.as_deref()
+    }
+
+    /// Find the desktop entry for the given snap app, falling back to the entry for the app with
+    /// the same name as the snap if no app is provided.
+    pub fn lookup(&self, snap: &str, app: Option<&str>) -> Option<DesktopEntry> {
+        let app = app.unwrap_or(snap);
+
+        self.data_dirs.iter().find_map(|dir| {
+            let dir = dir.join("applications");
+
+            read_entry(&dir.join(format!("{snap}_{app}.desktop")))
+                .or_else(|| self.scan_for_app(&dir, snap, app))
+        })
+    }
+
+    /// Desktop files are not required to be named after the app they launch, so fall back to
+    /// checking all of the entries for the snap.
+    fn scan_for_app(&self, dir: &Path, snap: &str, app: &str) -> Option<DesktopEntry> {
+        let prefix = format!("{snap}_");
+
+        fs::read_dir(dir)
+            .ok()?
+            .flatten()
+            .filter(|e| {
+                let name = e.file_name();
+                let name = name.to_string_lossy();
+                name.starts_with(&prefix) && name.ends_with(".desktop")
+            })
+            .filter_map(|e| read_entry(&e.path()))
+            .find(|entry| entry.snap_app_name.as_deref() == Some(app))
+    }
+}
+
+fn read_entry(path: &Path) -> Option<DesktopEntry> {
+    DesktopEntry::parse(&fs::read_to_string(path).ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+    use uuid::Uuid;
+
+    const FIREFOX: &str = "\
+[Desktop Entry]
+Version=1.0
+Name=Firefox Web Browser
+Name[de]=Firefox-Webbrowser
+Name[pt_BR]=Navegador Firefox
+Name[sr@latin]=Firefox veb pregledač
+X-SnapAppName=firefox
+Icon=/snap/firefox/4793/default256.png
+
+[Desktop Action new-window]
+Name=New Window
+Icon=ignored
+";
+
+    #[test]
+    fn desktop_entries_are_parsed() {
+        let entry = DesktopEntry::parse(FIREFOX).unwrap();
+
+        assert_eq!(entry.name(None), "Firefox Web Browser");
+        assert_eq!(entry.icon(), Some("/snap/firefox/4793/default256.png"));
+        assert_eq!(entry.snap_app_name.as_deref(), Some("firefox"));
+    }
+
+    #[test_case("de_DE.UTF-8", "Firefox-Webbrowser"; "language only match")]
+    #[test_case("pt_BR.UTF-8", "Navegador Firefox"; "language and country match")]
+    #[test_case("pt_PT.UTF-8", "Firefox Web Browser"; "no match")]
+    #[test_case("sr_RS.UTF-8@latin", "Firefox veb pregledač"; "language and modifier match")]
+    #[test_case("C", "Firefox Web Browser"; "c locale")]
+    #[test]
+    fn localized_names_are_selected(locale: &str, expected: &str) {
+        let entry = DesktopEntry::parse(FIREFOX).unwrap();
+
+        assert_eq!(entry.name(Some(locale)), expected);
+    }
+
+    #[test]
+    fn entries_without_a_name_are_rejected() {
+        assert_eq!(DesktopEntry::parse("[Desktop Entry]\nIcon=foo\n"), None);
+    }
+
+    #[test]
+    fn entries_are_found_in_data_dirs() {
+        let data_dir = env::temp_dir().join(Uuid::new_v4().to_string());
+        let apps = data_dir.join("applications");
+        fs::create_dir_all(&apps).unwrap();
+        fs::write(apps.join("firefox_firefox.desktop"), FIREFOX).unwrap();
+        fs::write(
+            apps.join("code_code-url.desktop"),
+            "[Desktop Entry]\nName=Visual Studio Code - URL Handler\nX-SnapAppName=url-handler\n",
+        )
+        .unwrap();
+
+        let entries = DesktopEntries::new(vec!["/does/not/exist".into(), data_dir.clone()], None);
+
+        let firefox = entries.lookup("firefox", None).unwrap();
+        assert_eq!(firefox.name(None), "Firefox Web Browser");
+        let url_handler = entries.lookup("code", Some("url-handler")).unwrap();
+        assert_eq!(url_handler.name(None), "Visual Studio Code - URL Handler");
+        assert_eq!(entries.lookup("code", Some("code")), None);
+
+        fs::remove_dir_all(data_dir).unwrap();
+    }
+}
@@ -14,6 +14,8 @@ use prompt_sequence::MatchError;
 
 pub mod cli_actions;
 pub mod daemon;
+pub mod desktop;
+pub mod procfs;
 pub mod prompt_sequence;
 pub mod protos;
 pub mod snapd_client;
@@ -22,10 +24,11 @@ mod recording;
 mod socket_client;
 mod util;
 
-use snapd_client::SnapdError;
+use snapd_client::{interfaces::home::path_pattern::PathPatternError, SnapdError};
 
 pub(crate) const SNAP_NAME: &str = "prompting-client";
 pub const SOCKET_ENV_VAR: &str = "PROMPTING_CLIENT_SOCKET";
+pub const POLICY_ENV_VAR: &str = "PROMPTING_CLIENT_POLICY";
 pub const DEFAULT_LOG_LEVEL: &str = "info";
 
 pub fn log_filter(filter: &str) -> String {
@@ -58,12 +61,24 @@ pub enum Error {
     #[error("failed prompt sequence: {error}")]
     FailedPromptSequence { error: MatchError },
 
+    #[error("invalid config: {reason}")]
+    InvalidConfig { reason: String },
+
     #[error("invalid custom permissions: requested={requested:?} but available={available:?}")]
     InvalidCustomPermissions {
         requested: Vec<String>,
         available: Vec<String>,
     },
 
+    #[error("invalid path pattern {pattern:?}: {error}")]
+    InvalidPathPattern {
+        pattern: String,
+        error: PathPatternError,
+    },
+
+    #[error("{version} is not a supported policy version")]
+    InvalidPolicyVersion { version: u8 },
+
     #[error("{version} is not supported recording version.")]
     InvalidRecordingVersion { version: u8 },
 
@@ -85,6 +100,9 @@ pub enum Error {
     #[error("unable to convert prompt to {interface} prompt")]
     PromptConversionError { interface: String },
 
+    #[error("{interface} prompts are not enabled in the experimental-prompts config")]
+    PromptNotEnabled { interface: String },
+
     #[error("error message returned from snapd: {message}")]
     SnapdError {
         status: StatusCode,
@@ -95,9 +113,18 @@ pub enum Error {
     #[error("{interface} is not currently supported for apparmor prompting")]
     UnsupportedInterface { interface: String },
 
+    #[error("unable to list recent prompts: {reason}")]
+    UnableToListRecentPrompts { reason: String },
+
     #[error("unable to update log filter: {reason}")]
     UnableToUpdateLogFilter { reason: String },
 
+    #[error("no recorded response for {method} {path}")]
+    NoRecordedResponse { method: String, path: String },
+
+    #[error("request to snapd timed out after {timeout:?}")]
+    RequestTimeout { timeout: std::time::Duration },
+
     #[error("snapd replied with status code {status} but didn't provide a valid error response")]
     InvalidSnapdErrorResponse { status: StatusCode },
 
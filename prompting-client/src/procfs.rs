@@ -0,0 +1,325 @@
+//! Details of the process that triggered a prompt, read from `/proc/<pid>`.
+//!
+//! Snapd gives us the pid of the process that triggered each prompt. Many snaps are made up of
+//! several apps and helper processes so looking up the executable, command line and ancestry of
+//! that process lets us tell the user which part of the snap is actually asking for access.
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Serialize, Serializer};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use tracing::{debug, warn};
+
+const DEFAULT_ROOT: &str = "/proc";
+
+/// The kernel reports process start times in clock ticks since boot. USER_HZ is fixed at 100 as
+/// part of the userspace ABI on Linux.
+const CLOCK_TICKS_PER_SECOND: i64 = 100;
+
+/// An upper bound on the number of ancestors we walk when building the parent chain.
+const MAX_PARENTS: usize = 16;
+
+/// The state of the process that triggered a prompt at the time we pulled the prompt details.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "kebab-case")]
+pub enum PromptingProcess {
+    /// We were unable to read the details of the process.
+    #[default]
+    Unknown,
+    /// The process is still running.
+    Running(ProcessInfo),
+    /// The process exited before we were able to look it up.
+    Exited,
+}
+
+impl PromptingProcess {
+    pub fn info(&self) -> Option<&ProcessInfo> {
+        match self {
+            Self::Running(info) => Some(info),
+            Self::Unknown | Self::Exited => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub exe: Option<PathBuf>,
+    pub cmdline: Vec<String>,
+    #[serde(serialize_with = "serialize_start_time")]
+    pub start_time: Option<DateTime<Utc>>,
+    /// The ancestors of the process, starting with its immediate parent.
+    pub parents: Vec<ParentProcess>,
+}
+
+fn serialize_start_time<S>(t: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match t {
+        Some(t) => serializer.serialize_str(&t.to_rfc3339()),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ParentProcess {
+    pub pid: u32,
+    pub comm: String,
+    pub exe: Option<PathBuf>,
+}
+
+/// A view of a procfs mount.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcFs {
+    root: PathBuf,
+}
+
+impl Default for ProcFs {
+    fn default() -> Self {
+        Self::new(DEFAULT_ROOT)
+    }
+}
+
+impl ProcFs {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Look up the current state of the given process.
+    pub fn prompting_process(&self, pid: i64) -> PromptingProcess {
+        let pid = match u32::try_from(pid) {
+            Ok(pid) if pid > 0 => pid,
+            _ => {
+                warn!(pid, "invalid pid for prompting process");
+                return PromptingProcess::Unknown;
+            }
+        };
+
+        let stat = match self.stat(pid) {
+            Ok(stat) => stat,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                debug!(pid, "prompting process has already exited");
+                return PromptingProcess::Exited;
+            }
+            Err(e) => {
+                warn!(pid, "unable to read process details: {e}");
+                return PromptingProcess::Unknown;
+            }
+        };
+
+        let start_time = self.boot_time().map(|btime| {
+            btime + TimeDelta::milliseconds(stat.start_ticks * 1000 / CLOCK_TICKS_PER_SECOND)
+        });
+
+        PromptingProcess::Running(ProcessInfo {
+            pid,
+            exe: self.exe(pid),
+            cmdline: self.cmdline(pid),
+            start_time,
+            parents: self.parents(stat.ppid),
+        })
+    }
+
+    fn parents(&self, mut ppid: u32) -> Vec<ParentProcess> {
+        let mut parents = Vec::new();
+
+        while ppid != 0 && parents.len() < MAX_PARENTS {
+            let stat = match self.stat(ppid) {
+                Ok(stat) => stat,
+                Err(_) => break,
+            };
+            parents.push(ParentProcess {
+                pid: ppid,
+                comm: stat.comm,
+                exe: self.exe(ppid),
+            });
+            ppid = stat.ppid;
+        }
+
+        parents
+    }
+
+    fn exe(&self, pid: u32) -> Option<PathBuf> {
+        fs::read_link(self.root.join(pid.to_string()).join("exe")).ok()
+    }
+
+    fn cmdline(&self, pid: u32) -> Vec<String> {
+        match fs::read(self.root.join(pid.to_string()).join("cmdline")) {
+            Ok(raw) => raw
+                .split(|&b| b == 0)
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn stat(&self, pid: u32) -> io::Result<Stat> {
+        let raw = fs::read_to_string(self.root.join(pid.to_string()).join("stat"))?;
+
+        Stat::parse(&raw).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("malformed stat: {raw:?}"),
+            )
+        })
+    }
+
+    fn boot_time(&self) -> Option<DateTime<Utc>> {
+        let raw = fs::read_to_string(self.root.join("stat")).ok()?;
+        let secs = raw
+            .lines()
+            .find_map(|line| line.strip_prefix("btime "))?
+            .trim()
+            .parse()
+            .ok()?;
+
+        DateTime::from_timestamp(secs, 0)
+    }
+}
+
+/// The fields we make use of from `/proc/<pid>/stat`.
+#[derive(Debug, PartialEq, Eq)]
+struct Stat {
+    comm: String,
+    ppid: u32,
+    start_ticks: i64,
+}
+
+impl Stat {
+    /// See proc_pid_stat(5) for the format. The comm field is wrapped in parentheses and may
+    /// itself contain spaces and parentheses so we split on the final ')'.
+    fn parse(raw: &str) -> Option<Self> {
+        let (_, rest) = raw.split_once('(')?;
+        let (comm, rest) = rest.rsplit_once(')')?;
+        // The remaining fields start from field 3 (state)
+        let fields: Vec<&str> = rest.split_whitespace().collect();
+
+        Some(Self {
+            comm: comm.to_string(),
+            ppid: fields.get(1)?.parse().ok()?,
+            start_ticks: fields.get(19)?.parse().ok()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, os::unix::fs::symlink};
+    use uuid::Uuid;
+
+    fn stat_line(pid: u32, comm: &str, ppid: u32, start_ticks: i64) -> String {
+        format!(
+            "{pid} ({comm}) S {ppid} {pid} {pid} 0 -1 4194560 1 0 0 0 0 0 0 0 20 0 1 0 {start_ticks} 0 0\n"
+        )
+    }
+
+    fn add_process(root: &Path, pid: u32, comm: &str, ppid: u32, exe: &str, cmdline: &[&str]) {
+        let dir = root.join(pid.to_string());
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("stat"), stat_line(pid, comm, ppid, 12345)).unwrap();
+        fs::write(dir.join("cmdline"), cmdline.join("\0") + "\0").unwrap();
+        symlink(exe, dir.join("exe")).unwrap();
+    }
+
+    fn fake_procfs() -> ProcFs {
+        let root = env::temp_dir().join(Uuid::new_v4().to_string());
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join("stat"),
+            "cpu  1 2 3 4\nbtime 1723628823\nprocesses 42\n",
+        )
+        .unwrap();
+
+        add_process(
+            &root,
+            1,
+            "systemd",
+            0,
+            "/usr/lib/systemd/systemd",
+            &["/sbin/init"],
+        );
+        add_process(
+            &root,
+            100,
+            "firefox",
+            1,
+            "/snap/firefox/4793/usr/lib/firefox/firefox",
+            &["/snap/firefox/4793/usr/lib/firefox/firefox"],
+        );
+        add_process(
+            &root,
+            200,
+            "Web Content",
+            100,
+            "/snap/firefox/4793/usr/lib/firefox/firefox",
+            &[
+                "/snap/firefox/4793/usr/lib/firefox/firefox",
+                "-contentproc",
+                "tab",
+            ],
+        );
+
+        ProcFs::new(root)
+    }
+
+    #[test]
+    fn stat_parsing_handles_parens_in_comm() {
+        let stat = Stat::parse(&stat_line(42, "a) (b", 7, 999)).unwrap();
+
+        assert_eq!(
+            stat,
+            Stat {
+                comm: "a) (b".to_string(),
+                ppid: 7,
+                start_ticks: 999
+            }
+        );
+    }
+
+    #[test]
+    fn running_process_details_are_read() {
+        let procfs = fake_procfs();
+
+        let info = match procfs.prompting_process(200) {
+            PromptingProcess::Running(info) => info,
+            p => panic!("expected running process, got {p:?}"),
+        };
+
+        assert_eq!(
+            info.exe,
+            Some(PathBuf::from("/snap/firefox/4793/usr/lib/firefox/firefox"))
+        );
+        assert_eq!(info.cmdline[1..], ["-contentproc", "tab"]);
+        assert_eq!(
+            info.start_time,
+            DateTime::from_timestamp(1723628823 + 123, 450_000_000)
+        );
+        let parents: Vec<_> = info
+            .parents
+            .iter()
+            .map(|p| (p.pid, p.comm.as_str()))
+            .collect();
+        assert_eq!(parents, [(100, "firefox"), (1, "systemd")]);
+
+        fs::remove_dir_all(procfs.root()).unwrap();
+    }
+
+    #[test]
+    fn exited_processes_are_flagged() {
+        let procfs = fake_procfs();
+
+        assert_eq!(procfs.prompting_process(300), PromptingProcess::Exited);
+
+        fs::remove_dir_all(procfs.root()).unwrap();
+    }
+}
@@ -1,11 +1,15 @@
 use crate::snapd_client::{
     interfaces::{
-        camera::CameraInterface, home::HomeInterface, microphone::MicrophoneInterface,
+        camera::CameraInterface,
+        files::{PersonalFilesInterface, SystemFilesInterface},
+        home::{path_pattern::PathPatternError, HomeInterface},
+        microphone::MicrophoneInterface,
+        removable_media::RemovableMediaInterface,
         ConstraintsFilter, ReplyConstraintsOverrides, SnapInterface,
     },
     Action, Lifespan, Prompt, PromptReply, TypedPrompt, TypedPromptReply,
 };
-use serde::{Deserialize, Deserializer, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 use std::{collections::VecDeque, fs};
 
 #[allow(dead_code)]
@@ -17,6 +21,8 @@ pub struct PromptSequence {
     prompts: VecDeque<TypedPromptCase>,
     #[serde(skip, default)]
     index: usize,
+    #[serde(skip, default)]
+    skip_reply_validation: bool,
 }
 
 impl PromptSequence {
@@ -34,6 +40,12 @@ impl PromptSequence {
         Ok((seq, content))
     }
 
+    /// Send replies to snapd without first checking them locally, leaving snapd to reject them if
+    /// they are invalid.
+    pub fn skip_reply_validation(&mut self) {
+        self.skip_reply_validation = true;
+    }
+
     pub fn should_handle(&self, p: &TypedPrompt) -> bool {
         match &self.filter {
             Some(f) => f.matches(p),
@@ -53,7 +65,7 @@ impl PromptSequence {
         match (case, p) {
             (TypedPromptCase::Camera(case), TypedPrompt::Camera(p)) => {
                 let res = case
-                    .into_reply_or_error(p, self.index)
+                    .into_reply_or_error(p, self.index, !self.skip_reply_validation)
                     .map(|res| res.map(TypedPromptReply::Camera));
                 self.index += 1;
 
@@ -61,7 +73,7 @@ impl PromptSequence {
             }
             (TypedPromptCase::Home(case), TypedPrompt::Home(p)) => {
                 let res = case
-                    .into_reply_or_error(p, self.index)
+                    .into_reply_or_error(p, self.index, !self.skip_reply_validation)
                     .map(|res| res.map(TypedPromptReply::Home));
                 self.index += 1;
 
@@ -69,22 +81,53 @@ impl PromptSequence {
             }
             (TypedPromptCase::Microphone(case), TypedPrompt::Microphone(p)) => {
                 let res = case
-                    .into_reply_or_error(p, self.index)
+                    .into_reply_or_error(p, self.index, !self.skip_reply_validation)
                     .map(|res| res.map(TypedPromptReply::Microphone));
                 self.index += 1;
 
                 res
             }
+            (TypedPromptCase::PersonalFiles(case), TypedPrompt::PersonalFiles(p)) => {
+                let res = case
+                    .into_reply_or_error(p, self.index, !self.skip_reply_validation)
+                    .map(|res| res.map(TypedPromptReply::PersonalFiles));
+                self.index += 1;
+
+                res
+            }
+            (TypedPromptCase::RemovableMedia(case), TypedPrompt::RemovableMedia(p)) => {
+                let res = case
+                    .into_reply_or_error(p, self.index, !self.skip_reply_validation)
+                    .map(|res| res.map(TypedPromptReply::RemovableMedia));
+                self.index += 1;
+
+                res
+            }
+            (TypedPromptCase::SystemFiles(case), TypedPrompt::SystemFiles(p)) => {
+                let res = case
+                    .into_reply_or_error(p, self.index, !self.skip_reply_validation)
+                    .map(|res| res.map(TypedPromptReply::SystemFiles));
+                self.index += 1;
+
+                res
+            }
             (case, p) => Err(MatchError::WrongInterface {
                 expected: match case {
                     TypedPromptCase::Camera(_) => CameraInterface::NAME.to_string(),
                     TypedPromptCase::Home(_) => HomeInterface::NAME.to_string(),
                     TypedPromptCase::Microphone(_) => MicrophoneInterface::NAME.to_string(),
+                    TypedPromptCase::PersonalFiles(_) => PersonalFilesInterface::NAME.to_string(),
+                    TypedPromptCase::RemovableMedia(_) => RemovableMediaInterface::NAME.to_string(),
+                    TypedPromptCase::SystemFiles(_) => SystemFilesInterface::NAME.to_string(),
                 },
                 seen: match p {
                     TypedPrompt::Camera(_) => CameraInterface::NAME.to_string(),
                     TypedPrompt::Home(_) => HomeInterface::NAME.to_string(),
                     TypedPrompt::Microphone(_) => MicrophoneInterface::NAME.to_string(),
+                    TypedPrompt::PersonalFiles(_) => PersonalFilesInterface::NAME.to_string(),
+                    TypedPrompt::RemovableMedia(_) => RemovableMediaInterface::NAME.to_string(),
+                    TypedPrompt::SystemFiles(_) => SystemFilesInterface::NAME.to_string(),
+                    TypedPrompt::Generic(p) => p.interface().to_string(),
                 },
             }),
         }
@@ -108,20 +151,93 @@ fn apply_vars(mut content: String, vars: &[(&str, &str)]) -> String {
     content
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize)]
 #[serde(untagged)]
 enum TypedPromptCase {
     Home(PromptCase<HomeInterface>),
     Camera(PromptCase<CameraInterface>),
     Microphone(PromptCase<MicrophoneInterface>),
+    PersonalFiles(PromptCase<PersonalFilesInterface>),
+    RemovableMedia(PromptCase<RemovableMediaInterface>),
+    SystemFiles(PromptCase<SystemFilesInterface>),
+}
+
+// The path based interfaces share the same constraint fields so we select the
+// variant based on the interface named in the filter when there is one. Otherwise we fall back to
+// trying each of the original interfaces in turn as an untagged enum would.
+impl<'de> Deserialize<'de> for TypedPromptCase {
+    fn deserialize<D>(de: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(de)?;
+        let interface = value
+            .pointer("/prompt-filter/interface")
+            .and_then(|i| i.as_str());
+
+        let res = match interface {
+            Some(CameraInterface::NAME) => serde_json::from_value(value).map(Self::Camera),
+            Some(HomeInterface::NAME) => serde_json::from_value(value).map(Self::Home),
+            Some(MicrophoneInterface::NAME) => serde_json::from_value(value).map(Self::Microphone),
+            Some(PersonalFilesInterface::NAME) => {
+                serde_json::from_value(value).map(Self::PersonalFiles)
+            }
+            Some(RemovableMediaInterface::NAME) => {
+                serde_json::from_value(value).map(Self::RemovableMedia)
+            }
+            Some(SystemFilesInterface::NAME) => {
+                serde_json::from_value(value).map(Self::SystemFiles)
+            }
+            _ => serde_json::from_value(value.clone())
+                .map(Self::Home)
+                .or_else(|_| serde_json::from_value(value.clone()).map(Self::Camera))
+                .or_else(|_| serde_json::from_value(value).map(Self::Microphone)),
+        };
+
+        res.map_err(de::Error::custom)
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize)]
 #[serde(untagged)]
 enum TypedPromptFilter {
     Camera(PromptFilter<CameraInterface>),
     Home(PromptFilter<HomeInterface>),
     Microphone(PromptFilter<MicrophoneInterface>),
+    PersonalFiles(PromptFilter<PersonalFilesInterface>),
+    RemovableMedia(PromptFilter<RemovableMediaInterface>),
+    SystemFiles(PromptFilter<SystemFilesInterface>),
+}
+
+impl<'de> Deserialize<'de> for TypedPromptFilter {
+    fn deserialize<D>(de: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(de)?;
+        let interface = value.get("interface").and_then(|i| i.as_str());
+
+        let res = match interface {
+            Some(CameraInterface::NAME) => serde_json::from_value(value).map(Self::Camera),
+            Some(HomeInterface::NAME) => serde_json::from_value(value).map(Self::Home),
+            Some(MicrophoneInterface::NAME) => serde_json::from_value(value).map(Self::Microphone),
+            Some(PersonalFilesInterface::NAME) => {
+                serde_json::from_value(value).map(Self::PersonalFiles)
+            }
+            Some(RemovableMediaInterface::NAME) => {
+                serde_json::from_value(value).map(Self::RemovableMedia)
+            }
+            Some(SystemFilesInterface::NAME) => {
+                serde_json::from_value(value).map(Self::SystemFiles)
+            }
+            _ => serde_json::from_value(value.clone())
+                .map(Self::Camera)
+                .or_else(|_| serde_json::from_value(value.clone()).map(Self::Home))
+                .or_else(|_| serde_json::from_value(value).map(Self::Microphone)),
+        };
+
+        res.map_err(de::Error::custom)
+    }
 }
 
 impl TypedPromptFilter {
@@ -130,6 +246,9 @@ impl TypedPromptFilter {
             (Self::Camera(f), TypedPrompt::Camera(p)) => f.matches(p).is_success(),
             (Self::Home(f), TypedPrompt::Home(p)) => f.matches(p).is_success(),
             (Self::Microphone(f), TypedPrompt::Microphone(p)) => f.matches(p).is_success(),
+            (Self::PersonalFiles(f), TypedPrompt::PersonalFiles(p)) => f.matches(p).is_success(),
+            (Self::RemovableMedia(f), TypedPrompt::RemovableMedia(p)) => f.matches(p).is_success(),
+            (Self::SystemFiles(f), TypedPrompt::SystemFiles(p)) => f.matches(p).is_success(),
             _ => false,
         }
     }
@@ -164,6 +283,7 @@ where
         self,
         p: Prompt<I>,
         index: usize,
+        validate: bool,
     ) -> Result<Option<PromptReply<I>>, MatchError> {
         match (self.prompt_filter.matches(&p), self.reply) {
             (MatchAttempt::Success, None) => Ok(None),
@@ -174,6 +294,9 @@ where
                 reply.duration = template.duration;
                 if let Some(constraints) = template.constraints {
                     reply.constraints = constraints.apply(reply.constraints);
+                    if validate {
+                        I::ReplyConstraintsOverrides::validate(&reply.constraints)?;
+                    }
                 }
 
                 Ok(Some(reply))
@@ -194,6 +317,12 @@ pub enum MatchError {
         failures: Vec<MatchFailure>,
     },
 
+    #[error("invalid path pattern {pattern:?} in reply: {error}")]
+    InvalidPathPattern {
+        pattern: String,
+        error: PathPatternError,
+    },
+
     #[error("the provided prompt sequence has no prompts remaining")]
     NoPromptsRemaining,
 
@@ -449,4 +578,14 @@ mod tests {
 
         assert!(res.is_ok(), "error parsing {path}: {res:?}");
     }
+
+    #[test]
+    fn prompt_cases_use_the_interface_from_the_filter() {
+        let raw = include_str!("../resources/prompt-sequence-tests/removable_media_sequence.json");
+        let (seq, _) =
+            PromptSequence::try_new_from_string(raw, &[("BASE_PATH", "/home/foo")]).unwrap();
+
+        assert!(matches!(seq.prompts[0], TypedPromptCase::RemovableMedia(_)));
+        assert!(matches!(seq.prompts[1], TypedPromptCase::Home(_)));
+    }
 }
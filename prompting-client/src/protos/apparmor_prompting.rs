@@ -7,7 +7,11 @@ pub struct PromptReply {
     pub action: i32,
     #[prost(enumeration = "Lifespan", tag = "3")]
     pub lifespan: i32,
-    #[prost(oneof = "prompt_reply::PromptReply", tags = "4, 5, 6")]
+    /// If snapd reports that this reply conflicts with existing rules, narrow or remove those rules
+    /// and retry the reply.
+    #[prost(bool, tag = "7")]
+    pub replace_conflicting_rules: bool,
+    #[prost(oneof = "prompt_reply::PromptReply", tags = "4, 5, 6, 8, 9, 10, 11, 12")]
     pub prompt_reply: ::core::option::Option<prompt_reply::PromptReply>,
 }
 /// Nested message and enum types in `PromptReply`.
@@ -20,6 +24,17 @@ pub mod prompt_reply {
         CameraPromptReply(super::CameraPromptReply),
         #[prost(message, tag = "6")]
         MicrophonePromptReply(super::MicrophonePromptReply),
+        #[prost(message, tag = "8")]
+        GenericPromptReply(super::GenericPromptReply),
+        #[prost(message, tag = "9")]
+        RemovableMediaPromptReply(super::RemovableMediaPromptReply),
+        /// Replies for the personal-files and system-files interfaces share the home reply format.
+        #[prost(message, tag = "10")]
+        PersonalFilesPromptReply(super::HomePromptReply),
+        #[prost(message, tag = "11")]
+        SystemFilesPromptReply(super::HomePromptReply),
+        #[prost(message, tag = "12")]
+        DevicePromptReply(super::DevicePromptReply),
     }
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -104,7 +119,10 @@ pub mod prompt_reply_response {
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetCurrentPromptResponse {
-    #[prost(oneof = "get_current_prompt_response::Prompt", tags = "1, 2, 3")]
+    #[prost(
+        oneof = "get_current_prompt_response::Prompt",
+        tags = "1, 2, 3, 4, 5, 6, 7, 8"
+    )]
     pub prompt: ::core::option::Option<get_current_prompt_response::Prompt>,
 }
 /// Nested message and enum types in `GetCurrentPromptResponse`.
@@ -117,6 +135,16 @@ pub mod get_current_prompt_response {
         CameraPrompt(super::CameraPrompt),
         #[prost(message, tag = "3")]
         MicrophonePrompt(super::MicrophonePrompt),
+        #[prost(message, tag = "4")]
+        GenericPrompt(super::GenericPrompt),
+        #[prost(message, tag = "5")]
+        RemovableMediaPrompt(super::RemovableMediaPrompt),
+        #[prost(message, tag = "6")]
+        PersonalFilesPrompt(super::FilesPrompt),
+        #[prost(message, tag = "7")]
+        SystemFilesPrompt(super::FilesPrompt),
+        #[prost(message, tag = "8")]
+        DevicePrompt(super::DevicePrompt),
     }
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -136,6 +164,29 @@ pub struct MicrophonePromptReply {
     #[prost(enumeration = "DevicePermission", repeated, tag = "1")]
     pub permissions: ::prost::alloc::vec::Vec<i32>,
 }
+/// A single decision applied to both the camera and microphone prompts of a DevicePrompt. The
+/// prompt_id of the enclosing PromptReply is that of the camera prompt.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DevicePromptReply {
+    #[prost(string, tag = "1")]
+    pub microphone_prompt_id: ::prost::alloc::string::String,
+    #[prost(enumeration = "DevicePermission", repeated, tag = "2")]
+    pub permissions: ::prost::alloc::vec::Vec<i32>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RemovableMediaPromptReply {
+    #[prost(string, tag = "1")]
+    pub path_pattern: ::prost::alloc::string::String,
+    #[prost(enumeration = "HomePermission", repeated, tag = "2")]
+    pub permissions: ::prost::alloc::vec::Vec<i32>,
+}
+/// Only allow or deny once is supported for generic prompts.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GenericPromptReply {
+    /// The requested permissions from the prompt being replied to.
+    #[prost(string, repeated, tag = "1")]
+    pub permissions: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct HomePrompt {
     #[prost(message, optional, tag = "1")]
@@ -179,6 +230,135 @@ pub struct MicrophonePrompt {
     #[prost(message, optional, tag = "1")]
     pub meta_data: ::core::option::Option<MetaData>,
 }
+/// Camera and microphone prompts from the same app presented as a single prompt. The prompt_id in
+/// meta_data is that of the camera prompt.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DevicePrompt {
+    #[prost(message, optional, tag = "1")]
+    pub meta_data: ::core::option::Option<MetaData>,
+    #[prost(string, tag = "2")]
+    pub microphone_prompt_id: ::prost::alloc::string::String,
+}
+/// A prompt for the personal-files or system-files interfaces. The pattern options are limited
+/// to those covered by the paths declared by the snap's plug.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FilesPrompt {
+    #[prost(message, optional, tag = "1")]
+    pub meta_data: ::core::option::Option<MetaData>,
+    #[prost(string, tag = "2")]
+    pub requested_path: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub home_dir: ::prost::alloc::string::String,
+    #[prost(enumeration = "HomePermission", repeated, tag = "4")]
+    pub requested_permissions: ::prost::alloc::vec::Vec<i32>,
+    #[prost(enumeration = "HomePermission", repeated, tag = "5")]
+    pub available_permissions: ::prost::alloc::vec::Vec<i32>,
+    #[prost(enumeration = "HomePermission", repeated, tag = "6")]
+    pub suggested_permissions: ::prost::alloc::vec::Vec<i32>,
+    #[prost(message, repeated, tag = "7")]
+    pub pattern_options: ::prost::alloc::vec::Vec<home_prompt::PatternOption>,
+    #[prost(int32, tag = "8")]
+    pub initial_pattern_option: i32,
+    #[prost(message, optional, tag = "9")]
+    pub enriched_path_kind: ::core::option::Option<EnrichedPathKind>,
+    #[prost(string, repeated, tag = "10")]
+    pub declared_paths: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+/// Removable media uses the same set of permissions as the home interface.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RemovableMediaPrompt {
+    #[prost(message, optional, tag = "1")]
+    pub meta_data: ::core::option::Option<MetaData>,
+    #[prost(string, tag = "2")]
+    pub requested_path: ::prost::alloc::string::String,
+    /// Where the drive containing the requested path is mounted. Empty if the drive is not known.
+    #[prost(string, tag = "3")]
+    pub mount_point: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub volume_label: ::prost::alloc::string::String,
+    #[prost(enumeration = "HomePermission", repeated, tag = "5")]
+    pub requested_permissions: ::prost::alloc::vec::Vec<i32>,
+    #[prost(enumeration = "HomePermission", repeated, tag = "6")]
+    pub available_permissions: ::prost::alloc::vec::Vec<i32>,
+    #[prost(enumeration = "HomePermission", repeated, tag = "7")]
+    pub suggested_permissions: ::prost::alloc::vec::Vec<i32>,
+    #[prost(message, repeated, tag = "8")]
+    pub pattern_options: ::prost::alloc::vec::Vec<removable_media_prompt::PatternOption>,
+    #[prost(int32, tag = "9")]
+    pub initial_pattern_option: i32,
+}
+/// Nested message and enum types in `RemovableMediaPrompt`.
+pub mod removable_media_prompt {
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct PatternOption {
+        #[prost(enumeration = "PatternType", tag = "1")]
+        pub pattern_type: i32,
+        #[prost(string, tag = "2")]
+        pub path_pattern: ::prost::alloc::string::String,
+        #[prost(bool, tag = "3")]
+        pub show_initially: bool,
+    }
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        PartialOrd,
+        Ord,
+        ::prost::Enumeration
+    )]
+    #[repr(i32)]
+    pub enum PatternType {
+        Drive = 0,
+        FolderOnDrive = 1,
+        RequestedDirectory = 2,
+        RequestedFile = 3,
+        MatchingFileExtension = 4,
+    }
+    impl PatternType {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                Self::Drive => "DRIVE",
+                Self::FolderOnDrive => "FOLDER_ON_DRIVE",
+                Self::RequestedDirectory => "REQUESTED_DIRECTORY",
+                Self::RequestedFile => "REQUESTED_FILE",
+                Self::MatchingFileExtension => "MATCHING_FILE_EXTENSION",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "DRIVE" => Some(Self::Drive),
+                "FOLDER_ON_DRIVE" => Some(Self::FolderOnDrive),
+                "REQUESTED_DIRECTORY" => Some(Self::RequestedDirectory),
+                "REQUESTED_FILE" => Some(Self::RequestedFile),
+                "MATCHING_FILE_EXTENSION" => Some(Self::MatchingFileExtension),
+                _ => None,
+            }
+        }
+    }
+}
+/// A prompt for an interface that the client does not have dedicated support for.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GenericPrompt {
+    #[prost(message, optional, tag = "1")]
+    pub meta_data: ::core::option::Option<MetaData>,
+    #[prost(string, tag = "2")]
+    pub interface: ::prost::alloc::string::String,
+    /// The constraints of the prompt as provided by snapd, encoded as JSON.
+    #[prost(string, tag = "3")]
+    pub constraints: ::prost::alloc::string::String,
+    #[prost(string, repeated, tag = "4")]
+    pub requested_permissions: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, repeated, tag = "5")]
+    pub available_permissions: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct MetaData {
     #[prost(string, tag = "1")]
@@ -195,6 +375,61 @@ pub struct MetaData {
     pub snap_icon: ::prost::alloc::vec::Vec<u8>,
     #[prost(string, tag = "7")]
     pub snap_icon_mime_type: ::prost::alloc::string::String,
+    /// The title of the snap as a whole: snapd does not provide titles for individual apps.
+    #[prost(string, tag = "8")]
+    pub snap_title: ::prost::alloc::string::String,
+    #[prost(string, tag = "9")]
+    pub summary: ::prost::alloc::string::String,
+    #[prost(string, tag = "10")]
+    pub version: ::prost::alloc::string::String,
+    #[prost(string, tag = "11")]
+    pub channel: ::prost::alloc::string::String,
+    #[prost(string, tag = "12")]
+    pub revision: ::prost::alloc::string::String,
+    /// One of "strict", "classic" or "devmode".
+    #[prost(string, tag = "13")]
+    pub confinement: ::prost::alloc::string::String,
+    #[prost(bool, tag = "14")]
+    pub devmode: bool,
+    /// Installed from a local file with --dangerous rather than from the store.
+    #[prost(bool, tag = "15")]
+    pub installed_locally: bool,
+    #[prost(enumeration = "PublisherValidation", tag = "16")]
+    pub publisher_validation: i32,
+    /// Not set if the process details could not be read.
+    #[prost(message, optional, tag = "17")]
+    pub process: ::core::option::Option<ProcessInfo>,
+    /// The app within the snap that triggered the prompt, empty if unknown.
+    #[prost(string, tag = "18")]
+    pub app_name: ::prost::alloc::string::String,
+    /// The localised name of the app from its desktop entry, empty if unknown.
+    #[prost(string, tag = "19")]
+    pub app_title: ::prost::alloc::string::String,
+}
+/// The process that triggered the prompt.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProcessInfo {
+    #[prost(uint32, tag = "1")]
+    pub pid: u32,
+    #[prost(string, tag = "2")]
+    pub exe: ::prost::alloc::string::String,
+    #[prost(string, repeated, tag = "3")]
+    pub cmdline: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// RFC 3339 timestamp, empty if unknown.
+    #[prost(string, tag = "4")]
+    pub start_time: ::prost::alloc::string::String,
+    /// The ancestors of the process, starting with its immediate parent.
+    #[prost(message, repeated, tag = "5")]
+    pub parents: ::prost::alloc::vec::Vec<ParentProcess>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ParentProcess {
+    #[prost(uint32, tag = "1")]
+    pub pid: u32,
+    #[prost(string, tag = "2")]
+    pub comm: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub exe: ::prost::alloc::string::String,
 }
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct ResolveHomePatternTypeResponse {
@@ -207,8 +442,64 @@ pub struct SetLoggingFilterResponse {
     pub current: ::prost::alloc::string::String,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReloadConfigResponse {
+    /// The path the config was loaded from.
+    #[prost(string, tag = "1")]
+    pub path: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListRecentPromptsResponse {
+    /// Most recently handled prompts first.
+    #[prost(message, repeated, tag = "1")]
+    pub prompts: ::prost::alloc::vec::Vec<RecentPrompt>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RecentPrompt {
+    #[prost(string, tag = "1")]
+    pub prompt_id: ::prost::alloc::string::String,
+    /// When the outcome for the prompt was determined.
+    #[prost(string, tag = "2")]
+    pub timestamp: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub snap: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub interface: ::prost::alloc::string::String,
+    #[prost(oneof = "recent_prompt::Outcome", tags = "5, 6, 7, 8, 9, 10, 11, 12")]
+    pub outcome: ::core::option::Option<recent_prompt::Outcome>,
+}
+/// Nested message and enum types in `RecentPrompt`.
+pub mod recent_prompt {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Outcome {
+        #[prost(message, tag = "5")]
+        Replied(super::RecentPromptReply),
+        #[prost(message, tag = "6")]
+        Policy(super::RecentPromptReply),
+        #[prost(string, tag = "7")]
+        Error(::prost::alloc::string::String),
+        #[prost(message, tag = "8")]
+        Timeout(()),
+        #[prost(message, tag = "9")]
+        NotFound(()),
+        /// The ID of the prompt whose reply also resolved this one.
+        #[prost(string, tag = "10")]
+        ResolvedBy(::prost::alloc::string::String),
+        #[prost(message, tag = "11")]
+        Cancelled(()),
+        #[prost(message, tag = "12")]
+        ProcessExited(()),
+    }
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct RecentPromptReply {
+    #[prost(enumeration = "Action", tag = "1")]
+    pub action: i32,
+    #[prost(enumeration = "RuleLifespan", tag = "2")]
+    pub lifespan: i32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct EnrichedPathKind {
-    #[prost(oneof = "enriched_path_kind::Kind", tags = "1, 2, 3, 4, 5, 6")]
+    #[prost(oneof = "enriched_path_kind::Kind", tags = "1, 2, 3, 4, 5, 6, 7")]
     pub kind: ::core::option::Option<enriched_path_kind::Kind>,
 }
 /// Nested message and enum types in `EnrichedPathKind`.
@@ -227,8 +518,16 @@ pub mod enriched_path_kind {
         TopLevelDirFile(super::TopLevelDirFile),
         #[prost(message, tag = "6")]
         SubDirFile(super::SubDirFile),
+        #[prost(message, tag = "7")]
+        DotfileConfigDir(super::DotfileConfigDir),
     }
 }
+/// A path within a hidden configuration directory such as ~/.config/foo or ~/.foo.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DotfileConfigDir {
+    #[prost(string, tag = "1")]
+    pub dirname: ::prost::alloc::string::String,
+}
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct HomeDir {}
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -252,6 +551,95 @@ pub struct TopLevelDirFile {
 }
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct SubDirFile {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListRulesResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub rules: ::prost::alloc::vec::Vec<Rule>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RemoveRuleResponse {
+    #[prost(message, optional, tag = "1")]
+    pub rule: ::core::option::Option<Rule>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Rule {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub timestamp: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub snap: ::prost::alloc::string::String,
+    #[prost(oneof = "rule::Constraints", tags = "4, 5, 6, 7, 8, 9")]
+    pub constraints: ::core::option::Option<rule::Constraints>,
+}
+/// Nested message and enum types in `Rule`.
+pub mod rule {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Constraints {
+        #[prost(message, tag = "4")]
+        HomeRuleConstraints(super::HomeRuleConstraints),
+        #[prost(message, tag = "5")]
+        CameraRuleConstraints(super::CameraRuleConstraints),
+        #[prost(message, tag = "6")]
+        MicrophoneRuleConstraints(super::MicrophoneRuleConstraints),
+        #[prost(message, tag = "7")]
+        RemovableMediaRuleConstraints(super::RemovableMediaRuleConstraints),
+        #[prost(message, tag = "8")]
+        PersonalFilesRuleConstraints(super::HomeRuleConstraints),
+        #[prost(message, tag = "9")]
+        SystemFilesRuleConstraints(super::HomeRuleConstraints),
+    }
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RulePermissionEntry {
+    #[prost(enumeration = "Action", tag = "1")]
+    pub outcome: i32,
+    #[prost(enumeration = "RuleLifespan", tag = "2")]
+    pub lifespan: i32,
+    #[prost(string, tag = "3")]
+    pub expiration: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HomeRuleConstraints {
+    #[prost(string, tag = "1")]
+    pub path_pattern: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "2")]
+    pub permissions: ::prost::alloc::vec::Vec<home_rule_constraints::HomeRulePermission>,
+}
+/// Nested message and enum types in `HomeRuleConstraints`.
+pub mod home_rule_constraints {
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct HomeRulePermission {
+        #[prost(enumeration = "super::HomePermission", tag = "1")]
+        pub permission: i32,
+        #[prost(message, optional, tag = "2")]
+        pub entry: ::core::option::Option<super::RulePermissionEntry>,
+    }
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeviceRulePermission {
+    #[prost(enumeration = "DevicePermission", tag = "1")]
+    pub permission: i32,
+    #[prost(message, optional, tag = "2")]
+    pub entry: ::core::option::Option<RulePermissionEntry>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CameraRuleConstraints {
+    #[prost(message, repeated, tag = "1")]
+    pub permissions: ::prost::alloc::vec::Vec<DeviceRulePermission>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MicrophoneRuleConstraints {
+    #[prost(message, repeated, tag = "1")]
+    pub permissions: ::prost::alloc::vec::Vec<DeviceRulePermission>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RemovableMediaRuleConstraints {
+    #[prost(string, tag = "1")]
+    pub path_pattern: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "2")]
+    pub permissions: ::prost::alloc::vec::Vec<home_rule_constraints::HomeRulePermission>,
+}
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum Action {
@@ -307,6 +695,40 @@ impl Lifespan {
         }
     }
 }
+/// The lifespan of an existing rule or of a reply that has already been sent to snapd. Unlike
+/// replies from the UI these may have a duration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum RuleLifespan {
+    Single = 0,
+    Session = 1,
+    Forever = 2,
+    Timespan = 3,
+}
+impl RuleLifespan {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Single => "RULE_LIFESPAN_SINGLE",
+            Self::Session => "RULE_LIFESPAN_SESSION",
+            Self::Forever => "RULE_LIFESPAN_FOREVER",
+            Self::Timespan => "RULE_LIFESPAN_TIMESPAN",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "RULE_LIFESPAN_SINGLE" => Some(Self::Single),
+            "RULE_LIFESPAN_SESSION" => Some(Self::Session),
+            "RULE_LIFESPAN_FOREVER" => Some(Self::Forever),
+            "RULE_LIFESPAN_TIMESPAN" => Some(Self::Timespan),
+            _ => None,
+        }
+    }
+}
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum HomePermission {
@@ -400,6 +822,35 @@ impl HomePatternType {
         }
     }
 }
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum PublisherValidation {
+    Unproven = 0,
+    Verified = 1,
+    Starred = 2,
+}
+impl PublisherValidation {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Unproven => "UNPROVEN",
+            Self::Verified => "VERIFIED",
+            Self::Starred => "STARRED",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "UNPROVEN" => Some(Self::Unproven),
+            "VERIFIED" => Some(Self::Verified),
+            "STARRED" => Some(Self::Starred),
+            _ => None,
+        }
+    }
+}
 /// Generated client implementations.
 pub mod app_armor_prompting_client {
     #![allow(
@@ -607,45 +1058,184 @@ pub mod app_armor_prompting_client {
                 );
             self.inner.unary(req, path, codec).await
         }
-    }
-}
-/// Generated server implementations.
-pub mod app_armor_prompting_server {
-    #![allow(
-        unused_variables,
-        dead_code,
-        missing_docs,
-        clippy::wildcard_imports,
-        clippy::let_unit_value,
-    )]
-    use tonic::codegen::*;
-    /// Generated trait containing gRPC methods that should be implemented for use with AppArmorPromptingServer.
-    #[async_trait]
-    pub trait AppArmorPrompting: std::marker::Send + std::marker::Sync + 'static {
-        /// Server streaming response type for the GetCurrentPrompt method.
-        type GetCurrentPromptStream: tonic::codegen::tokio_stream::Stream<
-                Item = std::result::Result<
-                    super::GetCurrentPromptResponse,
-                    tonic::Status,
-                >,
-            >
-            + std::marker::Send
-            + 'static;
-        async fn get_current_prompt(
-            &self,
-            request: tonic::Request<::prost::alloc::string::String>,
+        pub async fn list_rules(
+            &mut self,
+            request: impl tonic::IntoRequest<()>,
         ) -> std::result::Result<
-            tonic::Response<Self::GetCurrentPromptStream>,
+            tonic::Response<super::ListRulesResponse>,
             tonic::Status,
-        >;
-        async fn reply_to_prompt(
-            &self,
-            request: tonic::Request<super::PromptReply>,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/apparmor_prompting.AppArmorPrompting/ListRules",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("apparmor_prompting.AppArmorPrompting", "ListRules"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn list_rules_for_snap(
+            &mut self,
+            request: impl tonic::IntoRequest<::prost::alloc::string::String>,
         ) -> std::result::Result<
-            tonic::Response<super::PromptReplyResponse>,
+            tonic::Response<super::ListRulesResponse>,
             tonic::Status,
-        >;
-        async fn resolve_home_pattern_type(
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/apparmor_prompting.AppArmorPrompting/ListRulesForSnap",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "apparmor_prompting.AppArmorPrompting",
+                        "ListRulesForSnap",
+                    ),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn remove_rule(
+            &mut self,
+            request: impl tonic::IntoRequest<::prost::alloc::string::String>,
+        ) -> std::result::Result<
+            tonic::Response<super::RemoveRuleResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/apparmor_prompting.AppArmorPrompting/RemoveRule",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("apparmor_prompting.AppArmorPrompting", "RemoveRule"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn reload_config(
+            &mut self,
+            request: impl tonic::IntoRequest<()>,
+        ) -> std::result::Result<
+            tonic::Response<super::ReloadConfigResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/apparmor_prompting.AppArmorPrompting/ReloadConfig",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "apparmor_prompting.AppArmorPrompting",
+                        "ReloadConfig",
+                    ),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn list_recent_prompts(
+            &mut self,
+            request: impl tonic::IntoRequest<()>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListRecentPromptsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/apparmor_prompting.AppArmorPrompting/ListRecentPrompts",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "apparmor_prompting.AppArmorPrompting",
+                        "ListRecentPrompts",
+                    ),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod app_armor_prompting_server {
+    #![allow(
+        unused_variables,
+        dead_code,
+        missing_docs,
+        clippy::wildcard_imports,
+        clippy::let_unit_value,
+    )]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with AppArmorPromptingServer.
+    #[async_trait]
+    pub trait AppArmorPrompting: std::marker::Send + std::marker::Sync + 'static {
+        /// Server streaming response type for the GetCurrentPrompt method.
+        type GetCurrentPromptStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<
+                    super::GetCurrentPromptResponse,
+                    tonic::Status,
+                >,
+            >
+            + std::marker::Send
+            + 'static;
+        async fn get_current_prompt(
+            &self,
+            request: tonic::Request<::prost::alloc::string::String>,
+        ) -> std::result::Result<
+            tonic::Response<Self::GetCurrentPromptStream>,
+            tonic::Status,
+        >;
+        async fn reply_to_prompt(
+            &self,
+            request: tonic::Request<super::PromptReply>,
+        ) -> std::result::Result<
+            tonic::Response<super::PromptReplyResponse>,
+            tonic::Status,
+        >;
+        async fn resolve_home_pattern_type(
             &self,
             request: tonic::Request<::prost::alloc::string::String>,
         ) -> std::result::Result<
@@ -659,6 +1249,41 @@ pub mod app_armor_prompting_server {
             tonic::Response<super::SetLoggingFilterResponse>,
             tonic::Status,
         >;
+        async fn list_rules(
+            &self,
+            request: tonic::Request<()>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListRulesResponse>,
+            tonic::Status,
+        >;
+        async fn list_rules_for_snap(
+            &self,
+            request: tonic::Request<::prost::alloc::string::String>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListRulesResponse>,
+            tonic::Status,
+        >;
+        async fn remove_rule(
+            &self,
+            request: tonic::Request<::prost::alloc::string::String>,
+        ) -> std::result::Result<
+            tonic::Response<super::RemoveRuleResponse>,
+            tonic::Status,
+        >;
+        async fn reload_config(
+            &self,
+            request: tonic::Request<()>,
+        ) -> std::result::Result<
+            tonic::Response<super::ReloadConfigResponse>,
+            tonic::Status,
+        >;
+        async fn list_recent_prompts(
+            &self,
+            request: tonic::Request<()>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListRecentPromptsResponse>,
+            tonic::Status,
+        >;
     }
     #[derive(Debug)]
     pub struct AppArmorPromptingServer<T> {
@@ -931,6 +1556,225 @@ pub mod app_armor_prompting_server {
                     };
                     Box::pin(fut)
                 }
+                "/apparmor_prompting.AppArmorPrompting/ListRules" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListRulesSvc<T: AppArmorPrompting>(pub Arc<T>);
+                    impl<T: AppArmorPrompting> tonic::server::UnaryService<()>
+                    for ListRulesSvc<T> {
+                        type Response = super::ListRulesResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(&mut self, request: tonic::Request<()>) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AppArmorPrompting>::list_rules(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ListRulesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/apparmor_prompting.AppArmorPrompting/ListRulesForSnap" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListRulesForSnapSvc<T: AppArmorPrompting>(pub Arc<T>);
+                    impl<
+                        T: AppArmorPrompting,
+                    > tonic::server::UnaryService<::prost::alloc::string::String>
+                    for ListRulesForSnapSvc<T> {
+                        type Response = super::ListRulesResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<::prost::alloc::string::String>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AppArmorPrompting>::list_rules_for_snap(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ListRulesForSnapSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/apparmor_prompting.AppArmorPrompting/RemoveRule" => {
+                    #[allow(non_camel_case_types)]
+                    struct RemoveRuleSvc<T: AppArmorPrompting>(pub Arc<T>);
+                    impl<
+                        T: AppArmorPrompting,
+                    > tonic::server::UnaryService<::prost::alloc::string::String>
+                    for RemoveRuleSvc<T> {
+                        type Response = super::RemoveRuleResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<::prost::alloc::string::String>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AppArmorPrompting>::remove_rule(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = RemoveRuleSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/apparmor_prompting.AppArmorPrompting/ReloadConfig" => {
+                    #[allow(non_camel_case_types)]
+                    struct ReloadConfigSvc<T: AppArmorPrompting>(pub Arc<T>);
+                    impl<T: AppArmorPrompting> tonic::server::UnaryService<()>
+                    for ReloadConfigSvc<T> {
+                        type Response = super::ReloadConfigResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(&mut self, request: tonic::Request<()>) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AppArmorPrompting>::reload_config(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ReloadConfigSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/apparmor_prompting.AppArmorPrompting/ListRecentPrompts" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListRecentPromptsSvc<T: AppArmorPrompting>(pub Arc<T>);
+                    impl<T: AppArmorPrompting> tonic::server::UnaryService<()>
+                    for ListRecentPromptsSvc<T> {
+                        type Response = super::ListRecentPromptsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(&mut self, request: tonic::Request<()>) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AppArmorPrompting>::list_recent_prompts(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ListRecentPromptsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         let mut response = http::Response::new(
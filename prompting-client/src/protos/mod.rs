@@ -4,6 +4,8 @@ pub mod apparmor_prompting;
 pub use apparmor_prompting::{
     app_armor_prompting_server::{AppArmorPrompting, AppArmorPromptingServer},
     get_current_prompt_response::Prompt,
-    CameraPrompt, GetCurrentPromptResponse, HomePatternType, HomePrompt, MicrophonePrompt,
-    PromptReply, PromptReplyResponse, ResolveHomePatternTypeResponse,
+    CameraPrompt, DevicePrompt, FilesPrompt, GenericPrompt, GetCurrentPromptResponse,
+    HomePatternType, HomePrompt, ListRecentPromptsResponse, ListRulesResponse, MicrophonePrompt,
+    PromptReply, PromptReplyResponse, ReloadConfigResponse, RemovableMediaPrompt,
+    RemoveRuleResponse, ResolveHomePatternTypeResponse,
 };
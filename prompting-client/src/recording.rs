@@ -6,7 +6,7 @@ use crate::{
             home::{HomeConstraintsFilter, HomeInterface},
             SnapInterface,
         },
-        Action, Prompt, SnapdSocketClient, TypedPrompt, TypedPromptReply,
+        Action, Client, Prompt, SnapdClient, TypedPrompt, TypedPromptReply,
     },
     Error, Result, SNAP_NAME,
 };
@@ -119,7 +119,10 @@ impl PromptRecording {
         }
     }
 
-    pub async fn allow_write(&self, p: Prompt<HomeInterface>, c: &SnapdSocketClient) -> Result<()> {
+    pub async fn allow_write<C>(&self, p: Prompt<HomeInterface>, c: &SnapdClient<C>) -> Result<()>
+    where
+        C: Client,
+    {
         let id = p.id.clone();
         let reply = HomeInterface::prompt_to_reply(p, Action::Allow)
             .for_timespan("10s") // Using a timespan so our rule auto-removes
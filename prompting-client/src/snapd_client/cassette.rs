@@ -0,0 +1,505 @@
+//! Recording and replaying of the HTTP traffic between the client and snapd.
+//!
+//! [RecordingClient] wraps the real snapd socket client and appends every request it makes to a
+//! JSONL "cassette" file: the method, path and body of the request along with the status code,
+//! content type and body of the response (or the transport error if no response was received).
+//! [ReplayClient] loads a cassette and serves the recorded responses back, running them through
+//! the same response parsing as the real client so that a recording taken on a user's machine can
+//! be used to reproduce their exact prompt flow locally.
+//!
+//! When replaying, each request is matched against the first unused recorded interaction with the
+//! same method and path. The `after` and `timeout` query parameters of the notices API depend on
+//! when the client was started so they are ignored for the purposes of matching. Matching on the
+//! path rather than strictly replaying in order means that requests made concurrently (such as
+//! the poll loop pulling prompt details and snap meta-data) can not cause spurious mismatches.
+use crate::{
+    snapd_client::{
+        response::{parse_body, parse_raw_body},
+        snapd_uri, Client,
+    },
+    socket_client::{body_raw, UnixSocketClient, REQUEST_TIMEOUT},
+    Error, Result,
+};
+use hyper::{body::Bytes, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    future::pending,
+    io::{self, Write},
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+use strum::Display;
+use tracing::{debug, error};
+
+const NOTICES_PATH: &str = "notices";
+const IGNORED_PARAMS: [&str; 2] = ["after", "timeout"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Deserialize, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+#[strum(serialize_all = "UPPERCASE")]
+pub enum Method {
+    Get,
+    Post,
+}
+
+/// The response (or lack of one) that was received for a recorded request.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+enum RecordedResponse {
+    Response {
+        status: u16,
+        #[serde(rename = "content-type")]
+        content_type: String,
+        /// Set for JSON responses
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        body: Option<Value>,
+        /// Set for all other responses
+        #[serde(default, rename = "raw-body", skip_serializing_if = "Option::is_none")]
+        raw_body: Option<Vec<u8>>,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+/// A single request made to snapd along with the response that was received.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Interaction {
+    pub(crate) method: Method,
+    pub(crate) path: String,
+    #[serde(
+        default,
+        rename = "request-body",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) request_body: Option<Value>,
+    #[serde(flatten)]
+    response: RecordedResponse,
+}
+
+impl Interaction {
+    fn new(
+        method: Method,
+        path: &str,
+        request_body: Option<Value>,
+        res: &Result<(StatusCode, Bytes, String)>,
+    ) -> Self {
+        let response = match res {
+            Ok((status, bytes, content_type)) => {
+                let body = if content_type.starts_with("application/json") {
+                    serde_json::from_slice(bytes).ok()
+                } else {
+                    None
+                };
+                let raw_body = body.is_none().then(|| bytes.to_vec());
+
+                RecordedResponse::Response {
+                    status: status.as_u16(),
+                    content_type: content_type.clone(),
+                    body,
+                    raw_body,
+                }
+            }
+
+            Err(e) => RecordedResponse::Failed {
+                error: e.to_string(),
+            },
+        };
+
+        Self {
+            method,
+            path: path.to_string(),
+            request_body,
+            response,
+        }
+    }
+
+    pub fn method(&self) -> Method {
+        self.method
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn matches(&self, method: Method, path: &str) -> bool {
+        self.method == method && normalize_path(&self.path) == normalize_path(path)
+    }
+
+    fn replay(&self) -> Result<(StatusCode, Bytes, String)> {
+        match &self.response {
+            RecordedResponse::Response {
+                status,
+                content_type,
+                body,
+                raw_body,
+            } => {
+                let status = StatusCode::from_u16(*status)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                let bytes = match (body, raw_body) {
+                    (Some(body), _) => Bytes::from(serde_json::to_vec(body)?),
+                    (None, Some(raw)) => Bytes::from(raw.clone()),
+                    (None, None) => Bytes::new(),
+                };
+
+                Ok((status, bytes, content_type.clone()))
+            }
+
+            RecordedResponse::Failed { error } => Err(io::Error::other(error.clone()).into()),
+        }
+    }
+}
+
+/// Strip the query parameters that vary between runs of the client.
+fn normalize_path(path: &str) -> String {
+    let (path, query) = match path.split_once('?') {
+        Some((path, query)) => (path, query),
+        None => return path.to_string(),
+    };
+
+    let params: Vec<&str> = query
+        .split('&')
+        .filter(|kv| {
+            let key = kv.split_once('=').map(|(k, _)| k).unwrap_or(kv);
+            !IGNORED_PARAMS.contains(&key)
+        })
+        .collect();
+
+    if params.is_empty() {
+        path.to_string()
+    } else {
+        format!("{path}?{}", params.join("&"))
+    }
+}
+
+/// A [Client] that records all requests made to snapd to a cassette file.
+#[derive(Debug, Clone)]
+pub struct RecordingClient {
+    inner: UnixSocketClient,
+    cassette: Arc<Mutex<File>>,
+}
+
+impl RecordingClient {
+    /// Wrap the provided client, appending interactions to the cassette at the given path.
+    pub fn try_new(inner: UnixSocketClient, path: impl AsRef<Path>) -> Result<Self> {
+        let f = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path.as_ref())?;
+
+        Ok(Self {
+            inner,
+            cassette: Arc::new(Mutex::new(f)),
+        })
+    }
+
+    async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<Value>,
+        timeout: Duration,
+    ) -> Result<(StatusCode, Bytes, String)> {
+        let res = self.send(method, path, body.as_ref(), timeout).await;
+        self.record(&Interaction::new(method, path, body, &res));
+
+        res
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&Value>,
+        timeout: Duration,
+    ) -> Result<(StatusCode, Bytes, String)> {
+        let uri = snapd_uri(path)?;
+        let res = match method {
+            Method::Get => self.inner.get_with_timeout(uri, timeout).await?,
+            Method::Post => {
+                let body = serde_json::to_vec(&body)?;
+                self.inner.post(uri, "application/json", body).await?
+            }
+        };
+
+        let status = res.status();
+        let (bytes, content_type) = body_raw(res).await?;
+
+        Ok((status, bytes, content_type))
+    }
+
+    /// Failing to write to the cassette should not prevent the client from working so any errors
+    /// are logged rather than returned.
+    fn record(&self, interaction: &Interaction) {
+        let res = serde_json::to_vec(interaction)
+            .map_err(io::Error::from)
+            .and_then(|mut line| {
+                line.push(b'\n');
+                let mut f = match self.cassette.lock() {
+                    Ok(guard) => guard,
+                    Err(err) => err.into_inner(),
+                };
+                f.write_all(&line)
+            });
+
+        match res {
+            Ok(()) => {
+                debug!(method=%interaction.method, path=%interaction.path, "interaction recorded")
+            }
+            Err(e) => error!("unable to write to cassette: {e}"),
+        }
+    }
+}
+
+impl Client for RecordingClient {
+    async fn get_json<T>(&self, path: &str) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        self.get_json_with_timeout(path, REQUEST_TIMEOUT).await
+    }
+
+    async fn get_json_with_timeout<T>(&self, path: &str, timeout: Duration) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let (status, bytes, _) = self.request(Method::Get, path, None, timeout).await?;
+
+        parse_body(status, &bytes)
+    }
+
+    async fn post_json<T, U>(&self, path: &str, body: U) -> Result<T>
+    where
+        T: DeserializeOwned,
+        U: Serialize + Send,
+    {
+        let body = serde_json::to_value(body)?;
+        let (status, bytes, _) = self
+            .request(Method::Post, path, Some(body), REQUEST_TIMEOUT)
+            .await?;
+
+        parse_body(status, &bytes)
+    }
+
+    async fn get_raw(&self, path: &str) -> Result<(Bytes, String)> {
+        let (status, bytes, content_type) = self
+            .request(Method::Get, path, None, REQUEST_TIMEOUT)
+            .await?;
+
+        parse_raw_body(status, bytes, content_type)
+    }
+
+    async fn is_available(&self) -> bool {
+        self.inner.is_available().await
+    }
+}
+
+/// A [Client] that serves responses from a previously recorded cassette.
+///
+/// Clones of a [ReplayClient] share the same cassette.
+#[derive(Debug, Clone)]
+pub struct ReplayClient {
+    interactions: Arc<Mutex<VecDeque<Interaction>>>,
+}
+
+impl ReplayClient {
+    pub fn try_new_from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+
+        Self::try_new_from_string(&content)
+    }
+
+    pub fn try_new_from_string(content: &str) -> Result<Self> {
+        let interactions = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<serde_json::Result<_>>()?;
+
+        Ok(Self {
+            interactions: Arc::new(Mutex::new(interactions)),
+        })
+    }
+
+    /// The number of recorded interactions that have not yet been replayed.
+    pub fn remaining(&self) -> usize {
+        self.interactions().len()
+    }
+
+    fn interactions(&self) -> MutexGuard<'_, VecDeque<Interaction>> {
+        match self.interactions.lock() {
+            Ok(guard) => guard,
+            Err(err) => err.into_inner(),
+        }
+    }
+
+    fn take(&self, method: Method, path: &str) -> Option<Interaction> {
+        let mut interactions = self.interactions();
+        let ix = interactions.iter().position(|i| i.matches(method, path))?;
+
+        interactions.remove(ix)
+    }
+
+    async fn replay(&self, method: Method, path: &str) -> Result<(StatusCode, Bytes, String)> {
+        match self.take(method, path) {
+            Some(interaction) => interaction.replay(),
+
+            // Once the cassette has run out of notices we behave as a long poll that never sees
+            // another notice rather than erroring and causing the poll loop to back off and exit.
+            None if method == Method::Get && normalize_path(path) == NOTICES_PATH => {
+                pending().await
+            }
+
+            None => Err(Error::NoRecordedResponse {
+                method: method.to_string(),
+                path: path.to_string(),
+            }),
+        }
+    }
+}
+
+impl Client for ReplayClient {
+    async fn get_json<T>(&self, path: &str) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let (status, bytes, _) = self.replay(Method::Get, path).await?;
+
+        parse_body(status, &bytes)
+    }
+
+    async fn get_json_with_timeout<T>(&self, path: &str, _timeout: Duration) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        self.get_json(path).await
+    }
+
+    async fn post_json<T, U>(&self, path: &str, _body: U) -> Result<T>
+    where
+        T: DeserializeOwned,
+        U: Serialize + Send,
+    {
+        let (status, bytes, _) = self.replay(Method::Post, path).await?;
+
+        parse_body(status, &bytes)
+    }
+
+    async fn get_raw(&self, path: &str) -> Result<(Bytes, String)> {
+        let (status, bytes, content_type) = self.replay(Method::Get, path).await?;
+
+        parse_raw_body(status, bytes, content_type)
+    }
+
+    async fn is_available(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        daemon::{PollLoop, PromptUpdate},
+        snapd_client::{PromptId, SnapdClient},
+    };
+    use chrono::Utc;
+    use simple_test_case::{dir_cases, test_case};
+    use tokio::{sync::mpsc::unbounded_channel, time::timeout};
+
+    #[test_case("notices?types=foo&timeout=300s&after=2024-08-14T09:47:03Z", "notices?types=foo"; "notices")]
+    #[test_case("notices?after=2024-08-14T09:47:03Z", "notices"; "only ignored params")]
+    #[test_case("interfaces/requests/rules?snap=firefox", "interfaces/requests/rules?snap=firefox"; "other params")]
+    #[test_case("snaps/firefox", "snaps/firefox"; "no params")]
+    #[test]
+    fn normalize_path_works(path: &str, expected: &str) {
+        assert_eq!(normalize_path(path), expected);
+    }
+
+    #[test_case(
+        (StatusCode::OK, Bytes::from_static(br#"{"result":{}}"#), "application/json".to_string());
+        "json"
+    )]
+    #[test_case(
+        (StatusCode::OK, Bytes::from_static(b"\x89PNG"), "image/png".to_string());
+        "raw"
+    )]
+    #[test_case(
+        (StatusCode::NOT_FOUND, Bytes::from_static(br#"{"type":"error"}"#), "application/json".to_string());
+        "error status"
+    )]
+    #[test]
+    fn interactions_round_trip(raw: (StatusCode, Bytes, String)) {
+        let interaction = Interaction::new(Method::Get, "snaps/firefox", None, &Ok(raw.clone()));
+        let line = serde_json::to_string(&interaction).unwrap();
+        let parsed: Interaction = serde_json::from_str(&line).unwrap();
+
+        assert_eq!(parsed, interaction);
+        assert_eq!(parsed.replay().unwrap(), raw);
+    }
+
+    #[test]
+    fn failed_interactions_replay_as_errors() {
+        let res = Err(io::Error::from(io::ErrorKind::ConnectionRefused).into());
+        let interaction = Interaction::new(Method::Get, "system-info", None, &res);
+
+        assert!(matches!(interaction.replay(), Err(Error::Io(_))));
+    }
+
+    #[tokio::test]
+    async fn unmatched_requests_error() {
+        let client = ReplayClient::try_new_from_string("").unwrap();
+
+        let res: Result<Value> = client.get_json("system-info").await;
+
+        assert!(matches!(res, Err(Error::NoRecordedResponse { .. })));
+    }
+
+    // Each cassette is replayed against the poll loop which needs to consume every recorded
+    // interaction. Prompts that were resolved in the recording should have been dropped again.
+    #[dir_cases("resources/cassette-tests")]
+    #[test]
+    fn cassette_replays_through_poll_loop(path: &str, contents: &str) {
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(replay_through_poll_loop(path, contents));
+    }
+
+    async fn replay_through_poll_loop(path: &str, contents: &str) {
+        let replay = ReplayClient::try_new_from_string(contents).unwrap();
+        let client = SnapdClient::new_with_client(replay.clone(), Utc::now());
+        let (tx, mut rx) = unbounded_channel();
+        let handle = tokio::spawn(PollLoop::new(client, tx).run());
+
+        let mut pending: Vec<PromptId> = Vec::new();
+        loop {
+            // Once the cassette has been consumed we only need to wait for any remaining updates
+            let wait = if replay.remaining() == 0 {
+                Duration::from_millis(200)
+            } else {
+                Duration::from_secs(5)
+            };
+
+            match timeout(wait, rx.recv()).await {
+                Ok(Some(PromptUpdate::Add(ep))) => pending.push(ep.prompt.id().clone()),
+                Ok(Some(PromptUpdate::Drop(id))) => pending.retain(|p| p != &id),
+                Ok(None) => panic!("poll loop exited for {path}"),
+                Err(_) if replay.remaining() == 0 => break,
+                Err(_) => panic!(
+                    "{path}: timed out with {} interactions remaining",
+                    replay.remaining()
+                ),
+            }
+        }
+
+        handle.abort();
+        assert!(
+            pending.is_empty(),
+            "{path}: prompts left pending: {pending:?}"
+        );
+    }
+}
@@ -0,0 +1,234 @@
+//! Parsing of the cgroup paths reported by snapd for prompting processes.
+//!
+//! Snapd places each snap process in its own systemd unit, encoding the snap and the app or hook
+//! being run in the name of the unit:
+//!   - apps run by the user: `snap.<snap>.<app>-<uuid>.scope`
+//!   - hooks: `snap.<snap>.hook.<hook>-<uuid>.scope`
+//!   - services: `snap.<snap>.<app>.service`
+//!
+//! Older versions of snapd use `.` rather than `-` to separate the uuid from the app or hook name.
+use crate::snapd_client::Cgroup;
+
+const UUID_LEN: usize = 36;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CgroupError {
+    #[error("{cgroup:?} is not a snap cgroup")]
+    NotASnap { cgroup: String },
+
+    #[error("{cgroup:?} does not have a recognised systemd unit type")]
+    UnknownUnit { cgroup: String },
+
+    #[error("{cgroup:?} does not name an app or hook")]
+    Malformed { cgroup: String },
+
+    #[error("cgroup is for snap {found:?} but the prompt is for {expected:?}")]
+    SnapMismatch { expected: String, found: String },
+}
+
+/// The type of systemd unit that a snap process is running under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitKind {
+    /// A transient scope created when an app or hook is run.
+    Scope,
+    /// A service declared by the snap.
+    Service,
+}
+
+/// The part of a snap that a process belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapProcess {
+    App(String),
+    Hook(String),
+}
+
+/// The snap identity encoded in a [Cgroup].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapCgroup {
+    pub snap: String,
+    pub process: SnapProcess,
+    pub unit: UnitKind,
+}
+
+impl SnapCgroup {
+    /// The name of the app if this is not a hook.
+    pub fn app(&self) -> Option<&str> {
+        match &self.process {
+            SnapProcess::App(app) => Some(app),
+            SnapProcess::Hook(_) => None,
+        }
+    }
+}
+
+impl Cgroup {
+    /// Parse the snap identity from the systemd unit that this cgroup is for.
+    pub fn snap_cgroup(&self) -> Result<SnapCgroup, CgroupError> {
+        let cgroup = &self.0;
+        let unit = cgroup.rsplit('/').next().unwrap_or(cgroup);
+        let not_a_snap = || CgroupError::NotASnap {
+            cgroup: cgroup.clone(),
+        };
+        let malformed = || CgroupError::Malformed {
+            cgroup: cgroup.clone(),
+        };
+
+        let rest = unit.strip_prefix("snap.").ok_or_else(not_a_snap)?;
+        let (rest, kind) = if let Some(rest) = rest.strip_suffix(".scope") {
+            (strip_uuid(rest).ok_or_else(malformed)?, UnitKind::Scope)
+        } else if let Some(rest) = rest.strip_suffix(".service") {
+            (rest, UnitKind::Service)
+        } else {
+            return Err(CgroupError::UnknownUnit {
+                cgroup: cgroup.clone(),
+            });
+        };
+
+        let (snap, rest) = rest.split_once('.').ok_or_else(malformed)?;
+        let process = match rest.split_once('.') {
+            Some(("hook", hook)) if !hook.is_empty() && !hook.contains('.') => {
+                SnapProcess::Hook(hook.to_string())
+            }
+            None if !rest.is_empty() => SnapProcess::App(rest.to_string()),
+            _ => return Err(malformed()),
+        };
+
+        if snap.is_empty() {
+            return Err(malformed());
+        }
+
+        Ok(SnapCgroup {
+            snap: snap.to_string(),
+            process,
+            unit: kind,
+        })
+    }
+
+    /// Parse the snap identity from this cgroup, checking that it is for the given snap.
+    pub fn snap_cgroup_for(&self, snap: &str) -> Result<SnapCgroup, CgroupError> {
+        let snap_cgroup = self.snap_cgroup()?;
+        if snap_cgroup.snap != snap {
+            return Err(CgroupError::SnapMismatch {
+                expected: snap.to_string(),
+                found: snap_cgroup.snap,
+            });
+        }
+
+        Ok(snap_cgroup)
+    }
+}
+
+/// Strip the trailing `-<uuid>` (or `.<uuid>` for older versions of snapd) from a scope name.
+fn strip_uuid(s: &str) -> Option<&str> {
+    let split = s.len().checked_sub(UUID_LEN + 1)?;
+    if !s.is_char_boundary(split) {
+        return None;
+    }
+    let (name, uuid) = s.split_at(split);
+    let uuid = uuid.strip_prefix(['-', '.'])?;
+
+    let is_uuid = uuid.char_indices().all(|(i, c)| match i {
+        8 | 13 | 18 | 23 => c == '-',
+        _ => c.is_ascii_hexdigit(),
+    });
+
+    is_uuid.then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    const UUID: &str = "4b9a3f4e-1f67-4a6e-9d0b-2b1c0e4f7a11";
+
+    fn app(snap: &str, app: &str, unit: UnitKind) -> SnapCgroup {
+        SnapCgroup {
+            snap: snap.to_string(),
+            process: SnapProcess::App(app.to_string()),
+            unit,
+        }
+    }
+
+    #[test_case(
+        &format!("/user.slice/user-1000.slice/user@1000.service/app.slice/snap.firefox.firefox-{UUID}.scope"),
+        app("firefox", "firefox", UnitKind::Scope);
+        "app scope"
+    )]
+    #[test_case(
+        &format!("/user.slice/user-1000.slice/user@1000.service/app.slice/snap.firefox.firefox.{UUID}.scope"),
+        app("firefox", "firefox", UnitKind::Scope);
+        "legacy app scope"
+    )]
+    #[test_case(
+        &format!("/user.slice/user-1000.slice/user@1000.service/app.slice/snap.code.url-handler-{UUID}.scope"),
+        app("code", "url-handler", UnitKind::Scope);
+        "app name with hyphen"
+    )]
+    #[test_case(
+        &format!("/user.slice/user-1000.slice/user@1000.service/app.slice/snap.firefox_beta.firefox-{UUID}.scope"),
+        app("firefox_beta", "firefox", UnitKind::Scope);
+        "parallel instance"
+    )]
+    #[test_case(
+        "/system.slice/snap.lxd.daemon.service",
+        app("lxd", "daemon", UnitKind::Service);
+        "service"
+    )]
+    #[test_case(
+        &format!("/system.slice/snap.firefox.hook.configure-{UUID}.scope"),
+        SnapCgroup {
+            snap: "firefox".to_string(),
+            process: SnapProcess::Hook("configure".to_string()),
+            unit: UnitKind::Scope,
+        };
+        "hook"
+    )]
+    #[test]
+    fn snap_cgroups_are_parsed(raw: &str, expected: SnapCgroup) {
+        assert_eq!(Cgroup(raw.to_string()).snap_cgroup(), Ok(expected));
+    }
+
+    #[test_case(
+        "/user.slice/user-1000.slice/user@1000.service/app.slice/myapp.scope",
+        CgroupError::NotASnap { cgroup: String::new() };
+        "not a snap"
+    )]
+    #[test_case(
+        "/user.slice/snap.firefox.firefox.slice",
+        CgroupError::UnknownUnit { cgroup: String::new() };
+        "unknown unit"
+    )]
+    #[test_case(
+        "/user.slice/snap.firefox.firefox.scope",
+        CgroupError::Malformed { cgroup: String::new() };
+        "scope without uuid"
+    )]
+    #[test_case(
+        &format!("/user.slice/snap.firefox-{UUID}.scope"),
+        CgroupError::Malformed { cgroup: String::new() };
+        "missing app"
+    )]
+    #[test]
+    fn invalid_cgroups_error(raw: &str, expected: CgroupError) {
+        let err = Cgroup(raw.to_string()).snap_cgroup().unwrap_err();
+
+        assert_eq!(
+            std::mem::discriminant(&err),
+            std::mem::discriminant(&expected)
+        );
+    }
+
+    #[test]
+    fn mismatched_snaps_error() {
+        let cgroup = Cgroup(format!("/app.slice/snap.firefox.firefox-{UUID}.scope"));
+
+        assert_eq!(
+            cgroup.snap_cgroup_for("thunderbird"),
+            Err(CgroupError::SnapMismatch {
+                expected: "thunderbird".to_string(),
+                found: "firefox".to_string()
+            })
+        );
+        assert!(cgroup.snap_cgroup_for("firefox").is_ok());
+    }
+}
@@ -0,0 +1,767 @@
+//! An in-memory stand-in for snapd that implements [Client].
+//!
+//! [FakeSnapd] holds prompts, notices, rules, plugs and snap meta-data in memory and serves them
+//! using the same request paths and JSON payloads as the real snapd API, so anything built on top
+//! of [SnapdClient] can be exercised without a socket:
+//!
+//! ```no_run
+//! # use prompting_client::snapd_client::{FakeSnapd, SnapdClient, TypedPrompt};
+//! # async fn example(prompt: TypedPrompt) -> prompting_client::Result<()> {
+//! let fake = FakeSnapd::builder().with_prompt(prompt).build();
+//! let client = SnapdClient::new_with_client(fake.clone(), chrono::Utc::now());
+//!
+//! let pending = client.all_pending_prompt_details().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Clones of a [FakeSnapd] share the same state, allowing tests to inject prompts and inspect
+//! replies while the client is in use elsewhere. Long polling on the notices API behaves as it
+//! does for snapd: the request blocks until there are new notices or the requested timeout
+//! expires.
+use crate::{
+    snapd_client::{
+        prompt::RawPrompt, rule::RawRule, Client, PromptId, RuleId, SnapMeta, SnapdError,
+        TypedPrompt, TypedRule, FEATURE_NAME, NOTICE_TYPES,
+    },
+    Error, Result,
+};
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use hyper::{body::Bytes, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    io,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+use tokio::{sync::Notify, time::timeout};
+
+const FAKE_USER_ID: u32 = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FakeNotice {
+    key: PromptId,
+    last_occurred: DateTime<Utc>,
+    resolved: bool,
+}
+
+#[derive(Debug)]
+struct State {
+    available: bool,
+    prompting_enabled: bool,
+    system_info_failures: usize,
+    prompts: Vec<RawPrompt>,
+    notices: Vec<FakeNotice>,
+    replies: Vec<(PromptId, Value)>,
+    rules: Vec<RawRule>,
+    snaps: HashMap<String, SnapMeta>,
+    plugs: Vec<Value>,
+    next_rule_id: usize,
+    last_occurred: DateTime<Utc>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            available: true,
+            prompting_enabled: true,
+            system_info_failures: 0,
+            prompts: Vec::new(),
+            notices: Vec::new(),
+            replies: Vec::new(),
+            rules: Vec::new(),
+            snaps: HashMap::new(),
+            plugs: Vec::new(),
+            next_rule_id: 1,
+            last_occurred: DateTime::UNIX_EPOCH,
+        }
+    }
+}
+
+impl State {
+    /// Notice timestamps need to be strictly increasing for the `after` filter to work.
+    fn now(&mut self) -> DateTime<Utc> {
+        let now = Utc::now().max(self.last_occurred + TimeDelta::nanoseconds(1));
+        self.last_occurred = now;
+
+        now
+    }
+
+    /// Record a new occurrence of the notice for the given prompt.
+    fn notify(&mut self, key: PromptId, resolved: bool) {
+        let last_occurred = self.now();
+        self.notices.retain(|n| n.key != key);
+        self.notices.push(FakeNotice {
+            key,
+            last_occurred,
+            resolved,
+        });
+    }
+
+    fn add_prompt(&mut self, prompt: TypedPrompt) {
+        let raw: RawPrompt = serde_json::to_value(prompt)
+            .and_then(serde_json::from_value)
+            .expect("typed prompts to serialize as raw prompts");
+        let id = raw.id.clone();
+        self.prompts.retain(|p| p.id != id);
+        self.prompts.push(raw);
+        self.notify(id, false);
+    }
+
+    fn add_rule(&mut self, snap: String, interface: String, constraints: Value) -> RawRule {
+        let id = RuleId(format!("fake-rule-{}", self.next_rule_id));
+        self.next_rule_id += 1;
+        let rule = RawRule {
+            id,
+            timestamp: self.now().to_rfc3339_opts(SecondsFormat::Nanos, true),
+            user: FAKE_USER_ID,
+            snap,
+            interface,
+            constraints,
+        };
+        self.rules.push(rule.clone());
+
+        rule
+    }
+
+    fn notices_after(&self, after: Option<DateTime<Utc>>) -> Vec<Value> {
+        self.notices
+            .iter()
+            .filter(|n| after.map(|a| n.last_occurred > a).unwrap_or(true))
+            .map(|n| {
+                let mut notice = json!({
+                    "type": NOTICE_TYPES,
+                    "key": n.key.0,
+                    "last-occurred": n.last_occurred.to_rfc3339_opts(SecondsFormat::Nanos, true),
+                });
+                if n.resolved {
+                    notice["last-data"] = json!({ "resolved": "replied" });
+                }
+
+                notice
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    state: Mutex<State>,
+    changed: Notify,
+}
+
+/// An in-memory fake of the snapd API. See the [module level docs][self] for details.
+#[derive(Debug, Clone, Default)]
+pub struct FakeSnapd(Arc<Inner>);
+
+impl FakeSnapd {
+    pub fn builder() -> FakeSnapdBuilder {
+        FakeSnapdBuilder::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        match self.0.state.lock() {
+            Ok(guard) => guard,
+            Err(err) => err.into_inner(),
+        }
+    }
+
+    fn update<T>(&self, f: impl FnOnce(&mut State) -> T) -> T {
+        let res = f(&mut self.state());
+        self.0.changed.notify_waiters();
+
+        res
+    }
+
+    /// Add a new pending prompt, waking any outstanding long polls on the notices API.
+    pub fn add_prompt(&self, prompt: TypedPrompt) {
+        self.update(|s| s.add_prompt(prompt));
+    }
+
+    /// Resolve a pending prompt without a reply from the client (as happens when the requesting
+    /// process exits or a matching rule is created elsewhere).
+    pub fn resolve_prompt(&self, id: &PromptId) {
+        self.update(|s| {
+            s.prompts.retain(|p| &p.id != id);
+            s.notify(id.clone(), true);
+        });
+    }
+
+    /// Add a rule, returning the rule as snapd would with its newly assigned ID.
+    pub fn add_rule(&self, rule: TypedRule) -> TypedRule {
+        let raw: RawRule = serde_json::to_value(rule)
+            .and_then(serde_json::from_value)
+            .expect("typed rules to serialize as raw rules");
+
+        self.update(|s| s.add_rule(raw.snap, raw.interface, raw.constraints))
+            .try_into()
+            .expect("rule to be valid")
+    }
+
+    /// Simulate snapd going away (for example while it is restarted) or coming back.
+    pub fn set_available(&self, available: bool) {
+        self.update(|s| s.available = available);
+    }
+
+    pub fn set_prompting_enabled(&self, enabled: bool) {
+        self.update(|s| s.prompting_enabled = enabled);
+    }
+
+    /// Make the next `n` requests for system info fail, as can happen while snapd is still
+    /// starting up after a restart.
+    pub fn fail_system_info(&self, n: usize) {
+        self.update(|s| s.system_info_failures = n);
+    }
+
+    /// The IDs of all currently pending prompts, in the order that they were added.
+    pub fn pending_prompt_ids(&self) -> Vec<PromptId> {
+        self.state().prompts.iter().map(|p| p.id.clone()).collect()
+    }
+
+    /// The replies that have been made to prompts, in the order that they were received.
+    pub fn replies(&self) -> Vec<(PromptId, Value)> {
+        self.state().replies.clone()
+    }
+
+    /// All current rules for supported interfaces.
+    pub fn rules(&self) -> Vec<TypedRule> {
+        self.state()
+            .rules
+            .iter()
+            .filter_map(|r| r.clone().try_into().ok())
+            .collect()
+    }
+
+    fn check_available(&self) -> Result<()> {
+        if self.state().available {
+            Ok(())
+        } else {
+            Err(io::Error::from(io::ErrorKind::ConnectionRefused).into())
+        }
+    }
+
+    async fn get(&self, path: &str) -> Result<Value> {
+        self.check_available()?;
+        let (path, query) = split_query(path);
+
+        if path == "notices" {
+            return self.long_poll_notices(&query).await;
+        }
+
+        let mut s = self.state();
+        let segments: Vec<&str> = path.split('/').collect();
+
+        match segments.as_slice() {
+            ["system-info"] if s.system_info_failures > 0 => {
+                s.system_info_failures -= 1;
+                Err(io::Error::other("snapd is still starting up").into())
+            }
+
+            ["system-info"] => Ok(json!({
+                "features": {
+                    FEATURE_NAME: { "enabled": s.prompting_enabled, "supported": true }
+                }
+            })),
+
+            ["interfaces", "requests", "prompts"] => Ok(serde_json::to_value(&s.prompts)?),
+
+            ["interfaces", "requests", "prompts", id] => {
+                match s.prompts.iter().find(|p| p.id.0 == *id) {
+                    Some(p) => Ok(serde_json::to_value(p)?),
+                    None => Err(not_found(SnapdError::PromptNotFound)),
+                }
+            }
+
+            ["interfaces", "requests", "rules"] => {
+                let rules: Vec<&RawRule> = s
+                    .rules
+                    .iter()
+                    .filter(|r| query.get("snap").map(|v| r.snap == *v).unwrap_or(true))
+                    .filter(|r| {
+                        query
+                            .get("interface")
+                            .map(|v| r.interface == *v)
+                            .unwrap_or(true)
+                    })
+                    .collect();
+
+                Ok(serde_json::to_value(rules)?)
+            }
+
+            ["interfaces", "requests", "rules", id] => match s.rules.iter().find(|r| r.id.0 == *id)
+            {
+                Some(r) => Ok(serde_json::to_value(r)?),
+                None => Err(not_found(SnapdError::RuleNotFound)),
+            },
+
+            ["connections"] => {
+                let plugs: Vec<&Value> = s
+                    .plugs
+                    .iter()
+                    .filter(|p| query.get("snap").map(|v| p["snap"] == *v).unwrap_or(true))
+                    .filter(|p| {
+                        query
+                            .get("interface")
+                            .map(|v| p["interface"] == *v)
+                            .unwrap_or(true)
+                    })
+                    .collect();
+
+                Ok(json!({ "established": [], "plugs": plugs, "slots": [] }))
+            }
+
+            ["snaps", name] => match s.snaps.get(*name) {
+                Some(meta) => Ok(json!({
+                    "title": meta.snap_title,
+                    "summary": meta.summary,
+                    "version": meta.version,
+                    "channel": meta.channel,
+                    "revision": meta.revision,
+                    "confinement": meta.confinement,
+                    "devmode": meta.devmode,
+                    "install-date": meta.updated_at,
+                    "publisher": {
+                        "display-name": meta.publisher,
+                        "validation": meta.publisher_validation,
+                    },
+                })),
+                None => Err(not_found(SnapdError::Raw)),
+            },
+
+            _ => Err(not_found(SnapdError::Raw)),
+        }
+    }
+
+    async fn long_poll_notices(&self, query: &HashMap<&str, &str>) -> Result<Value> {
+        let after = query
+            .get("after")
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+            .map(|dt| dt.with_timezone(&Utc));
+        let poll_timeout = query
+            .get("timeout")
+            .and_then(|s| s.strip_suffix('s'))
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or_default();
+
+        match timeout(poll_timeout, self.wait_for_notices(after)).await {
+            Ok(res) => res,
+            Err(_) => Ok(Value::Array(Vec::new())),
+        }
+    }
+
+    async fn wait_for_notices(&self, after: Option<DateTime<Utc>>) -> Result<Value> {
+        loop {
+            // Register for notifications before checking the current state so that we can't
+            // miss an update that lands in between the two.
+            let changed = self.0.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+
+            self.check_available()?;
+            let notices = self.state().notices_after(after);
+            if !notices.is_empty() {
+                return Ok(Value::Array(notices));
+            }
+
+            changed.await;
+        }
+    }
+
+    fn post(&self, path: &str, body: Value) -> Result<Value> {
+        self.check_available()?;
+        let (path, _) = split_query(path);
+        let segments: Vec<&str> = path.split('/').collect();
+
+        match segments.as_slice() {
+            ["interfaces", "requests", "prompts", id] => self.update(|s| {
+                let id = PromptId(id.to_string());
+                if !s.prompts.iter().any(|p| p.id == id) {
+                    return Err(not_found(SnapdError::PromptNotFound));
+                }
+
+                s.prompts.retain(|p| p.id != id);
+                s.replies.push((id.clone(), body));
+                s.notify(id, true);
+
+                Ok(Value::Null)
+            }),
+
+            ["interfaces", "requests", "rules"] => self.update(|s| {
+                let rule = &body["rule"];
+                let (Some(snap), Some(interface)) =
+                    (rule["snap"].as_str(), rule["interface"].as_str())
+                else {
+                    return Err(bad_request("missing snap or interface"));
+                };
+                let rule = s.add_rule(
+                    snap.to_string(),
+                    interface.to_string(),
+                    rule["constraints"].clone(),
+                );
+
+                Ok(serde_json::to_value(rule)?)
+            }),
+
+            ["interfaces", "requests", "rules", id] => self.update(|s| {
+                let Some(ix) = s.rules.iter().position(|r| r.id.0 == *id) else {
+                    return Err(not_found(SnapdError::RuleNotFound));
+                };
+
+                match body["action"].as_str() {
+                    Some("patch") => {
+                        let constraints = body["rule"]["constraints"].clone();
+                        s.rules[ix].constraints = constraints;
+                        Ok(serde_json::to_value(&s.rules[ix])?)
+                    }
+                    Some("remove") => Ok(serde_json::to_value(s.rules.remove(ix))?),
+                    _ => Err(bad_request("unknown rule action")),
+                }
+            }),
+
+            _ => Err(not_found(SnapdError::Raw)),
+        }
+    }
+}
+
+impl Client for FakeSnapd {
+    async fn get_json<T>(&self, path: &str) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        Ok(serde_json::from_value(self.get(path).await?)?)
+    }
+
+    async fn get_json_with_timeout<T>(&self, path: &str, request_timeout: Duration) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        match timeout(request_timeout, self.get_json(path)).await {
+            Ok(res) => res,
+            Err(_) => Err(Error::RequestTimeout {
+                timeout: request_timeout,
+            }),
+        }
+    }
+
+    async fn post_json<T, U>(&self, path: &str, body: U) -> Result<T>
+    where
+        T: DeserializeOwned,
+        U: Serialize + Send,
+    {
+        let body = serde_json::to_value(body)?;
+
+        Ok(serde_json::from_value(self.post(path, body)?)?)
+    }
+
+    async fn get_raw(&self, path: &str) -> Result<(Bytes, String)> {
+        self.check_available()?;
+        let s = self.state();
+
+        let icon = path
+            .strip_prefix("icons/")
+            .and_then(|p| p.strip_suffix("/icon"))
+            .and_then(|name| s.snaps.get(name))
+            .and_then(|meta| meta.snap_icon.as_ref());
+
+        match icon {
+            Some(icon) => Ok((icon.bytes.clone(), icon.mime_type.clone())),
+            None => Err(not_found(SnapdError::Raw)),
+        }
+    }
+
+    async fn is_available(&self) -> bool {
+        self.state().available
+    }
+}
+
+/// Builder for setting up the initial state of a [FakeSnapd].
+#[derive(Debug, Default)]
+pub struct FakeSnapdBuilder {
+    state: State,
+}
+
+impl FakeSnapdBuilder {
+    /// Add a pending prompt along with its associated notice.
+    pub fn with_prompt(mut self, prompt: TypedPrompt) -> Self {
+        self.state.add_prompt(prompt);
+        self
+    }
+
+    /// Add an existing rule. The ID of the provided rule is replaced with a newly assigned one.
+    pub fn with_rule(mut self, rule: TypedRule) -> Self {
+        let raw: RawRule = serde_json::to_value(rule)
+            .and_then(serde_json::from_value)
+            .expect("typed rules to serialize as raw rules");
+        self.state
+            .add_rule(raw.snap, raw.interface, raw.constraints);
+        self
+    }
+
+    /// Add meta-data (and optionally an icon) for an installed snap.
+    ///
+    /// The `store_url` field is ignored: snapd clients always derive it from the snap name.
+    pub fn with_snap_meta(mut self, meta: SnapMeta) -> Self {
+        self.state.snaps.insert(meta.name.clone(), meta);
+        self
+    }
+
+    /// Add a connected plug for a snap, with attributes as they would appear in the snap's
+    /// `snap.yaml`.
+    pub fn with_plug(mut self, snap: &str, plug: &str, interface: &str, attrs: Value) -> Self {
+        self.state.plugs.push(json!({
+            "snap": snap,
+            "plug": plug,
+            "interface": interface,
+            "attrs": attrs,
+        }));
+        self
+    }
+
+    pub fn with_prompting_enabled(mut self, enabled: bool) -> Self {
+        self.state.prompting_enabled = enabled;
+        self
+    }
+
+    pub fn build(self) -> FakeSnapd {
+        FakeSnapd(Arc::new(Inner {
+            state: Mutex::new(self.state),
+            changed: Notify::new(),
+        }))
+    }
+}
+
+fn split_query(path: &str) -> (&str, HashMap<&str, &str>) {
+    match path.split_once('?') {
+        Some((path, query)) => {
+            let params = query
+                .split('&')
+                .filter_map(|kv| kv.split_once('='))
+                .collect();
+
+            (path, params)
+        }
+        None => (path, HashMap::new()),
+    }
+}
+
+fn snapd_error(status: StatusCode, message: &str, err: SnapdError) -> Error {
+    Error::SnapdError {
+        status,
+        message: message.to_string(),
+        err: Box::new(err),
+    }
+}
+
+fn not_found(err: SnapdError) -> Error {
+    snapd_error(StatusCode::NOT_FOUND, "not found", err)
+}
+
+fn bad_request(message: &str) -> Error {
+    snapd_error(StatusCode::BAD_REQUEST, message, SnapdError::Raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapd_client::{
+        interfaces::{
+            files::{FilesConstraints, PersonalFilesInterface},
+            home::{HomeConstraints, HomeInterface},
+            SnapInterface,
+        },
+        Prompt, PromptNotice, PublisherValidation, SnapIcon, SnapdClient,
+    };
+
+    fn home_prompt(id: &str) -> TypedPrompt {
+        TypedPrompt::Home(Prompt {
+            id: PromptId(id.to_string()),
+            timestamp: "2024-08-14T09:47:03.350324989Z".to_string(),
+            snap: "firefox".to_string(),
+            pid: 1234,
+            cgroup: "cgroup".into(),
+            interface: HomeInterface::NAME.to_string(),
+            constraints: HomeConstraints {
+                path: "/home/user/file.txt".to_string(),
+                requested_permissions: vec!["read".to_string()],
+                available_permissions: vec!["read".to_string(), "write".to_string()],
+            },
+        })
+    }
+
+    #[tokio::test]
+    async fn prompts_can_be_listed_and_replied_to() {
+        let fake = FakeSnapd::builder()
+            .with_prompt(home_prompt("1"))
+            .with_prompt(home_prompt("2"))
+            .build();
+        let client = SnapdClient::new_with_client(fake.clone(), DateTime::UNIX_EPOCH);
+
+        let ids: Vec<_> = client
+            .all_pending_prompt_details()
+            .await
+            .unwrap()
+            .iter()
+            .map(|p| p.id().clone())
+            .collect();
+        assert_eq!(ids, vec![PromptId("1".into()), PromptId("2".into())]);
+
+        let prompt = client.prompt_details(&PromptId("1".into())).await.unwrap();
+        client
+            .reply_to_prompt(prompt.id(), prompt.clone().into_deny_once())
+            .await
+            .unwrap();
+
+        assert_eq!(fake.pending_prompt_ids(), vec![PromptId("2".into())]);
+        let replies = fake.replies();
+        assert_eq!(replies.len(), 1);
+        assert_eq!(replies[0].1["action"], "deny");
+
+        let res = client.prompt_details(&PromptId("1".into())).await;
+        assert!(
+            matches!(
+                res,
+                Err(Error::SnapdError {
+                    status: StatusCode::NOT_FOUND,
+                    ..
+                })
+            ),
+            "{res:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn long_poll_waits_for_new_notices() {
+        let fake = FakeSnapd::default();
+        let mut client = SnapdClient::new_with_client(fake.clone(), Utc::now());
+
+        let handle = tokio::spawn(async move {
+            let notices = client.pending_prompt_notices().await.unwrap();
+            (client, notices)
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!handle.is_finished(), "long poll returned early");
+
+        fake.add_prompt(home_prompt("1"));
+        let (mut client, notices) = handle.await.unwrap();
+        assert_eq!(notices, vec![PromptNotice::Update(PromptId("1".into()))]);
+
+        fake.resolve_prompt(&PromptId("1".into()));
+        let notices = client.pending_prompt_notices().await.unwrap();
+        assert_eq!(notices, vec![PromptNotice::Resolved(PromptId("1".into()))]);
+    }
+
+    #[tokio::test]
+    async fn unavailable_fake_errors() {
+        let fake = FakeSnapd::default();
+        let client = SnapdClient::new_with_client(fake.clone(), Utc::now());
+        assert!(client.is_prompting_enabled().await.unwrap());
+
+        fake.set_available(false);
+
+        assert!(!client.is_available().await);
+        assert!(matches!(
+            client.is_prompting_enabled().await,
+            Err(Error::Io(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn rules_can_be_managed() {
+        let fake = FakeSnapd::default();
+        let client = SnapdClient::new_with_client(fake.clone(), Utc::now());
+
+        let rule = client
+            .add_rule::<HomeInterface>("firefox", Default::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            client
+                .list_rules(Some("firefox"), None)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(client
+            .list_rules(Some("other"), None)
+            .await
+            .unwrap()
+            .is_empty());
+
+        client.remove_rule(rule.id()).await.unwrap();
+        assert!(fake.rules().is_empty());
+    }
+
+    #[tokio::test]
+    async fn declared_plug_paths_are_attached_to_files_prompts() {
+        let prompt = TypedPrompt::PersonalFiles(Prompt {
+            id: PromptId("1".to_string()),
+            timestamp: "2024-08-14T09:47:03.350324989Z".to_string(),
+            snap: "firefox".to_string(),
+            pid: 1234,
+            cgroup: "cgroup".into(),
+            interface: PersonalFilesInterface::NAME.to_string(),
+            constraints: FilesConstraints {
+                path: "/home/user/.mozilla/firefox/profiles.ini".to_string(),
+                requested_permissions: vec!["read".to_string()],
+                available_permissions: vec!["read".to_string(), "write".to_string()],
+                ..Default::default()
+            },
+        });
+        let fake = FakeSnapd::builder()
+            .with_prompt(prompt)
+            .with_plug(
+                "firefox",
+                "dot-mozilla",
+                PersonalFilesInterface::NAME,
+                json!({ "read": ["$HOME/.mozilla"], "write": ["$HOME/.mozilla/firefox"] }),
+            )
+            .with_plug(
+                "other",
+                "dot-other",
+                PersonalFilesInterface::NAME,
+                json!({ "read": ["$HOME/.other"] }),
+            )
+            .build();
+        let client = SnapdClient::new_with_client(fake, Utc::now());
+
+        let p = match client.prompt_details(&PromptId("1".into())).await.unwrap() {
+            TypedPrompt::PersonalFiles(p) => p,
+            p => panic!("expected personal-files prompt, got {p:?}"),
+        };
+
+        assert_eq!(
+            p.declared_paths(),
+            ["$HOME/.mozilla", "$HOME/.mozilla/firefox"]
+        );
+    }
+
+    #[tokio::test]
+    async fn snap_metadata_is_served() {
+        let meta = SnapMeta {
+            name: "firefox".to_string(),
+            updated_at: "2024-08-14".to_string(),
+            store_url: "snap://firefox".to_string(),
+            publisher: "Mozilla".to_string(),
+            snap_icon: Some(SnapIcon {
+                bytes: Bytes::from_static(b"icon"),
+                mime_type: "image/png".to_string(),
+            }),
+            snap_title: "Firefox".to_string(),
+            version: "129.0.1-1".to_string(),
+            channel: "latest/stable".to_string(),
+            revision: "4793".to_string(),
+            confinement: "strict".to_string(),
+            publisher_validation: PublisherValidation::Verified,
+            ..Default::default()
+        };
+        let fake = FakeSnapd::builder().with_snap_meta(meta.clone()).build();
+        let client = SnapdClient::new_with_client(fake, Utc::now());
+
+        assert_eq!(client.snap_metadata("firefox").await, Some(meta));
+        assert_eq!(client.snap_metadata("missing").await, None);
+    }
+}
@@ -1,19 +1,25 @@
 use crate::{
     prompt_sequence::MatchAttempt,
     protos::{
-        apparmor_prompting::{CameraPromptReply, DevicePermission, MetaData},
+        apparmor_prompting::{
+            rule::Constraints as ProtoRuleConstraints, CameraPromptReply,
+            CameraRuleConstraints as ProtoCameraRuleConstraints, DevicePermission,
+            DeviceRulePermission,
+        },
         CameraPrompt as ProtoCameraPrompt,
     },
     snapd_client::{
         interfaces::{
-            ConstraintsFilter, Prompt, PromptReply, ProtoPrompt, ReplyConstraintsOverrides,
-            SnapInterface,
+            map_permission_entry, proto_meta_data, ConstraintsFilter, Prompt, PromptReply,
+            ProtoPrompt, ReplyConstraintsOverrides, SnapInterface,
         },
         prompt::UiInput,
+        rule::PermissionEntry,
         Action, Error, Lifespan, Result, SnapMeta,
     },
 };
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use tonic::Status;
 
 impl Prompt<CameraInterface> {
@@ -53,6 +59,7 @@ impl SnapInterface for CameraInterface {
 
     type Constraints = CameraConstraints;
     type ReplyConstraints = CameraReplyConstraints;
+    type RuleConstraints = CameraRuleConstraints;
 
     type ConstraintsFilter = CameraConstraintsFilter;
     type ReplyConstraintsOverrides = CameraReplyConstraintsOverrides;
@@ -75,48 +82,55 @@ impl SnapInterface for CameraInterface {
     fn ui_input_from_prompt(prompt: Prompt<Self>, meta: Option<SnapMeta>) -> Result<UiInput<Self>> {
         let meta = meta.unwrap_or_else(|| SnapMeta {
             name: prompt.snap,
-            updated_at: String::default(),
-            store_url: String::default(),
-            publisher: String::default(),
-            snap_icon: None,
+            ..Default::default()
         });
 
         Ok(UiInput {
             id: prompt.id,
             meta,
+            process: None,
             data: CameraUiInputData {},
         })
     }
 
     fn proto_prompt_from_ui_input(input: UiInput<Self>) -> Result<ProtoPrompt, Status> {
-        let SnapMeta {
-            name,
-            updated_at,
-            store_url,
-            publisher,
-            snap_icon,
-        } = input.meta;
-
-        let (snap_icon, snap_icon_mime_type) = snap_icon
-            .map(|icon| (icon.bytes.into(), icon.mime_type))
-            .unwrap_or_default();
-
         Ok(ProtoPrompt::CameraPrompt(ProtoCameraPrompt {
-            meta_data: Some(MetaData {
-                prompt_id: input.id.0,
-                snap_name: name,
-                store_url,
-                publisher,
-                updated_at,
-                snap_icon,
-                snap_icon_mime_type,
-            }),
+            meta_data: Some(proto_meta_data(input.id, input.meta, input.process)),
         }))
     }
 
+    fn proto_rule_constraints(
+        constraints: CameraRuleConstraints,
+    ) -> Result<ProtoRuleConstraints, Status> {
+        let permissions = constraints
+            .permissions
+            .into_iter()
+            .map(|(perm, entry)| {
+                let permission = match perm.as_str() {
+                    "access" => DevicePermission::Access,
+                    _ => {
+                        return Err(Status::internal(format!(
+                            "invalid permission for camera interface: {perm}"
+                        )))
+                    }
+                };
+
+                Ok(DeviceRulePermission {
+                    permission: permission as i32,
+                    entry: Some(map_permission_entry(entry)),
+                })
+            })
+            .collect::<Result<Vec<_>, Status>>()?;
+
+        Ok(ProtoRuleConstraints::CameraRuleConstraints(
+            ProtoCameraRuleConstraints { permissions },
+        ))
+    }
+
     fn map_proto_reply_constraints(
         &self,
         raw_constraints: CameraPromptReply,
+        _prompt: Option<&Prompt<Self>>,
     ) -> Result<CameraReplyConstraints, String> {
         let permissions = raw_constraints
             .permissions
@@ -158,6 +172,29 @@ pub struct CameraReplyConstraints {
     pub(crate) available_permissions: Vec<String>,
 }
 
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CameraRuleConstraints {
+    pub(crate) permissions: BTreeMap<String, PermissionEntry>,
+}
+
+impl CameraRuleConstraints {
+    pub fn new(
+        permissions: impl IntoIterator<Item = (impl Into<String>, PermissionEntry)>,
+    ) -> Self {
+        Self {
+            permissions: permissions
+                .into_iter()
+                .map(|(p, e)| (p.into(), e))
+                .collect(),
+        }
+    }
+
+    pub fn permissions(&self) -> &BTreeMap<String, PermissionEntry> {
+        &self.permissions
+    }
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "kebab-case")]
 pub struct CameraConstraintsFilter {}
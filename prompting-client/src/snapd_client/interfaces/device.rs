@@ -0,0 +1,158 @@
+//! Camera and microphone prompts presented to the user as a single prompt.
+//!
+//! Video call apps typically request access to the camera and the microphone back to back. Rather
+//! than showing two dialogs one after the other, the worker pairs these prompts up when they are
+//! pending for the same snap and cgroup and the user's decision is applied to both of them.
+use crate::{
+    protos::{
+        apparmor_prompting::{
+            prompt_reply::PromptReply as ProtoConstraints, CameraPromptReply,
+            MicrophonePromptReply, PromptReply as ProtoPromptReply,
+        },
+        DevicePrompt as ProtoDevicePrompt,
+    },
+    snapd_client::{
+        interfaces::{
+            camera::CameraInterface, microphone::MicrophoneInterface, proto_meta_data, ProtoPrompt,
+            TypedPromptReply,
+        },
+        prompt::UiInput,
+        PromptId,
+    },
+};
+use tonic::Status;
+
+/// UI input for a camera prompt and a microphone prompt from the same process.
+///
+/// The camera prompt is treated as the primary prompt: its ID is the one used for the combined
+/// prompt in the UI and for the reply received from the UI.
+#[derive(Debug, Clone)]
+pub struct DeviceUiInput {
+    pub(crate) camera: UiInput<CameraInterface>,
+    pub(crate) microphone: UiInput<MicrophoneInterface>,
+}
+
+impl DeviceUiInput {
+    pub fn id(&self) -> &PromptId {
+        &self.camera.id
+    }
+
+    pub fn microphone_id(&self) -> &PromptId {
+        &self.microphone.id
+    }
+
+    pub(crate) fn proto_prompt(self) -> ProtoPrompt {
+        ProtoPrompt::DevicePrompt(ProtoDevicePrompt {
+            meta_data: Some(proto_meta_data(
+                self.camera.id,
+                self.camera.meta,
+                self.camera.process,
+            )),
+            microphone_prompt_id: self.microphone.id.0,
+        })
+    }
+}
+
+/// Split a reply to a device prompt from the UI into the replies for the underlying camera and
+/// microphone prompts, in that order.
+///
+/// Returns `None` if the reply is not for a device prompt.
+pub fn split_device_reply(
+    raw_reply: &ProtoPromptReply,
+) -> Option<Result<[(PromptId, TypedPromptReply); 2], Status>> {
+    let Some(ProtoConstraints::DevicePromptReply(device)) = &raw_reply.prompt_reply else {
+        return None;
+    };
+
+    let with_constraints = |prompt_id: &str, constraints: ProtoConstraints| {
+        let reply = ProtoPromptReply {
+            prompt_id: prompt_id.to_string(),
+            prompt_reply: Some(constraints),
+            ..raw_reply.clone()
+        };
+
+        TypedPromptReply::try_from(reply).map(|reply| (PromptId(prompt_id.to_string()), reply))
+    };
+
+    let res = with_constraints(
+        &raw_reply.prompt_id,
+        ProtoConstraints::CameraPromptReply(CameraPromptReply {
+            permissions: device.permissions.clone(),
+        }),
+    )
+    .and_then(|camera| {
+        let microphone = with_constraints(
+            &device.microphone_prompt_id,
+            ProtoConstraints::MicrophonePromptReply(MicrophonePromptReply {
+                permissions: device.permissions.clone(),
+            }),
+        )?;
+
+        Ok([camera, microphone])
+    });
+
+    Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        protos::apparmor_prompting::{self, DevicePermission, DevicePromptReply},
+        snapd_client::{Action, Lifespan},
+    };
+
+    fn device_reply(lifespan: apparmor_prompting::Lifespan) -> ProtoPromptReply {
+        ProtoPromptReply {
+            prompt_id: "camera".to_string(),
+            action: apparmor_prompting::Action::Allow as i32,
+            lifespan: lifespan as i32,
+            prompt_reply: Some(ProtoConstraints::DevicePromptReply(DevicePromptReply {
+                microphone_prompt_id: "microphone".to_string(),
+                permissions: vec![DevicePermission::Access as i32],
+            })),
+            replace_conflicting_rules: false,
+        }
+    }
+
+    #[test]
+    fn device_replies_fan_out_to_both_prompts() {
+        let [(camera_id, camera), (microphone_id, microphone)] =
+            split_device_reply(&device_reply(apparmor_prompting::Lifespan::Forever))
+                .expect("a device reply")
+                .unwrap();
+
+        assert_eq!(camera_id, PromptId("camera".to_string()));
+        assert_eq!(microphone_id, PromptId("microphone".to_string()));
+        for reply in [&camera, &microphone] {
+            assert_eq!(reply.action(), Action::Allow);
+            assert_eq!(reply.lifespan(), Lifespan::Forever);
+        }
+        assert!(matches!(camera, TypedPromptReply::Camera(_)));
+        assert!(matches!(microphone, TypedPromptReply::Microphone(_)));
+    }
+
+    #[test]
+    fn invalid_device_replies_are_rejected() {
+        let mut reply = device_reply(apparmor_prompting::Lifespan::Forever);
+        if let Some(ProtoConstraints::DevicePromptReply(device)) = &mut reply.prompt_reply {
+            device.permissions = vec![42];
+        }
+
+        let res = split_device_reply(&reply).expect("a device reply");
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn other_replies_are_not_split() {
+        let reply = ProtoPromptReply {
+            prompt_reply: Some(ProtoConstraints::CameraPromptReply(CameraPromptReply {
+                permissions: vec![DevicePermission::Access as i32],
+            })),
+            ..device_reply(apparmor_prompting::Lifespan::Single)
+        };
+
+        assert!(split_device_reply(&reply).is_none());
+    }
+}
@@ -0,0 +1,556 @@
+//! Handling for the `personal-files` and `system-files` interfaces.
+//!
+//! Both interfaces grant access to a fixed set of paths declared by the plug of the snap making
+//! the request, typically dotfiles such as `~/.config/foo` or configuration under `/etc`. Prompts
+//! for them are handled using the same path pattern logic as the [home][super::home] interface,
+//! with the options presented to the user limited to those covered by the declared paths.
+use crate::{
+    field_matches,
+    prompt_sequence::{MatchAttempt, MatchFailure},
+    protos::{
+        apparmor_prompting::{rule::Constraints as ProtoRuleConstraints, HomePromptReply},
+        FilesPrompt as ProtoFilesPrompt,
+    },
+    snapd_client::{
+        interfaces::{
+            home::{
+                dotfile_config_dir, home_dir_from_env, map_home_prompt_reply, map_pattern_option,
+                map_permissions, proto_home_rule_constraints, EnrichedPathKind,
+                HomeReplyConstraints, HomeReplyConstraintsOverrides, HomeRuleConstraints,
+                PatternOptions, PatternType, TypedPathPattern,
+            },
+            proto_meta_data, ConstraintsFilter, Prompt, PromptReply, ProtoPrompt, SnapInterface,
+        },
+        prompt::UiInput,
+        Action, Lifespan, Result, SnapMeta,
+    },
+    util::serde_option_regex,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tonic::Status;
+
+impl<I> Prompt<I>
+where
+    I: SnapInterface<Constraints = FilesConstraints>,
+{
+    pub fn path(&self) -> &str {
+        &self.constraints.path
+    }
+
+    pub fn requested_permissions(&self) -> &[String] {
+        &self.constraints.requested_permissions
+    }
+
+    /// The paths declared by the plug of the requesting snap, if they have been looked up.
+    pub fn declared_paths(&self) -> &[String] {
+        &self.constraints.declared_paths
+    }
+}
+
+/// The interface for allowing access to files in the user's home directory declared by a snap.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PersonalFilesInterface;
+
+impl SnapInterface for PersonalFilesInterface {
+    const NAME: &'static str = "personal-files";
+
+    type Constraints = FilesConstraints;
+    type ReplyConstraints = HomeReplyConstraints;
+    type RuleConstraints = HomeRuleConstraints;
+
+    type ConstraintsFilter = FilesConstraintsFilter;
+    type ReplyConstraintsOverrides = HomeReplyConstraintsOverrides;
+
+    type UiInputData = FilesUiInputData;
+    type UiReplyConstraints = HomePromptReply;
+
+    fn prompt_to_reply(prompt: Prompt<Self>, action: Action) -> PromptReply<Self> {
+        files_prompt_to_reply(prompt, action)
+    }
+
+    fn ui_input_from_prompt(prompt: Prompt<Self>, meta: Option<SnapMeta>) -> Result<UiInput<Self>> {
+        files_ui_input_from_prompt(prompt, meta, &home_dir_from_env())
+    }
+
+    fn proto_prompt_from_ui_input(input: UiInput<Self>) -> Result<ProtoPrompt, Status> {
+        Ok(ProtoPrompt::PersonalFilesPrompt(proto_files_prompt(input)?))
+    }
+
+    fn proto_rule_constraints(
+        constraints: HomeRuleConstraints,
+    ) -> Result<ProtoRuleConstraints, Status> {
+        Ok(ProtoRuleConstraints::PersonalFilesRuleConstraints(
+            proto_home_rule_constraints(constraints)?,
+        ))
+    }
+
+    fn map_proto_reply_constraints(
+        &self,
+        raw_constraints: HomePromptReply,
+        prompt: Option<&Prompt<Self>>,
+    ) -> Result<HomeReplyConstraints, String> {
+        map_home_prompt_reply(raw_constraints, prompt.map(|p| p.constraints.path.as_str()))
+    }
+}
+
+/// The interface for allowing access to system files declared by a snap.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SystemFilesInterface;
+
+impl SnapInterface for SystemFilesInterface {
+    const NAME: &'static str = "system-files";
+
+    type Constraints = FilesConstraints;
+    type ReplyConstraints = HomeReplyConstraints;
+    type RuleConstraints = HomeRuleConstraints;
+
+    type ConstraintsFilter = FilesConstraintsFilter;
+    type ReplyConstraintsOverrides = HomeReplyConstraintsOverrides;
+
+    type UiInputData = FilesUiInputData;
+    type UiReplyConstraints = HomePromptReply;
+
+    fn prompt_to_reply(prompt: Prompt<Self>, action: Action) -> PromptReply<Self> {
+        files_prompt_to_reply(prompt, action)
+    }
+
+    fn ui_input_from_prompt(prompt: Prompt<Self>, meta: Option<SnapMeta>) -> Result<UiInput<Self>> {
+        files_ui_input_from_prompt(prompt, meta, &home_dir_from_env())
+    }
+
+    fn proto_prompt_from_ui_input(input: UiInput<Self>) -> Result<ProtoPrompt, Status> {
+        Ok(ProtoPrompt::SystemFilesPrompt(proto_files_prompt(input)?))
+    }
+
+    fn proto_rule_constraints(
+        constraints: HomeRuleConstraints,
+    ) -> Result<ProtoRuleConstraints, Status> {
+        Ok(ProtoRuleConstraints::SystemFilesRuleConstraints(
+            proto_home_rule_constraints(constraints)?,
+        ))
+    }
+
+    fn map_proto_reply_constraints(
+        &self,
+        raw_constraints: HomePromptReply,
+        prompt: Option<&Prompt<Self>>,
+    ) -> Result<HomeReplyConstraints, String> {
+        map_home_prompt_reply(raw_constraints, prompt.map(|p| p.constraints.path.as_str()))
+    }
+}
+
+fn files_prompt_to_reply<I>(prompt: Prompt<I>, action: Action) -> PromptReply<I>
+where
+    I: SnapInterface<Constraints = FilesConstraints, ReplyConstraints = HomeReplyConstraints>,
+{
+    PromptReply {
+        action,
+        lifespan: Lifespan::Single,
+        duration: None,
+        constraints: HomeReplyConstraints {
+            requested_path: prompt.constraints.path.clone(),
+            path_pattern: prompt.constraints.path,
+            permissions: prompt.constraints.requested_permissions,
+            available_permissions: prompt.constraints.available_permissions,
+        },
+    }
+}
+
+fn files_ui_input_from_prompt<I>(
+    prompt: Prompt<I>,
+    meta: Option<SnapMeta>,
+    home_dir: &str,
+) -> Result<UiInput<I>>
+where
+    I: SnapInterface<Constraints = FilesConstraints, UiInputData = FilesUiInputData>,
+{
+    let FilesConstraints {
+        path,
+        requested_permissions,
+        available_permissions,
+        declared_paths,
+    } = prompt.constraints;
+    let declared_paths: Vec<String> = declared_paths
+        .iter()
+        .map(|p| expand_home(p, home_dir))
+        .collect();
+
+    let PatternOptions {
+        enriched_path_kind,
+        initial_pattern_option,
+        pattern_options,
+    } = limit_to_declared_paths(PatternOptions::new(&path, home_dir), &declared_paths);
+    let enriched_path_kind = match dotfile_config_dir(&path, home_dir) {
+        Some(dirname) => EnrichedPathKind::DotfileConfigDir { dirname },
+        None => enriched_path_kind,
+    };
+
+    let meta = meta.unwrap_or_else(|| SnapMeta {
+        name: prompt.snap,
+        ..Default::default()
+    });
+
+    // As with the home interface, write access is elevated to read/write where possible.
+    let mut suggested_permissions = requested_permissions.clone();
+    if requested_permissions == ["write"] && available_permissions.iter().any(|p| p == "read") {
+        suggested_permissions.push("read".to_string());
+    }
+
+    Ok(UiInput {
+        id: prompt.id,
+        meta,
+        process: None,
+        data: FilesUiInputData {
+            requested_path: path,
+            home_dir: home_dir.to_string(),
+            requested_permissions,
+            available_permissions,
+            suggested_permissions,
+            initial_pattern_option,
+            pattern_options,
+            enriched_path_kind,
+            declared_paths,
+        },
+    })
+}
+
+fn proto_files_prompt<I>(input: UiInput<I>) -> Result<ProtoFilesPrompt, Status>
+where
+    I: SnapInterface<UiInputData = FilesUiInputData>,
+{
+    let FilesUiInputData {
+        requested_path,
+        home_dir,
+        requested_permissions,
+        available_permissions,
+        suggested_permissions,
+        initial_pattern_option,
+        pattern_options,
+        enriched_path_kind,
+        declared_paths,
+    } = input.data;
+
+    Ok(ProtoFilesPrompt {
+        meta_data: Some(proto_meta_data(input.id, input.meta, input.process)),
+        requested_path,
+        home_dir,
+        requested_permissions: map_permissions(requested_permissions)?,
+        available_permissions: map_permissions(available_permissions)?,
+        suggested_permissions: map_permissions(suggested_permissions)?,
+        pattern_options: pattern_options
+            .into_iter()
+            .map(map_pattern_option)
+            .collect(),
+        initial_pattern_option: initial_pattern_option as i32,
+        enriched_path_kind: Some(enriched_path_kind.into()),
+        declared_paths,
+    })
+}
+
+/// Plugs for these interfaces may declare paths relative to the home directory using `$HOME`.
+fn expand_home(path: &str, home_dir: &str) -> String {
+    match path.strip_prefix("$HOME") {
+        Some(rest) => format!("{}{rest}", home_dir.trim_end_matches('/')),
+        None => path.to_string(),
+    }
+}
+
+/// The fixed portion of a path pattern before any glob characters, without a trailing `/`.
+fn pattern_base(path_pattern: &str) -> &str {
+    let ix = path_pattern
+        .find(['*', '?', '[', '{'])
+        .unwrap_or(path_pattern.len());
+
+    path_pattern[..ix].trim_end_matches('/')
+}
+
+/// Drop any pattern options that would grant access beyond the paths declared by the plug.
+///
+/// The requested path itself is always retained as snapd only prompts for paths that the plug
+/// covers. If we were unable to look up the declared paths then this is the only option offered.
+fn limit_to_declared_paths(options: PatternOptions, declared_paths: &[String]) -> PatternOptions {
+    let PatternOptions {
+        enriched_path_kind,
+        initial_pattern_option,
+        pattern_options,
+    } = options;
+    let initial = pattern_options[initial_pattern_option].clone();
+
+    let is_requested = |opt: &TypedPathPattern| {
+        matches!(
+            opt.pattern_type,
+            PatternType::RequestedFile | PatternType::RequestedDirectory
+        )
+    };
+    let is_declared = |opt: &TypedPathPattern| {
+        let base = Path::new(pattern_base(&opt.path_pattern));
+        declared_paths
+            .iter()
+            .any(|p| base.starts_with(pattern_base(p)))
+    };
+
+    let mut pattern_options: Vec<TypedPathPattern> = pattern_options
+        .into_iter()
+        .filter(|opt| is_requested(opt) || is_declared(opt))
+        .collect();
+
+    let initial_pattern_option = pattern_options
+        .iter()
+        .position(|opt| opt == &initial)
+        .or_else(|| pattern_options.iter().position(is_requested))
+        .unwrap_or_default();
+    pattern_options[initial_pattern_option].show_initially = true;
+
+    PatternOptions {
+        enriched_path_kind,
+        initial_pattern_option,
+        pattern_options,
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FilesConstraints {
+    pub(crate) path: String,
+    pub(crate) requested_permissions: Vec<String>,
+    pub(crate) available_permissions: Vec<String>,
+    /// Not provided by snapd as part of the prompt: these are looked up from the connected plug.
+    #[serde(skip)]
+    pub(crate) declared_paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilesUiInputData {
+    pub(crate) requested_path: String,
+    pub(crate) home_dir: String,
+    pub(crate) requested_permissions: Vec<String>,
+    pub(crate) available_permissions: Vec<String>,
+    pub(crate) suggested_permissions: Vec<String>,
+    pub(crate) initial_pattern_option: usize,
+    pub(crate) pattern_options: Vec<TypedPathPattern>,
+    pub(crate) enriched_path_kind: EnrichedPathKind,
+    pub(crate) declared_paths: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct FilesConstraintsFilter {
+    #[serde(with = "serde_option_regex", default)]
+    pub path: Option<Regex>,
+    pub requested_permissions: Option<Vec<String>>,
+    pub available_permissions: Option<Vec<String>>,
+}
+
+impl FilesConstraintsFilter {
+    pub fn try_with_path(&mut self, path: impl Into<String>) -> Result<&mut Self> {
+        let re = Regex::new(&path.into())?;
+        self.path = Some(re);
+        Ok(self)
+    }
+
+    pub fn with_requested_permissions(&mut self, permissions: Vec<impl Into<String>>) -> &mut Self {
+        self.requested_permissions = Some(permissions.into_iter().map(|p| p.into()).collect());
+        self
+    }
+
+    pub fn with_available_permissions(&mut self, permissions: Vec<impl Into<String>>) -> &mut Self {
+        self.available_permissions = Some(permissions.into_iter().map(|p| p.into()).collect());
+        self
+    }
+}
+
+impl ConstraintsFilter for FilesConstraintsFilter {
+    type Constraints = FilesConstraints;
+
+    fn matches(&self, constraints: &Self::Constraints) -> MatchAttempt {
+        let mut failures = Vec::new();
+
+        if let Some(re) = &self.path {
+            if !re.is_match(&constraints.path) {
+                failures.push(MatchFailure {
+                    field: "path",
+                    expected: format!("{:?}", re.to_string()),
+                    seen: format!("{:?}", constraints.path),
+                });
+            }
+        }
+
+        field_matches!(self, constraints, failures, requested_permissions);
+        field_matches!(self, constraints, failures, available_permissions);
+
+        if failures.is_empty() {
+            MatchAttempt::Success
+        } else {
+            MatchAttempt::Failure(failures)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapd_client::{PromptId, RawPrompt, TypedPrompt};
+    use simple_test_case::test_case;
+
+    const HOME: &str = "/home/user";
+
+    fn prompt<I>(path: &str, declared_paths: &[&str]) -> Prompt<I>
+    where
+        I: SnapInterface<Constraints = FilesConstraints>,
+    {
+        Prompt {
+            id: PromptId("C7OUCCDWCE6CC===".to_string()),
+            timestamp: "2024-08-14T09:47:03.350324989Z".to_string(),
+            snap: "foo".to_string(),
+            pid: 1234,
+            cgroup: "cgroup".into(),
+            interface: I::NAME.to_string(),
+            constraints: FilesConstraints {
+                path: path.to_string(),
+                requested_permissions: vec!["write".to_string()],
+                available_permissions: vec!["read".to_string(), "write".to_string()],
+                declared_paths: declared_paths.iter().map(|s| s.to_string()).collect(),
+            },
+        }
+    }
+
+    fn options(input: &UiInput<impl SnapInterface<UiInputData = FilesUiInputData>>) -> Vec<&str> {
+        input
+            .data
+            .pattern_options
+            .iter()
+            .map(|opt| opt.path_pattern.as_str())
+            .collect()
+    }
+
+    #[test_case("$HOME/.config/foo", "/home/user/.config/foo"; "home relative")]
+    #[test_case("/etc/foo", "/etc/foo"; "absolute")]
+    #[test]
+    fn expand_home_works(path: &str, expected: &str) {
+        assert_eq!(expand_home(path, HOME), expected);
+    }
+
+    #[test_case("/etc/foo/**", "/etc/foo"; "recursive glob")]
+    #[test_case("/home/user/**/*.toml", "/home/user"; "extension glob")]
+    #[test_case("/etc/foo.conf", "/etc/foo.conf"; "no glob")]
+    #[test]
+    fn pattern_base_works(pattern: &str, expected: &str) {
+        assert_eq!(pattern_base(pattern), expected);
+    }
+
+    #[test]
+    fn personal_files_options_are_limited_to_declared_paths() {
+        let p = prompt::<PersonalFilesInterface>(
+            "/home/user/.config/foo/nested/settings.toml",
+            &["$HOME/.config/foo"],
+        );
+
+        let input = files_ui_input_from_prompt(p, None, HOME).unwrap();
+
+        assert_eq!(
+            options(&input),
+            vec![
+                "/home/user/.config/foo/nested/**",
+                "/home/user/.config/foo/nested/settings.toml",
+            ]
+        );
+        assert_eq!(input.data.initial_pattern_option, 1);
+        assert_eq!(
+            input.data.declared_paths,
+            vec!["/home/user/.config/foo".to_string()]
+        );
+        assert_eq!(
+            input.data.enriched_path_kind,
+            EnrichedPathKind::DotfileConfigDir {
+                dirname: ".config/foo".to_string()
+            }
+        );
+        assert_eq!(input.data.suggested_permissions, vec!["write", "read"]);
+    }
+
+    #[test]
+    fn system_files_options_are_limited_to_declared_paths() {
+        let p = prompt::<SystemFilesInterface>("/etc/foo/conf.d/bar.conf", &["/etc/foo"]);
+
+        let input = files_ui_input_from_prompt(p, None, HOME).unwrap();
+
+        assert_eq!(
+            options(&input),
+            vec!["/etc/foo/conf.d/**", "/etc/foo/conf.d/bar.conf"]
+        );
+        assert_eq!(input.data.initial_pattern_option, 1);
+        assert_eq!(input.data.enriched_path_kind, EnrichedPathKind::SubDirFile);
+    }
+
+    #[test]
+    fn only_the_requested_path_is_offered_without_declared_paths() {
+        let p = prompt::<PersonalFilesInterface>("/home/user/.foo/bar", &[]);
+
+        let input = files_ui_input_from_prompt(p, None, HOME).unwrap();
+
+        assert_eq!(options(&input), vec!["/home/user/.foo/bar"]);
+        assert_eq!(input.data.initial_pattern_option, 0);
+        assert!(input.data.pattern_options[0].show_initially);
+        assert_eq!(
+            input.data.enriched_path_kind,
+            EnrichedPathKind::DotfileConfigDir {
+                dirname: ".foo".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn declared_files_are_offered_as_the_requested_file() {
+        let p = prompt::<PersonalFilesInterface>("/home/user/.bashrc", &["$HOME/.bashrc"]);
+
+        let input = files_ui_input_from_prompt(p, None, HOME).unwrap();
+
+        assert_eq!(options(&input), vec!["/home/user/.bashrc"]);
+        assert!(input.data.pattern_options[0].show_initially);
+        assert_eq!(
+            input.data.enriched_path_kind,
+            EnrichedPathKind::HomeDirFile {
+                filename: ".bashrc".to_string()
+            }
+        );
+    }
+
+    #[test_case(PersonalFilesInterface::NAME; "personal files")]
+    #[test_case(SystemFilesInterface::NAME; "system files")]
+    #[test]
+    fn prompts_deserialize_for_both_interfaces(interface: &str) {
+        let raw = serde_json::json!({
+            "id": "C7OUCCDWCE6CC===",
+            "timestamp": "2024-08-14T09:47:03.350324989Z",
+            "snap": "foo",
+            "pid": 1234,
+            "cgroup": "cgroup",
+            "interface": interface,
+            "constraints": {
+                "path": "/etc/foo/bar.conf",
+                "requested-permissions": ["read"],
+                "available-permissions": ["read", "write"]
+            }
+        });
+
+        let prompt: TypedPrompt = serde_json::from_value::<RawPrompt>(raw)
+            .unwrap()
+            .try_into()
+            .unwrap();
+
+        match prompt {
+            TypedPrompt::PersonalFiles(p) => {
+                assert_eq!(interface, PersonalFilesInterface::NAME);
+                assert_eq!(p.path(), "/etc/foo/bar.conf");
+            }
+            TypedPrompt::SystemFiles(p) => {
+                assert_eq!(interface, SystemFilesInterface::NAME);
+                assert_eq!(p.path(), "/etc/foo/bar.conf");
+            }
+            p => panic!("unexpected prompt: {p:?}"),
+        }
+    }
+}
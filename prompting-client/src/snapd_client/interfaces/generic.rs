@@ -0,0 +1,297 @@
+//! Fallback handling for prompts from interfaces that we do not have dedicated support for.
+//!
+//! Without this, prompts for new interfaces added to snapd would be left unanswered until they
+//! time out. We don't know how to interpret the constraints of these prompts so the user is only
+//! offered the choice of allowing or denying the requested permissions once.
+use crate::{
+    prompt_sequence::MatchAttempt,
+    protos::{
+        apparmor_prompting::{rule::Constraints as ProtoRuleConstraints, GenericPromptReply},
+        GenericPrompt as ProtoGenericPrompt,
+    },
+    snapd_client::{
+        interfaces::{
+            proto_meta_data, ConstraintsFilter, Prompt, PromptReply, ProtoPrompt,
+            ReplyConstraintsOverrides, SnapInterface,
+        },
+        prompt::UiInput,
+        Action, Lifespan, Result, SnapMeta,
+    },
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tonic::Status;
+
+impl Prompt<GenericInterface> {
+    pub fn requested_permissions(&self) -> &[String] {
+        &self.constraints.requested_permissions
+    }
+
+    pub fn raw_constraints(&self) -> &Value {
+        &self.constraints.raw
+    }
+}
+
+/// Handling for any interface not covered by one of the other [SnapInterface] implementations.
+///
+/// Unlike the other interfaces, [SnapInterface::NAME] is not used to match prompts: the interface
+/// name provided by snapd is preserved on each [Prompt].
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GenericInterface;
+
+impl SnapInterface for GenericInterface {
+    const NAME: &'static str = "generic";
+
+    type Constraints = GenericConstraints;
+    type ReplyConstraints = GenericReplyConstraints;
+    type RuleConstraints = Value;
+
+    type ConstraintsFilter = GenericConstraintsFilter;
+    type ReplyConstraintsOverrides = GenericReplyConstraintsOverrides;
+
+    type UiInputData = GenericUiInputData;
+    type UiReplyConstraints = GenericPromptReply;
+
+    fn prompt_to_reply(prompt: Prompt<Self>, action: Action) -> PromptReply<Self> {
+        PromptReply {
+            action,
+            lifespan: Lifespan::Single,
+            duration: None,
+            constraints: GenericReplyConstraints {
+                permissions: prompt.constraints.requested_permissions,
+            },
+        }
+    }
+
+    fn ui_input_from_prompt(prompt: Prompt<Self>, meta: Option<SnapMeta>) -> Result<UiInput<Self>> {
+        let meta = meta.unwrap_or_else(|| SnapMeta {
+            name: prompt.snap,
+            ..Default::default()
+        });
+        let GenericConstraints {
+            raw,
+            requested_permissions,
+            available_permissions,
+        } = prompt.constraints;
+
+        Ok(UiInput {
+            id: prompt.id,
+            meta,
+            process: None,
+            data: GenericUiInputData {
+                interface: prompt.interface,
+                constraints: raw,
+                requested_permissions,
+                available_permissions,
+            },
+        })
+    }
+
+    fn proto_prompt_from_ui_input(input: UiInput<Self>) -> Result<ProtoPrompt, Status> {
+        let GenericUiInputData {
+            interface,
+            constraints,
+            requested_permissions,
+            available_permissions,
+        } = input.data;
+
+        Ok(ProtoPrompt::GenericPrompt(ProtoGenericPrompt {
+            meta_data: Some(proto_meta_data(input.id, input.meta, input.process)),
+            interface,
+            constraints: constraints.to_string(),
+            requested_permissions,
+            available_permissions,
+        }))
+    }
+
+    fn proto_rule_constraints(_constraints: Value) -> Result<ProtoRuleConstraints, Status> {
+        Err(Status::unimplemented(
+            "rules are not supported for generic prompts",
+        ))
+    }
+
+    fn map_proto_reply_constraints(
+        &self,
+        raw_constraints: GenericPromptReply,
+        _prompt: Option<&Prompt<Self>>,
+    ) -> Result<GenericReplyConstraints, String> {
+        Ok(GenericReplyConstraints {
+            permissions: raw_constraints.permissions,
+        })
+    }
+}
+
+/// The `constraints` of a prompt for an unknown interface.
+///
+/// The raw JSON is retained for display in the UI, with the permission lists that most interfaces
+/// provide being extracted when they are present.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(from = "Value", into = "Value")]
+pub struct GenericConstraints {
+    pub(crate) raw: Value,
+    pub(crate) requested_permissions: Vec<String>,
+    pub(crate) available_permissions: Vec<String>,
+}
+
+impl From<Value> for GenericConstraints {
+    fn from(raw: Value) -> Self {
+        let permissions = |key: &str| -> Vec<String> {
+            raw.get(key)
+                .and_then(Value::as_array)
+                .map(|perms| {
+                    perms
+                        .iter()
+                        .filter_map(|p| p.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        Self {
+            requested_permissions: permissions("requested-permissions"),
+            available_permissions: permissions("available-permissions"),
+            raw,
+        }
+    }
+}
+
+impl From<GenericConstraints> for Value {
+    fn from(constraints: GenericConstraints) -> Self {
+        constraints.raw
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenericUiInputData {
+    pub(crate) interface: String,
+    pub(crate) constraints: Value,
+    pub(crate) requested_permissions: Vec<String>,
+    pub(crate) available_permissions: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct GenericReplyConstraints {
+    pub(crate) permissions: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct GenericConstraintsFilter {}
+
+impl ConstraintsFilter for GenericConstraintsFilter {
+    type Constraints = GenericConstraints;
+
+    fn matches(&self, _constraints: &Self::Constraints) -> MatchAttempt {
+        MatchAttempt::Success
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct GenericReplyConstraintsOverrides {}
+
+impl ReplyConstraintsOverrides for GenericReplyConstraintsOverrides {
+    type ReplyConstraints = GenericReplyConstraints;
+
+    fn apply(self, constraints: Self::ReplyConstraints) -> Self::ReplyConstraints {
+        constraints
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        protos::apparmor_prompting::{
+            self, prompt_reply::PromptReply as ProtoConstraints, PromptReply as ProtoPromptReply,
+        },
+        snapd_client::{RawPrompt, TypedPrompt, TypedPromptReply},
+    };
+    use serde_json::json;
+    use simple_test_case::test_case;
+
+    const BLUETOOTH_PROMPT: &str = r#"{
+      "id": "C7OUCCDWCE6CC===",
+      "timestamp": "2024-06-28T19:15:37.321782305Z",
+      "snap": "firefox",
+      "pid": 1234,
+      "cgroup": "/user.slice/user-1000.slice/user@1000.service/app.slice/myapp.scope",
+      "interface": "bluetooth-control",
+      "constraints": {
+        "device": "hci0",
+        "requested-permissions": [
+            "pair"
+        ],
+        "available-permissions": [
+            "pair",
+            "connect"
+        ]
+      }
+    }"#;
+
+    fn generic_prompt() -> Prompt<GenericInterface> {
+        let raw: RawPrompt = serde_json::from_str(BLUETOOTH_PROMPT).unwrap();
+        match raw.try_into().unwrap() {
+            TypedPrompt::Generic(p) => p,
+            p => panic!("expected generic prompt, got {p:?}"),
+        }
+    }
+
+    #[test]
+    fn unknown_interfaces_deserialize_as_generic_prompts() {
+        let p = generic_prompt();
+
+        assert_eq!(p.interface(), "bluetooth-control");
+        assert_eq!(p.requested_permissions(), ["pair"]);
+        assert_eq!(p.constraints.available_permissions, ["pair", "connect"]);
+        assert_eq!(p.raw_constraints()["device"], "hci0");
+    }
+
+    #[test]
+    fn missing_permissions_are_left_empty() {
+        let constraints = GenericConstraints::from(json!({ "path": "/dev/ttyUSB0" }));
+
+        assert!(constraints.requested_permissions.is_empty());
+        assert!(constraints.available_permissions.is_empty());
+        assert_eq!(Value::from(constraints), json!({ "path": "/dev/ttyUSB0" }));
+    }
+
+    #[test]
+    fn replies_echo_the_requested_permissions() {
+        let reply = TypedPrompt::Generic(generic_prompt()).into_allow_forever();
+
+        assert_eq!(
+            serde_json::to_value(reply).unwrap(),
+            json!({
+                "action": "allow",
+                "lifespan": "single",
+                "constraints": { "permissions": ["pair"] }
+            })
+        );
+    }
+
+    #[test_case(apparmor_prompting::Lifespan::Single, true; "single")]
+    #[test_case(apparmor_prompting::Lifespan::Session, false; "session")]
+    #[test_case(apparmor_prompting::Lifespan::Forever, false; "forever")]
+    #[test]
+    fn only_single_lifespans_are_accepted_from_the_ui(
+        lifespan: apparmor_prompting::Lifespan,
+        ok: bool,
+    ) {
+        let raw_reply = ProtoPromptReply {
+            prompt_id: "C7OUCCDWCE6CC===".to_string(),
+            action: apparmor_prompting::Action::Deny as i32,
+            lifespan: lifespan as i32,
+            prompt_reply: Some(ProtoConstraints::GenericPromptReply(GenericPromptReply {
+                permissions: vec!["pair".to_string()],
+            })),
+            replace_conflicting_rules: false,
+        };
+
+        let res = TypedPromptReply::try_from(raw_reply);
+
+        assert_eq!(res.is_ok(), ok, "{res:?}");
+    }
+}
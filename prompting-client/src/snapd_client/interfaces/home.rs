@@ -1,28 +1,35 @@
 use crate::{
     field_matches, map_enum,
-    prompt_sequence::{MatchAttempt, MatchFailure},
+    prompt_sequence::{MatchAttempt, MatchError, MatchFailure},
     protos::{
         apparmor_prompting::{
-            home_prompt::PatternOption, EnrichedPathKind as ProtoEnrichedPathKind, HomePatternType,
-            HomePermission, HomePromptReply, MetaData,
+            home_prompt::PatternOption, home_rule_constraints::HomeRulePermission,
+            rule::Constraints as ProtoRuleConstraints, EnrichedPathKind as ProtoEnrichedPathKind,
+            HomePatternType, HomePermission, HomePromptReply,
+            HomeRuleConstraints as ProtoHomeRuleConstraints,
         },
         HomePrompt as ProtoHomePrompt,
     },
     snapd_client::{
         interfaces::{
-            ConstraintsFilter, Prompt, PromptReply, ProtoPrompt, ReplyConstraintsOverrides,
-            SnapInterface,
+            map_permission_entry, proto_meta_data, ConstraintsFilter, Prompt, PromptReply,
+            ProtoPrompt, ReplyConstraintsOverrides, SnapInterface,
         },
         prompt::UiInput,
+        rule::PermissionEntry,
         Action, Error, Lifespan, Result, SnapMeta,
     },
     util::serde_option_regex,
 };
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::{env, path::PathBuf};
+use std::{collections::BTreeMap, env, path::PathBuf};
 use tonic::Status;
 
+pub mod path_pattern;
+
+use path_pattern::PathPattern;
+
 impl Prompt<HomeInterface> {
     pub fn path(&self) -> &str {
         &self.constraints.path
@@ -34,12 +41,32 @@ impl Prompt<HomeInterface> {
 }
 
 impl PromptReply<HomeInterface> {
-    /// Specify a custom path pattern to replace the one originally requested in the parent [Prompt].
+    /// Attempt to set a custom path pattern to replace the one originally requested in the parent
+    /// [Prompt].
+    ///
+    /// This method will error if the path pattern provided is invalid or does not match the path
+    /// originally requested in the parent [Prompt].
+    pub fn with_custom_path_pattern(mut self, path_pattern: impl Into<String>) -> Result<Self> {
+        let path_pattern = path_pattern.into();
+        PathPattern::parse_for_requested(&path_pattern, &self.constraints.requested_path).map_err(
+            |error| Error::InvalidPathPattern {
+                pattern: path_pattern.clone(),
+                error,
+            },
+        )?;
+
+        self.constraints.path_pattern = path_pattern;
+
+        Ok(self)
+    }
+
+    /// Specify a custom path pattern without checking it against the path originally requested in
+    /// the parent [Prompt].
     ///
     /// If the path pattern provided is invalid or does not apply to the path originally requested
     /// in the parent prompt then submitting this reply will result in an error being returned by
     /// snapd.
-    pub fn with_custom_path_pattern(mut self, path_pattern: impl Into<String>) -> Self {
+    pub fn with_unchecked_custom_path_pattern(mut self, path_pattern: impl Into<String>) -> Self {
         self.constraints.path_pattern = path_pattern.into();
         self
     }
@@ -69,10 +96,10 @@ impl PromptReply<HomeInterface> {
 #[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub struct HomeInterface;
 
-struct PatternOptions {
-    enriched_path_kind: EnrichedPathKind,
-    initial_pattern_option: usize,
-    pattern_options: Vec<TypedPathPattern>,
+pub(crate) struct PatternOptions {
+    pub(crate) enriched_path_kind: EnrichedPathKind,
+    pub(crate) initial_pattern_option: usize,
+    pub(crate) pattern_options: Vec<TypedPathPattern>,
 }
 
 impl PatternOptions {
@@ -81,7 +108,7 @@ impl PatternOptions {
     ///
     /// Details of the cases and rationale behind how we handle this can be found here:
     ///   https://www.figma.com/board/1DIGbaCf4ZjTcShYjLiAIi/24.10-AppArmor-prompting---MVP-logic?node-id=0-1&t=4kUtDaqmQEvLA8v7-0
-    fn new(path: &str, home_dir: &str) -> Self {
+    pub(crate) fn new(path: &str, home_dir: &str) -> Self {
         let cpath = CategorisedPath::from_path(path, home_dir);
         let everything_in_home_pattern = TypedPathPattern::after_more_options(
             PatternType::HomeDirectory,
@@ -161,7 +188,12 @@ impl PatternOptions {
     }
 }
 
-fn home_dir_from_env() -> String {
+/// The hidden configuration directory (relative to `home_dir`) containing `path`, if any.
+pub(crate) fn dotfile_config_dir(path: &str, home_dir: &str) -> Option<String> {
+    CategorisedPath::from_path(path, home_dir).dotfile_config_dir()
+}
+
+pub(crate) fn home_dir_from_env() -> String {
     env::var("SNAP_REAL_HOME").expect("to be running inside of a snap")
 }
 
@@ -170,6 +202,13 @@ impl HomeInterface {
         let path = &prompt.constraints.path;
         Ok(PatternOptions::new(path, &home_dir_from_env()))
     }
+
+    /// Determine the [PatternType] of an arbitrary path pattern (typically one that has been
+    /// edited by the user in the UI) relative to the home directory of the user we are running
+    /// under.
+    pub fn resolve_pattern_type(path_pattern: &str) -> PatternType {
+        PatternType::from_path_pattern(path_pattern, &home_dir_from_env())
+    }
 }
 
 impl SnapInterface for HomeInterface {
@@ -177,6 +216,7 @@ impl SnapInterface for HomeInterface {
 
     type Constraints = HomeConstraints;
     type ReplyConstraints = HomeReplyConstraints;
+    type RuleConstraints = HomeRuleConstraints;
 
     type ConstraintsFilter = HomeConstraintsFilter;
     type ReplyConstraintsOverrides = HomeReplyConstraintsOverrides;
@@ -190,6 +230,7 @@ impl SnapInterface for HomeInterface {
             lifespan: Lifespan::Single,
             duration: None,
             constraints: HomeReplyConstraints {
+                requested_path: prompt.constraints.path.clone(),
                 path_pattern: prompt.constraints.path,
                 permissions: prompt.constraints.requested_permissions,
                 available_permissions: prompt.constraints.available_permissions,
@@ -205,10 +246,7 @@ impl SnapInterface for HomeInterface {
         } = Self::ui_options(&prompt)?;
         let meta = meta.unwrap_or_else(|| SnapMeta {
             name: prompt.snap,
-            updated_at: String::default(),
-            store_url: String::default(),
-            publisher: String::default(),
-            snap_icon: None,
+            ..Default::default()
         });
 
         // We elevate the suggested permissions in the ui from write -> read/write in order to
@@ -222,6 +260,7 @@ impl SnapInterface for HomeInterface {
         Ok(UiInput {
             id: prompt.id,
             meta,
+            process: None,
             data: HomeUiInputData {
                 requested_path: prompt.constraints.path,
                 home_dir: home_dir_from_env(),
@@ -236,18 +275,6 @@ impl SnapInterface for HomeInterface {
     }
 
     fn proto_prompt_from_ui_input(input: UiInput<Self>) -> Result<ProtoPrompt, Status> {
-        let SnapMeta {
-            name,
-            updated_at,
-            store_url,
-            publisher,
-            snap_icon,
-        } = input.meta;
-
-        let (snap_icon, snap_icon_mime_type) = snap_icon
-            .map(|icon| (icon.bytes.into(), icon.mime_type))
-            .unwrap_or_default();
-
         let HomeUiInputData {
             requested_path,
             home_dir,
@@ -260,15 +287,7 @@ impl SnapInterface for HomeInterface {
         } = input.data;
 
         Ok(ProtoPrompt::HomePrompt(ProtoHomePrompt {
-            meta_data: Some(MetaData {
-                prompt_id: input.id.0,
-                snap_name: name,
-                store_url,
-                publisher,
-                updated_at,
-                snap_icon,
-                snap_icon_mime_type,
-            }),
+            meta_data: Some(proto_meta_data(input.id, input.meta, input.process)),
             requested_path,
             home_dir,
             requested_permissions: map_permissions(requested_permissions)?,
@@ -283,35 +302,61 @@ impl SnapInterface for HomeInterface {
         }))
     }
 
+    fn proto_rule_constraints(
+        constraints: HomeRuleConstraints,
+    ) -> Result<ProtoRuleConstraints, Status> {
+        Ok(ProtoRuleConstraints::HomeRuleConstraints(
+            proto_home_rule_constraints(constraints)?,
+        ))
+    }
+
     fn map_proto_reply_constraints(
         &self,
         raw_constraints: HomePromptReply,
+        prompt: Option<&Prompt<Self>>,
     ) -> Result<HomeReplyConstraints, String> {
-        let permissions = raw_constraints
-            .permissions
-            .into_iter()
-            .map(|id| {
-                let perm = HomePermission::try_from(id)
-                    .map_err(|_| format!("unknown permission id: {id}"))?;
-                let s = match perm {
-                    HomePermission::Read => "read".to_owned(),
-                    HomePermission::Write => "write".to_owned(),
-                    HomePermission::Execute => "execute".to_owned(),
-                };
+        map_home_prompt_reply(raw_constraints, prompt.map(|p| p.path()))
+    }
+}
 
-                Ok(s)
-            })
-            .collect::<std::result::Result<Vec<_>, String>>()?;
+/// Parse the path pattern and permissions provided by the Flutter UI in a reply.
+///
+/// If the path originally requested by the prompt is known then the path pattern is checked
+/// against it, otherwise we can only check that the pattern is well formed and rely on snapd to
+/// reject patterns that do not match the request.
+///
+/// This is shared with the other interfaces that make use of the home reply format.
+pub(crate) fn map_home_prompt_reply(
+    raw_constraints: HomePromptReply,
+    requested_path: Option<&str>,
+) -> std::result::Result<HomeReplyConstraints, String> {
+    let permissions = map_proto_permissions(raw_constraints.permissions)?;
+    let path_pattern = raw_constraints.path_pattern;
+    check_reply_path_pattern(&path_pattern, requested_path)?;
+
+    Ok(HomeReplyConstraints {
+        path_pattern,
+        permissions,
+        available_permissions: Vec::new(),
+        requested_path: requested_path.unwrap_or_default().to_string(),
+    })
+}
 
-        Ok(HomeReplyConstraints {
-            path_pattern: raw_constraints.path_pattern,
-            permissions,
-            available_permissions: Vec::new(),
-        })
+/// Check a path pattern received from the Flutter UI, against the requested path if known.
+pub(crate) fn check_reply_path_pattern(
+    path_pattern: &str,
+    requested_path: Option<&str>,
+) -> std::result::Result<(), String> {
+    match requested_path {
+        Some(requested_path) => PathPattern::parse_for_requested(path_pattern, requested_path),
+        None => PathPattern::parse(path_pattern),
     }
+    .map_err(|e| format!("invalid path pattern {path_pattern:?}: {e}"))?;
+
+    Ok(())
 }
 
-fn map_permission(perm: &str) -> Result<i32, Status> {
+pub(crate) fn map_permission(perm: &str) -> Result<i32, Status> {
     match perm {
         "read" => Ok(HomePermission::Read as i32),
         "write" => Ok(HomePermission::Write as i32),
@@ -322,29 +367,59 @@ fn map_permission(perm: &str) -> Result<i32, Status> {
     }
 }
 
-fn map_permissions(perms: Vec<String>) -> Result<Vec<i32>, Status> {
+pub(crate) fn map_permissions(perms: Vec<String>) -> Result<Vec<i32>, Status> {
     perms.iter().map(|s| map_permission(s)).collect()
 }
 
-fn map_pattern_option(
+/// Parse the permissions provided by the Flutter UI in a reply.
+pub(crate) fn map_proto_permissions(ids: Vec<i32>) -> std::result::Result<Vec<String>, String> {
+    ids.into_iter()
+        .map(|id| {
+            let perm =
+                HomePermission::try_from(id).map_err(|_| format!("unknown permission id: {id}"))?;
+            let s = match perm {
+                HomePermission::Read => "read".to_owned(),
+                HomePermission::Write => "write".to_owned(),
+                HomePermission::Execute => "execute".to_owned(),
+            };
+
+            Ok(s)
+        })
+        .collect()
+}
+
+/// Maps path based rule constraints to their protobuf serialization type.
+///
+/// This is shared with the other interfaces that make use of the home rule format.
+pub(crate) fn proto_home_rule_constraints(
+    constraints: HomeRuleConstraints,
+) -> Result<ProtoHomeRuleConstraints, Status> {
+    let permissions = constraints
+        .permissions
+        .into_iter()
+        .map(|(perm, entry)| {
+            Ok(HomeRulePermission {
+                permission: map_permission(&perm)?,
+                entry: Some(map_permission_entry(entry)),
+            })
+        })
+        .collect::<Result<Vec<_>, Status>>()?;
+
+    Ok(ProtoHomeRuleConstraints {
+        path_pattern: constraints.path_pattern,
+        permissions,
+    })
+}
+
+pub(crate) fn map_pattern_option(
     TypedPathPattern {
         pattern_type,
         path_pattern,
         show_initially,
     }: TypedPathPattern,
 ) -> PatternOption {
-    let home_pattern_type = map_enum!(
-        PatternType => HomePatternType;
-        [
-            RequestedDirectory, RequestedFile, TopLevelDirectory,
-            HomeDirectory, MatchingFileExtension, ContainingDirectory,
-            RequestedDirectoryContents
-        ];
-        pattern_type;
-    );
-
     PatternOption {
-        home_pattern_type: home_pattern_type as i32,
+        home_pattern_type: HomePatternType::from(pattern_type) as i32,
         path_pattern,
         show_initially,
     }
@@ -414,6 +489,38 @@ pub struct HomeReplyConstraints {
     pub(crate) permissions: Vec<String>,
     #[serde(skip)]
     pub(crate) available_permissions: Vec<String>,
+    #[serde(skip)]
+    pub(crate) requested_path: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct HomeRuleConstraints {
+    pub(crate) path_pattern: String,
+    pub(crate) permissions: BTreeMap<String, PermissionEntry>,
+}
+
+impl HomeRuleConstraints {
+    pub fn new(
+        path_pattern: impl Into<String>,
+        permissions: impl IntoIterator<Item = (impl Into<String>, PermissionEntry)>,
+    ) -> Self {
+        Self {
+            path_pattern: path_pattern.into(),
+            permissions: permissions
+                .into_iter()
+                .map(|(p, e)| (p.into(), e))
+                .collect(),
+        }
+    }
+
+    pub fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    pub fn permissions(&self) -> &BTreeMap<String, PermissionEntry> {
+        &self.permissions
+    }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -490,24 +597,45 @@ impl ReplyConstraintsOverrides for HomeReplyConstraintsOverrides {
 
         constraints
     }
+
+    fn validate(constraints: &Self::ReplyConstraints) -> std::result::Result<(), MatchError> {
+        PathPattern::parse_for_requested(&constraints.path_pattern, &constraints.requested_path)
+            .map_err(|error| MatchError::InvalidPathPattern {
+                pattern: constraints.path_pattern.clone(),
+                error,
+            })?;
+
+        Ok(())
+    }
 }
 
 /// PathKind's enriched with file names and directory names when needed for templating in the prompting UI.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum EnrichedPathKind {
     HomeDir,
-    TopLevelDir { dirname: String },
+    TopLevelDir {
+        dirname: String,
+    },
     SubDir,
-    HomeDirFile { filename: String },
-    TopLevelDirFile { dirname: String, filename: String },
+    HomeDirFile {
+        filename: String,
+    },
+    TopLevelDirFile {
+        dirname: String,
+        filename: String,
+    },
     SubDirFile,
+    /// A path within a hidden configuration directory such as `~/.config/foo` or `~/.foo`.
+    DotfileConfigDir {
+        dirname: String,
+    },
 }
 
 impl From<EnrichedPathKind> for ProtoEnrichedPathKind {
     fn from(k: EnrichedPathKind) -> Self {
         use crate::protos::apparmor_prompting::{
-            enriched_path_kind::Kind, HomeDir, HomeDirFile, SubDir, SubDirFile, TopLevelDir,
-            TopLevelDirFile,
+            enriched_path_kind::Kind, DotfileConfigDir, HomeDir, HomeDirFile, SubDir, SubDirFile,
+            TopLevelDir, TopLevelDirFile,
         };
 
         match k {
@@ -529,6 +657,9 @@ impl From<EnrichedPathKind> for ProtoEnrichedPathKind {
             EnrichedPathKind::SubDirFile => Self {
                 kind: Some(Kind::SubDirFile(SubDirFile {})),
             },
+            EnrichedPathKind::DotfileConfigDir { dirname } => Self {
+                kind: Some(Kind::DotfileConfigDir(DotfileConfigDir { dirname })),
+            },
         }
     }
 }
@@ -623,10 +754,38 @@ impl<'a> CategorisedPath<'a> {
         segments.pop();
         let pb: PathBuf = segments.into_iter().collect();
 
-        TypedPathPattern::initial(
-            PatternType::ContainingDirectory,
-            format!("{}/{}/**", self.home_dir, pb.to_string_lossy()),
-        )
+        // Paths outside of the home directory are left as absolute paths
+        let path_pattern = match self.kind {
+            PathKind::OutsideOfHomeFile => format!("{}/**", pb.to_string_lossy()),
+            _ => format!("{}/{}/**", self.home_dir, pb.to_string_lossy()),
+        };
+
+        TypedPathPattern::initial(PatternType::ContainingDirectory, path_pattern)
+    }
+
+    /// The hidden configuration directory within the home directory that this path sits inside
+    /// of, if any.
+    ///
+    /// Directories under `~/.config` are identified by their first two segments
+    /// (`.config/foo`) and all other hidden top level directories by their first segment.
+    fn dotfile_config_dir(&self) -> Option<String> {
+        if matches!(
+            self.kind,
+            PathKind::HomeDir
+                | PathKind::HomeDirFile
+                | PathKind::OutsideOfHomeDir
+                | PathKind::OutsideOfHomeFile
+        ) {
+            return None;
+        }
+
+        let segments: Vec<_> = self.path.iter().map(|s| s.to_string_lossy()).collect();
+        match segments.as_slice() {
+            [config, _] if config == ".config" && !self.is_dir => None,
+            [config, name, ..] if config == ".config" => Some(format!(".config/{name}")),
+            [dir, ..] if dir.starts_with('.') && dir != ".config" => Some(dir.to_string()),
+            _ => None,
+        }
     }
 
     fn dir_contents_pattern(&self) -> TypedPathPattern {
@@ -672,6 +831,54 @@ pub enum PatternType {
     RequestedDirectoryContents,
 }
 
+impl PatternType {
+    /// Classify an arbitrary path pattern based on the shapes of the patterns we generate
+    /// ourselves when building the UI options for a prompt.
+    ///
+    /// Patterns for the contents of a directory are indistinguishable from the pattern for the
+    /// directory containing a requested file without knowing which path was requested, so these
+    /// are always reported as [PatternType::ContainingDirectory]. Anything that does not match one
+    /// of our generated shapes is treated as a request for a specific file or directory.
+    fn from_path_pattern(path_pattern: &str, home_dir: &str) -> Self {
+        let home_dir = home_dir.trim_end_matches('/');
+
+        if let Some(ext) = path_pattern.strip_prefix(&format!("{home_dir}/**/*.")) {
+            if !ext.is_empty() && !ext.contains(['/', '*', '?', '{', '}', '[', ']']) {
+                return Self::MatchingFileExtension;
+            }
+        }
+
+        if let Some(dir) = path_pattern.strip_suffix("/**") {
+            let dir = format!("{dir}/");
+            return match CategorisedPath::from_path(&dir, home_dir).kind {
+                PathKind::HomeDir => Self::HomeDirectory,
+                PathKind::TopLevelDir => Self::TopLevelDirectory,
+                _ => Self::ContainingDirectory,
+            };
+        }
+
+        if CategorisedPath::from_path(path_pattern, home_dir).is_dir {
+            Self::RequestedDirectory
+        } else {
+            Self::RequestedFile
+        }
+    }
+}
+
+impl From<PatternType> for HomePatternType {
+    fn from(pattern_type: PatternType) -> Self {
+        map_enum!(
+            PatternType => HomePatternType;
+            [
+                RequestedDirectory, RequestedFile, TopLevelDirectory,
+                HomeDirectory, MatchingFileExtension, ContainingDirectory,
+                RequestedDirectoryContents
+            ];
+            pattern_type;
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -728,6 +935,67 @@ mod tests {
         }
     }
 
+    #[test_case("/home/user/Documents/**", None, true; "well formed pattern")]
+    #[test_case("home/user/**", None, false; "malformed pattern")]
+    #[test_case("/home/user/Documents/**", Some("/home/user/Documents/foo.txt"), true; "matching pattern")]
+    #[test_case("/home/user/Pictures/**", Some("/home/user/Documents/foo.txt"), false; "non-matching pattern")]
+    #[test]
+    fn ui_reply_path_pattern_is_checked_against_requested_path(
+        path_pattern: &str,
+        requested_path: Option<&str>,
+        ok: bool,
+    ) {
+        let raw = HomePromptReply {
+            path_pattern: path_pattern.to_string(),
+            permissions: vec![HomePermission::Read as i32],
+        };
+
+        let res = map_home_prompt_reply(raw, requested_path);
+
+        assert_eq!(res.is_ok(), ok, "{res:?}");
+    }
+
+    #[test_case("/home/user/Documents/**", true; "matching pattern")]
+    #[test_case("/home/user/Pictures/**", false; "non-matching pattern")]
+    #[test_case("home/user/**", false; "malformed pattern")]
+    #[test]
+    fn custom_reply_path_pattern_is_validated(path_pattern: &str, ok: bool) {
+        let reply: PromptReply<HomeInterface> = PromptReply {
+            constraints: HomeReplyConstraints {
+                requested_path: "/home/user/Documents/foo.txt".into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        match reply.with_custom_path_pattern(path_pattern) {
+            Ok(reply) if ok => assert_eq!(reply.constraints.path_pattern, path_pattern),
+            Err(Error::InvalidPathPattern { pattern, .. }) if !ok => {
+                assert_eq!(pattern, path_pattern)
+            }
+            res => panic!("unexpected result: {res:?}"),
+        }
+    }
+
+    #[test]
+    fn overriding_path_pattern_with_non_matching_pattern_errors() {
+        let overrides = HomeReplyConstraintsOverrides {
+            path_pattern: Some("/home/user/Pictures/**".into()),
+            permissions: None,
+        };
+        let constraints = HomeReplyConstraints {
+            requested_path: "/home/user/Documents/foo.txt".into(),
+            ..Default::default()
+        };
+
+        let res = HomeReplyConstraintsOverrides::validate(&overrides.apply(constraints));
+
+        assert!(
+            matches!(res, Err(MatchError::InvalidPathPattern { .. })),
+            "{res:?}"
+        );
+    }
+
     #[test_case("/home/user"; "default home")]
     #[test_case("/mnt"; "non standard home short")]
     #[test_case("/non/standard/home/user"; "non standard home long")]
@@ -750,6 +1018,28 @@ mod tests {
         assert_eq!(patt.path_pattern, format!("{home_dir}/Documents/notes/**"));
     }
 
+    #[test]
+    fn containing_dir_pattern_outside_of_home_works() {
+        let cpath = CategorisedPath::from_path("/etc/foo/bar.conf", "/home/user");
+        let patt = cpath.containing_dir_pattern();
+        assert_eq!(patt.path_pattern, "/etc/foo/**");
+    }
+
+    #[test_case(".config/foo/", Some(".config/foo"); "config dir")]
+    #[test_case(".config/foo/bar.toml", Some(".config/foo"); "file in config dir")]
+    #[test_case(".config/foo.toml", None; "file directly in dot config")]
+    #[test_case(".config/", None; "dot config itself")]
+    #[test_case(".foo/", Some(".foo"); "hidden top level dir")]
+    #[test_case(".foo/nested/bar", Some(".foo"); "file in hidden top level dir")]
+    #[test_case(".bashrc", None; "hidden file in home")]
+    #[test_case("Documents/foo.txt", None; "regular file")]
+    #[test]
+    fn dotfile_config_dir_works(path: &str, expected: Option<&str>) {
+        let full_path = format!("/home/user/{path}");
+        let dirname = dotfile_config_dir(&full_path, "/home/user");
+        assert_eq!(dirname.as_deref(), expected);
+    }
+
     #[test_case("/home/user"; "default home")]
     #[test_case("/mnt"; "non standard home short")]
     #[test_case("/non/standard/home/user"; "non standard home long")]
@@ -934,6 +1224,35 @@ mod tests {
                 options, expected,
                 "options with default home_dir={home_dir}"
             );
+
+            for pd in p.pattern_options.iter() {
+                // Directory contents and containing directory patterns have the same shape
+                let expected = match pd.pattern_type {
+                    PatternType::RequestedDirectoryContents => PatternType::ContainingDirectory,
+                    pattern_type => pattern_type,
+                };
+
+                assert_eq!(
+                    PatternType::from_path_pattern(&pd.path_pattern, home_dir),
+                    expected,
+                    "resolved pattern type for {:?} with home_dir={home_dir}",
+                    pd.path_pattern
+                );
+            }
         }
     }
+
+    #[test_case("/home/user/Documents/**/*.md", PatternType::RequestedFile; "nested extension")]
+    #[test_case("/home/user/**/*.{jpg,png}", PatternType::RequestedFile; "extension group")]
+    #[test_case("/home/user/Documents/*.md", PatternType::RequestedFile; "single level glob")]
+    #[test_case("/home/user/Documents", PatternType::RequestedFile; "directory without trailing slash")]
+    #[test_case("/home/user/Documents/notes/", PatternType::RequestedDirectory; "directory")]
+    #[test_case("/home/user/", PatternType::RequestedDirectory; "home directory only")]
+    #[test]
+    fn resolving_custom_pattern_types_works(path_pattern: &str, expected: PatternType) {
+        assert_eq!(
+            PatternType::from_path_pattern(path_pattern, "/home/user"),
+            expected
+        );
+    }
 }
@@ -0,0 +1,350 @@
+//! An offline implementation of the path pattern syntax accepted by snapd for the `home`
+//! interface.
+//!
+//! The grammar supported here mirrors the one documented for snapd's prompting rules:
+//!   - patterns must be absolute (start with `/`)
+//!   - `*` matches zero or more characters within a single path component
+//!   - `**` as a complete path component matches zero or more directories, with a trailing `/**`
+//!     also matching the directory itself
+//!   - `?` matches exactly one character other than `/`
+//!   - `{a,b}` groups (which may be nested) match any one of their comma separated alternatives
+//!   - `\` escapes the following character so that it is matched literally
+//!   - unescaped `[` and `]` are not supported
+//!
+//! This allows us to reject malformed patterns, or patterns that do not apply to the path that was
+//! originally requested, before making the round trip to snapd.
+use regex::Regex;
+
+/// The maximum depth of nested groups that we allow in a pattern.
+const MAX_GROUP_DEPTH: usize = 32;
+
+/// The maximum number of distinct patterns that a pattern containing groups may expand to.
+const MAX_EXPANDED_PATTERNS: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PathPatternError {
+    #[error("pattern must start with '/'")]
+    NotAbsolute,
+
+    #[error("pattern contains unescaped {0:?}")]
+    UnsupportedChar(char),
+
+    #[error("pattern has a trailing unescaped '\\'")]
+    TrailingEscape,
+
+    #[error("pattern contains an unmatched '}}'")]
+    UnmatchedGroupClose,
+
+    #[error("pattern contains an unclosed '{{'")]
+    UnclosedGroup,
+
+    #[error("pattern nests groups more than {MAX_GROUP_DEPTH} levels deep")]
+    GroupsTooDeep,
+
+    #[error("pattern expands to more than {MAX_EXPANDED_PATTERNS} patterns")]
+    TooManyExpansions,
+
+    #[error("pattern does not match the requested path {requested:?}")]
+    DoesNotMatch { requested: String },
+}
+
+/// A validated path pattern that can be checked against concrete paths.
+#[derive(Debug, Clone)]
+pub struct PathPattern {
+    raw: String,
+    re: Regex,
+}
+
+impl PathPattern {
+    /// Parse and validate a path pattern.
+    pub fn parse(raw: &str) -> Result<Self, PathPatternError> {
+        if !raw.starts_with('/') {
+            return Err(PathPatternError::NotAbsolute);
+        }
+
+        let tokens = tokenize(raw)?;
+        let (nodes, _) = parse_nodes(&tokens, 0, 0)?;
+        let expanded = expand(&nodes)?;
+
+        let alternatives: Vec<String> = expanded.iter().map(|toks| to_regex(toks)).collect();
+        let re = Regex::new(&format!("^(?:{})$", alternatives.join("|")))
+            .expect("generated regex to be valid");
+
+        Ok(Self {
+            raw: raw.to_string(),
+            re,
+        })
+    }
+
+    /// Parse a path pattern and check that it matches the given requested path.
+    pub fn parse_for_requested(raw: &str, requested: &str) -> Result<Self, PathPatternError> {
+        let pattern = Self::parse(raw)?;
+        if !pattern.matches(requested) {
+            return Err(PathPatternError::DoesNotMatch {
+                requested: requested.to_string(),
+            });
+        }
+
+        Ok(pattern)
+    }
+
+    /// Whether or not this pattern matches the provided path.
+    pub fn matches(&self, path: &str) -> bool {
+        self.re.is_match(path)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Literal(char),
+    Star,
+    Any,
+    GroupOpen,
+    GroupClose,
+    Comma,
+}
+
+fn tokenize(raw: &str) -> Result<Vec<Token>, PathPatternError> {
+    let mut tokens = Vec::with_capacity(raw.len());
+    let mut chars = raw.chars();
+
+    while let Some(c) = chars.next() {
+        let tok = match c {
+            '\\' => Token::Literal(chars.next().ok_or(PathPatternError::TrailingEscape)?),
+            '[' | ']' => return Err(PathPatternError::UnsupportedChar(c)),
+            '*' => Token::Star,
+            '?' => Token::Any,
+            '{' => Token::GroupOpen,
+            '}' => Token::GroupClose,
+            ',' => Token::Comma,
+            c => Token::Literal(c),
+        };
+        tokens.push(tok);
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Token(Token),
+    Group(Vec<Vec<Node>>),
+}
+
+/// Parse tokens into a sequence of nodes, stopping at the end of the input or at the close of
+/// the group we are currently inside of. Returns the parsed nodes along with the number of tokens
+/// consumed.
+fn parse_nodes(
+    tokens: &[Token],
+    start: usize,
+    depth: usize,
+) -> Result<(Vec<Node>, usize), PathPatternError> {
+    let mut nodes = Vec::new();
+    let mut i = start;
+
+    while i < tokens.len() {
+        match tokens[i] {
+            Token::GroupOpen => {
+                if depth == MAX_GROUP_DEPTH {
+                    return Err(PathPatternError::GroupsTooDeep);
+                }
+                let mut alternatives = Vec::new();
+                i += 1;
+                loop {
+                    let (alt, next) = parse_nodes(tokens, i, depth + 1)?;
+                    alternatives.push(alt);
+                    match tokens.get(next) {
+                        Some(Token::Comma) => i = next + 1,
+                        Some(Token::GroupClose) => {
+                            i = next + 1;
+                            break;
+                        }
+                        _ => return Err(PathPatternError::UnclosedGroup),
+                    }
+                }
+                nodes.push(Node::Group(alternatives));
+                continue;
+            }
+
+            Token::GroupClose | Token::Comma if depth > 0 => return Ok((nodes, i)),
+            Token::GroupClose => return Err(PathPatternError::UnmatchedGroupClose),
+            Token::Comma => nodes.push(Node::Token(Token::Literal(','))),
+            tok => nodes.push(Node::Token(tok)),
+        }
+        i += 1;
+    }
+
+    if depth > 0 {
+        return Err(PathPatternError::UnclosedGroup);
+    }
+
+    Ok((nodes, i))
+}
+
+/// Expand all groups in the parsed pattern to give the full set of group-free patterns it
+/// represents.
+fn expand(nodes: &[Node]) -> Result<Vec<Vec<Token>>, PathPatternError> {
+    let mut expanded = vec![Vec::new()];
+
+    for node in nodes {
+        match node {
+            Node::Token(tok) => expanded.iter_mut().for_each(|e| e.push(*tok)),
+            Node::Group(alternatives) => {
+                let mut suffixes = Vec::new();
+                for alt in alternatives {
+                    suffixes.extend(expand(alt)?);
+                }
+                if expanded.len() * suffixes.len() > MAX_EXPANDED_PATTERNS {
+                    return Err(PathPatternError::TooManyExpansions);
+                }
+                expanded = expanded
+                    .iter()
+                    .flat_map(|prefix| {
+                        suffixes.iter().map(move |suffix| {
+                            let mut e = prefix.clone();
+                            e.extend_from_slice(suffix);
+                            e
+                        })
+                    })
+                    .collect();
+            }
+        }
+    }
+
+    Ok(expanded)
+}
+
+/// Convert a group-free pattern into an (unanchored) regular expression.
+fn to_regex(tokens: &[Token]) -> String {
+    let mut re = String::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        match tokens[i] {
+            Token::Literal('/') => {
+                let n_stars = tokens[i + 1..]
+                    .iter()
+                    .take_while(|t| **t == Token::Star)
+                    .count();
+                let end = i + 1 + n_stars;
+
+                match (n_stars >= 2, tokens.get(end)) {
+                    (true, None) => {
+                        re.push_str("(?:/.*)?");
+                        i = end;
+                    }
+                    (true, Some(Token::Literal('/'))) => {
+                        re.push_str("/(?:.*/)?");
+                        i = end + 1;
+                    }
+                    _ => {
+                        re.push('/');
+                        i += 1;
+                    }
+                }
+                continue;
+            }
+
+            Token::Literal(c) => re.push_str(&regex::escape(&c.to_string())),
+            Token::Any => re.push_str("[^/]"),
+            Token::Star => {
+                while tokens.get(i + 1) == Some(&Token::Star) {
+                    i += 1;
+                }
+                re.push_str("[^/]*");
+            }
+            Token::GroupOpen | Token::GroupClose | Token::Comma => {
+                unreachable!("groups are removed during expansion")
+            }
+        }
+        i += 1;
+    }
+
+    re
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    #[test_case("/home/user/file.txt", "/home/user/file.txt", true; "exact match")]
+    #[test_case("/home/user/file.txt", "/home/user/other.txt", false; "exact mismatch")]
+    #[test_case("/home/user/*.txt", "/home/user/file.txt", true; "star in file name")]
+    #[test_case("/home/user/*", "/home/user/dir/file.txt", false; "star does not cross dirs")]
+    #[test_case("/home/user/**", "/home/user/dir/file.txt", true; "trailing globstar")]
+    #[test_case("/home/user/**", "/home/user", true; "trailing globstar matches dir itself")]
+    #[test_case("/home/user/**", "/home/user/", true; "trailing globstar matches dir with slash")]
+    #[test_case("/home/user/**", "/home/username", false; "trailing globstar respects components")]
+    #[test_case("/home/user/**/*.md", "/home/user/notes.md", true; "inner globstar zero dirs")]
+    #[test_case("/home/user/**/*.md", "/home/user/a/b/notes.md", true; "inner globstar many dirs")]
+    #[test_case("/home/user/**/*.md", "/home/user/a/notes.txt", false; "inner globstar wrong ext")]
+    #[test_case("/home/user/a**", "/home/user/a/b", false; "partial globstar is single star")]
+    #[test_case("/home/user/a**", "/home/user/abc", true; "partial globstar matches component")]
+    #[test_case("/home/user/fil?.txt", "/home/user/file.txt", true; "any char")]
+    #[test_case("/home/user/fil?.txt", "/home/user/fil/.txt", false; "any char is not slash")]
+    #[test_case("/home/user/*.{jpg,png}", "/home/user/a.png", true; "group")]
+    #[test_case("/home/user/*.{jpg,png}", "/home/user/a.gif", false; "group mismatch")]
+    #[test_case("/home/user/{a,b{c,d}}/x", "/home/user/bd/x", true; "nested group")]
+    #[test_case("/home/user/{,foo/}x", "/home/user/x", true; "empty alternative")]
+    #[test_case("/home/user/a,b", "/home/user/a,b", true; "top level comma is literal")]
+    #[test_case(r"/home/user/\*", "/home/user/*", true; "escaped star is literal")]
+    #[test_case(r"/home/user/\*", "/home/user/a", false; "escaped star does not glob")]
+    #[test_case(r"/home/user/\[x\]", "/home/user/[x]", true; "escaped brackets")]
+    #[test_case("/home/user/a.b", "/home/user/aXb", false; "regex chars are literal")]
+    #[test]
+    fn matching_works(pattern: &str, path: &str, expected: bool) {
+        let p = PathPattern::parse(pattern).expect("pattern to be valid");
+        assert_eq!(p.matches(path), expected);
+    }
+
+    #[test_case("", PathPatternError::NotAbsolute; "empty")]
+    #[test_case("home/user/*", PathPatternError::NotAbsolute; "relative")]
+    #[test_case("/home/user/[ab]", PathPatternError::UnsupportedChar('['); "open bracket")]
+    #[test_case("/home/user/a]", PathPatternError::UnsupportedChar(']'); "close bracket")]
+    #[test_case(r"/home/user/a\", PathPatternError::TrailingEscape; "trailing escape")]
+    #[test_case("/home/user/a}", PathPatternError::UnmatchedGroupClose; "unmatched close")]
+    #[test_case("/home/user/{a,b", PathPatternError::UnclosedGroup; "unclosed group")]
+    #[test_case("/home/user/{a,{b}", PathPatternError::UnclosedGroup; "unclosed nested group")]
+    #[test]
+    fn invalid_patterns_error(pattern: &str, expected: PathPatternError) {
+        let err = PathPattern::parse(pattern).unwrap_err();
+        assert_eq!(err, expected);
+    }
+
+    #[test]
+    fn deeply_nested_groups_error() {
+        let depth = MAX_GROUP_DEPTH + 1;
+        let pattern = format!("/{}a{}", "{".repeat(depth), "}".repeat(depth));
+        let err = PathPattern::parse(&pattern).unwrap_err();
+
+        assert_eq!(err, PathPatternError::GroupsTooDeep);
+    }
+
+    #[test]
+    fn too_many_expansions_error() {
+        let pattern = "/{a,b}".repeat(10);
+        let err = PathPattern::parse(&pattern).unwrap_err();
+
+        assert_eq!(err, PathPatternError::TooManyExpansions);
+    }
+
+    #[test_case("/home/user/**", true; "matching")]
+    #[test_case("/home/other/**", false; "not matching")]
+    #[test]
+    fn parse_for_requested_works(pattern: &str, ok: bool) {
+        let res = PathPattern::parse_for_requested(pattern, "/home/user/file.txt");
+
+        match res {
+            Ok(_) if ok => (),
+            Err(PathPatternError::DoesNotMatch { requested }) if !ok => {
+                assert_eq!(requested, "/home/user/file.txt")
+            }
+            res => panic!("unexpected result: {res:?}"),
+        }
+    }
+}
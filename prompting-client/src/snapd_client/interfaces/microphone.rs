@@ -1,19 +1,24 @@
 use crate::{
     prompt_sequence::MatchAttempt,
     protos::{
-        apparmor_prompting::{DevicePermission, MetaData, MicrophonePromptReply},
+        apparmor_prompting::{
+            rule::Constraints as ProtoRuleConstraints, DevicePermission, DeviceRulePermission,
+            MicrophonePromptReply, MicrophoneRuleConstraints as ProtoMicrophoneRuleConstraints,
+        },
         MicrophonePrompt as ProtoMicrophonePrompt,
     },
     snapd_client::{
         interfaces::{
-            ConstraintsFilter, Prompt, PromptReply, ProtoPrompt, ReplyConstraintsOverrides,
-            SnapInterface,
+            map_permission_entry, proto_meta_data, ConstraintsFilter, Prompt, PromptReply,
+            ProtoPrompt, ReplyConstraintsOverrides, SnapInterface,
         },
         prompt::UiInput,
+        rule::PermissionEntry,
         Action, Error, Lifespan, Result, SnapMeta,
     },
 };
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use tonic::Status;
 
 impl Prompt<MicrophoneInterface> {
@@ -53,6 +58,7 @@ impl SnapInterface for MicrophoneInterface {
 
     type Constraints = MicrophoneConstraints;
     type ReplyConstraints = MicrophoneReplyConstraints;
+    type RuleConstraints = MicrophoneRuleConstraints;
 
     type ConstraintsFilter = MicrophoneConstraintsFilter;
     type ReplyConstraintsOverrides = MicrophoneReplyConstraintsOverrides;
@@ -75,48 +81,55 @@ impl SnapInterface for MicrophoneInterface {
     fn ui_input_from_prompt(prompt: Prompt<Self>, meta: Option<SnapMeta>) -> Result<UiInput<Self>> {
         let meta = meta.unwrap_or_else(|| SnapMeta {
             name: prompt.snap,
-            updated_at: String::default(),
-            store_url: String::default(),
-            publisher: String::default(),
-            snap_icon: None,
+            ..Default::default()
         });
 
         Ok(UiInput {
             id: prompt.id,
             meta,
+            process: None,
             data: MicrophoneUiInputData {},
         })
     }
 
     fn proto_prompt_from_ui_input(input: UiInput<Self>) -> Result<ProtoPrompt, Status> {
-        let SnapMeta {
-            name,
-            updated_at,
-            store_url,
-            publisher,
-            snap_icon,
-        } = input.meta;
-
-        let (snap_icon, snap_icon_mime_type) = snap_icon
-            .map(|icon| (icon.bytes.into(), icon.mime_type))
-            .unwrap_or_default();
-
         Ok(ProtoPrompt::MicrophonePrompt(ProtoMicrophonePrompt {
-            meta_data: Some(MetaData {
-                prompt_id: input.id.0,
-                snap_name: name,
-                store_url,
-                publisher,
-                updated_at,
-                snap_icon,
-                snap_icon_mime_type,
-            }),
+            meta_data: Some(proto_meta_data(input.id, input.meta, input.process)),
         }))
     }
 
+    fn proto_rule_constraints(
+        constraints: MicrophoneRuleConstraints,
+    ) -> Result<ProtoRuleConstraints, Status> {
+        let permissions = constraints
+            .permissions
+            .into_iter()
+            .map(|(perm, entry)| {
+                let permission = match perm.as_str() {
+                    "access" => DevicePermission::Access,
+                    _ => {
+                        return Err(Status::internal(format!(
+                            "invalid permission for microphone interface: {perm}"
+                        )))
+                    }
+                };
+
+                Ok(DeviceRulePermission {
+                    permission: permission as i32,
+                    entry: Some(map_permission_entry(entry)),
+                })
+            })
+            .collect::<Result<Vec<_>, Status>>()?;
+
+        Ok(ProtoRuleConstraints::MicrophoneRuleConstraints(
+            ProtoMicrophoneRuleConstraints { permissions },
+        ))
+    }
+
     fn map_proto_reply_constraints(
         &self,
         raw_constraints: MicrophonePromptReply,
+        _prompt: Option<&Prompt<Self>>,
     ) -> Result<MicrophoneReplyConstraints, String> {
         let permissions = raw_constraints
             .permissions
@@ -158,6 +171,29 @@ pub struct MicrophoneReplyConstraints {
     pub(crate) available_permissions: Vec<String>,
 }
 
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MicrophoneRuleConstraints {
+    pub(crate) permissions: BTreeMap<String, PermissionEntry>,
+}
+
+impl MicrophoneRuleConstraints {
+    pub fn new(
+        permissions: impl IntoIterator<Item = (impl Into<String>, PermissionEntry)>,
+    ) -> Self {
+        Self {
+            permissions: permissions
+                .into_iter()
+                .map(|(p, e)| (p.into(), e))
+                .collect(),
+        }
+    }
+
+    pub fn permissions(&self) -> &BTreeMap<String, PermissionEntry> {
+        &self.permissions
+    }
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "kebab-case")]
 pub struct MicrophoneConstraintsFilter {}
@@ -10,29 +10,42 @@
 //! implementations of [SnapInterface] directly.
 use crate::{
     daemon::EnrichedPrompt,
-    prompt_sequence::MatchAttempt,
+    procfs::{ParentProcess, ProcessInfo},
+    prompt_sequence::{MatchAttempt, MatchError},
     protos::apparmor_prompting::{
         self, get_current_prompt_response::Prompt as ProtoPrompt,
-        prompt_reply::PromptReply as ProtoConstraints, PromptReply as ProtoPromptReply,
+        prompt_reply::PromptReply as ProtoConstraints, rule::Constraints as ProtoRuleConstraints,
+        MetaData, ParentProcess as ProtoParentProcess, ProcessInfo as ProtoProcessInfo,
+        PromptReply as ProtoPromptReply, Rule as ProtoRule, RulePermissionEntry,
     },
     snapd_client::{
         self,
         prompt::{Prompt, RawPrompt, UiInput},
-        Action, Cgroup, PromptId, PromptReply, SnapMeta,
+        rule::{PermissionEntry, RawRule, Rule, RuleId},
+        Action, Cgroup, CgroupError, PromptId, PromptReply, SnapCgroup, SnapMeta,
     },
     Error, Result,
 };
+use chrono::{DateTime, Utc};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use std::fmt;
+use std::{collections::BTreeMap, fmt, path::PathBuf};
 use tonic::{Code, Status};
 
 pub mod camera;
+pub mod device;
+pub mod files;
+pub mod generic;
 pub mod home;
 pub mod microphone;
+pub mod removable_media;
 
 use camera::CameraInterface;
+use device::DeviceUiInput;
+use files::{PersonalFilesInterface, SystemFilesInterface};
+use generic::GenericInterface;
 use home::HomeInterface;
 use microphone::MicrophoneInterface;
+use removable_media::RemovableMediaInterface;
 
 #[macro_export]
 macro_rules! map_enum {
@@ -69,6 +82,9 @@ macro_rules! map_enum {
 ///   - The `ProtoPromptReply` type received from the UI is then converted to a `TypedPromptReply`
 ///     before being submitted to Snapd
 ///
+/// Rules stored by Snapd are handled in a similar way: a `RawRule` is converted to a `TypedRule`
+/// based on its interface name and then to a `ProtoRule` for listing in the Flutter UI.
+///
 /// See the documentation on each of the associated types and methods for more details.
 #[allow(async_fn_in_trait)]
 pub trait SnapInterface: fmt::Debug + Sized {
@@ -100,6 +116,12 @@ pub trait SnapInterface: fmt::Debug + Sized {
     /// structure received from Snapd under the `constraints` field of prompts for this interface.
     type ReplyConstraints: fmt::Debug + Clone + Serialize;
 
+    /// Serialization type for the `constraints` field of rules for this interface.
+    ///
+    /// The same structure is used both for rules received from Snapd and for the constraints
+    /// provided when adding or patching rules.
+    type RuleConstraints: fmt::Debug + Clone + Serialize + DeserializeOwned + Send;
+
     /// Serialization type for use with the scripted client in order to match on received prompts
     /// and determine whether or not they should be handled or ignored.
     ///
@@ -131,11 +153,21 @@ pub trait SnapInterface: fmt::Debug + Sized {
     fn proto_prompt_from_ui_input(ui_input: UiInput<Self>) -> Result<ProtoPrompt, Status>;
 
     /// Used to parse the interface specific `constraints` data received from the Flutter UI.
+    ///
+    /// When the prompt being replied to is known it is provided so that the reply can be checked
+    /// against what was originally requested before it is sent to Snapd.
     fn map_proto_reply_constraints(
         &self,
         raw_constraints: Self::UiReplyConstraints,
+        prompt: Option<&Prompt<Self>>,
     ) -> Result<Self::ReplyConstraints, String>;
 
+    /// Maps the constraints of a rule stored by Snapd to the protobuf serialization type required
+    /// for sending data to the Flutter UI.
+    fn proto_rule_constraints(
+        constraints: Self::RuleConstraints,
+    ) -> Result<ProtoRuleConstraints, Status>;
+
     /// Helper method for directly mapping a prompt to a reply.
     ///
     /// This is used by the scripted client along with [ReplyConstraintsOverrides] to generate
@@ -167,6 +199,12 @@ pub trait ReplyConstraintsOverrides:
 
     /// Apply the contained overrides to a given prompt reply.
     fn apply(self, constraints: Self::ReplyConstraints) -> Self::ReplyConstraints;
+
+    /// Check that overridden reply constraints are valid for the prompt being replied to, rather
+    /// than relying on snapd to reject them.
+    fn validate(_constraints: &Self::ReplyConstraints) -> std::result::Result<(), MatchError> {
+        Ok(())
+    }
 }
 
 /// Generic-free counterpart to [Prompt].
@@ -176,6 +214,10 @@ pub enum TypedPrompt {
     Camera(Prompt<CameraInterface>),
     Home(Prompt<HomeInterface>),
     Microphone(Prompt<MicrophoneInterface>),
+    PersonalFiles(Prompt<PersonalFilesInterface>),
+    RemovableMedia(Prompt<RemovableMediaInterface>),
+    SystemFiles(Prompt<SystemFilesInterface>),
+    Generic(Prompt<GenericInterface>),
 }
 
 impl TypedPrompt {
@@ -184,6 +226,39 @@ impl TypedPrompt {
             Self::Camera(p) => CameraInterface::prompt_to_reply(p, Action::Deny).into(),
             Self::Home(p) => HomeInterface::prompt_to_reply(p, Action::Deny).into(),
             Self::Microphone(p) => MicrophoneInterface::prompt_to_reply(p, Action::Deny).into(),
+            Self::PersonalFiles(p) => {
+                PersonalFilesInterface::prompt_to_reply(p, Action::Deny).into()
+            }
+            Self::RemovableMedia(p) => {
+                RemovableMediaInterface::prompt_to_reply(p, Action::Deny).into()
+            }
+            Self::SystemFiles(p) => SystemFilesInterface::prompt_to_reply(p, Action::Deny).into(),
+            Self::Generic(p) => GenericInterface::prompt_to_reply(p, Action::Deny).into(),
+        }
+    }
+
+    pub fn into_deny_for_session(self) -> TypedPromptReply {
+        match self {
+            Self::Camera(p) => CameraInterface::prompt_to_reply(p, Action::Deny)
+                .for_session()
+                .into(),
+            Self::Home(p) => HomeInterface::prompt_to_reply(p, Action::Deny)
+                .for_session()
+                .into(),
+            Self::Microphone(p) => MicrophoneInterface::prompt_to_reply(p, Action::Deny)
+                .for_session()
+                .into(),
+            Self::PersonalFiles(p) => PersonalFilesInterface::prompt_to_reply(p, Action::Deny)
+                .for_session()
+                .into(),
+            Self::RemovableMedia(p) => RemovableMediaInterface::prompt_to_reply(p, Action::Deny)
+                .for_session()
+                .into(),
+            Self::SystemFiles(p) => SystemFilesInterface::prompt_to_reply(p, Action::Deny)
+                .for_session()
+                .into(),
+            // Only single lifespans are supported for interfaces we don't know about
+            Self::Generic(p) => GenericInterface::prompt_to_reply(p, Action::Deny).into(),
         }
     }
 
@@ -192,6 +267,14 @@ impl TypedPrompt {
             Self::Camera(p) => CameraInterface::prompt_to_reply(p, Action::Allow).into(),
             Self::Home(p) => HomeInterface::prompt_to_reply(p, Action::Allow).into(),
             Self::Microphone(p) => MicrophoneInterface::prompt_to_reply(p, Action::Allow).into(),
+            Self::PersonalFiles(p) => {
+                PersonalFilesInterface::prompt_to_reply(p, Action::Allow).into()
+            }
+            Self::RemovableMedia(p) => {
+                RemovableMediaInterface::prompt_to_reply(p, Action::Allow).into()
+            }
+            Self::SystemFiles(p) => SystemFilesInterface::prompt_to_reply(p, Action::Allow).into(),
+            Self::Generic(p) => GenericInterface::prompt_to_reply(p, Action::Allow).into(),
         }
     }
 
@@ -206,6 +289,17 @@ impl TypedPrompt {
             Self::Microphone(p) => MicrophoneInterface::prompt_to_reply(p, Action::Allow)
                 .for_forever()
                 .into(),
+            Self::PersonalFiles(p) => PersonalFilesInterface::prompt_to_reply(p, Action::Allow)
+                .for_forever()
+                .into(),
+            Self::RemovableMedia(p) => RemovableMediaInterface::prompt_to_reply(p, Action::Allow)
+                .for_forever()
+                .into(),
+            Self::SystemFiles(p) => SystemFilesInterface::prompt_to_reply(p, Action::Allow)
+                .for_forever()
+                .into(),
+            // Only single lifespans are supported for interfaces we don't know about
+            Self::Generic(p) => GenericInterface::prompt_to_reply(p, Action::Allow).into(),
         }
     }
 
@@ -214,6 +308,22 @@ impl TypedPrompt {
             Self::Camera(p) => &p.id,
             Self::Home(p) => &p.id,
             Self::Microphone(p) => &p.id,
+            Self::PersonalFiles(p) => &p.id,
+            Self::RemovableMedia(p) => &p.id,
+            Self::SystemFiles(p) => &p.id,
+            Self::Generic(p) => &p.id,
+        }
+    }
+
+    pub fn timestamp(&self) -> &str {
+        match self {
+            Self::Camera(p) => &p.timestamp,
+            Self::Home(p) => &p.timestamp,
+            Self::Microphone(p) => &p.timestamp,
+            Self::PersonalFiles(p) => &p.timestamp,
+            Self::RemovableMedia(p) => &p.timestamp,
+            Self::SystemFiles(p) => &p.timestamp,
+            Self::Generic(p) => &p.timestamp,
         }
     }
 
@@ -222,6 +332,10 @@ impl TypedPrompt {
             Self::Camera(p) => &p.snap,
             Self::Home(p) => &p.snap,
             Self::Microphone(p) => &p.snap,
+            Self::PersonalFiles(p) => &p.snap,
+            Self::RemovableMedia(p) => &p.snap,
+            Self::SystemFiles(p) => &p.snap,
+            Self::Generic(p) => &p.snap,
         }
     }
 
@@ -230,6 +344,10 @@ impl TypedPrompt {
             Self::Camera(p) => p.pid,
             Self::Home(p) => p.pid,
             Self::Microphone(p) => p.pid,
+            Self::PersonalFiles(p) => p.pid,
+            Self::RemovableMedia(p) => p.pid,
+            Self::SystemFiles(p) => p.pid,
+            Self::Generic(p) => p.pid,
         }
     }
 
@@ -238,6 +356,28 @@ impl TypedPrompt {
             Self::Camera(p) => &p.cgroup,
             Self::Home(p) => &p.cgroup,
             Self::Microphone(p) => &p.cgroup,
+            Self::PersonalFiles(p) => &p.cgroup,
+            Self::RemovableMedia(p) => &p.cgroup,
+            Self::SystemFiles(p) => &p.cgroup,
+            Self::Generic(p) => &p.cgroup,
+        }
+    }
+
+    /// The snap identity encoded in the cgroup of the prompting process, checked against the snap
+    /// that snapd reports the prompt as being for.
+    pub fn snap_cgroup(&self) -> Result<SnapCgroup, CgroupError> {
+        self.cgroup().snap_cgroup_for(self.snap())
+    }
+
+    pub fn interface(&self) -> &str {
+        match self {
+            Self::Camera(p) => &p.interface,
+            Self::Home(p) => &p.interface,
+            Self::Microphone(p) => &p.interface,
+            Self::PersonalFiles(p) => &p.interface,
+            Self::RemovableMedia(p) => &p.interface,
+            Self::SystemFiles(p) => &p.interface,
+            Self::Generic(p) => &p.interface,
         }
     }
 }
@@ -255,6 +395,136 @@ impl TryFrom<RawPrompt> for TypedPrompt {
                 MicrophoneInterface::NAME => {
                     Ok(TypedPrompt::Microphone(Prompt::try_from_raw(raw)?))
                 }
+                PersonalFilesInterface::NAME => {
+                    Ok(TypedPrompt::PersonalFiles(Prompt::try_from_raw(raw)?))
+                }
+                RemovableMediaInterface::NAME => {
+                    Ok(TypedPrompt::RemovableMedia(Prompt::try_from_raw(raw)?))
+                }
+                SystemFilesInterface::NAME => {
+                    Ok(TypedPrompt::SystemFiles(Prompt::try_from_raw(raw)?))
+                }
+                _ => Ok(TypedPrompt::Generic(Prompt::generic_from_raw(raw))),
+            }
+        }
+    }
+}
+
+/// Generic-free counterpart to [Rule].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum TypedRule {
+    Camera(Rule<CameraInterface>),
+    Home(Rule<HomeInterface>),
+    Microphone(Rule<MicrophoneInterface>),
+    PersonalFiles(Rule<PersonalFilesInterface>),
+    RemovableMedia(Rule<RemovableMediaInterface>),
+    SystemFiles(Rule<SystemFilesInterface>),
+}
+
+impl TypedRule {
+    pub fn id(&self) -> &RuleId {
+        match self {
+            Self::Camera(r) => &r.id,
+            Self::Home(r) => &r.id,
+            Self::Microphone(r) => &r.id,
+            Self::PersonalFiles(r) => &r.id,
+            Self::RemovableMedia(r) => &r.id,
+            Self::SystemFiles(r) => &r.id,
+        }
+    }
+
+    pub fn snap(&self) -> &str {
+        match self {
+            Self::Camera(r) => &r.snap,
+            Self::Home(r) => &r.snap,
+            Self::Microphone(r) => &r.snap,
+            Self::PersonalFiles(r) => &r.snap,
+            Self::RemovableMedia(r) => &r.snap,
+            Self::SystemFiles(r) => &r.snap,
+        }
+    }
+
+    pub fn interface(&self) -> &str {
+        match self {
+            Self::Camera(r) => &r.interface,
+            Self::Home(r) => &r.interface,
+            Self::Microphone(r) => &r.interface,
+            Self::PersonalFiles(r) => &r.interface,
+            Self::RemovableMedia(r) => &r.interface,
+            Self::SystemFiles(r) => &r.interface,
+        }
+    }
+
+    pub(crate) fn permissions(&self) -> &BTreeMap<String, PermissionEntry> {
+        match self {
+            Self::Camera(r) => &r.constraints.permissions,
+            Self::Home(r) => &r.constraints.permissions,
+            Self::Microphone(r) => &r.constraints.permissions,
+            Self::PersonalFiles(r) => &r.constraints.permissions,
+            Self::RemovableMedia(r) => &r.constraints.permissions,
+            Self::SystemFiles(r) => &r.constraints.permissions,
+        }
+    }
+
+    fn permissions_mut(&mut self) -> &mut BTreeMap<String, PermissionEntry> {
+        match self {
+            Self::Camera(r) => &mut r.constraints.permissions,
+            Self::Home(r) => &mut r.constraints.permissions,
+            Self::Microphone(r) => &mut r.constraints.permissions,
+            Self::PersonalFiles(r) => &mut r.constraints.permissions,
+            Self::RemovableMedia(r) => &mut r.constraints.permissions,
+            Self::SystemFiles(r) => &mut r.constraints.permissions,
+        }
+    }
+
+    /// Remove the given permissions from this rule, returning `None` if no permissions remain.
+    pub fn without_permissions(mut self, permissions: &[String]) -> Option<Self> {
+        let perms = self.permissions_mut();
+        perms.retain(|p, _| !permissions.contains(p));
+
+        if perms.is_empty() {
+            None
+        } else {
+            Some(self)
+        }
+    }
+
+    /// Prepare the constraints of this rule for use in creating a new rule, dropping any
+    /// permissions that have expired as of `now`. Returns `None` if no permissions remain.
+    pub(crate) fn remaining_from(mut self, now: DateTime<Utc>) -> Option<Self> {
+        let perms = self.permissions_mut();
+        *perms = std::mem::take(perms)
+            .into_iter()
+            .filter_map(|(p, e)| Some((p, e.remaining_from(now)?)))
+            .collect();
+
+        if perms.is_empty() {
+            None
+        } else {
+            Some(self)
+        }
+    }
+}
+
+impl TryFrom<RawRule> for TypedRule {
+    type Error = Error;
+
+    fn try_from(raw: RawRule) -> Result<Self> {
+        // SAFETY: we are only deserializing the rule constraints data after checking the value
+        //         of raw.interface is correct for the SnapInterface we are using.
+        unsafe {
+            match raw.interface.as_str() {
+                CameraInterface::NAME => Ok(TypedRule::Camera(Rule::try_from_raw(raw)?)),
+                HomeInterface::NAME => Ok(TypedRule::Home(Rule::try_from_raw(raw)?)),
+                MicrophoneInterface::NAME => Ok(TypedRule::Microphone(Rule::try_from_raw(raw)?)),
+                PersonalFilesInterface::NAME => {
+                    Ok(TypedRule::PersonalFiles(Rule::try_from_raw(raw)?))
+                }
+                RemovableMediaInterface::NAME => {
+                    Ok(TypedRule::RemovableMedia(Rule::try_from_raw(raw)?))
+                }
+                SystemFilesInterface::NAME => Ok(TypedRule::SystemFiles(Rule::try_from_raw(raw)?)),
                 _ => Err(Error::UnsupportedInterface {
                     interface: raw.interface,
                 }),
@@ -263,12 +533,182 @@ impl TryFrom<RawPrompt> for TypedPrompt {
     }
 }
 
+impl TryFrom<TypedRule> for ProtoRule {
+    type Error = Status;
+
+    fn try_from(rule: TypedRule) -> Result<Self, Status> {
+        let (id, timestamp, snap, constraints) = match rule {
+            TypedRule::Camera(r) => (
+                r.id,
+                r.timestamp,
+                r.snap,
+                CameraInterface::proto_rule_constraints(r.constraints)?,
+            ),
+            TypedRule::Home(r) => (
+                r.id,
+                r.timestamp,
+                r.snap,
+                HomeInterface::proto_rule_constraints(r.constraints)?,
+            ),
+            TypedRule::Microphone(r) => (
+                r.id,
+                r.timestamp,
+                r.snap,
+                MicrophoneInterface::proto_rule_constraints(r.constraints)?,
+            ),
+            TypedRule::PersonalFiles(r) => (
+                r.id,
+                r.timestamp,
+                r.snap,
+                PersonalFilesInterface::proto_rule_constraints(r.constraints)?,
+            ),
+            TypedRule::RemovableMedia(r) => (
+                r.id,
+                r.timestamp,
+                r.snap,
+                RemovableMediaInterface::proto_rule_constraints(r.constraints)?,
+            ),
+            TypedRule::SystemFiles(r) => (
+                r.id,
+                r.timestamp,
+                r.snap,
+                SystemFilesInterface::proto_rule_constraints(r.constraints)?,
+            ),
+        };
+
+        Ok(ProtoRule {
+            id: id.0,
+            timestamp,
+            snap,
+            constraints: Some(constraints),
+        })
+    }
+}
+
+/// Maps the interface agnostic portion of a rule's per-permission constraints to its protobuf
+/// serialization type.
+pub(crate) fn map_permission_entry(
+    PermissionEntry {
+        outcome,
+        lifespan,
+        expiration,
+        ..
+    }: PermissionEntry,
+) -> RulePermissionEntry {
+    let outcome = map_enum!(
+        snapd_client::Action => apparmor_prompting::Action;
+        [Allow, Deny];
+        outcome;
+    );
+    let lifespan = map_enum!(
+        snapd_client::Lifespan => apparmor_prompting::RuleLifespan;
+        [Single, Session, Forever, Timespan];
+        lifespan;
+    );
+
+    RulePermissionEntry {
+        outcome: outcome as i32,
+        lifespan: lifespan as i32,
+        expiration: expiration.unwrap_or_default(),
+    }
+}
+
+/// Maps the interface agnostic snap metadata and process details for a prompt to their protobuf
+/// serialization type.
+pub(crate) fn proto_meta_data(
+    id: PromptId,
+    SnapMeta {
+        name,
+        updated_at,
+        store_url,
+        publisher,
+        snap_icon,
+        snap_title,
+        summary,
+        version,
+        channel,
+        revision,
+        confinement,
+        devmode,
+        installed_locally,
+        publisher_validation,
+        app_name,
+        app_title,
+    }: SnapMeta,
+    process: Option<ProcessInfo>,
+) -> MetaData {
+    let (snap_icon, snap_icon_mime_type) = snap_icon
+        .map(|icon| (icon.bytes.into(), icon.mime_type))
+        .unwrap_or_default();
+    let publisher_validation = map_enum!(
+        snapd_client::PublisherValidation => apparmor_prompting::PublisherValidation;
+        [Unproven, Verified, Starred];
+        publisher_validation;
+    );
+
+    MetaData {
+        prompt_id: id.0,
+        snap_name: name,
+        store_url,
+        publisher,
+        updated_at,
+        snap_icon,
+        snap_icon_mime_type,
+        snap_title,
+        summary,
+        version,
+        channel,
+        revision,
+        confinement,
+        devmode,
+        installed_locally,
+        publisher_validation: publisher_validation as i32,
+        app_name,
+        app_title,
+        process: process.map(proto_process_info),
+    }
+}
+
+fn proto_process_info(
+    ProcessInfo {
+        pid,
+        exe,
+        cmdline,
+        start_time,
+        parents,
+    }: ProcessInfo,
+) -> ProtoProcessInfo {
+    let display_path = |p: Option<PathBuf>| p.map(|p| p.display().to_string()).unwrap_or_default();
+
+    ProtoProcessInfo {
+        pid,
+        exe: display_path(exe),
+        cmdline,
+        start_time: start_time.map(|t| t.to_rfc3339()).unwrap_or_default(),
+        parents: parents
+            .into_iter()
+            .map(|ParentProcess { pid, comm, exe }| ProtoParentProcess {
+                pid,
+                comm,
+                exe: display_path(exe),
+            })
+            .collect(),
+    }
+}
+
 /// Generic-free counterpart to [UiInput].
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone)]
 pub enum TypedUiInput {
     Camera(UiInput<CameraInterface>),
     Home(UiInput<HomeInterface>),
     Microphone(UiInput<MicrophoneInterface>),
+    PersonalFiles(UiInput<PersonalFilesInterface>),
+    RemovableMedia(UiInput<RemovableMediaInterface>),
+    SystemFiles(UiInput<SystemFilesInterface>),
+    Generic(UiInput<GenericInterface>),
+    /// A camera and a microphone prompt presented to the user together.
+    Device(DeviceUiInput),
 }
 
 impl TypedUiInput {
@@ -277,6 +717,11 @@ impl TypedUiInput {
             Self::Camera(input) => &input.id,
             Self::Home(input) => &input.id,
             Self::Microphone(input) => &input.id,
+            Self::PersonalFiles(input) => &input.id,
+            Self::RemovableMedia(input) => &input.id,
+            Self::SystemFiles(input) => &input.id,
+            Self::Generic(input) => &input.id,
+            Self::Device(input) => input.id(),
         }
     }
 }
@@ -285,7 +730,8 @@ impl TryFrom<EnrichedPrompt> for TypedUiInput {
     type Error = Error;
 
     fn try_from(ep: EnrichedPrompt) -> Result<Self, Self::Error> {
-        let typed_prompt = match ep.prompt {
+        let process = ep.process.info().cloned();
+        let mut typed_prompt = match ep.prompt {
             TypedPrompt::Camera(p) => {
                 Self::Camera(CameraInterface::ui_input_from_prompt(p, ep.meta)?)
             }
@@ -293,7 +739,32 @@ impl TryFrom<EnrichedPrompt> for TypedUiInput {
             TypedPrompt::Microphone(p) => {
                 Self::Microphone(MicrophoneInterface::ui_input_from_prompt(p, ep.meta)?)
             }
+            TypedPrompt::PersonalFiles(p) => {
+                Self::PersonalFiles(PersonalFilesInterface::ui_input_from_prompt(p, ep.meta)?)
+            }
+            TypedPrompt::RemovableMedia(p) => {
+                Self::RemovableMedia(RemovableMediaInterface::ui_input_from_prompt(p, ep.meta)?)
+            }
+            TypedPrompt::SystemFiles(p) => {
+                Self::SystemFiles(SystemFilesInterface::ui_input_from_prompt(p, ep.meta)?)
+            }
+            TypedPrompt::Generic(p) => {
+                Self::Generic(GenericInterface::ui_input_from_prompt(p, ep.meta)?)
+            }
         };
+        // Details of the prompting process are interface agnostic so they are attached here
+        // rather than by each interface.
+        match &mut typed_prompt {
+            Self::Camera(input) => input.process = process,
+            Self::Home(input) => input.process = process,
+            Self::Microphone(input) => input.process = process,
+            Self::PersonalFiles(input) => input.process = process,
+            Self::RemovableMedia(input) => input.process = process,
+            Self::SystemFiles(input) => input.process = process,
+            Self::Generic(input) => input.process = process,
+            // Device input is only built by the worker by pairing up existing UI input
+            Self::Device(_) => (),
+        }
 
         Ok(typed_prompt)
     }
@@ -309,6 +780,17 @@ impl TryFrom<TypedUiInput> for ProtoPrompt {
             TypedUiInput::Microphone(input) => {
                 MicrophoneInterface::proto_prompt_from_ui_input(input)?
             }
+            TypedUiInput::PersonalFiles(input) => {
+                PersonalFilesInterface::proto_prompt_from_ui_input(input)?
+            }
+            TypedUiInput::RemovableMedia(input) => {
+                RemovableMediaInterface::proto_prompt_from_ui_input(input)?
+            }
+            TypedUiInput::SystemFiles(input) => {
+                SystemFilesInterface::proto_prompt_from_ui_input(input)?
+            }
+            TypedUiInput::Generic(input) => GenericInterface::proto_prompt_from_ui_input(input)?,
+            TypedUiInput::Device(input) => input.proto_prompt(),
         };
 
         Ok(proto)
@@ -322,12 +804,69 @@ pub enum TypedPromptReply {
     Camera(PromptReply<CameraInterface>),
     Home(PromptReply<HomeInterface>),
     Microphone(PromptReply<MicrophoneInterface>),
+    PersonalFiles(PromptReply<PersonalFilesInterface>),
+    RemovableMedia(PromptReply<RemovableMediaInterface>),
+    SystemFiles(PromptReply<SystemFilesInterface>),
+    Generic(PromptReply<GenericInterface>),
+}
+
+impl TypedPromptReply {
+    pub fn action(&self) -> Action {
+        match self {
+            Self::Camera(r) => r.action,
+            Self::Home(r) => r.action,
+            Self::Microphone(r) => r.action,
+            Self::PersonalFiles(r) => r.action,
+            Self::RemovableMedia(r) => r.action,
+            Self::SystemFiles(r) => r.action,
+            Self::Generic(r) => r.action,
+        }
+    }
+
+    pub fn lifespan(&self) -> snapd_client::Lifespan {
+        match self {
+            Self::Camera(r) => r.lifespan,
+            Self::Home(r) => r.lifespan,
+            Self::Microphone(r) => r.lifespan,
+            Self::PersonalFiles(r) => r.lifespan,
+            Self::RemovableMedia(r) => r.lifespan,
+            Self::SystemFiles(r) => r.lifespan,
+            Self::Generic(r) => r.lifespan,
+        }
+    }
 }
 
 impl TryFrom<ProtoPromptReply> for TypedPromptReply {
     type Error = Status;
 
-    fn try_from(mut raw_reply: ProtoPromptReply) -> Result<Self, Status> {
+    fn try_from(raw_reply: ProtoPromptReply) -> Result<Self, Status> {
+        Self::try_from_proto_for_prompt(raw_reply, None)
+    }
+}
+
+impl TypedPromptReply {
+    /// Parse a reply received from the Flutter UI, checking it against the prompt being replied
+    /// to if that is known.
+    pub fn try_from_proto_for_prompt(
+        mut raw_reply: ProtoPromptReply,
+        prompt: Option<&TypedPrompt>,
+    ) -> Result<Self, Status> {
+        macro_rules! prompt_for {
+            ($variant:ident) => {
+                match prompt {
+                    None => None,
+                    Some(TypedPrompt::$variant(p)) => Some(p),
+                    Some(p) => {
+                        return Err(Status::invalid_argument(format!(
+                            "reply does not match the {} interface of prompt {}",
+                            p.interface(),
+                            p.id().0
+                        )))
+                    }
+                }
+            };
+        }
+
         let data = raw_reply.prompt_reply.take().ok_or(Status::new(
             Code::InvalidArgument,
             "recieved empty prompt_reply",
@@ -336,7 +875,7 @@ impl TryFrom<ProtoPromptReply> for TypedPromptReply {
         let reply = match data {
             ProtoConstraints::CameraPromptReply(r) => {
                 let constraints = CameraInterface
-                    .map_proto_reply_constraints(r)
+                    .map_proto_reply_constraints(r, prompt_for!(Camera))
                     .map_err(Status::internal)?;
 
                 TypedPromptReply::Camera(PromptReply {
@@ -356,7 +895,7 @@ impl TryFrom<ProtoPromptReply> for TypedPromptReply {
             }
             ProtoConstraints::HomePromptReply(r) => {
                 let constraints = HomeInterface
-                    .map_proto_reply_constraints(r)
+                    .map_proto_reply_constraints(r, prompt_for!(Home))
                     .map_err(Status::internal)?;
 
                 TypedPromptReply::Home(PromptReply {
@@ -376,7 +915,7 @@ impl TryFrom<ProtoPromptReply> for TypedPromptReply {
             }
             ProtoConstraints::MicrophonePromptReply(r) => {
                 let constraints = MicrophoneInterface
-                    .map_proto_reply_constraints(r)
+                    .map_proto_reply_constraints(r, prompt_for!(Microphone))
                     .map_err(Status::internal)?;
 
                 TypedPromptReply::Microphone(PromptReply {
@@ -394,6 +933,92 @@ impl TryFrom<ProtoPromptReply> for TypedPromptReply {
                     constraints,
                 })
             }
+            ProtoConstraints::PersonalFilesPromptReply(r) => {
+                let constraints = PersonalFilesInterface
+                    .map_proto_reply_constraints(r, prompt_for!(PersonalFiles))
+                    .map_err(Status::internal)?;
+
+                TypedPromptReply::PersonalFiles(PromptReply {
+                    action: map_enum!(
+                        apparmor_prompting::Action => snapd_client::Action;
+                        [Allow, Deny];
+                        raw_reply.action();
+                    ),
+                    lifespan: map_enum!(
+                        apparmor_prompting::Lifespan => snapd_client::Lifespan;
+                        [Single, Session, Forever];
+                        raw_reply.lifespan();
+                    ),
+                    duration: None, // we don't currently use the Timespan variant for `lifespan`
+                    constraints,
+                })
+            }
+            ProtoConstraints::RemovableMediaPromptReply(r) => {
+                let constraints = RemovableMediaInterface
+                    .map_proto_reply_constraints(r, prompt_for!(RemovableMedia))
+                    .map_err(Status::internal)?;
+
+                TypedPromptReply::RemovableMedia(PromptReply {
+                    action: map_enum!(
+                        apparmor_prompting::Action => snapd_client::Action;
+                        [Allow, Deny];
+                        raw_reply.action();
+                    ),
+                    lifespan: map_enum!(
+                        apparmor_prompting::Lifespan => snapd_client::Lifespan;
+                        [Single, Session, Forever];
+                        raw_reply.lifespan();
+                    ),
+                    duration: None, // we don't currently use the Timespan variant for `lifespan`
+                    constraints,
+                })
+            }
+            ProtoConstraints::SystemFilesPromptReply(r) => {
+                let constraints = SystemFilesInterface
+                    .map_proto_reply_constraints(r, prompt_for!(SystemFiles))
+                    .map_err(Status::internal)?;
+
+                TypedPromptReply::SystemFiles(PromptReply {
+                    action: map_enum!(
+                        apparmor_prompting::Action => snapd_client::Action;
+                        [Allow, Deny];
+                        raw_reply.action();
+                    ),
+                    lifespan: map_enum!(
+                        apparmor_prompting::Lifespan => snapd_client::Lifespan;
+                        [Single, Session, Forever];
+                        raw_reply.lifespan();
+                    ),
+                    duration: None, // we don't currently use the Timespan variant for `lifespan`
+                    constraints,
+                })
+            }
+            ProtoConstraints::GenericPromptReply(r) => {
+                if raw_reply.lifespan() != apparmor_prompting::Lifespan::Single {
+                    return Err(Status::invalid_argument(
+                        "only single lifespans are supported for generic prompts",
+                    ));
+                }
+                let constraints = GenericInterface
+                    .map_proto_reply_constraints(r, prompt_for!(Generic))
+                    .map_err(Status::internal)?;
+
+                TypedPromptReply::Generic(PromptReply {
+                    action: map_enum!(
+                        apparmor_prompting::Action => snapd_client::Action;
+                        [Allow, Deny];
+                        raw_reply.action();
+                    ),
+                    lifespan: snapd_client::Lifespan::Single,
+                    duration: None,
+                    constraints,
+                })
+            }
+            ProtoConstraints::DevicePromptReply(_) => {
+                return Err(Status::invalid_argument(
+                    "device prompt replies must be split into camera and microphone replies",
+                ))
+            }
         };
 
         Ok(reply)
@@ -418,6 +1043,30 @@ impl From<PromptReply<MicrophoneInterface>> for TypedPromptReply {
     }
 }
 
+impl From<PromptReply<PersonalFilesInterface>> for TypedPromptReply {
+    fn from(value: PromptReply<PersonalFilesInterface>) -> Self {
+        Self::PersonalFiles(value)
+    }
+}
+
+impl From<PromptReply<RemovableMediaInterface>> for TypedPromptReply {
+    fn from(value: PromptReply<RemovableMediaInterface>) -> Self {
+        Self::RemovableMedia(value)
+    }
+}
+
+impl From<PromptReply<SystemFilesInterface>> for TypedPromptReply {
+    fn from(value: PromptReply<SystemFilesInterface>) -> Self {
+        Self::SystemFiles(value)
+    }
+}
+
+impl From<PromptReply<GenericInterface>> for TypedPromptReply {
+    fn from(value: PromptReply<GenericInterface>) -> Self {
+        Self::Generic(value)
+    }
+}
+
 impl TryFrom<TypedPrompt> for Prompt<CameraInterface> {
     type Error = Error;
 
@@ -456,3 +1105,55 @@ impl TryFrom<TypedPrompt> for Prompt<MicrophoneInterface> {
         }
     }
 }
+
+impl TryFrom<TypedPrompt> for Prompt<PersonalFilesInterface> {
+    type Error = Error;
+
+    fn try_from(typed_prompt: TypedPrompt) -> Result<Self, Self::Error> {
+        match typed_prompt {
+            TypedPrompt::PersonalFiles(p) => Ok(p),
+            _ => Err(Error::PromptConversionError {
+                interface: PersonalFilesInterface::NAME.to_string(),
+            }),
+        }
+    }
+}
+
+impl TryFrom<TypedPrompt> for Prompt<RemovableMediaInterface> {
+    type Error = Error;
+
+    fn try_from(typed_prompt: TypedPrompt) -> Result<Self, Self::Error> {
+        match typed_prompt {
+            TypedPrompt::RemovableMedia(p) => Ok(p),
+            _ => Err(Error::PromptConversionError {
+                interface: RemovableMediaInterface::NAME.to_string(),
+            }),
+        }
+    }
+}
+
+impl TryFrom<TypedPrompt> for Prompt<SystemFilesInterface> {
+    type Error = Error;
+
+    fn try_from(typed_prompt: TypedPrompt) -> Result<Self, Self::Error> {
+        match typed_prompt {
+            TypedPrompt::SystemFiles(p) => Ok(p),
+            _ => Err(Error::PromptConversionError {
+                interface: SystemFilesInterface::NAME.to_string(),
+            }),
+        }
+    }
+}
+
+impl TryFrom<TypedPrompt> for Prompt<GenericInterface> {
+    type Error = Error;
+
+    fn try_from(typed_prompt: TypedPrompt) -> Result<Self, Self::Error> {
+        match typed_prompt {
+            TypedPrompt::Generic(p) => Ok(p),
+            _ => Err(Error::PromptConversionError {
+                interface: GenericInterface::NAME.to_string(),
+            }),
+        }
+    }
+}
@@ -0,0 +1,685 @@
+use crate::{
+    field_matches, map_enum,
+    prompt_sequence::{MatchAttempt, MatchError, MatchFailure},
+    protos::{
+        apparmor_prompting::{
+            home_rule_constraints::HomeRulePermission,
+            removable_media_prompt::{PatternOption, PatternType as ProtoPatternType},
+            rule::Constraints as ProtoRuleConstraints,
+            RemovableMediaPromptReply,
+            RemovableMediaRuleConstraints as ProtoRemovableMediaRuleConstraints,
+        },
+        RemovableMediaPrompt as ProtoRemovableMediaPrompt,
+    },
+    snapd_client::{
+        interfaces::{
+            home::{
+                check_reply_path_pattern, map_permission, map_permissions, map_proto_permissions,
+                path_pattern::PathPattern,
+            },
+            map_permission_entry, proto_meta_data, ConstraintsFilter, Prompt, PromptReply,
+            ProtoPrompt, ReplyConstraintsOverrides, SnapInterface,
+        },
+        prompt::UiInput,
+        rule::PermissionEntry,
+        Action, Error, Lifespan, Result, SnapMeta,
+    },
+    util::serde_option_regex,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+use tonic::Status;
+
+pub mod mounts;
+
+use mounts::{Drive, MountTable};
+
+impl Prompt<RemovableMediaInterface> {
+    pub fn path(&self) -> &str {
+        &self.constraints.path
+    }
+
+    pub fn requested_permissions(&self) -> &[String] {
+        &self.constraints.requested_permissions
+    }
+}
+
+impl PromptReply<RemovableMediaInterface> {
+    /// Attempt to set a custom path pattern to replace the one originally requested in the parent
+    /// [Prompt].
+    ///
+    /// This method will error if the path pattern provided is invalid or does not match the path
+    /// originally requested in the parent [Prompt].
+    pub fn with_custom_path_pattern(mut self, path_pattern: impl Into<String>) -> Result<Self> {
+        let path_pattern = path_pattern.into();
+        PathPattern::parse_for_requested(&path_pattern, &self.constraints.requested_path).map_err(
+            |error| Error::InvalidPathPattern {
+                pattern: path_pattern.clone(),
+                error,
+            },
+        )?;
+
+        self.constraints.path_pattern = path_pattern;
+
+        Ok(self)
+    }
+
+    /// Attempt to set a custom permission set for this reply.
+    ///
+    /// This method will error if the requested permissions are not available on the parent
+    /// [Prompt].
+    pub fn try_with_custom_permissions(mut self, permissions: Vec<String>) -> Result<Self> {
+        if !permissions
+            .iter()
+            .all(|p| self.constraints.available_permissions.contains(p))
+        {
+            return Err(Error::InvalidCustomPermissions {
+                requested: permissions,
+                available: self.constraints.available_permissions,
+            });
+        }
+
+        self.constraints.permissions = permissions;
+
+        Ok(self)
+    }
+}
+
+/// The interface for allowing access to removable drives mounted under `/media`, `/run/media` or
+/// `/mnt`.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RemovableMediaInterface;
+
+#[derive(Debug, PartialEq, Eq)]
+struct PatternOptions {
+    drive: Option<Drive>,
+    initial_pattern_option: usize,
+    pattern_options: Vec<MediaPathPattern>,
+}
+
+impl PatternOptions {
+    /// Build out the UI pattern options based on where the requested path sits on the drive it
+    /// belongs to.
+    ///
+    /// The whole drive is always offered first, followed by the folder on the drive containing the
+    /// request (if the request is not for the root of the drive) and then the requested path
+    /// itself, which is the option selected initially.
+    fn new(path: &str, mounts: &MountTable) -> Self {
+        let is_dir = path.ends_with('/');
+        let drive = Drive::for_path(Path::new(path), mounts);
+        let mut options = Vec::with_capacity(4);
+
+        if let Some(drive) = &drive {
+            options.push(MediaPathPattern::initial(
+                PatternType::Drive,
+                format!("{}/**", drive.mount_point.display()),
+            ));
+        }
+
+        let folder = if is_dir {
+            Some(path.trim_end_matches('/'))
+        } else {
+            path.rsplit_once('/').map(|(dir, _)| dir)
+        };
+        let folder_on_drive = folder.filter(|&f| match &drive {
+            Some(drive) => Path::new(f) != drive.mount_point,
+            None => !f.is_empty(),
+        });
+        if let Some(folder) = folder_on_drive {
+            options.push(MediaPathPattern::initial(
+                PatternType::FolderOnDrive,
+                format!("{folder}/**"),
+            ));
+        }
+
+        let requested_type = if is_dir {
+            PatternType::RequestedDirectory
+        } else {
+            PatternType::RequestedFile
+        };
+        options.push(MediaPathPattern::initial(requested_type, path));
+
+        if let (false, Some(drive)) = (is_dir, &drive) {
+            if let Some(ext) = Path::new(path).extension() {
+                options.push(MediaPathPattern::after_more_options(
+                    PatternType::MatchingFileExtension,
+                    format!(
+                        "{}/**/*.{}",
+                        drive.mount_point.display(),
+                        ext.to_string_lossy()
+                    ),
+                ));
+            }
+        }
+
+        let initial_pattern_option = options
+            .iter()
+            .position(|opt| opt.pattern_type == requested_type)
+            .expect("requested path option to be present");
+
+        Self {
+            drive,
+            initial_pattern_option,
+            pattern_options: options,
+        }
+    }
+}
+
+impl SnapInterface for RemovableMediaInterface {
+    const NAME: &'static str = "removable-media";
+
+    type Constraints = RemovableMediaConstraints;
+    type ReplyConstraints = RemovableMediaReplyConstraints;
+    type RuleConstraints = RemovableMediaRuleConstraints;
+
+    type ConstraintsFilter = RemovableMediaConstraintsFilter;
+    type ReplyConstraintsOverrides = RemovableMediaReplyConstraintsOverrides;
+
+    type UiInputData = RemovableMediaUiInputData;
+    type UiReplyConstraints = RemovableMediaPromptReply;
+
+    fn prompt_to_reply(prompt: Prompt<Self>, action: Action) -> PromptReply<Self> {
+        PromptReply {
+            action,
+            lifespan: Lifespan::Single,
+            duration: None,
+            constraints: RemovableMediaReplyConstraints {
+                requested_path: prompt.constraints.path.clone(),
+                path_pattern: prompt.constraints.path,
+                permissions: prompt.constraints.requested_permissions,
+                available_permissions: prompt.constraints.available_permissions,
+            },
+        }
+    }
+
+    fn ui_input_from_prompt(prompt: Prompt<Self>, meta: Option<SnapMeta>) -> Result<UiInput<Self>> {
+        let PatternOptions {
+            drive,
+            initial_pattern_option,
+            pattern_options,
+        } = PatternOptions::new(&prompt.constraints.path, &MountTable::read());
+        let meta = meta.unwrap_or_else(|| SnapMeta {
+            name: prompt.snap,
+            ..Default::default()
+        });
+        let (mount_point, volume_label) = drive
+            .map(|d| (d.mount_point.display().to_string(), d.label))
+            .unwrap_or_default();
+
+        // As with the home interface, write is elevated to read/write in the suggested
+        // permissions as apps typically go on to read back the files that they write.
+        let mut suggested_permissions = prompt.constraints.requested_permissions.clone();
+        if suggested_permissions == ["write"] {
+            suggested_permissions.push("read".to_string());
+        }
+
+        Ok(UiInput {
+            id: prompt.id,
+            meta,
+            process: None,
+            data: RemovableMediaUiInputData {
+                requested_path: prompt.constraints.path,
+                mount_point,
+                volume_label,
+                requested_permissions: prompt.constraints.requested_permissions,
+                available_permissions: prompt.constraints.available_permissions,
+                suggested_permissions,
+                initial_pattern_option,
+                pattern_options,
+            },
+        })
+    }
+
+    fn proto_prompt_from_ui_input(input: UiInput<Self>) -> Result<ProtoPrompt, Status> {
+        let RemovableMediaUiInputData {
+            requested_path,
+            mount_point,
+            volume_label,
+            requested_permissions,
+            available_permissions,
+            suggested_permissions,
+            initial_pattern_option,
+            pattern_options,
+        } = input.data;
+
+        Ok(ProtoPrompt::RemovableMediaPrompt(
+            ProtoRemovableMediaPrompt {
+                meta_data: Some(proto_meta_data(input.id, input.meta, input.process)),
+                requested_path,
+                mount_point,
+                volume_label,
+                requested_permissions: map_permissions(requested_permissions)?,
+                available_permissions: map_permissions(available_permissions)?,
+                suggested_permissions: map_permissions(suggested_permissions)?,
+                initial_pattern_option: initial_pattern_option as i32,
+                pattern_options: pattern_options
+                    .into_iter()
+                    .map(map_pattern_option)
+                    .collect(),
+            },
+        ))
+    }
+
+    fn proto_rule_constraints(
+        constraints: RemovableMediaRuleConstraints,
+    ) -> Result<ProtoRuleConstraints, Status> {
+        let permissions = constraints
+            .permissions
+            .into_iter()
+            .map(|(perm, entry)| {
+                Ok(HomeRulePermission {
+                    permission: map_permission(&perm)?,
+                    entry: Some(map_permission_entry(entry)),
+                })
+            })
+            .collect::<Result<Vec<_>, Status>>()?;
+
+        Ok(ProtoRuleConstraints::RemovableMediaRuleConstraints(
+            ProtoRemovableMediaRuleConstraints {
+                path_pattern: constraints.path_pattern,
+                permissions,
+            },
+        ))
+    }
+
+    fn map_proto_reply_constraints(
+        &self,
+        raw_constraints: RemovableMediaPromptReply,
+        prompt: Option<&Prompt<Self>>,
+    ) -> Result<RemovableMediaReplyConstraints, String> {
+        let permissions = map_proto_permissions(raw_constraints.permissions)?;
+        let requested_path = prompt.map(|p| p.path());
+        check_reply_path_pattern(&raw_constraints.path_pattern, requested_path)?;
+
+        Ok(RemovableMediaReplyConstraints {
+            path_pattern: raw_constraints.path_pattern,
+            permissions,
+            available_permissions: Vec::new(),
+            requested_path: requested_path.unwrap_or_default().to_string(),
+        })
+    }
+}
+
+fn map_pattern_option(
+    MediaPathPattern {
+        pattern_type,
+        path_pattern,
+        show_initially,
+    }: MediaPathPattern,
+) -> PatternOption {
+    PatternOption {
+        pattern_type: ProtoPatternType::from(pattern_type) as i32,
+        path_pattern,
+        show_initially,
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RemovableMediaConstraints {
+    pub(crate) path: String,
+    pub(crate) requested_permissions: Vec<String>,
+    pub(crate) available_permissions: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemovableMediaUiInputData {
+    pub(crate) requested_path: String,
+    pub(crate) mount_point: String,
+    pub(crate) volume_label: String,
+    pub(crate) requested_permissions: Vec<String>,
+    pub(crate) available_permissions: Vec<String>,
+    pub(crate) suggested_permissions: Vec<String>,
+    pub(crate) initial_pattern_option: usize,
+    pub(crate) pattern_options: Vec<MediaPathPattern>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaPathPattern {
+    pub(crate) pattern_type: PatternType,
+    pub(crate) path_pattern: String,
+    pub(crate) show_initially: bool,
+}
+
+impl MediaPathPattern {
+    fn initial(pattern_type: PatternType, path_pattern: impl Into<String>) -> Self {
+        Self {
+            pattern_type,
+            path_pattern: path_pattern.into(),
+            show_initially: true,
+        }
+    }
+
+    fn after_more_options(pattern_type: PatternType, path_pattern: impl Into<String>) -> Self {
+        Self {
+            pattern_type,
+            path_pattern: path_pattern.into(),
+            show_initially: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PatternType {
+    /// Everything on the drive containing the requested path.
+    Drive,
+    /// Everything within the folder on the drive that contains (or is) the requested path.
+    FolderOnDrive,
+    RequestedDirectory,
+    RequestedFile,
+    MatchingFileExtension,
+}
+
+impl From<PatternType> for ProtoPatternType {
+    fn from(pattern_type: PatternType) -> Self {
+        map_enum!(
+            PatternType => ProtoPatternType;
+            [
+                Drive, FolderOnDrive, RequestedDirectory, RequestedFile, MatchingFileExtension
+            ];
+            pattern_type;
+        )
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RemovableMediaReplyConstraints {
+    pub(crate) path_pattern: String,
+    pub(crate) permissions: Vec<String>,
+    #[serde(skip)]
+    pub(crate) available_permissions: Vec<String>,
+    #[serde(skip)]
+    pub(crate) requested_path: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RemovableMediaRuleConstraints {
+    pub(crate) path_pattern: String,
+    pub(crate) permissions: BTreeMap<String, PermissionEntry>,
+}
+
+impl RemovableMediaRuleConstraints {
+    pub fn new(
+        path_pattern: impl Into<String>,
+        permissions: impl IntoIterator<Item = (impl Into<String>, PermissionEntry)>,
+    ) -> Self {
+        Self {
+            path_pattern: path_pattern.into(),
+            permissions: permissions
+                .into_iter()
+                .map(|(p, e)| (p.into(), e))
+                .collect(),
+        }
+    }
+
+    pub fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    pub fn permissions(&self) -> &BTreeMap<String, PermissionEntry> {
+        &self.permissions
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct RemovableMediaConstraintsFilter {
+    #[serde(with = "serde_option_regex", default)]
+    pub path: Option<Regex>,
+    pub requested_permissions: Option<Vec<String>>,
+    pub available_permissions: Option<Vec<String>>,
+}
+
+impl ConstraintsFilter for RemovableMediaConstraintsFilter {
+    type Constraints = RemovableMediaConstraints;
+
+    fn matches(&self, constraints: &Self::Constraints) -> MatchAttempt {
+        let mut failures = Vec::new();
+
+        if let Some(re) = &self.path {
+            if !re.is_match(&constraints.path) {
+                failures.push(MatchFailure {
+                    field: "path",
+                    expected: format!("{:?}", re.to_string()),
+                    seen: format!("{:?}", constraints.path),
+                });
+            }
+        }
+
+        field_matches!(self, constraints, failures, requested_permissions);
+        field_matches!(self, constraints, failures, available_permissions);
+
+        if failures.is_empty() {
+            MatchAttempt::Success
+        } else {
+            MatchAttempt::Failure(failures)
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct RemovableMediaReplyConstraintsOverrides {
+    pub path_pattern: Option<String>,
+    pub permissions: Option<Vec<String>>,
+}
+
+impl ReplyConstraintsOverrides for RemovableMediaReplyConstraintsOverrides {
+    type ReplyConstraints = RemovableMediaReplyConstraints;
+
+    fn apply(self, mut constraints: Self::ReplyConstraints) -> Self::ReplyConstraints {
+        if let Some(path_pattern) = self.path_pattern {
+            constraints.path_pattern = path_pattern;
+        }
+        if let Some(permissions) = self.permissions {
+            constraints.permissions = permissions;
+        }
+
+        constraints
+    }
+
+    fn validate(constraints: &Self::ReplyConstraints) -> std::result::Result<(), MatchError> {
+        PathPattern::parse_for_requested(&constraints.path_pattern, &constraints.requested_path)
+            .map_err(|error| MatchError::InvalidPathPattern {
+                pattern: constraints.path_pattern.clone(),
+                error,
+            })?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        protos::apparmor_prompting::HomePermission,
+        snapd_client::{RawPrompt, TypedPrompt},
+    };
+    use simple_test_case::test_case;
+    use PatternType::*;
+
+    const REMOVABLE_MEDIA_PROMPT: &str = r#"{
+      "id": "C7OUCCDWCE6CC===",
+      "timestamp": "2024-06-28T19:15:37.321782305Z",
+      "snap": "vlc",
+      "pid": 1234,
+      "cgroup": "/user.slice/user-1000.slice/user@1000.service/app.slice/myapp.scope",
+      "interface": "removable-media",
+      "constraints": {
+        "path": "/media/ubuntu/My Photos/2024/beach.jpg",
+        "requested-permissions": [
+          "read"
+        ],
+        "available-permissions": [
+          "read",
+          "write",
+          "execute"
+        ]
+      }
+    }"#;
+
+    const MOUNTS: &str = "\
+/dev/nvme0n1p2 / ext4 rw,relatime 0 0
+/dev/sda1 /media/ubuntu/My\\040Photos vfat rw,nosuid,nodev 0 0
+";
+
+    #[test]
+    fn deserializing_a_removable_media_prompt_works() {
+        let raw: RawPrompt = serde_json::from_str(REMOVABLE_MEDIA_PROMPT).unwrap();
+        assert_eq!(raw.interface, "removable-media");
+
+        let p: TypedPrompt = raw.try_into().unwrap();
+        match p {
+            TypedPrompt::RemovableMedia(p) => {
+                assert_eq!(p.path(), "/media/ubuntu/My Photos/2024/beach.jpg");
+            }
+            p => panic!("expected removable media prompt, got {p:?}"),
+        }
+    }
+
+    #[test_case(
+        "/media/ubuntu/My Photos/2024/beach.jpg",
+        &[
+            (Drive, "/media/ubuntu/My Photos/**"),
+            (FolderOnDrive, "/media/ubuntu/My Photos/2024/**"),
+            (RequestedFile, "/media/ubuntu/My Photos/2024/beach.jpg"),
+            (MatchingFileExtension, "/media/ubuntu/My Photos/**/*.jpg"),
+        ],
+        2;
+        "file in folder on drive"
+    )]
+    #[test_case(
+        "/media/ubuntu/My Photos/notes",
+        &[
+            (Drive, "/media/ubuntu/My Photos/**"),
+            (RequestedFile, "/media/ubuntu/My Photos/notes"),
+        ],
+        1;
+        "file without extension at root of drive"
+    )]
+    #[test_case(
+        "/media/ubuntu/My Photos/2024/",
+        &[
+            (Drive, "/media/ubuntu/My Photos/**"),
+            (FolderOnDrive, "/media/ubuntu/My Photos/2024/**"),
+            (RequestedDirectory, "/media/ubuntu/My Photos/2024/"),
+        ],
+        2;
+        "folder on drive"
+    )]
+    #[test_case(
+        "/media/ubuntu/My Photos/",
+        &[
+            (Drive, "/media/ubuntu/My Photos/**"),
+            (RequestedDirectory, "/media/ubuntu/My Photos/"),
+        ],
+        1;
+        "root of drive"
+    )]
+    #[test_case(
+        "/run/media/ubuntu/BACKUP/docs/",
+        &[
+            (Drive, "/run/media/ubuntu/BACKUP/**"),
+            (FolderOnDrive, "/run/media/ubuntu/BACKUP/docs/**"),
+            (RequestedDirectory, "/run/media/ubuntu/BACKUP/docs/"),
+        ],
+        2;
+        "run media not in mount table"
+    )]
+    #[test_case(
+        "/srv/data/file.txt",
+        &[
+            (FolderOnDrive, "/srv/data/**"),
+            (RequestedFile, "/srv/data/file.txt"),
+        ],
+        1;
+        "not on a drive"
+    )]
+    #[test]
+    fn pattern_options_are_tailored_to_the_drive(
+        path: &str,
+        expected: &[(PatternType, &str)],
+        expected_initial: usize,
+    ) {
+        let opts = PatternOptions::new(path, &MountTable::parse(MOUNTS));
+        let options: Vec<_> = opts
+            .pattern_options
+            .iter()
+            .map(|o| (o.pattern_type, o.path_pattern.as_str()))
+            .collect();
+
+        assert_eq!(options, expected);
+        assert_eq!(opts.initial_pattern_option, expected_initial);
+        for opt in opts.pattern_options.iter() {
+            let res = PathPattern::parse_for_requested(&opt.path_pattern, path);
+            assert!(res.is_ok(), "{opt:?} does not match {path}: {res:?}");
+        }
+    }
+
+    #[test]
+    fn volume_label_is_taken_from_the_mount_table() {
+        let opts = PatternOptions::new(
+            "/media/ubuntu/My Photos/2024/beach.jpg",
+            &MountTable::parse(MOUNTS),
+        );
+
+        assert_eq!(opts.drive.map(|d| d.label).as_deref(), Some("My Photos"));
+    }
+
+    #[test_case("/media/ubuntu/My Photos/**", true; "drive pattern")]
+    #[test_case("/media/ubuntu/Other/**", false; "different drive")]
+    #[test]
+    fn reply_overrides_are_checked_against_the_requested_path(pattern: &str, ok: bool) {
+        let raw: RawPrompt = serde_json::from_str(REMOVABLE_MEDIA_PROMPT).unwrap();
+        let p = match TypedPrompt::try_from(raw).unwrap() {
+            TypedPrompt::RemovableMedia(p) => p,
+            p => panic!("expected removable media prompt, got {p:?}"),
+        };
+        let reply = RemovableMediaInterface::prompt_to_reply(p, Action::Allow);
+        let overrides = RemovableMediaReplyConstraintsOverrides {
+            path_pattern: Some(pattern.to_string()),
+            permissions: None,
+        };
+
+        let constraints = overrides.apply(reply.constraints);
+        let res = RemovableMediaReplyConstraintsOverrides::validate(&constraints);
+
+        assert_eq!(res.is_ok(), ok);
+    }
+
+    #[test_case("/media/ubuntu/My Photos/**", false, true; "well formed pattern")]
+    #[test_case("media/ubuntu/**", false, false; "malformed pattern")]
+    #[test_case("/media/ubuntu/My Photos/**", true, true; "matching pattern")]
+    #[test_case("/media/ubuntu/Other/**", true, false; "non-matching pattern")]
+    #[test]
+    fn ui_reply_path_pattern_is_checked_against_requested_path(
+        path_pattern: &str,
+        with_prompt: bool,
+        ok: bool,
+    ) {
+        let raw: RawPrompt = serde_json::from_str(REMOVABLE_MEDIA_PROMPT).unwrap();
+        let p = match TypedPrompt::try_from(raw).unwrap() {
+            TypedPrompt::RemovableMedia(p) => p,
+            p => panic!("expected removable media prompt, got {p:?}"),
+        };
+        let raw_reply = RemovableMediaPromptReply {
+            path_pattern: path_pattern.to_string(),
+            permissions: vec![HomePermission::Read as i32],
+        };
+
+        let res = RemovableMediaInterface
+            .map_proto_reply_constraints(raw_reply, with_prompt.then_some(&p));
+
+        assert_eq!(res.is_ok(), ok, "{res:?}");
+        if let Ok(constraints) = res {
+            let expected = if with_prompt { p.path() } else { "" };
+            assert_eq!(constraints.requested_path, expected);
+        }
+    }
+}
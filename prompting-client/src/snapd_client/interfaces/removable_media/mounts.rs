@@ -0,0 +1,190 @@
+//! Identifying the drive that a removable media path lives on using the mount table.
+//!
+//! Removable drives are mounted under one of a small number of well known locations:
+//!   - `/media/$USER/<label>` when mounted by udisks on most distributions
+//!   - `/run/media/$USER/<label>` when udisks is configured to use `/run`
+//!   - `/mnt/<name>` for drives mounted manually
+//!
+//! When the mount table has an entry covering the requested path we use it, falling back to the
+//! shape of the path otherwise so that we are still able to offer sensible options to the user.
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+use tracing::warn;
+
+const MOUNTS_PATH: &str = "/proc/self/mounts";
+
+/// The directories that removable media is mounted under along with the number of path components
+/// beneath them that make up the mount point of a drive.
+const MEDIA_ROOTS: [(&str, usize); 3] = [("/media", 2), ("/run/media", 2), ("/mnt", 1)];
+
+/// A single entry from the mount table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    pub source: String,
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+}
+
+/// The parsed contents of a mount table in the format used by `/proc/self/mounts`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MountTable {
+    mounts: Vec<Mount>,
+}
+
+impl MountTable {
+    /// Read the mount table for the current process, returning an empty table if it is
+    /// unavailable.
+    pub fn read() -> Self {
+        match fs::read_to_string(MOUNTS_PATH) {
+            Ok(raw) => Self::parse(&raw),
+            Err(e) => {
+                warn!("unable to read mount table: {e}");
+                Self::default()
+            }
+        }
+    }
+
+    /// See fstab(5) for the format: whitespace within fields is octal escaped.
+    pub fn parse(raw: &str) -> Self {
+        let mounts = raw
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace().map(unescape);
+                Some(Mount {
+                    source: fields.next()?,
+                    mount_point: fields.next()?.into(),
+                    fs_type: fields.next()?,
+                })
+            })
+            .collect();
+
+        Self { mounts }
+    }
+
+    /// The most specific removable media mount that contains the given path.
+    fn mount_for(&self, path: &Path) -> Option<&Mount> {
+        self.mounts
+            .iter()
+            .filter(|m| path.starts_with(&m.mount_point) && is_media_mount(&m.mount_point))
+            .max_by_key(|m| m.mount_point.components().count())
+    }
+}
+
+/// Decode the `\ooo` octal escapes used for whitespace and backslashes in mount table fields.
+fn unescape(field: &str) -> String {
+    let mut out = Vec::with_capacity(field.len());
+    let mut bytes = field.as_bytes();
+
+    while let Some((&b, rest)) = bytes.split_first() {
+        let escaped = rest
+            .get(..3)
+            .filter(|digits| digits.iter().all(|d| (b'0'..=b'7').contains(d)))
+            .and_then(|digits| {
+                let n = digits.iter().fold(0u32, |n, d| n * 8 + u32::from(d - b'0'));
+                u8::try_from(n).ok()
+            });
+
+        match (b, escaped) {
+            (b'\\', Some(c)) => {
+                out.push(c);
+                bytes = &rest[3..];
+            }
+            _ => {
+                out.push(b);
+                bytes = rest;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn is_media_mount(mount_point: &Path) -> bool {
+    MEDIA_ROOTS.iter().any(|(root, depth)| {
+        mount_point
+            .strip_prefix(root)
+            .is_ok_and(|rest| rest.components().count() >= *depth)
+    })
+}
+
+/// A mounted removable drive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Drive {
+    pub mount_point: PathBuf,
+    pub label: String,
+}
+
+impl Drive {
+    /// Locate the drive containing the given path, returning `None` if the path is not within
+    /// one of the locations that removable media is mounted under.
+    pub fn for_path(path: &Path, mounts: &MountTable) -> Option<Self> {
+        let mount_point = match mounts.mount_for(path) {
+            Some(m) => m.mount_point.clone(),
+            None => MEDIA_ROOTS.iter().find_map(|(root, depth)| {
+                let rest = path.strip_prefix(root).ok()?;
+                let segments: Vec<_> = rest
+                    .components()
+                    .take_while(|c| matches!(c, Component::Normal(_)))
+                    .take(*depth)
+                    .collect();
+
+                (segments.len() == *depth)
+                    .then(|| Path::new(root).join(segments.iter().collect::<PathBuf>()))
+            })?,
+        };
+        // udisks names mount points after the volume label so this is the best name we have
+        let label = mount_point.file_name()?.to_string_lossy().into_owned();
+
+        Some(Self { mount_point, label })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    const MOUNTS: &str = "\
+/dev/nvme0n1p2 / ext4 rw,relatime 0 0
+/dev/sda1 /media/ubuntu/My\\040Photos vfat rw,nosuid,nodev 0 0
+/dev/sdb1 /run/media/ubuntu/BACKUP exfat rw,nosuid,nodev 0 0
+/dev/sdc1 /mnt/usb ext4 rw 0 0
+tmpfs /media tmpfs rw 0 0
+";
+
+    #[test]
+    fn mount_table_entries_are_unescaped() {
+        let table = MountTable::parse(MOUNTS);
+
+        assert_eq!(
+            table.mounts[1],
+            Mount {
+                source: "/dev/sda1".to_string(),
+                mount_point: "/media/ubuntu/My Photos".into(),
+                fs_type: "vfat".to_string(),
+            }
+        );
+    }
+
+    #[test_case("/media/ubuntu/My Photos/2024/beach.jpg", Some(("/media/ubuntu/My Photos", "My Photos")); "media mount with space")]
+    #[test_case("/run/media/ubuntu/BACKUP/", Some(("/run/media/ubuntu/BACKUP", "BACKUP")); "run media mount point")]
+    #[test_case("/mnt/usb/notes.txt", Some(("/mnt/usb", "usb")); "mnt")]
+    #[test_case("/media/ubuntu/OTHER/a/b.txt", Some(("/media/ubuntu/OTHER", "OTHER")); "not in mount table")]
+    #[test_case("/media/ubuntu/", None; "user media dir")]
+    #[test_case("/home/ubuntu/notes.txt", None; "not removable media")]
+    #[test]
+    fn drives_are_identified(path: &str, expected: Option<(&str, &str)>) {
+        let table = MountTable::parse(MOUNTS);
+        let drive = Drive::for_path(Path::new(path), &table);
+
+        assert_eq!(
+            drive,
+            expected.map(|(mount_point, label)| Drive {
+                mount_point: mount_point.into(),
+                label: label.to_string()
+            })
+        );
+    }
+}
@@ -3,36 +3,63 @@
 use crate::{
     exit_with,
     snapd_client::{
+        interfaces::SnapInterface,
         prompt::RawPrompt,
         response::{parse_raw_response, parse_response},
+        rule::{AddRule, PatchPermissions, PatchRule, RawRule, RuleAction},
     },
-    socket_client::UnixSocketClient,
+    socket_client::{UnixSocketClient, REQUEST_TIMEOUT},
     Error, ExitStatus, Result,
 };
 use chrono::{DateTime, SecondsFormat, Utc};
 use hyper::{body::Bytes, Uri};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use std::{collections::HashMap, env, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    future::Future,
+    path::Path,
+    str::FromStr,
+    time::Duration,
+};
 use tokio::net::UnixStream;
 use tracing::{debug, error, info, warn};
 
+pub mod cassette;
+mod cgroup;
+pub mod fake;
 pub mod interfaces;
 mod prompt;
 mod response;
+mod rule;
 
-pub use interfaces::{TypedPrompt, TypedPromptReply, TypedUiInput};
+pub use cassette::{Interaction, RecordingClient, ReplayClient};
+pub use cgroup::{CgroupError, SnapCgroup, SnapProcess, UnitKind};
+pub use fake::{FakeSnapd, FakeSnapdBuilder};
+pub use interfaces::{TypedPrompt, TypedPromptReply, TypedRule, TypedUiInput};
 pub use prompt::{Action, Lifespan, Prompt, PromptReply, UiInput};
 pub use response::{RuleConflict, SnapdError};
+pub use rule::{PermissionEntry, Rule, RuleId};
 
 const FEATURE_NAME: &str = "apparmor-prompting";
-const LONG_POLL_TIMEOUT: &str = "1h";
+/// Kept short enough that a connection which has silently gone away is noticed quickly.
+const LONG_POLL_TIMEOUT: Duration = Duration::from_secs(5 * 60);
 const NOTICE_TYPES: &str = "interfaces-requests-prompt";
+const RULES_PATH: &str = "interfaces/requests/rules";
+/// Characters that need escaping in query parameter values: everything other than the unreserved
+/// characters from RFC 3986.
+const QUERY_VALUE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
 const SNAPD_BASE_URI: &str = "http://localhost/v2";
 const SNAPD_SOCKET: &str = "/run/snapd.socket";
 const SNAPD_SNAP_SOCKET: &str = "/run/snapd-snap.socket";
 const SNAPD_ABSTRACT_SNAP_SOCKET: &str = "\0/snapd/snapd-snap.socket";
 
-#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct PromptId(pub String);
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize, Hash)]
@@ -58,18 +85,40 @@ pub enum PromptNotice {
 
 /// Abstraction layer to make swapping out the underlying client possible for
 /// testing.
-#[allow(async_fn_in_trait)]
-pub trait Client {
-    async fn get_json<T>(&self, path: &str) -> Result<T>
+///
+/// The returned futures are required to be `Send` so that anything generic over the client (such
+/// as the [PollLoop][crate::daemon::PollLoop]) can be run as its own task.
+pub trait Client: Send + Sync {
+    fn get_json<T>(&self, path: &str) -> impl Future<Output = Result<T>> + Send
     where
         T: DeserializeOwned;
 
-    async fn post_json<T, U>(&self, path: &str, body: U) -> Result<T>
+    fn get_json_with_timeout<T>(
+        &self,
+        path: &str,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<T>> + Send
+    where
+        T: DeserializeOwned;
+
+    fn post_json<T, U>(&self, path: &str, body: U) -> impl Future<Output = Result<T>> + Send
     where
         T: DeserializeOwned,
-        U: Serialize;
+        U: Serialize + Send;
+
+    fn get_raw(&self, path: &str) -> impl Future<Output = Result<(Bytes, String)>> + Send;
+
+    /// Check whether or not snapd is currently reachable.
+    fn is_available(&self) -> impl Future<Output = bool> + Send;
+}
+
+fn snapd_uri(path: &str) -> Result<Uri> {
+    let s = format!("{SNAPD_BASE_URI}/{path}");
 
-    async fn get_raw(&self, path: &str) -> Result<(Bytes, String)>;
+    Uri::from_str(&s).map_err(|_| Error::InvalidUri {
+        reason: "malformed",
+        uri: s,
+    })
 }
 
 impl Client for UnixSocketClient {
@@ -77,13 +126,16 @@ impl Client for UnixSocketClient {
     where
         T: DeserializeOwned,
     {
-        let s = format!("{SNAPD_BASE_URI}/{path}");
-        let uri = Uri::from_str(&s).map_err(|_| Error::InvalidUri {
-            reason: "malformed",
-            uri: s,
-        })?;
+        self.get_json_with_timeout(path, REQUEST_TIMEOUT).await
+    }
 
-        let res = self.get(uri).await?;
+    async fn get_json_with_timeout<T>(&self, path: &str, timeout: Duration) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let uri = snapd_uri(path)?;
+
+        let res = self.get_with_timeout(uri, timeout).await?;
 
         parse_response(res).await
     }
@@ -91,13 +143,9 @@ impl Client for UnixSocketClient {
     async fn post_json<T, U>(&self, path: &str, body: U) -> Result<T>
     where
         T: DeserializeOwned,
-        U: Serialize,
+        U: Serialize + Send,
     {
-        let s = format!("{SNAPD_BASE_URI}/{path}");
-        let uri = Uri::from_str(&s).map_err(|_| Error::InvalidUri {
-            reason: "malformed",
-            uri: s,
-        })?;
+        let uri = snapd_uri(path)?;
 
         let res = self
             .post(uri, "application/json", serde_json::to_vec(&body)?)
@@ -107,16 +155,16 @@ impl Client for UnixSocketClient {
     }
 
     async fn get_raw(&self, path: &str) -> Result<(Bytes, String)> {
-        let s = format!("{SNAPD_BASE_URI}/{path}");
-        let uri = Uri::from_str(&s).map_err(|_| Error::InvalidUri {
-            reason: "malformed",
-            uri: s,
-        })?;
+        let uri = snapd_uri(path)?;
 
         let res = self.get(uri).await?;
 
         parse_raw_response(res).await
     }
+
+    async fn is_available(&self) -> bool {
+        UnixSocketClient::is_available(self).await
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -177,12 +225,40 @@ impl SnapdSocketClient {
             notices_after: dt.to_rfc3339_opts(SecondsFormat::Nanos, true),
         }
     }
+
+    /// Record all requests made by this client to the cassette at the given path so that they can
+    /// be replayed later using a [ReplayClient].
+    pub fn record_to(self, path: impl AsRef<Path>) -> Result<SnapdClient<RecordingClient>> {
+        Ok(SnapdClient {
+            client: RecordingClient::try_new(self.client, path)?,
+            notices_after: self.notices_after,
+        })
+    }
 }
 
 impl<C> SnapdClient<C>
 where
     C: Client,
 {
+    /// Create a new client that makes its requests using the provided [Client] implementation,
+    /// only returning notices that occurred after the given timestamp.
+    pub fn new_with_client(client: C, notices_after: DateTime<Utc>) -> Self {
+        Self {
+            client,
+            notices_after: notices_after.to_rfc3339_opts(SecondsFormat::Nanos, true),
+        }
+    }
+
+    /// The `after` timestamp that will be used for the next call to the notices API.
+    pub fn notices_after(&self) -> &str {
+        &self.notices_after
+    }
+
+    /// Check whether or not snapd is currently reachable.
+    pub async fn is_available(&self) -> bool {
+        self.client.is_available().await
+    }
+
     /// Check whether or not the apparmor-prompting feature is enabled on this system
     pub async fn is_prompting_enabled(&self) -> Result<bool> {
         let info: SysInfo = self.client.get_json("system-info").await?;
@@ -214,11 +290,17 @@ where
     /// been actioned and we should clear any internal state we have associated with that ID.
     pub async fn pending_prompt_notices(&mut self) -> Result<Vec<PromptNotice>> {
         let path = format!(
-            "notices?types={NOTICE_TYPES}&timeout={LONG_POLL_TIMEOUT}&after={}",
+            "notices?types={NOTICE_TYPES}&timeout={}s&after={}",
+            LONG_POLL_TIMEOUT.as_secs(),
             self.notices_after
         );
 
-        let raw_notices: Vec<Notice> = self.client.get_json(&path).await?;
+        // Allow snapd some leeway to reply once the long poll has expired before deciding that
+        // the connection is no longer alive.
+        let raw_notices: Vec<Notice> = self
+            .client
+            .get_json_with_timeout(&path, LONG_POLL_TIMEOUT + REQUEST_TIMEOUT)
+            .await?;
         if let Some(n) = raw_notices.last() {
             n.last_occurred.clone_into(&mut self.notices_after);
         }
@@ -276,7 +358,12 @@ where
             std::time::Instant::now()
         );
 
-        raw_prompts.into_iter().map(|p| p.try_into()).collect()
+        let mut prompts = Vec::with_capacity(raw_prompts.len());
+        for raw in raw_prompts {
+            prompts.push(self.with_declared_paths(raw.try_into()?).await);
+        }
+
+        Ok(prompts)
     }
 
     /// Pull details for a specific prompt from snapd
@@ -300,7 +387,46 @@ where
             std::time::Instant::now()
         );
 
-        prompt.try_into()
+        Ok(self.with_declared_paths(prompt.try_into()?).await)
+    }
+
+    /// Prompts for the personal-files and system-files interfaces are limited to the paths
+    /// declared by the snap's plug, which snapd does not include in the prompt itself.
+    async fn with_declared_paths(&self, mut prompt: TypedPrompt) -> TypedPrompt {
+        let snap = prompt.snap().to_string();
+        let interface = prompt.interface().to_string();
+        let declared_paths = match &mut prompt {
+            TypedPrompt::PersonalFiles(p) => &mut p.constraints.declared_paths,
+            TypedPrompt::SystemFiles(p) => &mut p.constraints.declared_paths,
+            _ => return prompt,
+        };
+
+        match self.plug_paths(&snap, &interface).await {
+            Ok(paths) => *declared_paths = paths,
+            Err(e) => warn!(%snap, %interface, "unable to look up declared plug paths: {e}"),
+        }
+
+        prompt
+    }
+
+    /// The paths declared in the `read` and `write` attributes of the connected plugs of a snap
+    /// for the given interface.
+    pub async fn plug_paths(&self, snap: &str, interface: &str) -> Result<Vec<String>> {
+        let connections: Connections = self
+            .client
+            .get_json(&format!("connections?snap={snap}&interface={interface}"))
+            .await?;
+
+        let mut paths: Vec<String> = connections
+            .plugs
+            .into_iter()
+            .filter(|p| p.snap == snap && p.interface == interface)
+            .flat_map(|p| p.attrs.read.into_iter().chain(p.attrs.write))
+            .collect();
+        paths.sort();
+        paths.dedup();
+
+        Ok(paths)
     }
 
     /// Submit a reply to the given prompt to snapd
@@ -333,7 +459,127 @@ where
         Ok(resp.unwrap_or_default())
     }
 
-    async fn snap_icon(&self, name: &str) -> Option<SnapIcon> {
+    /// List the prompting rules for the user we are running under, optionally restricted to a
+    /// given snap and/or interface.
+    ///
+    /// Rules for interfaces that we do not currently support are skipped.
+    pub async fn list_rules(
+        &self,
+        snap: Option<&str>,
+        interface: Option<&str>,
+    ) -> Result<Vec<TypedRule>> {
+        let params: Vec<String> = [("snap", snap), ("interface", interface)]
+            .into_iter()
+            .filter_map(|(k, v)| v.map(|v| format!("{k}={}", utf8_percent_encode(v, QUERY_VALUE))))
+            .collect();
+        let path = if params.is_empty() {
+            RULES_PATH.to_string()
+        } else {
+            format!("{RULES_PATH}?{}", params.join("&"))
+        };
+
+        let raw_rules: Vec<RawRule> = self.client.get_json(&path).await?;
+
+        let mut rules = Vec::with_capacity(raw_rules.len());
+        for raw in raw_rules {
+            match raw.try_into() {
+                Ok(rule) => rules.push(rule),
+                Err(Error::UnsupportedInterface { interface }) => {
+                    debug!(%interface, "skipping rule for unsupported interface")
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(rules)
+    }
+
+    /// Pull details for a specific rule from snapd
+    pub async fn get_rule(&self, id: &RuleId) -> Result<TypedRule> {
+        let rule: RawRule = self
+            .client
+            .get_json(&format!("{RULES_PATH}/{}", id.0))
+            .await?;
+
+        rule.try_into()
+    }
+
+    /// Create a new rule for the given snap and interface
+    pub async fn add_rule<I>(&self, snap: &str, constraints: I::RuleConstraints) -> Result<Rule<I>>
+    where
+        I: SnapInterface,
+    {
+        let rule = AddRule {
+            snap,
+            interface: I::NAME,
+            constraints,
+        };
+        let rule: Rule<I> = self
+            .client
+            .post_json(RULES_PATH, RuleAction::Add { rule })
+            .await?;
+
+        debug!(id = rule.id.0, snap, interface = I::NAME, "added rule");
+
+        Ok(rule)
+    }
+
+    /// Replace the constraints of an existing rule
+    pub async fn patch_rule<I>(
+        &self,
+        id: &RuleId,
+        constraints: I::RuleConstraints,
+    ) -> Result<Rule<I>>
+    where
+        I: SnapInterface,
+    {
+        let rule = PatchRule { constraints };
+
+        self.client
+            .post_json(
+                &format!("{RULES_PATH}/{}", id.0),
+                RuleAction::Patch { rule },
+            )
+            .await
+    }
+
+    /// Add, update or remove (by passing `None`) individual permissions of an existing rule,
+    /// leaving its other permissions as they are.
+    pub async fn patch_rule_permissions(
+        &self,
+        id: &RuleId,
+        permissions: BTreeMap<String, Option<PermissionEntry>>,
+    ) -> Result<TypedRule> {
+        let rule = PatchRule {
+            constraints: PatchPermissions { permissions },
+        };
+        let rule: RawRule = self
+            .client
+            .post_json(
+                &format!("{RULES_PATH}/{}", id.0),
+                RuleAction::Patch { rule },
+            )
+            .await?;
+
+        debug!(id = id.0, "patched rule permissions");
+
+        rule.try_into()
+    }
+
+    /// Remove an existing rule, returning the rule that was removed
+    pub async fn remove_rule(&self, id: &RuleId) -> Result<TypedRule> {
+        let rule: RawRule = self
+            .client
+            .post_json(&format!("{RULES_PATH}/{}", id.0), RuleAction::<()>::Remove)
+            .await?;
+
+        debug!(id = id.0, "removed rule");
+
+        rule.try_into()
+    }
+
+    /// Pull the icon for a snap using the `icons` snapd endpoint.
+    pub async fn snap_icon(&self, name: &str) -> Option<SnapIcon> {
         let res = self.client.get_raw(&format!("icons/{name}/icon")).await;
         match res {
             Ok((bytes, mime_type)) => Some(SnapIcon { bytes, mime_type }),
@@ -344,44 +590,104 @@ where
         }
     }
 
-    /// Pull metadata for rendering apparmor prompts using the `snaps` snapd endpoint.
+    /// Pull metadata for rendering apparmor prompts using the `snaps` and `icons` snapd endpoints.
     pub async fn snap_metadata(&self, name: &str) -> Option<SnapMeta> {
-        let snap_icon = self.snap_icon(name).await;
-        let res = self.client.get_json(&format!("snaps/{name}")).await;
-        return match res {
-            Ok(SnapDetails {
-                install_date,
-                publisher,
-            }) => Some(SnapMeta {
-                name: name.to_owned(),
-                updated_at: install_date
-                    .split_once('T')
-                    .map(|(s, _)| s.to_owned())
-                    .unwrap_or(install_date),
-                store_url: format!("snap://{name}"),
-                publisher: publisher.display_name,
-                snap_icon,
-            }),
+        let (snap_icon, meta) = tokio::join!(self.snap_icon(name), self.snap_details(name));
 
+        meta.map(|meta| SnapMeta { snap_icon, ..meta })
+    }
+
+    /// Pull metadata for rendering apparmor prompts using the `snaps` snapd endpoint, without
+    /// fetching the snap icon.
+    pub async fn snap_details(&self, name: &str) -> Option<SnapMeta> {
+        match self.client.get_json(&format!("snaps/{name}")).await {
+            Ok(details) => Some(SnapDetails::into_snap_meta(details, name, None)),
             Err(e) => {
                 error!("unable to pull snap metadata for {name}: {e}");
                 None
             }
-        };
+        }
+    }
+}
 
-        // Serde structs
+/// The subset of the response from the `connections` endpoint that we make use of.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Connections {
+    plugs: Vec<Plug>,
+}
 
-        #[derive(Debug, Default, Deserialize)]
-        #[serde(rename_all = "kebab-case")]
-        struct SnapDetails {
-            install_date: String,
-            publisher: Publisher,
-        }
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Plug {
+    snap: String,
+    interface: String,
+    attrs: PlugAttrs,
+}
 
-        #[derive(Debug, Default, Deserialize)]
-        #[serde(rename_all = "kebab-case")]
-        struct Publisher {
-            display_name: String,
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct PlugAttrs {
+    read: Vec<String>,
+    write: Vec<String>,
+}
+
+/// The subset of the response from the `snaps` endpoint that we make use of.
+///
+/// Snapd omits a number of these fields depending on how the snap was installed so everything
+/// has a default.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+struct SnapDetails {
+    title: String,
+    summary: String,
+    version: String,
+    channel: String,
+    tracking_channel: String,
+    revision: String,
+    confinement: String,
+    devmode: bool,
+    install_date: String,
+    publisher: Publisher,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+struct Publisher {
+    display_name: String,
+    validation: PublisherValidation,
+}
+
+impl SnapDetails {
+    fn into_snap_meta(self, name: &str, snap_icon: Option<SnapIcon>) -> SnapMeta {
+        let channel = if self.channel.is_empty() {
+            self.tracking_channel
+        } else {
+            self.channel
+        };
+
+        SnapMeta {
+            name: name.to_owned(),
+            updated_at: self
+                .install_date
+                .split_once('T')
+                .map(|(s, _)| s.to_owned())
+                .unwrap_or(self.install_date),
+            store_url: format!("snap://{name}"),
+            publisher: self.publisher.display_name,
+            snap_icon,
+            snap_title: self.title,
+            summary: self.summary,
+            version: self.version,
+            channel,
+            // Snaps installed with --dangerous are given local revisions of the form "x1"
+            installed_locally: self.revision.starts_with('x'),
+            revision: self.revision,
+            confinement: self.confinement,
+            devmode: self.devmode,
+            publisher_validation: self.publisher.validation,
+            app_name: String::new(),
+            app_title: String::new(),
         }
     }
 }
@@ -408,14 +714,40 @@ impl Serialize for SnapIcon {
     }
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
-#[serde(rename_all = "kebab-case")]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", default)]
 pub struct SnapMeta {
     pub name: String,
     pub updated_at: String,
     pub store_url: String,
     pub publisher: String,
+    #[serde(skip_deserializing)]
     pub snap_icon: Option<SnapIcon>,
+    /// The title of the snap as a whole: snapd does not provide titles for individual apps.
+    pub snap_title: String,
+    pub summary: String,
+    pub version: String,
+    pub channel: String,
+    pub revision: String,
+    pub confinement: String,
+    pub devmode: bool,
+    pub installed_locally: bool,
+    pub publisher_validation: PublisherValidation,
+    /// The app within the snap that triggered the prompt, if known.
+    pub app_name: String,
+    /// The localised name of the app from its desktop entry, if known.
+    pub app_title: String,
+}
+
+/// How the identity of a snap publisher has been validated by the store.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PublisherValidation {
+    Verified,
+    Starred,
+    #[default]
+    #[serde(other)]
+    Unproven,
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -492,4 +824,155 @@ mod tests {
             res => panic!("expected NotAvailable, got {res:?}"),
         }
     }
+
+    #[test]
+    fn snap_details_from_the_store_are_parsed() {
+        let details: SnapDetails = serde_json::from_value(serde_json::json!({
+            "title": "Firefox",
+            "summary": "Mozilla Firefox web browser",
+            "version": "129.0.1-1",
+            "channel": "stable",
+            "tracking-channel": "latest/stable",
+            "revision": "4793",
+            "confinement": "strict",
+            "devmode": false,
+            "install-date": "2024-08-14T12:34:56.123456789+01:00",
+            "publisher": { "display-name": "Mozilla", "validation": "verified" },
+        }))
+        .unwrap();
+
+        let meta = details.into_snap_meta("firefox", None);
+
+        assert_eq!(meta.updated_at, "2024-08-14");
+        assert_eq!(meta.snap_title, "Firefox");
+        assert_eq!(meta.channel, "stable");
+        assert_eq!(meta.revision, "4793");
+        assert!(!meta.installed_locally);
+        assert_eq!(meta.publisher_validation, PublisherValidation::Verified);
+    }
+
+    #[test]
+    fn snap_details_for_local_snaps_use_defaults() {
+        let details: SnapDetails = serde_json::from_value(serde_json::json!({
+            "version": "0.1",
+            "tracking-channel": "latest/edge",
+            "revision": "x1",
+            "confinement": "devmode",
+            "devmode": true,
+            "install-date": "2024-08-14T12:34:56.123456789+01:00",
+            "publisher": { "validation": "something-new" },
+        }))
+        .unwrap();
+
+        let meta = details.into_snap_meta("local", None);
+
+        assert_eq!(meta.snap_title, "");
+        assert_eq!(meta.channel, "latest/edge");
+        assert!(meta.installed_locally);
+        assert!(meta.devmode);
+        assert_eq!(meta.publisher_validation, PublisherValidation::Unproven);
+    }
+
+    /// A [Client] that always returns the same JSON value and records the paths it was called
+    /// with.
+    #[derive(Debug, Default)]
+    struct StaticClient {
+        value: serde_json::Value,
+        paths: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl Client for StaticClient {
+        async fn get_json<T>(&self, path: &str) -> Result<T>
+        where
+            T: DeserializeOwned,
+        {
+            self.paths.lock().unwrap().push(path.to_string());
+            Ok(serde_json::from_value(self.value.clone())?)
+        }
+
+        async fn get_json_with_timeout<T>(&self, path: &str, _timeout: Duration) -> Result<T>
+        where
+            T: DeserializeOwned,
+        {
+            self.get_json(path).await
+        }
+
+        async fn post_json<T, U>(&self, path: &str, _body: U) -> Result<T>
+        where
+            T: DeserializeOwned,
+            U: Serialize + Send,
+        {
+            self.get_json(path).await
+        }
+
+        async fn get_raw(&self, path: &str) -> Result<(Bytes, String)> {
+            Err(Error::Io(std::io::Error::other(format!(
+                "raw requests are not supported by StaticClient: {path}"
+            ))))
+        }
+
+        async fn is_available(&self) -> bool {
+            true
+        }
+    }
+
+    fn raw_rule(id: &str, interface: &str, constraints: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "timestamp": "2024-08-15T13:28:17.077016791Z",
+            "user": 1000,
+            "snap": "aa-prompting-test",
+            "interface": interface,
+            "constraints": constraints,
+        })
+    }
+
+    #[test_case(None, None, "interfaces/requests/rules"; "no filters")]
+    #[test_case(Some("firefox"), None, "interfaces/requests/rules?snap=firefox"; "snap")]
+    #[test_case(None, Some("home"), "interfaces/requests/rules?interface=home"; "interface")]
+    #[test_case(
+        Some("firefox"), Some("home"), "interfaces/requests/rules?snap=firefox&interface=home";
+        "snap and interface"
+    )]
+    #[test_case(
+        Some("a&b=c #d"), None, "interfaces/requests/rules?snap=a%26b%3Dc%20%23d";
+        "values are percent encoded"
+    )]
+    #[tokio::test]
+    async fn list_rules_skips_unsupported_interfaces(
+        snap: Option<&str>,
+        interface: Option<&str>,
+        expected_path: &str,
+    ) {
+        let permissions = serde_json::json!({
+            "access": { "outcome": "allow", "lifespan": "forever" }
+        });
+        let client = SnapdClient {
+            client: StaticClient {
+                value: serde_json::json!([
+                    raw_rule(
+                        "1",
+                        "camera",
+                        serde_json::json!({ "permissions": permissions })
+                    ),
+                    raw_rule("2", "not-an-interface", serde_json::json!({})),
+                    raw_rule(
+                        "3",
+                        "home",
+                        serde_json::json!({ "path-pattern": "/home/ubuntu/**", "permissions": {} })
+                    ),
+                ]),
+                ..Default::default()
+            },
+            notices_after: String::new(),
+        };
+
+        let rules = client.list_rules(snap, interface).await.unwrap();
+        let ids: Vec<&str> = rules.iter().map(|r| r.id().0.as_str()).collect();
+
+        assert_eq!(ids, vec!["1", "3"]);
+        assert!(matches!(rules[0], TypedRule::Camera(_)));
+        assert!(matches!(rules[1], TypedRule::Home(_)));
+        assert_eq!(*client.client.paths.lock().unwrap(), vec![expected_path]);
+    }
 }
@@ -14,7 +14,11 @@
 //!
 //!   [0]: crate::snapd_client::interfaces
 use crate::{
-    snapd_client::{interfaces::SnapInterface, Cgroup, PromptId, SnapMeta},
+    procfs::ProcessInfo,
+    snapd_client::{
+        interfaces::{generic::GenericInterface, SnapInterface},
+        Cgroup, PromptId, SnapMeta,
+    },
     Result,
 };
 use serde::{Deserialize, Serialize};
@@ -117,6 +121,32 @@ where
     }
 }
 
+impl Prompt<GenericInterface> {
+    /// Wrap a prompt for an interface without dedicated support, retaining the interface name
+    /// provided by snapd.
+    pub(crate) fn generic_from_raw(
+        RawPrompt {
+            id,
+            timestamp,
+            snap,
+            pid,
+            cgroup,
+            interface,
+            constraints,
+        }: RawPrompt,
+    ) -> Self {
+        Prompt {
+            id,
+            timestamp,
+            snap,
+            pid,
+            cgroup,
+            interface,
+            constraints: constraints.into(),
+        }
+    }
+}
+
 /// A reply to a prompt that contains [SnapInterface] specific reply-constraints.
 #[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
@@ -165,6 +195,7 @@ where
 {
     pub(crate) id: PromptId,
     pub(crate) meta: SnapMeta,
+    pub(crate) process: Option<ProcessInfo>,
     pub(crate) data: I::UiInputData,
 }
 
@@ -1,8 +1,5 @@
 //! Parsing of snapd API responses
-use crate::{
-    socket_client::{body_json, body_raw},
-    Error, Result,
-};
+use crate::{socket_client::body_raw, Error, Result};
 use hyper::{
     body::{Bytes, Incoming},
     Response, StatusCode,
@@ -51,10 +48,21 @@ pub struct RuleConflict {
 
 pub async fn parse_raw_response(res: Response<Incoming>) -> Result<(Bytes, String)> {
     let status = res.status();
+    let (bytes, content_type) = body_raw(res).await?;
+
+    parse_raw_body(status, bytes, content_type)
+}
+
+/// Parse the body of a response from snapd for a non-JSON resource (such as a snap icon).
+pub fn parse_raw_body(
+    status: StatusCode,
+    bytes: Bytes,
+    content_type: String,
+) -> Result<(Bytes, String)> {
     match status {
-        StatusCode::OK => body_raw(res).await,
+        StatusCode::OK => Ok((bytes, content_type)),
         _ => {
-            let response: SnapdResponse<()> = body_json(res).await?;
+            let response: SnapdResponse<()> = serde_json::from_slice(&bytes)?;
             match response.result {
                 Err((message, err)) => Err(Error::SnapdError {
                     status,
@@ -73,7 +81,17 @@ where
     T: DeserializeOwned,
 {
     let status = res.status();
-    let resp: SnapdResponse<T> = body_json(res).await?;
+    let (bytes, _) = body_raw(res).await?;
+
+    parse_body(status, &bytes)
+}
+
+/// Parse the body of a JSON response from snapd into our internal Result type
+pub fn parse_body<T>(status: StatusCode, bytes: &[u8]) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    let resp: SnapdResponse<T> = serde_json::from_slice(bytes)?;
 
     resp.result.map_err(|(message, err)| Error::SnapdError {
         status,
@@ -351,7 +369,7 @@ struct PermissionsError {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::snapd_client::{Cgroup, PromptId, RawPrompt};
+    use crate::snapd_client::{rule::RawRule, Cgroup, PromptId, RawPrompt, RuleId};
     use simple_test_case::dir_cases;
 
     // Files within this directory need to have a prefix that matches one of the assertion branches
@@ -433,4 +451,56 @@ mod tests {
 
         assert_eq!(raw.result, Ok(expected));
     }
+
+    const RAW_RULE: &str = r#"{
+  "result": {
+    "id": "0000000000000009",
+    "timestamp": "2024-08-15T13:28:17.077016791Z",
+    "user": 1000,
+    "snap": "aa-prompting-test",
+    "interface": "home",
+    "constraints": {
+      "path-pattern": "/home/ubuntu/test/**",
+      "permissions": {
+        "read": {
+          "outcome": "allow",
+          "lifespan": "forever"
+        },
+        "write": {
+          "outcome": "deny",
+          "lifespan": "timespan",
+          "expiration": "2024-08-15T13:38:17.077016791Z"
+        }
+      }
+    }
+  },
+  "status": "OK",
+  "status-code": 200,
+  "type": "sync"
+}"#;
+
+    #[test]
+    fn raw_rule_parsing_works() {
+        let raw: SnapdResponse<RawRule> = serde_json::from_str(RAW_RULE).unwrap();
+        let expected = RawRule {
+            id: RuleId("0000000000000009".to_string()),
+            timestamp: "2024-08-15T13:28:17.077016791Z".to_string(),
+            user: 1000,
+            snap: "aa-prompting-test".to_string(),
+            interface: "home".to_string(),
+            constraints: serde_json::json!({
+                "path-pattern": "/home/ubuntu/test/**",
+                "permissions": {
+                    "read": { "outcome": "allow", "lifespan": "forever" },
+                    "write": {
+                        "outcome": "deny",
+                        "lifespan": "timespan",
+                        "expiration": "2024-08-15T13:38:17.077016791Z"
+                    }
+                }
+            }),
+        };
+
+        assert_eq!(raw.result, Ok(expected));
+    }
 }
@@ -0,0 +1,309 @@
+//! Generic types for working with the prompting rules stored by snapd.
+//!
+//! Rules are created by snapd when a prompt is replied to with a lifespan other than
+//! [Lifespan::Single], and can also be managed directly via the `/v2/interfaces/requests/rules`
+//! API. As with prompts, the `constraints` field of a rule is specific to the interface that the
+//! rule applies to and the [TypedRule][0] enum is provided by the interfaces module.
+//!
+//!   [0]: crate::snapd_client::interfaces::TypedRule
+use crate::{
+    snapd_client::{interfaces::SnapInterface, Action, Lifespan},
+    Result,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+pub struct RuleId(pub String);
+
+/// Utility type for parsing the top level structure of the rule JSON object received from Snapd.
+/// At this stage there are no guarantees about the structure of the `constraints` field, which
+/// needs to be parsed based on the value of the top level `interface` field.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RawRule {
+    pub(crate) id: RuleId,
+    pub(crate) timestamp: String,
+    pub(crate) user: u32,
+    pub(crate) snap: String,
+    pub(crate) interface: String,
+    pub(crate) constraints: serde_json::Value,
+}
+
+/// A rule that contains [SnapInterface] specific constraints.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Rule<I>
+where
+    I: SnapInterface,
+{
+    pub(crate) id: RuleId,
+    pub(crate) timestamp: String,
+    pub(crate) user: u32,
+    pub(crate) snap: String,
+    pub(crate) interface: String,
+    pub(crate) constraints: I::RuleConstraints,
+}
+
+impl<I> Rule<I>
+where
+    I: SnapInterface,
+{
+    pub fn id(&self) -> &RuleId {
+        &self.id
+    }
+
+    pub fn timestamp(&self) -> &str {
+        &self.timestamp
+    }
+
+    pub fn user(&self) -> u32 {
+        self.user
+    }
+
+    pub fn snap(&self) -> &str {
+        &self.snap
+    }
+
+    pub fn interface(&self) -> &str {
+        &self.interface
+    }
+
+    pub fn constraints(&self) -> &I::RuleConstraints {
+        &self.constraints
+    }
+
+    /// Attempt to deserialize the `constraints` field into structured data associated with a
+    /// particular [SnapInterface].
+    ///
+    /// # Safety
+    ///
+    /// Calling this method on a [RawRule] that has constraints that parse correctly for the given
+    /// [SnapInterface], but not the correct interface name may result in rules being attributed
+    /// to the wrong interface. On debug builds this will trigger an assert.
+    pub(crate) unsafe fn try_from_raw(
+        RawRule {
+            id,
+            timestamp,
+            user,
+            snap,
+            interface,
+            constraints,
+        }: RawRule,
+    ) -> Result<Self> {
+        debug_assert_eq!(
+            interface,
+            I::NAME,
+            "Rule::try_from called for for wrong interface"
+        );
+
+        Ok(Rule {
+            id,
+            timestamp,
+            user,
+            snap,
+            interface,
+            constraints: serde_json::from_value(constraints)?,
+        })
+    }
+}
+
+/// The outcome and lifespan associated with a single permission within a rule.
+///
+/// Rules returned by snapd provide an `expiration` timestamp for [Lifespan::Timespan] entries,
+/// whereas when creating or patching rules a `duration` is provided instead.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PermissionEntry {
+    pub(crate) outcome: Action,
+    pub(crate) lifespan: Lifespan,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) duration: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) expiration: Option<String>,
+}
+
+impl PermissionEntry {
+    /// A new entry with the given outcome that will apply until it is removed.
+    pub fn forever(outcome: Action) -> Self {
+        Self::new(outcome, Lifespan::Forever, None)
+    }
+
+    /// A new entry with the given outcome that will apply until the user logs out.
+    pub fn for_session(outcome: Action) -> Self {
+        Self::new(outcome, Lifespan::Session, None)
+    }
+
+    /// A new entry with the given outcome that will apply for the specified timespan.
+    ///
+    /// Timespans are provided in the format parsable by go's [ParseDuration](https://pkg.go.dev/time#ParseDuration).
+    pub fn for_timespan(outcome: Action, duration: impl Into<String>) -> Self {
+        Self::new(outcome, Lifespan::Timespan, Some(duration.into()))
+    }
+
+    fn new(outcome: Action, lifespan: Lifespan, duration: Option<String>) -> Self {
+        Self {
+            outcome,
+            lifespan,
+            duration,
+            expiration: None,
+        }
+    }
+
+    pub fn outcome(&self) -> Action {
+        self.outcome
+    }
+
+    pub fn lifespan(&self) -> Lifespan {
+        self.lifespan
+    }
+
+    pub fn expiration(&self) -> Option<&str> {
+        self.expiration.as_deref()
+    }
+
+    /// Convert an entry taken from an existing rule into one that can be used to create a new
+    /// rule, replacing any expiration timestamp with the duration remaining as of `now`.
+    ///
+    /// Returns `None` if the entry has already expired.
+    pub(crate) fn remaining_from(self, now: DateTime<Utc>) -> Option<Self> {
+        let duration = match (self.lifespan, &self.expiration) {
+            (Lifespan::Timespan, Some(expiration)) => {
+                let expiration = DateTime::parse_from_rfc3339(expiration).ok()?;
+                let remaining = expiration.with_timezone(&Utc) - now;
+                if remaining.num_seconds() <= 0 {
+                    return None;
+                }
+
+                Some(format!("{}s", remaining.num_seconds()))
+            }
+            _ => self.duration,
+        };
+
+        Some(Self {
+            duration,
+            expiration: None,
+            ..self
+        })
+    }
+}
+
+/// The body of a request to add a new rule.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct AddRule<'a, C: Serialize> {
+    pub(crate) snap: &'a str,
+    pub(crate) interface: &'a str,
+    pub(crate) constraints: C,
+}
+
+/// The body of a request to patch an existing rule.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct PatchRule<C: Serialize> {
+    pub(crate) constraints: C,
+}
+
+/// Constraints for a patch that only changes the permissions of an existing rule.
+///
+/// Snapd merges the permissions in a patch with those of the existing rule: a `None` entry is
+/// serialized as `null`, which is the only way to remove a permission.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct PatchPermissions {
+    pub(crate) permissions: BTreeMap<String, Option<PermissionEntry>>,
+}
+
+/// The body of a POST request to the rules API.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub(crate) enum RuleAction<R: Serialize> {
+    Add { rule: R },
+    Patch { rule: R },
+    Remove,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    #[test_case(
+        PermissionEntry::forever(Action::Allow),
+        r#"{"outcome":"allow","lifespan":"forever"}"#;
+        "forever"
+    )]
+    #[test_case(
+        PermissionEntry::for_timespan(Action::Deny, "10m"),
+        r#"{"outcome":"deny","lifespan":"timespan","duration":"10m"}"#;
+        "timespan"
+    )]
+    #[test]
+    fn permission_entry_serializes(entry: PermissionEntry, expected: &str) {
+        let s = serde_json::to_string(&entry).unwrap();
+        assert_eq!(s, expected);
+    }
+
+    #[test_case(
+        RuleAction::Add { rule: serde_json::json!({"snap": "firefox"}) },
+        r#"{"action":"add","rule":{"snap":"firefox"}}"#;
+        "add"
+    )]
+    #[test_case(
+        RuleAction::Patch { rule: serde_json::json!({"constraints": {}}) },
+        r#"{"action":"patch","rule":{"constraints":{}}}"#;
+        "patch"
+    )]
+    #[test_case(RuleAction::Remove, r#"{"action":"remove"}"#; "remove")]
+    #[test]
+    fn rule_action_serializes(action: RuleAction<serde_json::Value>, expected: &str) {
+        let s = serde_json::to_string(&action).unwrap();
+        assert_eq!(s, expected);
+    }
+
+    #[test]
+    fn patch_permissions_serializes_removed_permissions_as_null() {
+        let patch = PatchPermissions {
+            permissions: BTreeMap::from([
+                ("read".to_string(), None),
+                (
+                    "write".to_string(),
+                    Some(PermissionEntry::forever(Action::Deny)),
+                ),
+            ]),
+        };
+
+        let s = serde_json::to_string(&patch).unwrap();
+
+        assert_eq!(
+            s,
+            r#"{"permissions":{"read":null,"write":{"outcome":"deny","lifespan":"forever"}}}"#
+        );
+    }
+
+    #[test_case(PermissionEntry::forever(Action::Allow), Some(PermissionEntry::forever(Action::Allow)); "forever")]
+    #[test_case(
+        PermissionEntry {
+            expiration: Some("2024-08-15T13:38:17Z".to_string()),
+            ..PermissionEntry::for_timespan(Action::Deny, "10m")
+        },
+        Some(PermissionEntry::for_timespan(Action::Deny, "300s"));
+        "timespan remaining"
+    )]
+    #[test_case(
+        PermissionEntry {
+            expiration: Some("2024-08-15T13:28:17Z".to_string()),
+            ..PermissionEntry::for_timespan(Action::Deny, "10m")
+        },
+        None;
+        "timespan expired"
+    )]
+    #[test]
+    fn remaining_from_works(entry: PermissionEntry, expected: Option<PermissionEntry>) {
+        let now = DateTime::parse_from_rfc3339("2024-08-15T13:33:17Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(entry.remaining_from(now), expected);
+    }
+}
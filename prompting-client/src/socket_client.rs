@@ -1,21 +1,39 @@
 use crate::{Error, Result};
-use http_body_util::{BodyExt, Empty, Full};
+use http_body_util::{BodyExt, Full};
 use hyper::{
-    body::{Body, Bytes, Incoming},
+    body::{Bytes, Incoming},
     client::conn::http1,
     header::{CONTENT_TYPE, HOST},
     http::request::Builder,
     Request, Response, Uri,
 };
 use hyper_util::rt::TokioIo;
-use serde::de::DeserializeOwned;
-use std::path::PathBuf;
-use tokio::net::UnixStream;
-use tracing::error;
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{net::UnixStream, time::timeout};
+use tracing::{debug, error};
+
+/// The maximum number of keep-alive connections to snapd that we hold on to between requests.
+const MAX_IDLE_CONNECTIONS: usize = 8;
+
+/// How long we wait for a response to a request before giving up on it.
+pub(crate) const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
 
+type Sender = http1::SendRequest<Full<Bytes>>;
+
+/// An HTTP/1 client for a unix socket that reuses connections between requests.
+///
+/// Connections are returned to the pool as soon as the response headers have been received and
+/// only become available to new requests once the previous response body has been read in full.
+/// Connections that have been closed (for example, because snapd has restarted) are discarded the
+/// next time the pool is checked.
 #[derive(Debug, Clone)]
 pub struct UnixSocketClient {
     socket_path: PathBuf,
+    pool: Arc<Mutex<Vec<Sender>>>,
 }
 
 impl UnixSocketClient {
@@ -25,32 +43,106 @@ impl UnixSocketClient {
     {
         Self {
             socket_path: path.into(),
+            pool: Default::default(),
         }
     }
 
-    async fn do_req<T>(&self, req: Request<T>) -> Result<Response<Incoming>>
-    where
-        T: Body + Send + Sync + 'static,
-        T::Error: std::error::Error + Send + Sync,
-        T::Data: Send + Sync,
-    {
+    /// Check whether or not the socket is currently accepting connections.
+    pub async fn is_available(&self) -> bool {
+        UnixStream::connect(&self.socket_path).await.is_ok()
+    }
+
+    async fn connect(&self) -> Result<Sender> {
         let stream = UnixStream::connect(&self.socket_path).await?;
-        let (mut sender, conn) = http1::handshake(TokioIo::new(stream)).await?;
+        let (sender, conn) = http1::handshake(TokioIo::new(stream)).await?;
         tokio::task::spawn(async move {
             if let Err(error) = conn.await {
                 error!(%error, "connection failed");
             }
         });
 
+        Ok(sender)
+    }
+
+    /// Take an idle connection from the pool if there is one available.
+    fn checkout(&self) -> Option<Sender> {
+        let mut pool = match self.pool.lock() {
+            Ok(guard) => guard,
+            Err(err) => err.into_inner(),
+        };
+
+        pool.retain(|sender| !sender.is_closed());
+        let ix = pool.iter().position(|sender| sender.is_ready())?;
+
+        Some(pool.swap_remove(ix))
+    }
+
+    fn checkin(&self, sender: Sender) {
+        if sender.is_closed() {
+            return;
+        }
+
+        let mut pool = match self.pool.lock() {
+            Ok(guard) => guard,
+            Err(err) => err.into_inner(),
+        };
+
+        if pool.len() < MAX_IDLE_CONNECTIONS {
+            pool.push(sender);
+        }
+    }
+
+    async fn do_req(
+        &self,
+        req: Request<Full<Bytes>>,
+        request_timeout: Duration,
+    ) -> Result<Response<Incoming>> {
+        match timeout(request_timeout, self.send(req)).await {
+            Ok(res) => res,
+            Err(_) => Err(Error::RequestTimeout {
+                timeout: request_timeout,
+            }),
+        }
+    }
+
+    async fn send(&self, mut req: Request<Full<Bytes>>) -> Result<Response<Incoming>> {
+        // A pooled connection may have been closed by snapd without us having noticed yet, in
+        // which case hyper hands us back the unsent request so that we can try again.
+        while let Some(mut sender) = self.checkout() {
+            match sender.try_send_request(req).await {
+                Ok(res) => {
+                    self.checkin(sender);
+                    return Ok(res);
+                }
+                Err(mut e) => match e.take_message() {
+                    Some(unsent) => {
+                        debug!(error=?e, "pooled connection unusable: retrying");
+                        req = unsent;
+                    }
+                    None => return Err(e.into_error().into()),
+                },
+            }
+        }
+
+        let mut sender = self.connect().await?;
         let res = sender.send_request(req).await?;
+        self.checkin(sender);
 
         Ok(res)
     }
 
     pub async fn get(&self, uri: Uri) -> Result<Response<Incoming>> {
-        let req = request_builder(&uri)?.body(Empty::<Bytes>::new())?;
+        self.get_with_timeout(uri, REQUEST_TIMEOUT).await
+    }
+
+    pub async fn get_with_timeout(
+        &self,
+        uri: Uri,
+        request_timeout: Duration,
+    ) -> Result<Response<Incoming>> {
+        let req = request_builder(&uri)?.body(Full::new(Bytes::new()))?;
 
-        self.do_req(req).await
+        self.do_req(req, request_timeout).await
     }
 
     pub async fn post(
@@ -64,7 +156,7 @@ impl UnixSocketClient {
             .header(CONTENT_TYPE, content_type)
             .body(Full::new(Bytes::from(body)))?;
 
-        self.do_req(req).await
+        self.do_req(req, REQUEST_TIMEOUT).await
     }
 }
 
@@ -90,16 +182,6 @@ fn request_builder(uri: &Uri) -> Result<Builder> {
         .header(HOST, authority))
 }
 
-pub(crate) async fn body_json<T>(res: Response<Incoming>) -> Result<T>
-where
-    T: DeserializeOwned,
-{
-    let (bytes, _) = body_raw(res).await?;
-    let t: T = serde_json::from_slice(&bytes)?;
-
-    Ok(t)
-}
-
 pub(crate) async fn body_raw(res: Response<Incoming>) -> Result<(Bytes, String)> {
     let content_type = res
         .headers()
@@ -111,3 +193,121 @@ pub(crate) async fn body_raw(res: Response<Incoming>) -> Result<(Bytes, String)>
 
     Ok((bytes, content_type))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        env,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::UnixListener,
+    };
+    use uuid::Uuid;
+
+    const RESPONSE: &[u8] =
+        b"HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 2\r\n\r\n{}";
+
+    /// A minimal HTTP/1 server that replies to every request with an empty JSON object, closing
+    /// each connection after `requests_per_conn` requests to simulate snapd restarting.
+    fn spawn_server(requests_per_conn: usize) -> (PathBuf, Arc<AtomicUsize>) {
+        let path = env::temp_dir().join(format!("{}.sock", Uuid::new_v4()));
+        let listener = UnixListener::bind(&path).unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let n_conns = connections.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                n_conns.fetch_add(1, Ordering::SeqCst);
+
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0; 1024];
+                    for _ in 0..requests_per_conn {
+                        while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                            match stream.read(&mut chunk).await {
+                                Ok(0) | Err(_) => return,
+                                Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                            }
+                        }
+                        buf.clear();
+                        stream.write_all(RESPONSE).await.unwrap();
+                    }
+                });
+            }
+        });
+
+        (path, connections)
+    }
+
+    async fn get(client: &UnixSocketClient) {
+        let uri = Uri::from_static("http://localhost/v2/system-info");
+        let res = client.get(uri).await.unwrap();
+        let (bytes, _) = body_raw(res).await.unwrap();
+        let _: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    }
+
+    #[tokio::test]
+    async fn connections_are_reused() {
+        let (path, connections) = spawn_server(usize::MAX);
+        let client = UnixSocketClient::new(&path);
+
+        for _ in 0..5 {
+            get(&client).await;
+        }
+
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn closed_connections_are_replaced() {
+        let (path, connections) = spawn_server(1);
+        let client = UnixSocketClient::new(&path);
+
+        for _ in 0..3 {
+            get(&client).await;
+        }
+
+        assert_eq!(connections.load(Ordering::SeqCst), 3);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn unresponsive_requests_time_out() {
+        let path = env::temp_dir().join(format!("{}.sock", Uuid::new_v4()));
+        let listener = UnixListener::bind(&path).unwrap();
+        // Accept connections but never reply to them
+        tokio::spawn(async move {
+            let mut streams = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                streams.push(stream);
+            }
+        });
+        let client = UnixSocketClient::new(&path);
+        let uri = Uri::from_static("http://localhost/v2/notices");
+
+        let res = client
+            .get_with_timeout(uri, Duration::from_millis(50))
+            .await;
+
+        assert!(
+            matches!(res, Err(Error::RequestTimeout { .. })),
+            "expected timeout, got {res:?}"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn availability_tracks_the_socket() {
+        let (path, _) = spawn_server(usize::MAX);
+        let client = UnixSocketClient::new(&path);
+        assert!(client.is_available().await);
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(!client.is_available().await);
+    }
+}
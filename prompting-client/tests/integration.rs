@@ -14,10 +14,13 @@ use prompting_client::{
     prompt_sequence::MatchError,
     snapd_client::{
         interfaces::{
-            camera::CameraInterface, home::HomeInterface, microphone::MicrophoneInterface,
+            camera::CameraInterface,
+            home::{path_pattern::PathPatternError, HomeInterface, HomeRuleConstraints},
+            microphone::MicrophoneInterface,
             SnapInterface,
         },
-        Action, Lifespan, PromptId, PromptNotice, SnapdSocketClient, TypedPrompt,
+        Action, Lifespan, PermissionEntry, PromptId, PromptNotice, SnapdError, SnapdSocketClient,
+        TypedPrompt,
     },
     Error, Result,
 };
@@ -115,6 +118,25 @@ macro_rules! expect_single_prompt {
                     assert_eq!(p.snap(), TEST_SNAP);
                     assert_eq!(p.requested_permissions(), $expected_permissions);
                 }
+                TypedPrompt::PersonalFiles(p) => {
+                    assert_eq!(p.snap(), TEST_SNAP);
+                    assert_eq!(p.path(), $expected_path);
+                    assert_eq!(p.requested_permissions(), $expected_permissions);
+                }
+                TypedPrompt::RemovableMedia(p) => {
+                    assert_eq!(p.snap(), TEST_SNAP);
+                    assert_eq!(p.path(), $expected_path);
+                    assert_eq!(p.requested_permissions(), $expected_permissions);
+                }
+                TypedPrompt::SystemFiles(p) => {
+                    assert_eq!(p.snap(), TEST_SNAP);
+                    assert_eq!(p.path(), $expected_path);
+                    assert_eq!(p.requested_permissions(), $expected_permissions);
+                }
+                TypedPrompt::Generic(p) => {
+                    assert_eq!(p.snap(), TEST_SNAP);
+                    assert_eq!(p.requested_permissions(), $expected_permissions);
+                }
             }
 
             (id, p)
@@ -157,7 +179,7 @@ async fn camera_interface_connected(
     assert_eq!(output.stdout, expected_stdout, "stdout");
     assert_eq!(
         output.stderr,
-        expected_stderr.replace("<DEVICE>", &device),
+        expected_stderr.replace("<DEVICE>", device),
         "stderr"
     );
 
@@ -170,6 +192,7 @@ async fn camera_interface_connected(
 #[test_case(Action::Deny, "Deny access to microphone\n", "timeout: failed to open <DEVICE>: Permission denied\n"; "deny")]
 #[tokio::test]
 #[serial]
+#[allow(clippy::assertions_on_constants)]
 async fn microphone_interface_connected(
     action: Action,
     expected_stdout: &str,
@@ -192,7 +215,7 @@ async fn microphone_interface_connected(
     assert_eq!(output.stdout, expected_stdout, "stdout");
     assert_eq!(
         output.stderr,
-        expected_stderr.replace("<DEVICE>", &device),
+        expected_stderr.replace("<DEVICE>", device),
         "stderr"
     );
 
@@ -251,7 +274,7 @@ async fn happy_path_create_multiple(action: Action, lifespan: Lifespan) -> Resul
     let path = format!("{dir_path}/test-1.txt");
     let (id, p) = expect_single_prompt!(&mut c, &path, &["write"]).await;
     let mut reply = HomeInterface::prompt_to_reply(p.try_into()?, action)
-        .with_custom_path_pattern(format!("{dir_path}/*"));
+        .with_custom_path_pattern(format!("{dir_path}/*"))?;
 
     reply = match lifespan {
         Lifespan::Timespan => reply.for_timespan("1s"),
@@ -308,7 +331,7 @@ async fn create_multiple_actioned_by_other_pid(action: Action, lifespan: Lifespa
     let path = format!("{dir_path}/test.txt");
     let (id, p) = expect_single_prompt!(&mut c, &path, &["write"]).await;
     let mut reply = HomeInterface::prompt_to_reply(p.try_into()?, action)
-        .with_custom_path_pattern(format!("{dir_path}/*"));
+        .with_custom_path_pattern(format!("{dir_path}/*"))?;
 
     reply = match lifespan {
         Lifespan::Timespan => reply.for_timespan("1s"),
@@ -364,6 +387,53 @@ async fn requesting_an_unknown_prompt_id_is_an_error() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+#[serial]
+async fn managing_rules_works() -> Result<()> {
+    let c = SnapdSocketClient::new().await;
+    let (_, dir_path) = setup_test_dir(None, &[])?;
+
+    let rule = c
+        .add_rule::<HomeInterface>(
+            TEST_SNAP,
+            HomeRuleConstraints::new(
+                format!("{dir_path}/**"),
+                [("read", PermissionEntry::forever(Action::Allow))],
+            ),
+        )
+        .await?;
+    let id = rule.id().clone();
+    assert_eq!(rule.constraints().path_pattern(), format!("{dir_path}/**"));
+
+    let rules = c.list_rules(Some(TEST_SNAP), Some("home")).await?;
+    assert!(rules.iter().any(|r| r.id() == &id), "rule not listed");
+
+    let patched = c
+        .patch_rule::<HomeInterface>(
+            &id,
+            HomeRuleConstraints::new(
+                format!("{dir_path}/*.txt"),
+                [("write", PermissionEntry::forever(Action::Deny))],
+            ),
+        )
+        .await?;
+    assert_eq!(
+        patched.constraints().path_pattern(),
+        format!("{dir_path}/*.txt")
+    );
+
+    let removed = c.remove_rule(&id).await?;
+    assert_eq!(removed.id(), &id);
+
+    match c.get_rule(&id).await {
+        Err(Error::SnapdError { err, .. }) => assert_eq!(*err, SnapdError::RuleNotFound),
+        Err(e) => panic!("expected a snapd error, got: {e}"),
+        Ok(r) => panic!("expected an error, got {r:?}"),
+    }
+
+    Ok(())
+}
+
 #[test_case("not a valid custom path!", "cannot decode request body into prompt reply: invalid path pattern: pattern must start with"; "malformed path")]
 #[test_case("/home/bob/*", "path pattern in reply constraints does not match originally requested path"; "invalid path")]
 #[tokio::test]
@@ -375,7 +445,7 @@ async fn incorrect_custom_paths_error(reply_path: &str, expected_prefix: &str) -
     let _rx = spawn_for_output("aa-prompting-test.read", vec![prefix]);
     let (id, p) = expect_single_prompt!(&mut c, &format!("{dir_path}/test.txt"), &["read"]).await;
     let reply = HomeInterface::prompt_to_reply(p.try_into()?, Action::Allow)
-        .with_custom_path_pattern(reply_path)
+        .with_unchecked_custom_path_pattern(reply_path)
         .into();
 
     match c.reply_to_prompt(&id, reply).await {
@@ -390,6 +460,34 @@ async fn incorrect_custom_paths_error(reply_path: &str, expected_prefix: &str) -
     Ok(())
 }
 
+#[test_case("not a valid custom path!", "pattern must start with '/'"; "malformed path")]
+#[test_case("/home/bob/*", "pattern does not match the requested path"; "invalid path")]
+#[tokio::test]
+#[serial]
+async fn incorrect_custom_paths_are_rejected_locally(
+    reply_path: &str,
+    expected_prefix: &str,
+) -> Result<()> {
+    let mut c = SnapdSocketClient::new().await;
+    let (prefix, dir_path) = setup_test_dir(None, &[("test.txt", "test")])?;
+
+    let _rx = spawn_for_output("aa-prompting-test.read", vec![prefix]);
+    let (_, p) = expect_single_prompt!(&mut c, &format!("{dir_path}/test.txt"), &["read"]).await;
+    let res = HomeInterface::prompt_to_reply(p.try_into()?, Action::Allow)
+        .with_custom_path_pattern(reply_path);
+
+    match res {
+        Err(Error::InvalidPathPattern { error, .. }) => assert!(
+            error.to_string().starts_with(expected_prefix),
+            "error format not as expected: {error}"
+        ),
+        Err(e) => panic!("expected an invalid path pattern error, got: {e:?}"),
+        Ok(_) => panic!("should have errored but got an OK response"),
+    }
+
+    Ok(())
+}
+
 #[test_case("9d", "cannot parse duration: time: unknown unit"; "unknown unit")]
 #[test_case("foo", "cannot parse duration: time: invalid duration"; "invalid duration")]
 #[test_case("5", "cannot parse duration: time: missing unit in duration"; "missing units")]
@@ -559,7 +657,8 @@ async fn invalid_prompt_sequence_reply_errors() -> Result<()> {
         format!("{dir_path}/seq.json"),
         &[("BASE_PATH", &dir_path)],
         c.clone(),
-    )?;
+    )?
+    .without_reply_validation();
 
     match scripted_client.run(&mut c, None).await {
         Err(Error::FailedPromptSequence {
@@ -579,6 +678,38 @@ async fn invalid_prompt_sequence_reply_errors() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+#[serial]
+async fn invalid_prompt_sequence_reply_is_rejected_locally() -> Result<()> {
+    let mut c = SnapdSocketClient::new().await;
+    let seq = include_str!("../resources/prompt-sequence-tests/e2e_erroring_write_test.json");
+    let (prefix, dir_path) = setup_test_dir(None, &[("seq.json", seq)])?;
+
+    spawn_for_output("aa-prompting-test.create", vec![prefix]);
+
+    let mut scripted_client = ScriptedClient::try_new(
+        format!("{dir_path}/seq.json"),
+        &[("BASE_PATH", &dir_path)],
+        c.clone(),
+    )?;
+
+    match scripted_client.run(&mut c, None).await {
+        Err(Error::FailedPromptSequence {
+            error: MatchError::InvalidPathPattern { pattern, error },
+        }) => {
+            assert_eq!(pattern, "/home/ubuntu/not-a-valid-path-for-this-prompt");
+            assert!(
+                matches!(error, PathPatternError::DoesNotMatch { .. }),
+                "{error}"
+            );
+        }
+        Err(e) => panic!("unexpected error: {e}"),
+        Ok(()) => panic!("expected client to error but it ran to completion"),
+    }
+
+    Ok(())
+}
+
 #[tokio::test]
 #[serial]
 async fn unexpected_prompt_in_sequence_errors() -> Result<()> {